@@ -1,31 +1,76 @@
 use std::{
-    env,
-    io::{self, ErrorKind},
-    path::PathBuf,
+    env, io,
+    path::{Path, PathBuf},
     process,
     rc::Rc,
+    time::Duration,
 };
 
 use clap::Parser;
 use crossterm::event::{self, Event, KeyCode, KeyEvent, KeyModifiers};
 use ratatui::{
-    layout::{Constraint, Direction, Layout, Position as TerminalPosition, Rect},
+    layout::{Alignment, Constraint, Direction, Layout, Position as TerminalPosition, Rect},
     prelude::{Backend, StatefulWidget},
     text::Text,
-    widgets::{Block, Borders, Paragraph, Widget, Wrap},
+    widgets::{
+        Block, Borders, Paragraph, Scrollbar, ScrollbarOrientation, ScrollbarState, Widget, Wrap,
+    },
     Terminal,
 };
 
 use crate::{
-    operations::Operation,
-    pike::Pike,
+    error::PikeError,
+    key_shortcut::KeyShortcut,
+    operations::{InputAction, Operation},
+    pike::{fuzzy_matches, surround_pair, LineFilterMode, Pike, SearchOptions},
     ui::{
         BufferDisplayOffset, BufferDisplayState, BufferDisplayWidget, CursorCalculationMode,
-        FileInput, FileInputRole, SearchInput, UIState,
+        DiffViewState, DiffWidget, FileInput, FileInputRole, HexDumpWidget, HexViewState,
+        PendingEdit, Picker, PickerAction, PickerItem, PickerWidget, ReplaceInput,
+        ReplaceInputState, SearchInput, StatusBarFields, UIState,
     },
     welcome_pike::WELCOME_MESSAGE,
 };
 
+/// Displayed when moving past the last search result wraps around to the first one
+const SEARCH_HIT_BOTTOM: &str = "Search hit bottom, continuing at top";
+/// Displayed when moving before the first search result wraps around to the last one
+const SEARCH_HIT_TOP: &str = "Search hit top, continuing at bottom";
+/// Height of the search input, and of the replace input under it, including borders
+const SEARCH_INPUT_HEIGHT: u16 = 3;
+/// Maximum number of pending events handled before the next redraw, so that
+/// a flood of events can't keep the UI from updating
+const MAX_BATCHED_EVENTS: usize = 64;
+/// Time between the frames of a smooth scroll, which are rendered on ticks
+const SMOOTH_SCROLL_FRAME: Duration = Duration::from_millis(16);
+/// Columns the view moves by when scrolled sideways by hand
+const HORIZONTAL_SCROLL_COLUMNS: usize = 4;
+
+/// How long the user has to be idle before the unsaved changes in
+/// the status bar are recomputed
+const UNSAVED_CHANGES_IDLE: Duration = Duration::from_millis(300);
+
+/// Displayed while waiting for the character to surround the selection with
+const SURROUND_PROMPT: &str = "Surround with: ";
+/// Displayed when opening or trying to edit a buffer which can't be edited
+const BUFFER_READ_ONLY: &str = "Buffer is read-only";
+/// Displayed before the first edit of a file outside the workspace or owned by root
+const EDIT_CONFIRMATION_PROMPT: &str = "Edit a file outside the workspace or owned by root? (y/n)";
+/// Appended to the message about a large file, which is opened once confirmed
+const LARGE_FILE_PROMPT: &str = "Open anyway? (y/n)";
+/// Displayed when a binary file is opened
+const BINARY_FILE_DETECTED: &str = "Binary file detected, toggle the hex view to inspect it";
+/// Displayed when a file with both LF and CRLF line endings is opened
+const MIXED_LINE_ENDINGS: &str = "Mixed line endings detected, normalize them to use one style";
+/// Displayed when the session of the workspace is saved
+const SESSION_SAVED: &str = "Session saved";
+/// Displayed when the session of the workspace is restored
+const SESSION_RESTORED: &str = "Session restored";
+/// Displayed when the file to open as of a git revision isn't given as `<revision>:<path>`
+const REVISION_PATH_EXPECTED: &str = "Expected a revision and a path, e.g. HEAD~1:src/main.rs";
+/// Displayed along with the key when an unbound ctrl-modified key is pressed
+const KEY_NOT_BOUND: &str = "Key not bound";
+
 /// TUI application which displays the UI and handles events
 #[allow(dead_code)]
 pub struct App {
@@ -37,7 +82,7 @@ pub struct App {
 #[allow(dead_code, unused_variables, unused_mut)]
 impl App {
     pub fn build(args: Args) -> App {
-        let cwd = env::current_dir().map_err(|_| "Failed to get current working directory");
+        let cwd = workspace_root(args.cwd.as_deref());
         if cwd.is_err() {
             eprintln!("{}", cwd.err().unwrap());
             process::exit(1);
@@ -47,11 +92,33 @@ impl App {
         let file_path = args.file.map(PathBuf::from);
         let no_file_open = file_path.is_none();
 
-        let backend: Result<Pike, String> =
-            Pike::build(cwd.expect("Error case was handled"), file_path, config_path);
+        let cwd = cwd.expect("Error case was handled");
+        let mut large_file = None;
+        let backend = match Pike::build(cwd.clone(), file_path, config_path.clone()) {
+            // A large file is only opened once confirmed, so start without it
+            Err(err @ PikeError::FileTooLarge(_, _)) => {
+                large_file = Some(err);
+                Pike::build(cwd, None, config_path)
+            }
+            backend => backend,
+        };
 
         match backend {
-            Ok(backend) => App::new(backend),
+            Ok(backend) => {
+                let mut app = App::new(backend);
+                if no_file_open {
+                    app.open_scratch_buffer();
+                }
+                if no_file_open && app.backend.restores_session() {
+                    app.restore_session(false);
+                }
+                app.report_opened_buffer_state();
+                if let Some(ref err @ PikeError::FileTooLarge(ref path, _)) = large_file {
+                    app.ui_state.awaiting_large_file = Some(path.clone());
+                    app.ui_state.status_message = Some(format!("{err}. {LARGE_FILE_PROMPT}"));
+                }
+                app
+            }
             Err(err) => {
                 eprintln!("{}", err);
                 process::exit(1);
@@ -61,13 +128,29 @@ impl App {
 
     fn new(backend: Pike) -> App {
         let offset = BufferDisplayOffset::default();
-        let buffer_state = BufferDisplayState::new(offset);
+        let mut buffer_state = BufferDisplayState::new(offset);
+        buffer_state.smooth_scroll = backend.smooth_scroll();
+        buffer_state.gutter_style = backend.gutter_style();
         let file_input = None;
         let search_input = None;
         let ui_state = UIState {
             buffer_state,
             file_input,
             search_input,
+            search_match_count: None,
+            search_options: SearchOptions::default(),
+            replace_input: None,
+            picker: None,
+            last_search_query: None,
+            relative_line_numbers: false,
+            awaiting_surround: false,
+            awaiting_edit_confirmation: None,
+            awaiting_large_file: None,
+            hex_view: None,
+            diff_view: None,
+            status_message: None,
+            unsaved_changes: None,
+            unsaved_changes_stale: false,
         };
 
         App {
@@ -81,19 +164,64 @@ impl App {
     fn build_default() -> Self {
         App::build(Args {
             config: None,
+            cwd: None,
             file: None,
         })
     }
 
     pub fn run<B: Backend>(&mut self, terminal: &mut Terminal<B>) -> io::Result<()> {
+        let mut needs_redraw = true;
         loop {
             if self.exit {
                 return Ok(());
             }
 
-            terminal.draw(|frame| self.draw(frame))?;
-            self.handle_events()?;
+            if needs_redraw {
+                terminal.draw(|frame| self.draw(frame))?;
+            }
+
+            match self.tick_interval() {
+                // Nothing timed is going on, so just wait for the next event
+                None => {
+                    self.handle_events()?;
+                    needs_redraw = true;
+                }
+                Some(interval) if event::poll(interval)? => {
+                    self.handle_events()?;
+                    needs_redraw = true;
+                }
+                Some(_) => needs_redraw = self.tick(),
+            }
+        }
+    }
+
+    /// Returns how often the app has to tick to drive its timed features,
+    /// or None if none of them is active, so that it doesn't wake up when idle
+    fn tick_interval(&self) -> Option<Duration> {
+        let scrolling = self
+            .ui_state
+            .buffer_state
+            .is_scrolling()
+            .then_some(SMOOTH_SCROLL_FRAME);
+        let unsaved_changes = self
+            .ui_state
+            .unsaved_changes_stale
+            .then_some(UNSAVED_CHANGES_IDLE);
+        scrolling.into_iter().chain(unsaved_changes).min()
+    }
+
+    /// Advance the timed features of the app when no event arrived within
+    /// the tick interval. Returns whether the UI has to be redrawn.
+    fn tick(&mut self) -> bool {
+        // Diffing against the saved file is too slow to do on every key press
+        let recomputed = self.ui_state.unsaved_changes_stale;
+        if recomputed {
+            self.ui_state.unsaved_changes = self.backend.unsaved_change_stats();
+            self.ui_state.unsaved_changes_stale = false;
         }
+
+        // A smooth scroll takes a step with each rendered frame
+        recomputed || self.ui_state.buffer_state.is_scrolling()
     }
 
     fn draw(&mut self, frame: &mut ratatui::Frame) {
@@ -106,7 +234,13 @@ impl App {
 
         let mut render_cursor_position;
 
-        self.render_buffer_contents(main_area, frame.buffer_mut());
+        if self.ui_state.hex_view.is_some() {
+            self.render_hex_view(main_area, frame.buffer_mut());
+        } else if let Some(ref mut diff_view) = self.ui_state.diff_view {
+            DiffWidget.render(main_area, frame.buffer_mut(), diff_view);
+        } else {
+            self.render_buffer_contents(main_area, frame.buffer_mut());
+        }
 
         let file_input_value = self.ui_state.file_input.clone();
         let search_input_value = self.ui_state.search_input.clone();
@@ -125,6 +259,37 @@ impl App {
                 &layout,
                 cursor_pos,
             );
+
+            // The replacement is entered in a second input under the search input,
+            // if the terminal is tall enough for both
+            let replace_input = self
+                .ui_state
+                .replace_input
+                .clone()
+                .filter(|_| status_bar_area.height > SEARCH_INPUT_HEIGHT);
+            if let Some(mut replace_input) = replace_input {
+                let replace_area = Rect {
+                    y: status_bar_area.y + SEARCH_INPUT_HEIGHT,
+                    height: status_bar_area.height.saturating_sub(SEARCH_INPUT_HEIGHT),
+                    ..status_bar_area
+                };
+                ReplaceInput::default().render(
+                    replace_area,
+                    frame.buffer_mut(),
+                    &mut replace_input,
+                );
+                if replace_input.is_focused {
+                    render_cursor_position = self
+                        .ui_state
+                        .calculate_cursor_for_file_input(&replace_input.input, replace_area);
+                }
+            }
+        } else if let Some(ref hex_view) = self.ui_state.hex_view {
+            render_cursor_position = hex_view.cursor_render_position(main_area);
+            self.render_status_bar(status_bar_area, frame.buffer_mut());
+        } else if self.ui_state.diff_view.is_some() {
+            render_cursor_position = main_area.as_position();
+            self.render_status_bar(status_bar_area, frame.buffer_mut());
         } else {
             render_cursor_position = self.ui_state.calculate_cursor_position(
                 CursorCalculationMode::Buffer,
@@ -134,6 +299,17 @@ impl App {
             self.render_status_bar(status_bar_area, frame.buffer_mut());
         }
 
+        // A picker is shown over everything else
+        if let Some(picker) = self.ui_state.picker.as_mut() {
+            PickerWidget::default().render(frame.area(), frame.buffer_mut(), picker);
+        }
+        if let Some(ref picker) = self.ui_state.picker {
+            let query_area = Picker::<PickerItem>::query_area(frame.area());
+            render_cursor_position = self
+                .ui_state
+                .calculate_cursor_for_file_input(&picker.query, query_area);
+        }
+
         self.render_cursor(frame, render_cursor_position);
     }
 
@@ -145,8 +321,10 @@ impl App {
 
         // if a file input is rendered in the status bar, an additional border
         // is rendered
-        let status_bar_height = if file_input_open || search_input_open {
-            3
+        let status_bar_height = if self.ui_state.replace_input.is_some() {
+            2 * SEARCH_INPUT_HEIGHT
+        } else if file_input_open || search_input_open {
+            SEARCH_INPUT_HEIGHT
         } else {
             2
         };
@@ -167,8 +345,73 @@ impl App {
         let contents = self.backend.current_buffer_contents();
         let cursor = self.backend.cursor_position();
 
+        // The scrollbar takes up the rightmost column of the area
+        let show_scrollbar =
+            self.backend.show_scrollbar() && self.backend.current_buffer().is_some();
+        let buffer_area = if show_scrollbar {
+            Rect {
+                width: area.width.saturating_sub(1),
+                ..area
+            }
+        } else {
+            area
+        };
+
+        self.ui_state.buffer_state.line_numbers =
+            self.backend.show_line_numbers() && self.backend.current_buffer().is_some();
+        self.ui_state.buffer_state.relative_line_numbers = self.ui_state.relative_line_numbers;
+        self.ui_state.buffer_state.line_marks = self.backend.current_buffer_line_marks();
+        self.ui_state.buffer_state.selection = self
+            .backend
+            .selection()
+            .map(|selection| selection.to_highlight());
+        // While typing a replacement, the focused match is the one replaced next
+        let replacing = self
+            .ui_state
+            .replace_input
+            .as_ref()
+            .is_some_and(|replace_input| replace_input.is_focused);
+        let highlight_state = &mut self.ui_state.buffer_state.highlight_state;
+        highlight_state.set_replace_target(replacing.then_some(highlight_state.focused));
         let widget = BufferDisplayWidget::new(&contents, cursor);
-        widget.render(area, buf, &mut self.ui_state.buffer_state);
+        widget.render(buffer_area, buf, &mut self.ui_state.buffer_state);
+
+        if let Some((start, end)) = self.backend.matching_tag() {
+            self.ui_state
+                .buffer_state
+                .render_matching_tag(buffer_area, buf, start, end);
+        }
+
+        if show_scrollbar {
+            self.render_scrollbar(area, buf);
+        }
+    }
+
+    /// Render a vertical scrollbar at the right edge of a given Rect, reflecting
+    /// the position of the displayed part of the buffer in all of its lines
+    fn render_scrollbar(&self, area: Rect, buf: &mut ratatui::prelude::Buffer) {
+        let mut state = ScrollbarState::new(self.backend.line_count())
+            .position(self.ui_state.buffer_state.offset.y)
+            .viewport_content_length(area.height as usize);
+
+        Scrollbar::new(ScrollbarOrientation::VerticalRight).render(area, buf, &mut state);
+
+        // Show where the search results are in the whole buffer
+        self.ui_state
+            .buffer_state
+            .highlight_state
+            .render_scrollbar_markers(self.backend.line_count(), area, buf);
+    }
+
+    /// Render the bytes of the currently opened buffer as a hex dump in a given Rect
+    fn render_hex_view(&mut self, area: Rect, buf: &mut ratatui::prelude::Buffer) {
+        let state = self
+            .ui_state
+            .hex_view
+            .as_mut()
+            .expect("Hex view is rendered only when open");
+
+        HexDumpWidget.render(area, buf, state);
     }
 
     fn render_welcome_banner(&self, area: Rect, buf: &mut ratatui::prelude::Buffer) {
@@ -179,16 +422,26 @@ impl App {
 
     /// Render the status bar in a given Rect
     fn render_status_bar(&self, area: Rect, buf: &mut ratatui::prelude::Buffer) {
-        let filename = self.backend.current_buffer_filename();
-        let is_modified = self.backend.is_current_buffer_modified();
-
-        let indicator = if is_modified { "*" } else { "" };
-        let text_widget = Text::from(format!("{}{}", filename, indicator));
+        let fields = StatusBarFields {
+            state: self.backend.editor_state_summary(),
+            unsaved_changes: self.ui_state.unsaved_changes,
+        };
+        let (left, right) = fields.format(self.backend.status_bar_format());
+        let text_widget = Text::from(left);
 
         let paragraph_widget = Paragraph::new(text_widget).wrap(Wrap { trim: false });
         let block_widget = paragraph_widget.block(Block::default().borders(Borders::TOP));
 
         block_widget.render(area, buf);
+
+        // A message takes the place of the right-aligned part of the status bar
+        let right = self.ui_state.status_message.clone().unwrap_or(right);
+        if !right.is_empty() {
+            Paragraph::new(right)
+                .alignment(Alignment::Right)
+                .block(Block::default().borders(Borders::TOP))
+                .render(area, buf);
+        }
     }
 
     /// Render the cursor in a given position
@@ -210,7 +463,12 @@ impl App {
 
     /// Render the search input in a given Rect
     fn render_search_input(&mut self, area: Rect, buf: &mut ratatui::prelude::Buffer) {
-        SearchInput::default().render(
+        SearchInput::new(
+            self.ui_state.status_message.clone(),
+            self.ui_state.search_match_count,
+            self.ui_state.search_options,
+        )
+        .render(
             area,
             buf,
             self.ui_state
@@ -222,10 +480,59 @@ impl App {
 
     fn open_search_input(&mut self, contents: &str) {
         self.ui_state.search_input = Some(contents.into());
+        self.ui_state.replace_input = None;
+        self.ui_state.search_match_count = None;
+    }
+
+    /// Moves the cursor to the next or previous match of the last searched query
+    /// without opening the search input, wrapping around the buffer
+    fn search_again(&mut self, forward: bool) {
+        let Some(query) = self.ui_state.last_search_query.clone() else {
+            self.ui_state.status_message = Some("No previous search".to_string());
+            return;
+        };
+        let Some(cursor) = self.backend.cursor_position() else {
+            return;
+        };
+        let results = self
+            .backend
+            .search_in_current_buffer(&query, self.ui_state.search_options)
+            .unwrap_or_default();
+        let starts: Vec<_> = results.iter().map(|result| result.start).collect();
+        let (Some(first), Some(last)) = (starts.first(), starts.last()) else {
+            self.ui_state.status_message = Some(format!("No matches for {query}"));
+            return;
+        };
+
+        let cursor = (cursor.line, cursor.offset);
+        let found = if forward {
+            starts
+                .iter()
+                .find(|start| (start.line, start.offset) > cursor)
+        } else {
+            starts
+                .iter()
+                .rev()
+                .find(|start| (start.line, start.offset) < cursor)
+        };
+        let target = match found {
+            Some(start) => *start,
+            None if forward => {
+                self.ui_state.status_message = Some(SEARCH_HIT_BOTTOM.to_string());
+                *first
+            }
+            None => {
+                self.ui_state.status_message = Some(SEARCH_HIT_TOP.to_string());
+                *last
+            }
+        };
+        self.backend.move_cursor_to(target);
     }
 
     fn close_search_input(&mut self) {
         self.ui_state.search_input = None;
+        self.ui_state.replace_input = None;
+        self.ui_state.search_match_count = None;
     }
 
     /// Open a file input with the given contents and store it in UIState
@@ -238,13 +545,94 @@ impl App {
         self.ui_state.file_input = None;
     }
 
+    /// Wait for an event and handle it along with all the events which are
+    /// already pending, e.g. repeats of a held key, so that they are applied
+    /// in bulk before a single redraw
     fn handle_events(&mut self) -> io::Result<()> {
-        match event::read()? {
+        let mut events = vec![event::read()?];
+        while events.len() < MAX_BATCHED_EVENTS && event::poll(Duration::ZERO)? {
+            events.push(event::read()?);
+        }
+
+        self.handle_event_batch(events)
+    }
+
+    /// Handle the events in order, stopping early if one of them quits the app
+    fn handle_event_batch(&mut self, events: Vec<Event>) -> io::Result<()> {
+        for event in events {
+            if self.exit {
+                break;
+            }
+            self.handle_event(event)?;
+        }
+
+        // The unsaved changes are only computed if they're shown
+        if self.backend.status_bar_format().contains("{changes}") {
+            self.ui_state.unsaved_changes_stale = true;
+        }
+        Ok(())
+    }
+
+    fn handle_event(&mut self, event: Event) -> io::Result<()> {
+        match event {
             Event::Key(key) => self.handle_key_event(key),
+            Event::Paste(text) => self.handle_paste(&text),
             _ => Ok(()),
         }
     }
 
+    /// Handle text pasted into the terminal, which arrives at once with bracketed
+    /// paste. It's typed into the open input, or inserted into the buffer as a
+    /// single undo step.
+    fn handle_paste(&mut self, text: &str) -> Result<(), io::Error> {
+        self.ui_state.status_message = None;
+        // Terminals may send line breaks as carriage returns
+        let text = text.replace("\r\n", "\n").replace('\r', "\n");
+
+        if let Some(picker) = self.ui_state.picker.as_mut() {
+            for ch in text.chars().filter(|&ch| ch != '\n') {
+                picker.handle(tui_input::InputRequest::InsertChar(ch));
+            }
+            return Ok(());
+        }
+
+        // Inputs hold a single line, but the search and replace inputs
+        // understand escaped line breaks
+        let escaped = text.replace('\\', "\\\\").replace('\n', "\\n");
+        let (input, text) = match (
+            self.ui_state.file_input.as_mut(),
+            self.ui_state.replace_input.as_mut(),
+            self.ui_state.search_input.as_mut(),
+        ) {
+            (Some(file_input), _, _) => (&mut file_input.input, text.replace('\n', "")),
+            (_, Some(replace_input), _) if replace_input.is_focused => {
+                (&mut replace_input.input, escaped)
+            }
+            (_, _, Some(search_input)) => (search_input, escaped),
+            _ => return self.paste_into_buffer(&text),
+        };
+        for ch in text.chars() {
+            input.handle(tui_input::InputRequest::InsertChar(ch));
+        }
+        Ok(())
+    }
+
+    /// Insert pasted text into the current buffer as it is, unless it can't be edited
+    fn paste_into_buffer(&mut self, text: &str) -> Result<(), io::Error> {
+        let read_only_view = self.ui_state.hex_view.is_some() || self.ui_state.diff_view.is_some();
+        if read_only_view || self.backend.current_buffer().is_none() {
+            return Ok(());
+        }
+        if self.backend.is_current_buffer_read_only() {
+            self.ui_state.status_message = Some(BUFFER_READ_ONLY.to_string());
+            return Ok(());
+        }
+        if self.ask_for_edit_confirmation(PendingEdit::Paste(text.to_string())) {
+            return Ok(());
+        }
+        self.backend.paste(text).map_err(io::Error::other)
+    }
+
     fn handle_key_event(&mut self, event: KeyEvent) -> Result<(), io::Error> {
         if let event::KeyEventKind::Press = event.kind {
             return self.handle_key_press(event);
@@ -252,9 +640,140 @@ impl App {
         Ok(())
     }
 
+    /// Try to handle the key press using the open picker. Keys bound to an
+    /// action of the picker move the selection, mark items, choose them or close
+    /// the picker, and others edit the query.
+    /// Returns a boolean indicating whether the event has been handled or not.
+    fn try_handle_key_press_with_picker(&mut self, key: KeyEvent) -> bool {
+        let action = self.backend.get_picker_keymap(&key.into());
+
+        let Some(picker) = self.ui_state.picker.as_mut() else {
+            return false;
+        };
+
+        match action {
+            Some(InputAction::Confirm) => {
+                let chosen = picker.confirm();
+                self.ui_state.picker = None;
+                for action in chosen {
+                    self.handle_picker_action(action);
+                }
+            }
+            Some(InputAction::Cancel) => self.ui_state.picker = None,
+            Some(InputAction::ToggleIgnoredFiles) => {
+                if let Some(included) = picker.includes_ignored_files {
+                    let query = picker.query.value().to_string();
+                    self.open_file_picker(!included);
+                    if let Some(picker) = self.ui_state.picker.as_mut() {
+                        picker.set_query(&query);
+                    }
+                }
+            }
+            Some(InputAction::Next) => picker.move_selection_down(),
+            Some(InputAction::Previous) => picker.move_selection_up(),
+            Some(InputAction::ToggleMark) => picker.toggle_mark(),
+            _ => {
+                if let Some(request) = Self::key_event_to_input_request(key) {
+                    picker.handle(request);
+                }
+            }
+        }
+        true
+    }
+
+    /// Do what was chosen in a picker. Of several chosen files, the last
+    /// one opened stays in view.
+    fn handle_picker_action(&mut self, action: PickerAction) {
+        match action {
+            PickerAction::OpenFile(path) => {
+                let path = self.backend.cwd().join(path);
+                self.open_file_from_path(path, false);
+            }
+            PickerAction::OpenFileAtLine(path, line) => {
+                let path = self.backend.cwd().join(path);
+                match self.backend.open_file(&path, line, 0) {
+                    Ok(()) => self.report_opened_buffer_state(),
+                    Err(err) => self.ui_state.status_message = Some(err.to_string()),
+                }
+            }
+            PickerAction::FocusBuffer(id) => {
+                self.backend.switch_to_buffer(id);
+            }
+        }
+    }
+
+    /// Open a picker listing the files in the working directory, narrowed
+    /// down by fuzzy matching their paths. Hidden and ignored files are only
+    /// listed if `include_ignored` is set.
+    fn open_file_picker(&mut self, include_ignored: bool) {
+        let items = self
+            .backend
+            .files_in_cwd(include_ignored)
+            .into_iter()
+            .map(|file| PickerItem {
+                action: PickerAction::OpenFile(PathBuf::from(&file)),
+                label: file,
+            })
+            .collect();
+        let title = if include_ignored {
+            "Find files (including hidden and ignored)"
+        } else {
+            "Find files"
+        };
+        self.open_picker(title, items);
+        if let Some(picker) = self.ui_state.picker.as_mut() {
+            picker.includes_ignored_files = Some(include_ignored);
+        }
+    }
+
+    /// Open a picker listing the open buffers, with a `*` after the names of
+    /// those with unsaved changes. The current buffer is listed last.
+    fn open_buffer_picker(&mut self) {
+        let items = self
+            .backend
+            .open_buffers()
+            .into_iter()
+            .map(|entry| PickerItem {
+                label: format!("{}{}", entry.name, if entry.is_modified { "*" } else { "" }),
+                action: PickerAction::FocusBuffer(entry.id),
+            })
+            .collect();
+        self.open_picker("Buffers", items);
+    }
+
+    /// Open a picker listing the lines of the files in the working directory
+    /// which contain the query, labelled with the path and the line number
+    fn open_text_picker(&mut self) {
+        let text_search = self.backend.text_search_in_cwd();
+        let search = move |query: &str| {
+            text_search(query)
+                .into_iter()
+                .map(|(path, index, line)| PickerItem {
+                    label: format!("{path}:{}: {}", index + 1, line.trim()),
+                    action: PickerAction::OpenFileAtLine(PathBuf::from(path), index),
+                })
+                .collect()
+        };
+        self.ui_state.picker = Some(Picker::new("Find text", search, picker_item_actions));
+    }
+
+    /// Open a picker of the given items, narrowed down by fuzzy matching their labels
+    fn open_picker(&mut self, title: &'static str, items: Vec<PickerItem>) {
+        let search = move |query: &str| {
+            items
+                .iter()
+                .filter(|item| fuzzy_matches(query, &item.label))
+                .cloned()
+                .collect()
+        };
+        self.ui_state.picker = Some(Picker::new(title, search, picker_item_actions));
+    }
+
     /// Try to handle the key press using a file input. Returns a boolean
     /// indicating whether the event has been handled or not.
     fn try_handle_key_press_with_file_input(&mut self, key: KeyEvent) -> bool {
+        let action = self.backend.get_file_input_keymap(&key.into());
+
         // No input means the event can't be handled
         let input = match self.ui_state.file_input.as_mut() {
             Some(input) => input,
@@ -262,14 +781,32 @@ impl App {
         };
 
         // Perform the corresponding operation and close the input
-        if (key.code, key.modifiers) == (KeyCode::Enter, KeyModifiers::NONE) {
-            let path = input.to_path();
+        if action == Some(InputAction::Confirm) {
+            let path = self.backend.resolve_path(&input.to_path());
             match input.role {
-                FileInputRole::GetOpenPath => self.open_file_from_path(path),
+                // Typing in a path is the way to create a new file
+                FileInputRole::GetOpenPath => self.open_file_from_path(path, true),
                 FileInputRole::GetSavePath => {
                     self.backend.bind_current_buffer_to_path(path);
                     self.handle_save_operation();
                 }
+                FileInputRole::GetDiffPath => self.open_diff_view(path),
+                FileInputRole::GetRevisionPath => {
+                    let spec = input.input.to_string();
+                    self.open_file_at_revision(&spec);
+                }
+                FileInputRole::GetLineFilter(mode) => {
+                    let pattern = input.input.to_string();
+                    if let Err(err) = self.backend.filter_selection_lines(&pattern, mode) {
+                        self.ui_state.status_message = Some(err.to_string());
+                    }
+                }
+                FileInputRole::GetCodePoint => {
+                    let code_point = input.input.to_string();
+                    if let Err(err) = self.backend.insert_code_point(&code_point) {
+                        self.ui_state.status_message = Some(err.to_string());
+                    }
+                }
             }
 
             self.close_file_input();
@@ -277,11 +814,25 @@ impl App {
         }
 
         // Close the input
-        if (key.code, key.modifiers) == (KeyCode::Esc, KeyModifiers::NONE) {
+        if action == Some(InputAction::Cancel) {
             self.close_file_input();
             return true;
         }
 
+        if action == Some(InputAction::Complete) {
+            // Only paths can be completed, unlike e.g. a revision or a regex
+            if matches!(
+                input.role,
+                FileInputRole::GetOpenPath
+                    | FileInputRole::GetSavePath
+                    | FileInputRole::GetDiffPath
+            ) {
+                let completed = self.backend.complete_path(&input.input.to_string());
+                input.input = completed.into();
+            }
+            return true;
+        }
+
         // Try to create a request to the file input and handle it
         match Self::key_event_to_input_request(key) {
             Some(request) => {
@@ -296,6 +847,8 @@ impl App {
     /// Handles searching, toggling through found items, and quitting.
     /// Returns a boolean indicating whether the event has been handled or not.
     fn try_handle_key_press_with_search_input(&mut self, key: KeyEvent) -> bool {
+        let action = self.backend.get_search_keymap(&key.into());
+
         // No input means the event can't be handled
         let input = match self.ui_state.search_input.as_mut() {
             Some(input) => input,
@@ -303,28 +856,31 @@ impl App {
         };
 
         // Perform the corresponding operation and close the input
-        if (key.code, key.modifiers) == (KeyCode::Enter, KeyModifiers::NONE) {
+        if action == Some(InputAction::Confirm) {
             let query = input.to_string();
-            let highlights = self
-                .backend
-                .search_in_current_buffer(&query)
-                .unwrap_or_else(|err| {
-                    eprintln!("Error searching in buffer: {}", err);
-                    vec![]
-                });
-
-            if highlights.is_empty() {
-                self.ui_state.clear_highlights();
-                return true;
-            }
-            self.ui_state.update_highlights(highlights);
-            self.backend
-                .move_cursor_to(self.ui_state.focused_highlight_position());
+            self.search_for_query(query);
+            return true;
+        }
 
+        // Toggling an option searches again if the query has been searched for
+        if matches!(
+            action,
+            Some(InputAction::ToggleCaseInsensitive | InputAction::ToggleWholeWord)
+        ) {
+            let options = &mut self.ui_state.search_options;
+            if action == Some(InputAction::ToggleCaseInsensitive) {
+                options.case_insensitive = !options.case_insensitive;
+            } else {
+                options.whole_word = !options.whole_word;
+            }
+            if self.ui_state.search_match_count.is_some() {
+                let query = input.to_string();
+                self.search_for_query(query);
+            }
             return true;
         }
 
-        if (key.code, key.modifiers) == (KeyCode::Right, KeyModifiers::NONE)
+        if action == Some(InputAction::Next)
             && !self
                 .ui_state
                 .buffer_state
@@ -332,13 +888,15 @@ impl App {
                 .highlights
                 .is_empty()
         {
-            self.ui_state.focus_next_highlight();
+            if self.ui_state.focus_next_highlight() {
+                self.ui_state.status_message = Some(SEARCH_HIT_BOTTOM.to_string());
+            }
             self.backend
                 .move_cursor_to(self.ui_state.focused_highlight_position());
             return true;
         }
 
-        if (key.code, key.modifiers) == (KeyCode::Left, KeyModifiers::NONE)
+        if action == Some(InputAction::Previous)
             && !self
                 .ui_state
                 .buffer_state
@@ -346,12 +904,16 @@ impl App {
                 .highlights
                 .is_empty()
         {
-            self.ui_state.focus_prev_highlight();
+            if self.ui_state.focus_prev_highlight() {
+                self.ui_state.status_message = Some(SEARCH_HIT_TOP.to_string());
+            }
+            self.backend
+                .move_cursor_to(self.ui_state.focused_highlight_position());
             return true;
         }
 
         // Close the input
-        if (key.code, key.modifiers) == (KeyCode::Esc, KeyModifiers::NONE) {
+        if action == Some(InputAction::Cancel) {
             self.ui_state.clear_highlights();
             self.close_search_input();
             return true;
@@ -367,12 +929,136 @@ impl App {
         }
     }
 
-    /// Open a file from a given path
-    fn open_file_from_path(&mut self, path: PathBuf) {
+    /// Handles replacing the matches of the searched query while the replacement is
+    /// being typed in. Moving between the matches is left to the search input.
+    /// Returns a boolean indicating whether the event has been handled or not.
+    fn try_handle_key_press_with_replace_input(&mut self, key: KeyEvent) -> bool {
+        let action = self.backend.get_search_keymap(&key.into());
+
+        let (Some(query), Some(replace_input)) = (
+            self.ui_state.search_input.as_ref(),
+            self.ui_state.replace_input.as_mut(),
+        ) else {
+            return false;
+        };
+        if !replace_input.is_focused {
+            return false;
+        }
+        let query = query.to_string();
+        let replacement = replace_input.input.to_string();
+
+        // Replace the focused match and move on to the next one
+        if action == Some(InputAction::Confirm) {
+            self.backend
+                .move_cursor_to(self.ui_state.focused_highlight_position());
+            let result = self.backend.replace_in_current_buffer(
+                &query,
+                &replacement,
+                self.ui_state.search_options,
+                false,
+            );
+            if let Err(err) = result {
+                self.ui_state.status_message = Some(err.to_string());
+            }
+            self.search_for_query(query);
+            return true;
+        }
+
+        if action == Some(InputAction::ReplaceAll) {
+            let result = self.backend.replace_in_current_buffer(
+                &query,
+                &replacement,
+                self.ui_state.search_options,
+                true,
+            );
+            self.ui_state.status_message = Some(match result {
+                Ok(count) => format!("Replaced {count} matches"),
+                Err(err) => err.to_string(),
+            });
+            self.ui_state.clear_highlights();
+            self.close_search_input();
+            return true;
+        }
+
+        if matches!(action, Some(InputAction::Cancel)) {
+            self.ui_state.clear_highlights();
+            self.close_search_input();
+            return true;
+        }
+
+        if matches!(action, Some(InputAction::Next | InputAction::Previous)) {
+            return false;
+        }
+
+        match Self::key_event_to_input_request(key) {
+            Some(request) => {
+                replace_input.input.handle(request);
+                true
+            }
+            None => false,
+        }
+    }
+
+    /// Searches for `query` in the current buffer with the options set in the search
+    /// input, highlights the results and moves the cursor to the first one after it
+    fn search_for_query(&mut self, query: String) {
+        if !query.is_empty() {
+            self.ui_state.last_search_query = Some(query.clone());
+        }
+        let options = self.ui_state.search_options;
+        let highlights = self
+            .backend
+            .search_around_cursor(&query, options)
+            .unwrap_or_else(|err| {
+                eprintln!("Error searching in buffer: {}", err);
+                vec![]
+            });
+        let match_count = self.backend.count_matches(&query, options);
+        self.ui_state.search_match_count = Some(match_count);
+
+        // Once there's something to replace, the replacement can be typed in
+        if let Some(replace_input) = self.ui_state.replace_input.as_mut() {
+            replace_input.is_focused = !highlights.is_empty();
+        }
+
+        if highlights.is_empty() {
+            self.ui_state.clear_highlights();
+            return;
+        }
+
+        // Start from the first match at or after the cursor, wrapping
+        // around to the first one if there is none
+        let first_from_cursor = self.backend.first_result_from_cursor(&highlights);
+        self.ui_state.update_highlights(highlights);
+        let max = self.backend.max_search_highlights();
+        if match_count > max {
+            self.ui_state.status_message = Some(format!("{max}+ matches"));
+        }
+        match first_from_cursor {
+            Some(index) => self.ui_state.focus_highlight(index),
+            None => self.ui_state.status_message = Some(SEARCH_HIT_BOTTOM.to_string()),
+        }
         self.backend
-            .create_and_open_file(&path)
-            // TODO: display message in the UI
-            .expect("Error opening file!");
+            .move_cursor_to(self.ui_state.focused_highlight_position());
+    }
+
+    /// Open the file at `path`, creating it first if it's missing and
+    /// `create_if_missing` is set. An error is shown in the status bar.
+    fn open_file_from_path(&mut self, path: PathBuf, create_if_missing: bool) {
+        let result = if create_if_missing {
+            self.backend.create_and_open_file(&path)
+        } else {
+            self.backend.open_existing_file(&path)
+        };
+
+        match result {
+            Ok(()) => self.report_opened_buffer_state(),
+            Err(err @ PikeError::FileTooLarge(_, _)) => {
+                self.ui_state.status_message = Some(format!("{err}. {LARGE_FILE_PROMPT}"));
+                self.ui_state.awaiting_large_file = Some(path);
+            }
+            Err(err) => self.ui_state.status_message = Some(err.to_string()),
+        }
     }
 
     /// Try to convert a given key event to an InputRequest to be sent to a tui_input::Input
@@ -396,74 +1082,377 @@ impl App {
     }
 
     fn handle_key_press(&mut self, key: KeyEvent) -> Result<(), io::Error> {
+        // Messages are only displayed until the next key press
+        self.ui_state.status_message = None;
+
+        if self.try_handle_key_press_with_picker(key) {
+            return Ok(());
+        }
+
         if self.try_handle_key_press_with_file_input(key) {
             return Ok(());
         }
 
+        if self.try_handle_key_press_with_replace_input(key) {
+            return Ok(());
+        }
+
         if self.try_handle_key_press_with_search_input(key) {
             return Ok(());
         }
 
+        if self.try_handle_key_press_awaiting_surround(key) {
+            return Ok(());
+        }
+
+        if self.try_handle_key_press_awaiting_edit_confirmation(key)? {
+            return Ok(());
+        }
+
+        if self.try_handle_key_press_awaiting_large_file(key) {
+            return Ok(());
+        }
+
+        if self.try_handle_welcome_keybind(key) {
+            return Ok(());
+        }
+
+        if self.try_handle_key_press_in_modal_editing(key) {
+            return Ok(());
+        }
+
         if self.try_handle_keybind(key) {
             return Ok(());
         }
 
+        // The hex view is read-only, so it consumes every other key
+        if self.ui_state.hex_view.is_some() {
+            self.handle_key_press_in_hex_view(key);
+            return Ok(());
+        }
+
+        // So is the diff view
+        if self.ui_state.diff_view.is_some() {
+            self.handle_key_press_in_diff_view(key);
+            return Ok(());
+        }
+
         if !key.modifiers.contains(KeyModifiers::CONTROL) && self.try_handle_input_key(key)? {
             return Ok(());
         }
 
-        self.try_handle_navigation(key);
+        if !self.try_handle_navigation(key) {
+            self.report_unbound_key(key);
+        }
 
         Ok(())
     }
 
-    fn try_handle_navigation(&mut self, key: KeyEvent) -> bool {
-        match key.code {
-            KeyCode::Left => {
-                if key.modifiers.contains(KeyModifiers::CONTROL) {
-                    self.backend.move_cursor_left_by_word();
-                } else {
-                    self.backend.move_cursor_left();
-                }
-                true
-            }
-            KeyCode::Right => {
-                if key.modifiers.contains(KeyModifiers::CONTROL) {
-                    self.backend.move_cursor_right_by_word();
-                } else {
-                    self.backend.move_cursor_right();
-                }
-                true
-            }
-            KeyCode::Up => {
-                self.backend.move_cursor_up();
-                true
-            }
-            KeyCode::Down => {
-                self.backend.move_cursor_down();
-                true
-            }
-            KeyCode::End => {
-                self.backend.move_cursor_to_end_of_line();
-                true
-            }
-            KeyCode::Home => {
-                self.backend.move_cursor_to_start_of_line();
-                true
-            }
-            _ => false,
+    /// Reports a ctrl-modified key which nothing handled in the status bar,
+    /// if enabled, as such a key press otherwise silently does nothing
+    fn report_unbound_key(&mut self, key: KeyEvent) {
+        if key.modifiers.contains(KeyModifiers::CONTROL) && self.backend.reports_unbound_keys() {
+            let shortcut = KeyShortcut::from(key).to_display_string();
+            self.ui_state.status_message = Some(format!("{KEY_NOT_BOUND}: {shortcut}"));
         }
     }
 
-    fn exit(&mut self) {
-        self.exit = true;
+    /// If a surround operation is waiting for its character, surround the
+    /// selection with the pair matching the pressed character. Any other key
+    /// cancels the operation.
+    fn try_handle_key_press_awaiting_surround(&mut self, key: KeyEvent) -> bool {
+        if !self.ui_state.awaiting_surround {
+            return false;
+        }
+        self.ui_state.awaiting_surround = false;
+
+        if let (KeyCode::Char(ch), KeyModifiers::NONE | KeyModifiers::SHIFT) =
+            (key.code, key.modifiers)
+        {
+            let (open, close) = surround_pair(ch);
+            self.backend.surround_selection(open, close);
+        }
+        true
     }
 
-    /// Tries to match the given key event to a registered keybind and handle it.
-    fn try_handle_keybind(&mut self, key: KeyEvent) -> bool {
-        match self.backend.get_keymap(&key.into()).cloned() {
-            Some(op) => {
-                self.handle_operation(&op);
+    /// Ask for the regex to filter the selected lines with, if there's a selection
+    fn open_line_filter_input(&mut self, mode: LineFilterMode) {
+        if self.backend.selection().is_some() {
+            self.open_file_input("", FileInputRole::GetLineFilter(mode));
+        }
+    }
+
+    /// Wait for the character to surround the selection with, if there's one
+    fn start_surround(&mut self) {
+        if self.backend.selection().is_some() {
+            self.ui_state.awaiting_surround = true;
+            self.ui_state.status_message = Some(SURROUND_PROMPT.to_string());
+        }
+    }
+
+    /// If the first edit of the current buffer is waiting for confirmation,
+    /// allow editing it and make the pending edit if 'y' is pressed.
+    /// Any other key declines.
+    fn try_handle_key_press_awaiting_edit_confirmation(
+        &mut self,
+        key: KeyEvent,
+    ) -> Result<bool, io::Error> {
+        let Some(pending) = self.ui_state.awaiting_edit_confirmation.take() else {
+            return Ok(false);
+        };
+        if (key.code, key.modifiers) != (KeyCode::Char('y'), KeyModifiers::NONE) {
+            return Ok(true);
+        }
+
+        self.backend.confirm_current_buffer_edits();
+        match pending {
+            PendingEdit::Key(key) => {
+                self.try_handle_input_key(key)?;
+            }
+            PendingEdit::Operation(op) => self.handle_operation(&op),
+            PendingEdit::Paste(text) => self.paste_into_buffer(&text)?,
+        }
+        Ok(true)
+    }
+
+    /// If a large file is waiting to be opened, open it if 'y' is pressed.
+    /// Any other key declines.
+    fn try_handle_key_press_awaiting_large_file(&mut self, key: KeyEvent) -> bool {
+        let Some(path) = self.ui_state.awaiting_large_file.take() else {
+            return false;
+        };
+
+        if (key.code, key.modifiers) == (KeyCode::Char('y'), KeyModifiers::NONE) {
+            match self.backend.open_large_file(&path) {
+                Ok(()) => self.report_opened_buffer_state(),
+                Err(err) => self.ui_state.status_message = Some(err.to_string()),
+            }
+        }
+        true
+    }
+
+    /// Ask whether to edit the current buffer if it requires confirming its
+    /// first edit. Returns whether `pending` has to wait for the answer.
+    fn ask_for_edit_confirmation(&mut self, pending: PendingEdit) -> bool {
+        if !self.backend.current_buffer_needs_edit_confirmation() {
+            return false;
+        }
+        self.ui_state.awaiting_edit_confirmation = Some(pending);
+        self.ui_state.status_message = Some(EDIT_CONFIRMATION_PROMPT.to_string());
+        true
+    }
+
+    /// Move the cursor of the hex view by bytes according to the pressed key
+    fn handle_key_press_in_hex_view(&mut self, key: KeyEvent) {
+        let Some(hex_view) = self.ui_state.hex_view.as_mut() else {
+            return;
+        };
+        let len = hex_view.bytes.len();
+
+        match key.code {
+            KeyCode::Left => hex_view.move_left(),
+            KeyCode::Right => hex_view.move_right(len),
+            KeyCode::Up => hex_view.move_up(),
+            KeyCode::Down => hex_view.move_down(len),
+            KeyCode::Home => hex_view.move_to_start_of_row(),
+            KeyCode::End => hex_view.move_to_end_of_row(len),
+            _ => {}
+        }
+    }
+
+    /// Open the hex view of the current buffer or close it if it's open
+    fn toggle_hex_view(&mut self) {
+        if self.ui_state.hex_view.is_some() {
+            self.ui_state.hex_view = None;
+        } else if self.backend.current_buffer().is_some() {
+            self.ui_state.diff_view = None;
+            // The view is read-only, so the bytes are only read once when opened
+            let bytes = self.backend.current_buffer_bytes();
+            self.ui_state.hex_view = Some(HexViewState::new(bytes));
+        }
+    }
+
+    /// Scroll the diff view according to the pressed key or close it on escape
+    fn handle_key_press_in_diff_view(&mut self, key: KeyEvent) {
+        let Some(diff_view) = self.ui_state.diff_view.as_mut() else {
+            return;
+        };
+
+        match key.code {
+            KeyCode::Up => diff_view.scroll_up(),
+            KeyCode::Down => diff_view.scroll_down(),
+            KeyCode::Esc => self.ui_state.diff_view = None,
+            _ => {}
+        }
+    }
+
+    /// Ask for the path of a file to diff the current buffer against,
+    /// or close the diff view if it's open
+    fn toggle_diff_view(&mut self) {
+        if self.ui_state.diff_view.is_some() {
+            self.ui_state.diff_view = None;
+        } else if self.backend.current_buffer().is_some() {
+            self.open_file_input("", FileInputRole::GetDiffPath);
+        }
+    }
+
+    /// Open the diff of the current buffer against the file at `path`
+    fn open_diff_view(&mut self, path: PathBuf) {
+        match self.backend.diff_against(&path) {
+            Ok(lines) => {
+                self.ui_state.hex_view = None;
+                self.ui_state.diff_view = Some(DiffViewState::new(lines));
+            }
+            Err(err) => self.ui_state.status_message = Some(err.to_string()),
+        }
+    }
+
+    /// Opens the scratch buffer at startup if it's enabled, which is only done
+    /// when no file was given. Failing to open it is reported, but doesn't stop
+    /// the editor from starting.
+    fn open_scratch_buffer(&mut self) {
+        if !self.backend.opens_scratch_buffer() {
+            return;
+        }
+        let result = self.backend.scratch_file_path().and_then(|path| {
+            self.backend
+                .open_scratch_file(&path)
+                .map_err(|err| err.to_string())
+        });
+        if let Err(err) = result {
+            self.ui_state.status_message = Some(err);
+        }
+    }
+
+    /// Let the user know about anything unusual in a newly opened buffer - that it
+    /// can't be edited or that the hex view is available because it's binary
+    fn report_opened_buffer_state(&mut self) {
+        if self.backend.is_current_buffer_read_only() {
+            self.ui_state.status_message = Some(BUFFER_READ_ONLY.to_string());
+        } else if self.ui_state.hex_view.is_none() && self.backend.is_current_buffer_binary() {
+            self.ui_state.status_message = Some(BINARY_FILE_DETECTED.to_string());
+        } else if self.backend.line_ending_stats().is_mixed() {
+            self.ui_state.status_message = Some(MIXED_LINE_ENDINGS.to_string());
+        }
+    }
+
+    /// Move the cursor according to the pressed key. Holding shift extends
+    /// the selection to the new cursor position, otherwise the selection is cleared.
+    fn try_handle_navigation(&mut self, key: KeyEvent) -> bool {
+        let is_navigation = matches!(
+            key.code,
+            KeyCode::Left
+                | KeyCode::Right
+                | KeyCode::Up
+                | KeyCode::Down
+                | KeyCode::End
+                | KeyCode::Home
+        );
+        if !is_navigation {
+            return false;
+        }
+
+        let selecting = key.modifiers.contains(KeyModifiers::SHIFT);
+        if selecting && self.backend.selection().is_none() {
+            self.backend.start_selection();
+        }
+
+        self.move_cursor(key);
+
+        match self.backend.cursor_position() {
+            Some(pos) if selecting => self.backend.extend_selection_to(pos),
+            _ => self.backend.clear_selection(),
+        }
+        true
+    }
+
+    /// Move the cursor according to a navigation key
+    fn move_cursor(&mut self, key: KeyEvent) {
+        match key.code {
+            KeyCode::Left if key.modifiers.contains(KeyModifiers::CONTROL) => {
+                self.backend.move_cursor_left_by_word()
+            }
+            KeyCode::Left => self.backend.move_cursor_left(),
+            KeyCode::Right if key.modifiers.contains(KeyModifiers::CONTROL) => {
+                self.backend.move_cursor_right_by_word()
+            }
+            KeyCode::Right => self.backend.move_cursor_right(),
+            KeyCode::Up => self.backend.move_cursor_up(),
+            KeyCode::Down => self.backend.move_cursor_down(),
+            KeyCode::End => self.backend.move_cursor_to_end_of_line(),
+            KeyCode::Home => self.backend.move_cursor_to_start_of_line(),
+            _ => {}
+        }
+    }
+
+    fn exit(&mut self) {
+        self.exit = true;
+    }
+
+    /// Tries to match the given key event to a registered keybind and handle it.
+    fn try_handle_keybind(&mut self, key: KeyEvent) -> bool {
+        // Escape always closes the diff view, even if it's bound in the buffer
+        if key.code == KeyCode::Esc && self.ui_state.diff_view.is_some() {
+            return false;
+        }
+        match self.backend.get_keymap(&key.into()).cloned() {
+            Some(op) => {
+                self.handle_operation(&op);
+                true
+            }
+            None => false,
+        }
+    }
+
+    /// Try to handle the key press as a command of modal editing. Escape leaves
+    /// the insert mode, while in the normal mode `i` and `a` enter the insert mode
+    /// before or after the cursor, keys of the normal keymap trigger operations
+    /// and other typed keys do nothing. Returns a boolean indicating whether
+    /// the event has been handled or not.
+    fn try_handle_key_press_in_modal_editing(&mut self, key: KeyEvent) -> bool {
+        let in_buffer = self.backend.current_buffer().is_some()
+            && self.ui_state.hex_view.is_none()
+            && self.ui_state.diff_view.is_none();
+        if !self.backend.modal_editing() || !in_buffer {
+            return false;
+        }
+
+        if !self.backend.is_in_normal_mode() {
+            if key.code == KeyCode::Esc && key.modifiers == KeyModifiers::NONE {
+                self.backend.set_normal_mode(true);
+                return true;
+            }
+            return false;
+        }
+
+        if let Some(op) = self.backend.get_normal_keymap(&key.into()).cloned() {
+            self.handle_operation(&op);
+            return true;
+        }
+        if key.modifiers.contains(KeyModifiers::CONTROL) {
+            return false;
+        }
+        match key.code {
+            KeyCode::Char('i') => self.backend.set_normal_mode(false),
+            KeyCode::Char('a') => {
+                self.backend.move_cursor_right();
+                self.backend.set_normal_mode(false);
+            }
+            KeyCode::Char(_) | KeyCode::Enter | KeyCode::Backspace | KeyCode::Tab => {}
+            _ => return false,
+        }
+        true
+    }
+
+    /// Try to handle the key press using the keymap which is only active
+    /// while no buffer is open, i.e. on the welcome screen
+    fn try_handle_welcome_keybind(&mut self, key: KeyEvent) -> bool {
+        if self.backend.current_buffer().is_some() {
+            return false;
+        }
+        match self.backend.get_welcome_keymap(&key.into()).cloned() {
+            Some(op) => {
+                self.handle_operation(&op);
                 true
             }
             None => false,
@@ -474,24 +1463,29 @@ impl App {
         if self.backend.current_buffer().is_none() {
             return Ok(false);
         }
+        let is_input = matches!(
+            key.code,
+            KeyCode::Char(_) | KeyCode::Enter | KeyCode::Backspace
+        );
+        if self.backend.is_current_buffer_read_only() {
+            if is_input {
+                self.ui_state.status_message = Some(BUFFER_READ_ONLY.to_string());
+            }
+            return Ok(is_input);
+        }
+        if is_input && self.ask_for_edit_confirmation(PendingEdit::Key(key)) {
+            return Ok(true);
+        }
         if let KeyCode::Char(ch) = key.code {
-            self.backend
-                .write_to_current_buffer(&ch.to_string())
-                .map_err(|e| io::Error::new(ErrorKind::Other, e.to_string()))?;
+            self.backend.type_character(ch).map_err(io::Error::other)?;
 
             return Ok(true);
         }
         match key.code {
             KeyCode::Enter => {
                 self.backend
-                    .write_to_current_buffer("\n")
-                    .map_err(|e| io::Error::new(ErrorKind::Other, e.to_string()))?;
-                Ok(true)
-            }
-            KeyCode::Tab => {
-                self.backend
-                    .write_to_current_buffer("    ")
-                    .map_err(|e| io::Error::new(ErrorKind::Other, e.to_string()))?;
+                    .type_character('\n')
+                    .map_err(io::Error::other)?;
                 Ok(true)
             }
             KeyCode::Backspace => {
@@ -503,22 +1497,139 @@ impl App {
     }
 
     fn handle_operation(&mut self, op: &Operation) {
+        // The hex and diff views are read-only
+        let read_only_view = self.ui_state.hex_view.is_some() || self.ui_state.diff_view.is_some();
+        if read_only_view && op.modifies_buffer() {
+            return;
+        }
+        if self.backend.is_current_buffer_read_only() && op.modifies_buffer() {
+            self.ui_state.status_message = Some(BUFFER_READ_ONLY.to_string());
+            return;
+        }
+        if op.modifies_buffer()
+            && self.ask_for_edit_confirmation(PendingEdit::Operation(op.clone()))
+        {
+            return;
+        }
+
         match op {
             Operation::OpenFile => self.open_file_input("", FileInputRole::GetOpenPath),
-            Operation::Quit => self.exit(),
+            Operation::Quit => {
+                if self.backend.restores_session() {
+                    self.save_session();
+                }
+                if self.backend.opens_scratch_buffer() {
+                    if let Ok(path) = self.backend.scratch_file_path() {
+                        // Quitting goes on, so a failure couldn't be reported
+                        let _ = self.backend.save_scratch_file(&path);
+                    }
+                }
+                self.exit();
+            }
+            // Exits right away, without anything which could stop it
+            Operation::ForceQuit => self.exit(),
             Operation::CreateNewBuffer => self.backend.open_new_buffer(),
             Operation::SwitchToPreviousBuffer => self.backend.previous_buffer(),
             Operation::SwitchToNextBuffer => self.backend.next_buffer(),
             Operation::SaveBufferToFile => self.handle_save_operation(),
 
-            Operation::SearchInCurrentBuffer => self.open_search_input(""),
+            Operation::SearchInCurrentBuffer => {
+                let query = self.ui_state.last_search_query.clone().unwrap_or_default();
+                self.open_search_input(&query);
+            }
+            Operation::SearchAndReplaceInCurrentBuffer => {
+                let query = self.ui_state.last_search_query.clone().unwrap_or_default();
+                self.open_search_input(&query);
+                self.ui_state.replace_input = Some(ReplaceInputState::default());
+            }
+            Operation::SearchNext => self.search_again(true),
+            Operation::SearchPrev => self.search_again(false),
 
             Operation::Undo => self.backend.undo(),
             Operation::Redo => self.backend.redo(),
+            Operation::ReflowParagraph => self.backend.reflow_paragraph(),
+            Operation::ToggleLineMark => self.backend.toggle_mark_on_cursor_line(),
+            Operation::ToggleHexView => self.toggle_hex_view(),
+            Operation::Indent => self.backend.indent(),
+            Operation::Surround => self.start_surround(),
+            Operation::TransposeChars => self.backend.transpose_chars(),
+            Operation::TransposeWords => self.backend.transpose_words(),
+            Operation::SaveAll => self.handle_save_all_operation(),
+            Operation::DiffWith => self.toggle_diff_view(),
+            Operation::ToggleReadOnly => self.backend.toggle_current_buffer_read_only(),
+            Operation::ToggleRelativeLineNumbers => {
+                self.ui_state.relative_line_numbers = !self.ui_state.relative_line_numbers;
+            }
+            Operation::NormalizeLineEndings => {
+                let style = self.backend.line_ending();
+                self.backend.normalize_line_endings(style);
+            }
+            Operation::InsertDateTime => {
+                if let Err(err) = self.backend.insert_datetime() {
+                    self.ui_state.status_message = Some(err.to_string());
+                }
+            }
+            Operation::SaveSession => {
+                if self.save_session() {
+                    self.ui_state.status_message = Some(SESSION_SAVED.to_string());
+                }
+            }
+            Operation::RestoreSession => self.restore_session(true),
+            Operation::OpenAtRevision => self.open_file_input("", FileInputRole::GetRevisionPath),
+            Operation::NextParagraph => self.backend.move_to_next_paragraph(),
+            Operation::PrevParagraph => self.backend.move_to_prev_paragraph(),
+            Operation::GoToLastEdit => self.backend.go_to_last_edit(),
+            Operation::DeleteToLineEnd => self.backend.delete_to_line_end(),
+            Operation::DeleteToLineStart => self.backend.delete_to_line_start(),
+            Operation::InsertUnicode => self.open_file_input("", FileInputRole::GetCodePoint),
+            Operation::JumpToMatchingTag => self.backend.jump_to_matching_tag(),
+            Operation::SwitchToAlternateBuffer => self.backend.switch_to_alternate_buffer(),
+            Operation::Copy => self.backend.copy_selection(),
+            Operation::Cut => self.backend.cut_selection(),
+            Operation::ShowHelp => self.backend.show_help(),
+            Operation::FindFilesInCWD => self.open_file_picker(false),
+            Operation::OpenBufferPicker => self.open_buffer_picker(),
+            Operation::FindTextInCWD => self.open_text_picker(),
+            Operation::ClearHighlights => {
+                self.ui_state.clear_highlights();
+                self.backend.clear_selection();
+            }
+            Operation::Paste => {
+                if let Err(err) = self.backend.paste_at_cursor() {
+                    self.ui_state.status_message = Some(err.to_string());
+                }
+            }
+            Operation::PasteCycle => {
+                if let Err(err) = self.backend.paste_cycle() {
+                    self.ui_state.status_message = Some(err.to_string());
+                }
+            }
+            Operation::KeepSelectionMatches => {
+                self.open_line_filter_input(LineFilterMode::KeepMatch)
+            }
+            Operation::RemoveSelectionMatches => {
+                self.open_line_filter_input(LineFilterMode::RemoveMatch)
+            }
+            Operation::ScrollLineRight => {
+                let cursor = self.backend.cursor_position();
+                self.ui_state
+                    .buffer_state
+                    .scroll_right(HORIZONTAL_SCROLL_COLUMNS, cursor);
+            }
+            Operation::ScrollLineLeft => {
+                let cursor = self.backend.cursor_position();
+                self.ui_state
+                    .buffer_state
+                    .scroll_left(HORIZONTAL_SCROLL_COLUMNS, cursor);
+            }
         }
     }
 
     fn handle_save_operation(&mut self) {
+        if self.backend.is_current_buffer_read_only() {
+            self.ui_state.status_message = Some(BUFFER_READ_ONLY.to_string());
+            return;
+        }
         if let Some(path) = self.backend.current_buffer_path() {
             if let Err(err) = self.backend.save_current_buffer() {
                 eprintln!("Failed to save buffer: {}", err);
@@ -528,15 +1639,122 @@ impl App {
             self.open_file_input("", FileInputRole::GetSavePath);
         }
     }
+
+    /// Save the session of the workspace, returning whether it succeeded.
+    /// A failure is shown in the status bar.
+    fn save_session(&mut self) -> bool {
+        let result = self
+            .backend
+            .session_file_path()
+            .and_then(|path| self.backend.save_session(&path));
+        if let Err(err) = &result {
+            self.ui_state.status_message = Some(err.to_string());
+        }
+        result.is_ok()
+    }
+
+    /// Restore the session of the workspace and report files which couldn't be
+    /// reopened. A missing session file is only reported if `report_missing` is set,
+    /// as there's none before the first session in a workspace is saved.
+    fn restore_session(&mut self, report_missing: bool) {
+        let path = match self.backend.session_file_path() {
+            Ok(path) if path.exists() || report_missing => path,
+            Ok(_) => return,
+            Err(err) => {
+                self.ui_state.status_message = Some(err.to_string());
+                return;
+            }
+        };
+
+        let skipped = match self.backend.load_session(&path) {
+            Ok(skipped) => skipped,
+            Err(err) => {
+                self.ui_state.status_message = Some(err.to_string());
+                return;
+            }
+        };
+        let (large, missing): (Vec<_>, Vec<_>) = skipped
+            .into_iter()
+            .partition(|err| matches!(err, PikeError::FileTooLarge(_, _)));
+
+        let mut message = match missing.len() {
+            0 => SESSION_RESTORED.to_string(),
+            count => format!("{SESSION_RESTORED}, {count} missing file(s) skipped"),
+        };
+        // Only one large file can be waiting to be opened, so ask about the first
+        if let Some(err @ PikeError::FileTooLarge(path, _)) = large.first() {
+            message = format!("{message}. {err}. {LARGE_FILE_PROMPT}");
+            self.ui_state.awaiting_large_file = Some(path.clone());
+        }
+        self.ui_state.status_message = Some(message);
+    }
+
+    /// Open a file as of a git revision, given as `<revision>:<path>`,
+    /// in a read-only buffer
+    fn open_file_at_revision(&mut self, spec: &str) {
+        let Some((rev, path)) = spec.split_once(':') else {
+            self.ui_state.status_message = Some(REVISION_PATH_EXPECTED.to_string());
+            return;
+        };
+
+        match self.backend.open_file_from_git(rev, Path::new(path)) {
+            Ok(()) => self.ui_state.status_message = Some(BUFFER_READ_ONLY.to_string()),
+            Err(err) => self.ui_state.status_message = Some(err.to_string()),
+        }
+    }
+
+    /// Save all modified buffers and report how it went. If some of them
+    /// aren't bound to a file, switch to the first one and ask for its path.
+    fn handle_save_all_operation(&mut self) {
+        let results = self.backend.save_all();
+        let saved = results.iter().filter(|result| result.is_ok()).count();
+        let first_error = results.iter().find_map(|result| result.as_ref().err());
+
+        self.ui_state.status_message = Some(match first_error {
+            None => format!("Saved {saved} buffer(s)"),
+            Some(error) => format!(
+                "Saved {saved} buffer(s), {} failed: {error}",
+                results.len() - saved
+            ),
+        });
+
+        if self.backend.select_unbound_modified_buffer() {
+            self.open_file_input("", FileInputRole::GetSavePath);
+        }
+    }
+}
+
+/// Returns the actions of the items chosen in a picker
+fn picker_item_actions(items: Vec<&PickerItem>) -> Vec<PickerAction> {
+    items.into_iter().map(|item| item.action.clone()).collect()
+}
+
+/// Returns the directory to root the workspace at, which is `cwd` if given
+/// and the current working directory otherwise
+fn workspace_root(cwd: Option<&str>) -> Result<PathBuf, String> {
+    let Some(cwd) = cwd else {
+        return env::current_dir().map_err(|_| "Failed to get current working directory".into());
+    };
+
+    let path = PathBuf::from(cwd);
+    if !path.is_dir() {
+        return Err(format!("Not a directory: {cwd}"));
+    }
+    path.canonicalize()
+        .map_err(|e| format!("Failed to resolve {cwd}: {e}"))
 }
 
 #[derive(Parser, Debug)]
 #[command(version, about, long_about=None)]
 pub struct Args {
-    /// The configuration file to use
+    /// The configuration file to use, taking precedence over `PIKE_CONFIG`
     #[arg(short, long, value_name = "FILE")]
     config: Option<String>,
 
+    /// The directory to use as the workspace root instead of the current one
+    #[arg(long, value_name = "DIR")]
+    cwd: Option<String>,
+
     #[arg(value_name = "FILE")]
     file: Option<String>,
 }
@@ -546,27 +1764,34 @@ mod tests {
 
     use std::io::Write;
 
-    use crossterm::event::{KeyCode, KeyEvent, KeyModifiers};
+    use crossterm::event::{Event, KeyCode, KeyEvent, KeyModifiers};
     use insta::assert_snapshot;
     use ratatui::{backend::TestBackend, buffer::Buffer, layout::Rect, Terminal};
+    use scribe::buffer::Position;
     use tempfile::NamedTempFile;
     use tui_input::InputRequest;
 
     use crate::{
         operations::Operation,
+        pike::{DiffLine, EditorMode, SearchOptions},
         test_util::{
+            cursor::{assert_buffer_cursor_render_pos, assert_input_cursor_render_pos},
             temp_file_with_contents,
-            ui::{n_spaces, solid_border},
+            ui::{n_spaces, nth_line_from_terminal_buffer, solid_border},
         },
         ui::FileInputRole,
     };
 
-    use super::App;
+    use super::{
+        workspace_root, App, Pike, LARGE_FILE_PROMPT, MIXED_LINE_ENDINGS, SEARCH_HIT_BOTTOM,
+        SEARCH_HIT_TOP,
+    };
 
     /// Create an App instance with a given file open
     fn app_with_file(filename: &str) -> super::App {
         App::build(super::Args {
             config: None,
+            cwd: None,
             file: Some(filename.to_string()),
         })
     }
@@ -584,10 +1809,28 @@ mod tests {
         let filename = config_file.path().to_str().unwrap().to_string();
         App::build(super::Args {
             config: Some(filename),
+            cwd: None,
             file: None,
         })
     }
 
+    /// Create an App instance with a given config and a file containing the given contents open
+    fn app_with_config_and_file_contents(config_contents: &str, contents: &str) -> App {
+        let config_file = temp_file_with_contents(config_contents);
+        let file = temp_file_with_contents(contents);
+        App::build(super::Args {
+            config: Some(config_file.path().to_str().unwrap().to_string()),
+            cwd: None,
+            file: Some(file.path().to_str().unwrap().to_string()),
+        })
+    }
+
+    /// Create an App instance with a file containing the given contents open,
+    /// rendered without the line number gutter
+    fn app_without_line_numbers(contents: &str) -> App {
+        app_with_config_and_file_contents("line_numbers = false", contents)
+    }
+
     /// Used in unit tests to provide the UI element, based on which the cursor
     /// position should be calculated, so that a testing buffer can be created only
     /// to accommodate this element instead of the whole UI.
@@ -604,22 +1847,15 @@ mod tests {
         renderer: CursorRenderingWidget,
         expected: (u16, u16),
     ) {
-        let pos = match renderer {
+        match renderer {
             CursorRenderingWidget::CurrentBuffer => {
                 let cursor_position = app.backend.cursor_position();
-
-                if let Some(cp) = cursor_position {
-                    // Scroll horizontally
-                    app.ui_state
-                        .buffer_state
-                        .update_x_offset(buf.area, cp.offset);
-                    // Scroll vertically
-                    app.ui_state.buffer_state.update_y_offset(buf.area, cp.line);
-                }
-
-                // 3) Ask UIState where the cursor _should_ be rendered:
-                app.ui_state
-                    .calculate_cursor_for_buffer(buf.area, cursor_position)
+                assert_buffer_cursor_render_pos(
+                    &mut app.ui_state,
+                    buf.area,
+                    cursor_position,
+                    expected,
+                );
             }
 
             CursorRenderingWidget::FileInput => {
@@ -628,12 +1864,9 @@ mod tests {
                     .file_input
                     .as_ref()
                     .expect("A file input should be open when testing cursor in file input");
-                app.ui_state
-                    .calculate_cursor_for_file_input(&input.input, buf.area)
+                assert_input_cursor_render_pos(&app.ui_state, &input.input, buf.area, expected);
             }
-        };
-
-        assert_eq!(pos, expected.into());
+        }
     }
 
     /// Shorthand for defining the renderer in unit tests and calling assert_cursor_render_pos
@@ -673,7 +1906,7 @@ mod tests {
     #[test]
     fn test_render_buffer_contents_fit() {
         let contents = String::from("Hello, world!");
-        let mut app = app_with_file_contents(&contents);
+        let mut app = app_without_line_numbers(&contents);
         let width = 15;
 
         let mut buf = Buffer::empty(Rect::new(0, 0, width, 2));
@@ -685,7 +1918,7 @@ mod tests {
     #[test]
     fn test_render_buffer_contents_too_long() {
         let contents = "Hello, world!";
-        let mut app = app_with_file_contents(contents);
+        let mut app = app_without_line_numbers(contents);
         let width = 4;
         let mut buf = Buffer::empty(Rect::new(0, 0, width, 1));
         let expected = Buffer::with_lines(vec!["Hell".to_string()]);
@@ -707,25 +1940,6 @@ mod tests {
         assert_eq!(buf, expected)
     }
 
-    #[allow(dead_code)]
-    /// Helper function to assert the position to render the cursor at in the visible
-    /// buffer after syncing the buffer contents and cursor position from the backend.
-    fn assert_cursor_render_pos_no_input(app: &mut App, buf: &Buffer, expected: (u16, u16)) {
-        let cursor_position = app.backend.cursor_position();
-
-        if let Some(cp) = cursor_position {
-            app.ui_state
-                .buffer_state
-                .update_x_offset(buf.area, cp.offset);
-            app.ui_state.buffer_state.update_y_offset(buf.area, cp.line);
-        }
-
-        let pos = app
-            .ui_state
-            .calculate_cursor_for_buffer(buf.area, cursor_position);
-
-        assert_eq!(pos, expected.into());
-    }
     /// The cursor should not move past the bounds of the buffer
     #[test]
     fn test_cant_move_cursor_too_far_right() {
@@ -758,7 +1972,7 @@ mod tests {
     /// are too long to render can be inspected by moving further right.
     #[test]
     fn test_buffer_shifts_when_moving_outside_visible_chars() {
-        let mut app = app_with_file_contents("123\n456");
+        let mut app = app_without_line_numbers("123\n456");
         let mut buf = Buffer::empty(Rect::new(0, 0, 1, 2));
 
         // Verify initial buffer rendering after the first cursor move.
@@ -775,7 +1989,7 @@ mod tests {
     /// cursor should be moved to the left
     #[test]
     fn test_buffer_does_not_shift_left_until_necessary() {
-        let mut app = app_with_file_contents("1234");
+        let mut app = app_without_line_numbers("1234");
         let mut buf = Buffer::empty(Rect::new(0, 0, 2, 1));
         assert_cursor_and_buffer(&mut app, &mut buf, (0, 0), vec!["12"]);
 
@@ -802,7 +2016,7 @@ mod tests {
     /// are too long to render can be inspected by moving further down.
     #[test]
     fn test_buffer_shifts_when_moving_outside_visible_lines() {
-        let mut app = app_with_file_contents("123\n456\n789");
+        let mut app = app_without_line_numbers("123\n456\n789");
         let mut buf = Buffer::empty(Rect::new(0, 0, 3, 1));
 
         // Verify initial buffer rendering after the first cursor move.
@@ -819,7 +2033,7 @@ mod tests {
     /// cursor should be moved up
     #[test]
     fn test_buffer_does_not_shift_up_until_necessary() {
-        let mut app = app_with_file_contents("123\n456\n789");
+        let mut app = app_without_line_numbers("123\n456\n789");
         let mut buf = Buffer::empty(Rect::new(0, 0, 3, 2));
         assert_cursor_and_buffer(&mut app, &mut buf, (0, 0), vec!["123", "456"]);
 
@@ -958,6 +2172,84 @@ mod tests {
         assert_eq!(app.backend.current_buffer_contents(), "    ");
     }
 
+    #[test]
+    fn tab_can_be_rebound_from_indentation() {
+        let mut app = app_with_config_and_file_contents(
+            r#"
+            [keymaps]
+            "alt+i" = "indent"
+            "#,
+            "",
+        );
+
+        app.handle_key_event(KeyEvent::new(KeyCode::Tab, KeyModifiers::NONE))
+            .expect("Failed to handle key event");
+        assert_eq!(app.backend.current_buffer_contents(), "");
+
+        app.handle_key_event(KeyEvent::new(KeyCode::Char('i'), KeyModifiers::ALT))
+            .expect("Failed to handle key event");
+        assert_eq!(app.backend.current_buffer_contents(), "    ");
+    }
+
+    #[test]
+    fn selection_set_by_backend_is_rendered() {
+        let mut app = app_with_file_contents("a word here");
+        let mut terminal = Terminal::new(TestBackend::new(40, 10)).unwrap();
+        let (start, end) = (
+            Position { line: 0, offset: 6 },
+            Position { line: 0, offset: 2 },
+        );
+        assert!(app.backend.set_selection(start, end));
+
+        terminal.draw(|frame| app.draw(frame)).unwrap();
+        let selection = app.ui_state.buffer_state.selection.as_ref().unwrap();
+        assert_eq!((selection.start, selection.end), (end, start));
+        assert!(selection.is_text_selection);
+
+        app.backend.clear_selection();
+        terminal.draw(|frame| app.draw(frame)).unwrap();
+        assert!(app.ui_state.buffer_state.selection.is_none());
+    }
+
+    #[test]
+    fn surround_selection_made_with_shift() {
+        let mut app = app_with_file_contents("a word here");
+        let key = |code, modifiers| KeyEvent::new(code, modifiers);
+
+        app.handle_key_event(key(KeyCode::Right, KeyModifiers::NONE))
+            .expect("Failed to handle key event");
+        app.handle_key_event(key(KeyCode::Right, KeyModifiers::NONE))
+            .expect("Failed to handle key event");
+        for _ in 0..4 {
+            app.handle_key_event(key(KeyCode::Right, KeyModifiers::SHIFT))
+                .expect("Failed to handle key event");
+        }
+        app.handle_key_event(key(KeyCode::Char('s'), KeyModifiers::ALT))
+            .expect("Failed to handle key event");
+        app.handle_key_event(key(KeyCode::Char(']'), KeyModifiers::NONE))
+            .expect("Failed to handle key event");
+
+        assert_eq!(app.backend.current_buffer_contents(), "a [word] here");
+        assert!(!app.ui_state.awaiting_surround);
+    }
+
+    #[test]
+    fn surround_is_cancelled_by_other_keys() {
+        let mut app = app_with_file_contents("word");
+        let key = |code, modifiers| KeyEvent::new(code, modifiers);
+
+        app.handle_key_event(key(KeyCode::End, KeyModifiers::SHIFT))
+            .expect("Failed to handle key event");
+        app.handle_key_event(key(KeyCode::Char('s'), KeyModifiers::ALT))
+            .expect("Failed to handle key event");
+        app.handle_key_event(key(KeyCode::Esc, KeyModifiers::NONE))
+            .expect("Failed to handle key event");
+        app.handle_key_event(key(KeyCode::Char('('), KeyModifiers::NONE))
+            .expect("Failed to handle key event");
+
+        assert_eq!(app.backend.current_buffer_contents(), "word(");
+    }
+
     #[test]
     fn test_app_does_not_write_when_banner_open() {
         let mut app = App::build_default();
@@ -969,26 +2261,289 @@ mod tests {
     }
 
     #[test]
-    fn handles_navigation_keys() {
-        let mut app = app_with_file_contents("line1\nline2\nline3");
-        let buf = Buffer::empty(Rect::new(0, 0, 10, 3));
+    fn welcome_keybind_fires_only_without_buffer() {
+        let mut app = app_with_config("[welcome_keymaps]\n\"n\" = \"new_buffer\"");
+        let key = KeyEvent::new(KeyCode::Char('n'), KeyModifiers::NONE);
 
-        // Test cases for navigation
-        let navigation_cases = vec![
-            (KeyEvent::new(KeyCode::Left, KeyModifiers::NONE), (0, 0)),
-            (KeyEvent::new(KeyCode::Right, KeyModifiers::NONE), (1, 0)),
-            (KeyEvent::new(KeyCode::Up, KeyModifiers::NONE), (1, 0)), // Should remain at the top
-            (KeyEvent::new(KeyCode::Down, KeyModifiers::NONE), (1, 1)), // Moves to the second line
-            (KeyEvent::new(KeyCode::End, KeyModifiers::NONE), (5, 1)), // End of second line
-            (KeyEvent::new(KeyCode::Home, KeyModifiers::NONE), (0, 1)), // Start of second line
-            (
-                KeyEvent::new(KeyCode::Left, KeyModifiers::CONTROL),
-                (0, 0), // Move left by word (should go to line start)
-            ),
-            (
-                KeyEvent::new(KeyCode::Right, KeyModifiers::CONTROL),
-                (5, 0), // Move right by word (end of line)
-            ),
+        app.handle_key_event(key)
+            .expect("Failed to handle key event");
+        assert!(app.backend.current_buffer().is_some());
+
+        app.handle_key_event(key)
+            .expect("Failed to handle key event");
+        assert_eq!(app.backend.current_buffer_contents(), "n");
+    }
+
+    #[test]
+    fn read_only_toggle_affects_only_current_buffer() {
+        let mut app = app_with_file_contents("first");
+        let key = KeyEvent::new(KeyCode::Char('x'), KeyModifiers::NONE);
+
+        app.handle_operation(&Operation::ToggleReadOnly);
+        app.handle_operation(&Operation::CreateNewBuffer);
+        app.handle_key_event(key)
+            .expect("Failed to handle key event");
+        assert_eq!(app.backend.current_buffer_contents(), "x");
+
+        app.handle_operation(&Operation::SwitchToPreviousBuffer);
+        let area = Rect::new(0, 0, 80, 2);
+        let mut buf = Buffer::empty(area);
+        app.render_status_bar(area, &mut buf);
+        assert!(nth_line_from_terminal_buffer(&buf, 1).contains("[RO]"));
+
+        app.handle_key_event(key)
+            .expect("Failed to handle key event");
+        assert_eq!(app.backend.current_buffer_contents(), "first");
+    }
+
+    #[test]
+    fn selection_is_kept_after_copy_until_the_cursor_moves_if_configured() {
+        let key = |code, modifiers| KeyEvent::new(code, modifiers);
+        let select_and_copy = |app: &mut App| {
+            for _ in 0..2 {
+                app.handle_key_event(key(KeyCode::Right, KeyModifiers::SHIFT))
+                    .expect("Failed to handle key event");
+            }
+            app.handle_key_event(key(KeyCode::Char('c'), KeyModifiers::CONTROL))
+                .expect("Failed to handle key event");
+        };
+
+        let mut app = app_with_file_contents("copied");
+        select_and_copy(&mut app);
+        assert!(app.backend.selection().is_none());
+
+        let mut app =
+            app_with_config_and_file_contents("keep_selection_after_copy = true", "copied");
+        select_and_copy(&mut app);
+        assert_eq!(app.backend.selected_text().as_deref(), Some("co"));
+
+        app.handle_key_event(key(KeyCode::Right, KeyModifiers::NONE))
+            .expect("Failed to handle key event");
+        assert!(app.backend.selection().is_none());
+    }
+
+    #[test]
+    fn relative_line_numbers_are_toggled() {
+        let mut app = app_with_file_contents("a\nb\nc");
+        app.backend.move_cursor_to(Position { line: 1, offset: 0 });
+        let area = Rect::new(0, 0, 5, 3);
+
+        app.handle_operation(&Operation::ToggleRelativeLineNumbers);
+        let mut buf = Buffer::empty(area);
+        app.render_buffer_contents(area, &mut buf);
+        assert_eq!(nth_line_from_terminal_buffer(&buf, 0), "1 a  ");
+        assert_eq!(nth_line_from_terminal_buffer(&buf, 1), "2 b  ");
+        assert_eq!(nth_line_from_terminal_buffer(&buf, 2), "1 c  ");
+
+        app.handle_operation(&Operation::ToggleRelativeLineNumbers);
+        let mut buf = Buffer::empty(area);
+        app.render_buffer_contents(area, &mut buf);
+        assert_eq!(nth_line_from_terminal_buffer(&buf, 2), "3 c  ");
+    }
+
+    #[test]
+    fn scrollbar_is_rendered_next_to_buffer_when_enabled() {
+        let contents = "0123456789\n".repeat(20);
+        let area = Rect::new(0, 0, 10, 5);
+
+        let mut app = app_without_line_numbers(&contents);
+        let mut buf = Buffer::empty(area);
+        app.render_buffer_contents(area, &mut buf);
+        assert_eq!(nth_line_from_terminal_buffer(&buf, 0), "0123456789");
+
+        let config = "scrollbar = true\nline_numbers = false";
+        let mut app = app_with_config_and_file_contents(config, &contents);
+        let mut buf = Buffer::empty(area);
+        app.render_buffer_contents(area, &mut buf);
+        assert_eq!(nth_line_from_terminal_buffer(&buf, 0), "012345678▲");
+        assert_eq!(nth_line_from_terminal_buffer(&buf, 4), "012345678▼");
+    }
+
+    #[test]
+    fn mixed_line_endings_are_reported_on_open() {
+        let app = app_with_file_contents("one\r\ntwo\n");
+        assert_eq!(
+            app.ui_state.status_message.as_deref(),
+            Some(MIXED_LINE_ENDINGS)
+        );
+
+        let app = app_with_file_contents("one\r\ntwo\r\n");
+        assert_eq!(app.ui_state.status_message, None);
+    }
+
+    #[test]
+    fn first_edit_outside_workspace_is_confirmed() {
+        let outside = temp_file_with_contents("outside");
+        let config = temp_file_with_contents("confirm_edit_outside_workspace = true");
+        let workspace = tempfile::tempdir().expect("Failed to create directory");
+        let backend = Pike::build(
+            workspace.path().to_path_buf(),
+            Some(outside.path().to_path_buf()),
+            Some(config.path().to_path_buf()),
+        )
+        .expect("Failed to build Pike");
+        let mut app = App::new(backend);
+        let key = |ch| KeyEvent::new(KeyCode::Char(ch), KeyModifiers::NONE);
+
+        for ch in ['a', 'n', 'b', 'y', 'c'] {
+            app.handle_key_event(key(ch))
+                .expect("Failed to handle key event");
+        }
+
+        // The key which asked for the confirmation is typed once it's given
+        assert_eq!(app.backend.current_buffer_contents(), "bcoutside");
+    }
+
+    #[test]
+    fn confirmed_first_edit_outside_workspace_is_pasted() {
+        let outside = temp_file_with_contents("outside");
+        let config = temp_file_with_contents("confirm_edit_outside_workspace = true");
+        let workspace = tempfile::tempdir().expect("Failed to create directory");
+        let backend = Pike::build(
+            workspace.path().to_path_buf(),
+            Some(outside.path().to_path_buf()),
+            Some(config.path().to_path_buf()),
+        )
+        .expect("Failed to build Pike");
+        let mut app = App::new(backend);
+
+        app.handle_event_batch(vec![Event::Paste("pasted ".to_string())])
+            .expect("Failed to handle paste");
+        assert_eq!(app.backend.current_buffer_contents(), "outside");
+
+        app.handle_key_event(KeyEvent::new(KeyCode::Char('y'), KeyModifiers::NONE))
+            .expect("Failed to handle key event");
+        assert_eq!(app.backend.current_buffer_contents(), "pasted outside");
+    }
+
+    #[test]
+    fn app_ticks_only_while_timed_features_are_active() {
+        let mut app = app_with_config_and_file_contents("smooth_scroll = true", &"\n".repeat(100));
+        assert_eq!(app.tick_interval(), None);
+
+        app.backend.move_cursor_to(Position {
+            line: 90,
+            offset: 0,
+        });
+        let area = Rect::new(0, 0, 10, 10);
+        let mut buf = Buffer::empty(area);
+        app.render_buffer_contents(area, &mut buf);
+        assert!(app.tick_interval().is_some());
+
+        while app.tick() {
+            app.render_buffer_contents(area, &mut buf);
+        }
+        assert_eq!(app.ui_state.buffer_state.offset.y, 81);
+        assert_eq!(app.tick_interval(), None);
+    }
+
+    #[test]
+    fn unsaved_changes_are_recomputed_when_idle() {
+        let config_file = temp_file_with_contents("status_bar_format = \"{filename}%={changes}\"");
+        let file = temp_file_with_contents("one\ntwo");
+        let mut app = App::build(super::Args {
+            config: Some(config_file.path().to_str().unwrap().to_string()),
+            cwd: None,
+            file: Some(file.path().to_str().unwrap().to_string()),
+        });
+
+        app.handle_event_batch(vec![Event::Key(KeyEvent::new(
+            KeyCode::Char('x'),
+            KeyModifiers::NONE,
+        ))])
+        .unwrap();
+        assert_eq!(app.ui_state.unsaved_changes, None);
+        assert!(app.tick_interval().is_some());
+
+        assert!(app.tick());
+        assert_eq!(app.ui_state.unsaved_changes, Some((1, 1)));
+        assert_eq!(app.tick_interval(), None);
+    }
+
+    #[test]
+    fn pasted_text_is_inserted_as_a_single_undo_step() {
+        let mut app = app_with_file_contents("abc");
+        app.handle_event_batch(vec![Event::Paste("one\r\ntwo\rthree ".to_string())])
+            .unwrap();
+        assert_eq!(app.backend.current_buffer_contents(), "one\ntwo\nthree abc");
+        app.handle_operation(&Operation::Undo);
+        assert_eq!(app.backend.current_buffer_contents(), "abc");
+
+        // Line breaks are escaped in the search input
+        app.handle_operation(&Operation::SearchInCurrentBuffer);
+        app.handle_event_batch(vec![Event::Paste("a\\b\nc".to_string())])
+            .unwrap();
+        assert_eq!(
+            app.ui_state.search_input.as_ref().unwrap().to_string(),
+            "a\\\\b\\nc"
+        );
+    }
+
+    #[test]
+    fn pasted_lines_are_reindented_to_the_cursor() {
+        let mut app = app_with_file_contents("fn main() {\n    \n}");
+        app.backend.move_cursor_to(Position { line: 1, offset: 4 });
+        app.handle_event_batch(vec![Event::Paste("if a {\n  b();\n}".to_string())])
+            .unwrap();
+        assert_eq!(
+            app.backend.current_buffer_contents(),
+            "fn main() {\n    if a {\n      b();\n    }\n}"
+        );
+    }
+
+    #[test]
+    fn scrolls_sideways_without_moving_the_cursor() {
+        let mut app = app_without_line_numbers(&format!("{}\nshort", "a".repeat(40)));
+        let area = Rect::new(0, 0, 10, 5);
+        let mut buf = Buffer::empty(area);
+        let cursor = app.backend.cursor_position();
+
+        for _ in 0..3 {
+            app.handle_operation(&Operation::ScrollLineRight);
+        }
+        app.render_buffer_contents(area, &mut buf);
+        assert_eq!(app.ui_state.buffer_state.offset.x, 12);
+        assert_eq!(app.backend.cursor_position(), cursor);
+
+        app.handle_operation(&Operation::ScrollLineLeft);
+        app.render_buffer_contents(area, &mut buf);
+        assert_eq!(app.ui_state.buffer_state.offset.x, 8);
+
+        // The end of the longest line stops at the right edge
+        for _ in 0..20 {
+            app.handle_operation(&Operation::ScrollLineRight);
+        }
+        app.render_buffer_contents(area, &mut buf);
+        assert_eq!(app.ui_state.buffer_state.offset.x, 30);
+        assert_eq!(app.backend.cursor_position(), cursor);
+
+        // Moving the cursor brings the view back to it
+        app.backend.move_cursor_to(Position { line: 1, offset: 0 });
+        app.render_buffer_contents(area, &mut buf);
+        assert_eq!(app.ui_state.buffer_state.offset.x, 0);
+    }
+
+    #[test]
+    fn handles_navigation_keys() {
+        let mut app = app_with_file_contents("line1\nline2\nline3");
+        let buf = Buffer::empty(Rect::new(0, 0, 10, 3));
+
+        // Test cases for navigation
+        let navigation_cases = vec![
+            (KeyEvent::new(KeyCode::Left, KeyModifiers::NONE), (0, 0)),
+            (KeyEvent::new(KeyCode::Right, KeyModifiers::NONE), (1, 0)),
+            (KeyEvent::new(KeyCode::Up, KeyModifiers::NONE), (1, 0)), // Should remain at the top
+            (KeyEvent::new(KeyCode::Down, KeyModifiers::NONE), (1, 1)), // Moves to the second line
+            (KeyEvent::new(KeyCode::End, KeyModifiers::NONE), (5, 1)), // End of second line
+            (KeyEvent::new(KeyCode::Home, KeyModifiers::NONE), (0, 1)), // Start of second line
+            (
+                KeyEvent::new(KeyCode::Left, KeyModifiers::CONTROL),
+                (0, 0), // Move left by word (should go to line start)
+            ),
+            (
+                KeyEvent::new(KeyCode::Right, KeyModifiers::CONTROL),
+                (5, 0), // Move right by word (end of line)
+            ),
         ];
 
         for (event, expected_pos) in navigation_cases {
@@ -1133,6 +2688,971 @@ mod tests {
         assert_snapshot!(terminal.backend());
     }
 
+    /// Open the search input, type in the query and confirm it
+    fn search_for(app: &mut App, query: &str) {
+        app.handle_operation(&Operation::SearchInCurrentBuffer);
+        for ch in query.chars() {
+            app.handle_key_event(KeyEvent::new(KeyCode::Char(ch), KeyModifiers::NONE))
+                .expect("Failed to handle key event");
+        }
+        app.handle_key_event(KeyEvent::new(KeyCode::Enter, KeyModifiers::NONE))
+            .expect("Failed to handle key event");
+    }
+
+    #[test]
+    fn search_starts_from_the_match_nearest_to_the_cursor() {
+        let mut app = app_with_file_contents("foo bar foo bar foo");
+        app.backend.move_cursor_to(Position { line: 0, offset: 5 });
+
+        search_for(&mut app, "foo");
+
+        assert_eq!(
+            app.backend.cursor_position(),
+            Some(Position { line: 0, offset: 8 })
+        );
+        assert_eq!(app.ui_state.buffer_state.highlight_state.focused, 1);
+        assert!(app.ui_state.status_message.is_none());
+    }
+
+    #[test]
+    fn search_reports_wrapping_around_the_buffer() {
+        let mut app = app_with_file_contents("foo bar foo");
+        let right = KeyEvent::new(KeyCode::Right, KeyModifiers::NONE);
+        let left = KeyEvent::new(KeyCode::Left, KeyModifiers::NONE);
+
+        search_for(&mut app, "foo");
+
+        app.handle_key_event(right)
+            .expect("Failed to handle key event");
+        assert_eq!(
+            app.backend.cursor_position(),
+            Some(Position { line: 0, offset: 8 })
+        );
+        assert!(app.ui_state.status_message.is_none());
+
+        app.handle_key_event(right)
+            .expect("Failed to handle key event");
+        assert_eq!(
+            app.backend.cursor_position(),
+            Some(Position { line: 0, offset: 0 })
+        );
+        assert_eq!(
+            app.ui_state.status_message.as_deref(),
+            Some(SEARCH_HIT_BOTTOM)
+        );
+
+        app.handle_key_event(left)
+            .expect("Failed to handle key event");
+        assert_eq!(
+            app.backend.cursor_position(),
+            Some(Position { line: 0, offset: 8 })
+        );
+        assert_eq!(app.ui_state.status_message.as_deref(), Some(SEARCH_HIT_TOP));
+    }
+
+    #[test]
+    fn search_wraps_to_the_first_match_when_none_follows_the_cursor() {
+        let mut app = app_with_file_contents("foo bar");
+        app.backend.move_cursor_to(Position { line: 0, offset: 5 });
+
+        search_for(&mut app, "foo");
+
+        assert_eq!(
+            app.backend.cursor_position(),
+            Some(Position { line: 0, offset: 0 })
+        );
+        assert_eq!(
+            app.ui_state.status_message.as_deref(),
+            Some(SEARCH_HIT_BOTTOM)
+        );
+    }
+
+    #[test]
+    fn input_keys_can_be_rebound() {
+        let config = "[search_keymaps]\n\"ctrl+n\" = \"next\"\n\n\
+                      [file_input_keymaps]\n\"ctrl+g\" = \"cancel\"";
+        let mut app = app_with_config_and_file_contents(config, "foo foo foo");
+
+        search_for(&mut app, "foo");
+        app.handle_key_event(KeyEvent::new(KeyCode::Right, KeyModifiers::NONE))
+            .expect("Failed to handle key event");
+        assert_eq!(app.ui_state.focused_highlight_position().offset, 0);
+        app.handle_key_event(KeyEvent::new(KeyCode::Char('n'), KeyModifiers::CONTROL))
+            .expect("Failed to handle key event");
+        assert_eq!(app.ui_state.focused_highlight_position().offset, 4);
+
+        app.handle_key_event(KeyEvent::new(KeyCode::Esc, KeyModifiers::NONE))
+            .expect("Failed to handle key event");
+        app.handle_operation(&Operation::OpenFile);
+        app.handle_key_event(KeyEvent::new(KeyCode::Esc, KeyModifiers::NONE))
+            .expect("Failed to handle key event");
+        assert!(app.ui_state.file_input.is_some());
+        app.handle_key_event(KeyEvent::new(KeyCode::Char('g'), KeyModifiers::CONTROL))
+            .expect("Failed to handle key event");
+        assert!(app.ui_state.file_input.is_none());
+    }
+
+    #[test]
+    fn escape_can_be_bound_outside_of_inputs() {
+        let config = r#"
+            line_numbers = false
+
+            [keymaps]
+            "esc" = "clear_highlights"
+            "#;
+        let mut app = app_with_config_and_file_contents(config, "foo bar foo");
+        let escape = KeyEvent::new(KeyCode::Esc, KeyModifiers::NONE);
+
+        // Inside the search input, escape still closes it
+        search_for(&mut app, "foo");
+        app.handle_key_event(escape)
+            .expect("Failed to handle key event");
+        assert!(app.ui_state.search_input.is_none());
+
+        app.ui_state.update_highlights(
+            app.backend
+                .search_in_current_buffer("foo", SearchOptions::default())
+                .expect("Failed to search"),
+        );
+        app.backend.start_selection();
+        app.handle_key_event(escape)
+            .expect("Failed to handle key event");
+        assert!(app
+            .ui_state
+            .buffer_state
+            .highlight_state
+            .highlights
+            .is_empty());
+        assert!(app.backend.selection().is_none());
+    }
+
+    #[test]
+    fn search_options_are_toggled_in_the_search_input() {
+        let mut app = app_with_file_contents("Foo foo food");
+        let alt = |ch| KeyEvent::new(KeyCode::Char(ch), KeyModifiers::ALT);
+
+        search_for(&mut app, "foo");
+        assert_eq!(app.ui_state.search_match_count, Some(2));
+
+        // Toggling an option searches for the query again
+        app.handle_key_event(alt('c'))
+            .expect("Failed to handle key event");
+        assert!(app.ui_state.search_options.case_insensitive);
+        assert_eq!(app.ui_state.search_match_count, Some(3));
+        app.handle_key_event(alt('w'))
+            .expect("Failed to handle key event");
+        assert_eq!(app.ui_state.search_match_count, Some(2));
+        let cursor = app.backend.cursor_position().expect("No buffer is open");
+        assert_eq!((cursor.line, cursor.offset), (0, 4));
+
+        // The options are kept for the next search
+        app.handle_key_event(KeyEvent::new(KeyCode::Esc, KeyModifiers::NONE))
+            .expect("Failed to handle key event");
+        app.handle_operation(&Operation::SearchInCurrentBuffer);
+        assert_eq!(
+            app.ui_state.search_options,
+            SearchOptions {
+                case_insensitive: true,
+                whole_word: true
+            }
+        );
+    }
+
+    #[test]
+    fn search_and_replace_replaces_focused_or_all_matches() {
+        let mut app = app_with_file_contents("foo bar foo\nfoo");
+        let typed = |app: &mut App, text: &str| {
+            for ch in text.chars() {
+                app.handle_key_event(KeyEvent::new(KeyCode::Char(ch), KeyModifiers::NONE))
+                    .expect("Failed to handle key event");
+            }
+        };
+        let enter = KeyEvent::new(KeyCode::Enter, KeyModifiers::NONE);
+
+        app.handle_operation(&Operation::SearchAndReplaceInCurrentBuffer);
+        typed(&mut app, "foo");
+        app.handle_key_event(enter)
+            .expect("Failed to handle key event");
+        assert!(app.ui_state.replace_input.as_ref().unwrap().is_focused);
+        app.handle_key_event(KeyEvent::new(KeyCode::Right, KeyModifiers::NONE))
+            .expect("Failed to handle key event");
+
+        // The focused match is replaced and the next one focused
+        typed(&mut app, "baz");
+        app.handle_key_event(enter)
+            .expect("Failed to handle key event");
+        assert_eq!(app.backend.current_buffer_contents(), "foo bar baz\nfoo");
+        assert_eq!(app.ui_state.search_match_count, Some(2));
+        assert_eq!(app.ui_state.focused_highlight_position().line, 1);
+
+        app.handle_key_event(KeyEvent::new(KeyCode::Char('a'), KeyModifiers::ALT))
+            .expect("Failed to handle key event");
+        assert_eq!(app.backend.current_buffer_contents(), "baz bar baz\nbaz");
+        assert!(app.ui_state.search_input.is_none());
+        assert!(app.ui_state.replace_input.is_none());
+
+        app.handle_operation(&Operation::Undo);
+        assert_eq!(app.backend.current_buffer_contents(), "foo bar baz\nfoo");
+    }
+
+    #[test]
+    fn match_replaced_next_is_marked() {
+        let mut app = app_with_file_contents("foo bar foo");
+        let mut terminal = Terminal::new(TestBackend::new(40, 10)).unwrap();
+        let replace_targets = |app: &App| -> Vec<bool> {
+            app.ui_state
+                .buffer_state
+                .highlight_state
+                .highlights
+                .iter()
+                .map(|highlight| highlight.is_replace_target)
+                .collect()
+        };
+
+        app.handle_operation(&Operation::SearchAndReplaceInCurrentBuffer);
+        for ch in "foo".chars() {
+            app.handle_key_event(KeyEvent::new(KeyCode::Char(ch), KeyModifiers::NONE))
+                .expect("Failed to handle key event");
+        }
+        app.handle_key_event(KeyEvent::new(KeyCode::Enter, KeyModifiers::NONE))
+            .expect("Failed to handle key event");
+        terminal.draw(|frame| app.draw(frame)).unwrap();
+        assert_eq!(replace_targets(&app), vec![true, false]);
+
+        app.handle_key_event(KeyEvent::new(KeyCode::Right, KeyModifiers::NONE))
+            .expect("Failed to handle key event");
+        terminal.draw(|frame| app.draw(frame)).unwrap();
+        assert_eq!(replace_targets(&app), vec![false, true]);
+
+        // Once the search is closed, the matches are plain search results
+        app.close_search_input();
+        terminal.draw(|frame| app.draw(frame)).unwrap();
+        assert_eq!(replace_targets(&app), vec![false, false]);
+    }
+
+    #[test]
+    fn modal_editing_switches_between_normal_and_insert_mode() {
+        let mut app = app_with_config_and_file_contents("modal_editing = true", "abc");
+        let press = |app: &mut App, code| {
+            app.handle_key_event(KeyEvent::new(code, KeyModifiers::NONE))
+                .expect("Failed to handle key event");
+        };
+        assert!(app.backend.is_in_normal_mode());
+
+        // Letters which aren't bound aren't typed in the normal mode
+        press(&mut app, KeyCode::Char('z'));
+        assert_eq!(app.backend.current_buffer_contents(), "abc");
+
+        // Typing after the cursor, then leaving the insert mode
+        press(&mut app, KeyCode::Char('a'));
+        assert!(!app.backend.is_in_normal_mode());
+        press(&mut app, KeyCode::Char('u'));
+        assert_eq!(app.backend.current_buffer_contents(), "aubc");
+        press(&mut app, KeyCode::Esc);
+        assert!(app.backend.is_in_normal_mode());
+
+        // Letters of the normal keymap trigger operations
+        press(&mut app, KeyCode::Char('u'));
+        assert_eq!(app.backend.current_buffer_contents(), "abc");
+        press(&mut app, KeyCode::Char('/'));
+        assert!(app.ui_state.search_input.is_some());
+        press(&mut app, KeyCode::Esc);
+        assert!(app.ui_state.search_input.is_none());
+        assert!(app.backend.is_in_normal_mode());
+
+        press(&mut app, KeyCode::Char('i'));
+        press(&mut app, KeyCode::Char('x'));
+        assert_eq!(app.backend.current_buffer_contents(), "axbc");
+    }
+
+    #[test]
+    fn status_bar_shows_mode_of_modal_editing() {
+        let mut app = app_with_config_and_file_contents("modal_editing = true", "abc");
+        let mut terminal = Terminal::new(TestBackend::new(40, 10)).unwrap();
+        let status_bar = |terminal: &Terminal<TestBackend>| {
+            let buffer = terminal.backend().buffer();
+            (0..40)
+                .map(|x| buffer.cell((x, 9)).unwrap().symbol())
+                .collect::<String>()
+        };
+
+        terminal.draw(|frame| app.draw(frame)).unwrap();
+        assert!(status_bar(&terminal).starts_with("NORMAL "));
+        assert_eq!(app.backend.editor_state_summary().mode, EditorMode::Normal);
+
+        app.handle_key_event(KeyEvent::new(KeyCode::Char('i'), KeyModifiers::NONE))
+            .expect("Failed to handle key event");
+        terminal.draw(|frame| app.draw(frame)).unwrap();
+        assert!(status_bar(&terminal).starts_with("INSERT "));
+    }
+
+    #[test]
+    fn modeless_editing_is_the_default() {
+        let mut app = app_with_file_contents("abc");
+        app.handle_key_event(KeyEvent::new(KeyCode::Char('u'), KeyModifiers::NONE))
+            .expect("Failed to handle key event");
+        app.handle_key_event(KeyEvent::new(KeyCode::Esc, KeyModifiers::NONE))
+            .expect("Failed to handle key event");
+        app.handle_key_event(KeyEvent::new(KeyCode::Char('i'), KeyModifiers::NONE))
+            .expect("Failed to handle key event");
+        assert_eq!(app.backend.current_buffer_contents(), "uiabc");
+    }
+
+    #[test]
+    fn search_counts_matches_until_closed() {
+        let mut app = app_with_file_contents("foo bar foo");
+
+        app.handle_operation(&Operation::SearchInCurrentBuffer);
+        assert_eq!(app.ui_state.search_match_count, None);
+        app.handle_key_event(KeyEvent::new(KeyCode::Enter, KeyModifiers::NONE))
+            .expect("Failed to handle key event");
+        assert_eq!(app.ui_state.search_match_count, Some(0));
+        app.handle_key_event(KeyEvent::new(KeyCode::Esc, KeyModifiers::NONE))
+            .expect("Failed to handle key event");
+        assert_eq!(app.ui_state.search_match_count, None);
+
+        search_for(&mut app, "foo");
+        assert_eq!(app.ui_state.search_match_count, Some(2));
+    }
+
+    #[test]
+    fn search_highlights_non_ascii_matches_by_cell() {
+        let mut app = app_without_line_numbers("un café noir");
+        search_for(&mut app, "café");
+
+        let area = Rect::new(0, 0, 12, 1);
+        let mut buf = Buffer::empty(area);
+        app.render_buffer_contents(area, &mut buf);
+
+        let highlighted: Vec<u16> = (0..area.width)
+            .filter(|&x| buf[(x, 0)].bg != ratatui::style::Color::Reset)
+            .collect();
+        assert_eq!(highlighted, vec![3, 4, 5, 6]);
+        assert_eq!(nth_line_from_terminal_buffer(&buf, 0), "un café noir");
+    }
+
+    #[test]
+    fn search_input_reopens_with_the_last_query() {
+        let mut app = app_with_file_contents("foo bar foo");
+
+        search_for(&mut app, "foo");
+        app.handle_key_event(KeyEvent::new(KeyCode::Esc, KeyModifiers::NONE))
+            .expect("Failed to handle key event");
+        app.handle_operation(&Operation::SearchInCurrentBuffer);
+        assert_eq!(
+            app.ui_state
+                .search_input
+                .as_ref()
+                .map(|input| input.value()),
+            Some("foo")
+        );
+    }
+
+    #[test]
+    fn search_next_and_prev_reuse_the_last_query() {
+        let mut app = app_with_file_contents("foo bar foo bar");
+
+        app.handle_operation(&Operation::SearchNext);
+        assert_eq!(
+            app.ui_state.status_message.as_deref(),
+            Some("No previous search")
+        );
+        app.ui_state.status_message = None;
+
+        search_for(&mut app, "bar");
+        app.handle_key_event(KeyEvent::new(KeyCode::Esc, KeyModifiers::NONE))
+            .expect("Failed to handle key event");
+        assert_eq!(
+            app.backend.cursor_position(),
+            Some(Position { line: 0, offset: 4 })
+        );
+
+        app.handle_operation(&Operation::SearchNext);
+        assert!(app.ui_state.search_input.is_none());
+        assert_eq!(
+            app.backend.cursor_position(),
+            Some(Position {
+                line: 0,
+                offset: 12
+            })
+        );
+        assert!(app.ui_state.status_message.is_none());
+
+        app.handle_operation(&Operation::SearchNext);
+        assert_eq!(
+            app.backend.cursor_position(),
+            Some(Position { line: 0, offset: 4 })
+        );
+        assert_eq!(
+            app.ui_state.status_message.as_deref(),
+            Some(SEARCH_HIT_BOTTOM)
+        );
+
+        app.handle_operation(&Operation::SearchPrev);
+        assert_eq!(
+            app.backend.cursor_position(),
+            Some(Position {
+                line: 0,
+                offset: 12
+            })
+        );
+        assert_eq!(app.ui_state.status_message.as_deref(), Some(SEARCH_HIT_TOP));
+    }
+
+    #[test]
+    fn search_highlights_at_most_the_configured_number_of_matches() {
+        let mut app =
+            app_with_config_and_file_contents("max_search_highlights = 3", &"foo\n".repeat(10));
+        app.backend.move_cursor_to(Position { line: 5, offset: 0 });
+
+        search_for(&mut app, "foo");
+
+        let highlighted_lines: Vec<usize> = app
+            .ui_state
+            .buffer_state
+            .highlight_state
+            .highlights
+            .iter()
+            .map(|highlight| highlight.start.line)
+            .collect();
+        assert_eq!(highlighted_lines, vec![4, 5, 6]);
+        assert_eq!(app.ui_state.search_match_count, Some(10));
+        assert_eq!(app.ui_state.status_message.as_deref(), Some("3+ matches"));
+    }
+
+    #[test]
+    fn hex_view_is_read_only_and_moves_by_bytes() {
+        let mut app = app_with_file_contents("abc");
+        let key = |code| KeyEvent::new(code, KeyModifiers::NONE);
+
+        app.handle_operation(&Operation::ToggleHexView);
+        for code in [KeyCode::Char('x'), KeyCode::Enter, KeyCode::Backspace] {
+            app.handle_key_event(key(code))
+                .expect("Failed to handle key event");
+        }
+        app.handle_operation(&Operation::ReflowParagraph);
+        assert_eq!(app.backend.current_buffer_contents(), "abc");
+
+        for code in [KeyCode::Right, KeyCode::Right, KeyCode::Right] {
+            app.handle_key_event(key(code))
+                .expect("Failed to handle key event");
+        }
+        assert_eq!(app.ui_state.hex_view.as_ref().map(|h| h.cursor), Some(2));
+
+        app.handle_operation(&Operation::ToggleHexView);
+        assert!(app.ui_state.hex_view.is_none());
+        app.handle_key_event(key(KeyCode::Char('x')))
+            .expect("Failed to handle key event");
+        assert_eq!(app.backend.current_buffer_contents(), "xabc");
+    }
+
+    #[test]
+    fn save_all_reports_results_and_prompts_for_unbound_buffer() {
+        let mut app = app_with_file_contents("bound");
+        let key = |code| KeyEvent::new(code, KeyModifiers::NONE);
+
+        app.handle_key_event(key(KeyCode::Char('x')))
+            .expect("Failed to handle key event");
+        app.handle_operation(&Operation::CreateNewBuffer);
+        app.handle_key_event(key(KeyCode::Char('y')))
+            .expect("Failed to handle key event");
+        app.handle_operation(&Operation::SwitchToPreviousBuffer);
+
+        app.handle_operation(&Operation::SaveAll);
+
+        assert_eq!(
+            app.ui_state.status_message.as_deref(),
+            Some("Saved 1 buffer(s), 1 failed: Buffer isn't bound to a file")
+        );
+        assert_eq!(app.backend.current_buffer_contents(), "y");
+        assert!(app.ui_state.file_input.is_some());
+    }
+
+    #[test]
+    fn diff_view_is_opened_for_entered_path_and_closed_on_escape() {
+        let mut app = app_with_file_contents("one\ntwo");
+        let other = temp_file_with_contents("one\nthree");
+        let key = |code| KeyEvent::new(code, KeyModifiers::NONE);
+
+        app.handle_operation(&Operation::DiffWith);
+        for ch in other.path().to_str().unwrap().chars() {
+            app.handle_key_event(key(KeyCode::Char(ch)))
+                .expect("Failed to handle key event");
+        }
+        app.handle_key_event(key(KeyCode::Enter))
+            .expect("Failed to handle key event");
+
+        let diff_view = app.ui_state.diff_view.as_ref().expect("Diff view is open");
+        assert_eq!(
+            diff_view.lines,
+            vec![
+                DiffLine::Unchanged("one".to_string()),
+                DiffLine::Removed("three".to_string()),
+                DiffLine::Added("two".to_string()),
+            ]
+        );
+
+        app.handle_key_event(key(KeyCode::Char('x')))
+            .expect("Failed to handle key event");
+        app.handle_key_event(key(KeyCode::Esc))
+            .expect("Failed to handle key event");
+        assert!(app.ui_state.diff_view.is_none());
+        assert_eq!(app.backend.current_buffer_contents(), "one\ntwo");
+    }
+
+    #[test]
+    fn batched_navigation_stops_at_buffer_boundaries() {
+        let mut app = app_with_file_contents("one\ntwo\nthree");
+        let key = |code| Event::Key(KeyEvent::new(code, KeyModifiers::NONE));
+
+        app.handle_event_batch(vec![key(KeyCode::Down); 10])
+            .expect("Failed to handle events");
+        assert_eq!(
+            app.backend.cursor_position(),
+            Some(Position { line: 2, offset: 0 })
+        );
+
+        app.handle_event_batch(vec![key(KeyCode::Right); 10])
+            .expect("Failed to handle events");
+        assert_eq!(
+            app.backend.cursor_position(),
+            Some(Position { line: 2, offset: 5 })
+        );
+
+        app.handle_event_batch(vec![key(KeyCode::Up); 10])
+            .expect("Failed to handle events");
+        assert_eq!(
+            app.backend.cursor_position(),
+            Some(Position { line: 0, offset: 3 })
+        );
+    }
+
+    #[test]
+    fn unbound_keys_are_reported_if_enabled() {
+        let ctrl_g = KeyEvent::new(KeyCode::Char('g'), KeyModifiers::CONTROL);
+
+        let mut app = app_with_file_contents("abc");
+        app.handle_key_event(ctrl_g)
+            .expect("Failed to handle key event");
+        assert!(app.ui_state.status_message.is_none());
+
+        let mut app = app_with_config_and_file_contents("report_unbound_keys = true", "abc");
+        app.handle_key_event(ctrl_g)
+            .expect("Failed to handle key event");
+        assert_eq!(
+            app.ui_state.status_message.as_deref(),
+            Some("Key not bound: ctrl+g")
+        );
+
+        // Bound keys and navigation aren't reported
+        for key in [
+            KeyEvent::new(KeyCode::Char('z'), KeyModifiers::CONTROL),
+            KeyEvent::new(KeyCode::Left, KeyModifiers::CONTROL),
+        ] {
+            app.handle_key_event(key)
+                .expect("Failed to handle key event");
+            assert!(app.ui_state.status_message.is_none());
+        }
+        assert_eq!(app.backend.current_buffer_contents(), "abc");
+    }
+
+    #[test]
+    fn build_with_explicit_cwd() {
+        let dir = tempfile::tempdir().expect("Failed to create directory");
+        let cwd = dir.path().canonicalize().unwrap();
+        let app = App::build(super::Args {
+            config: None,
+            cwd: Some(dir.path().to_str().unwrap().to_string()),
+            file: None,
+        });
+
+        assert_eq!(
+            app.backend.resolve_path(std::path::Path::new("a.txt")),
+            cwd.join("a.txt")
+        );
+    }
+
+    #[test]
+    fn tab_completes_path_in_file_input() {
+        let dir = tempfile::tempdir().expect("Failed to create directory");
+        std::fs::write(dir.path().join("main.rs"), "").unwrap();
+        let mut app = App::build(super::Args {
+            config: None,
+            cwd: Some(dir.path().to_str().unwrap().to_string()),
+            file: None,
+        });
+
+        app.handle_operation(&Operation::OpenFile);
+        for code in [KeyCode::Char('m'), KeyCode::Tab] {
+            app.handle_key_event(KeyEvent::new(code, KeyModifiers::NONE))
+                .expect("Failed to handle key event");
+        }
+        let input = &app.ui_state.file_input.as_ref().unwrap().input;
+        assert_eq!(input.value(), "main.rs");
+        assert_eq!(input.cursor(), "main.rs".len());
+
+        app.handle_key_event(KeyEvent::new(KeyCode::Enter, KeyModifiers::NONE))
+            .expect("Failed to handle key event");
+        assert_eq!(app.backend.current_buffer_filename(), "main.rs");
+    }
+
+    #[test]
+    fn find_files_picker_opens_chosen_file() {
+        let dir = tempfile::tempdir().expect("Failed to create directory");
+        std::fs::create_dir(dir.path().join("src")).unwrap();
+        for file in ["main.rs", "src/app.rs", "src/ui.rs"] {
+            std::fs::write(dir.path().join(file), file).unwrap();
+        }
+        // Paths in the picker are relative to the working directory, whatever
+        // relative paths entered by hand are resolved against
+        let config = temp_file_with_contents("relative_path_base = \"current_file\"");
+        let mut app = App::build(super::Args {
+            config: Some(config.path().to_str().unwrap().to_string()),
+            cwd: Some(dir.path().to_str().unwrap().to_string()),
+            file: Some(dir.path().join("src/app.rs").to_str().unwrap().to_string()),
+        });
+
+        app.handle_operation(&Operation::FindFilesInCWD);
+        for code in [KeyCode::Char('s'), KeyCode::Char('r'), KeyCode::Down] {
+            app.handle_key_event(KeyEvent::new(code, KeyModifiers::NONE))
+                .expect("Failed to handle key event");
+        }
+        let picker = app.ui_state.picker.as_ref().unwrap();
+        assert_eq!(picker.query.value(), "sr");
+        assert_eq!(
+            picker.selected().map(|item| item.label.as_str()),
+            Some("src/ui.rs")
+        );
+
+        app.handle_key_event(KeyEvent::new(KeyCode::Enter, KeyModifiers::NONE))
+            .expect("Failed to handle key event");
+        assert!(app.ui_state.picker.is_none());
+        assert_eq!(app.backend.current_buffer_contents(), "src/ui.rs");
+    }
+
+    #[test]
+    fn find_files_picker_opens_all_marked_files() {
+        let dir = tempfile::tempdir().expect("Failed to create directory");
+        for file in ["a.rs", "b.rs", "c.rs"] {
+            std::fs::write(dir.path().join(file), file).unwrap();
+        }
+        let mut app = App::build(super::Args {
+            config: None,
+            cwd: Some(dir.path().to_str().unwrap().to_string()),
+            file: None,
+        });
+        let key = |code| KeyEvent::new(code, KeyModifiers::NONE);
+
+        app.handle_operation(&Operation::FindFilesInCWD);
+        for code in [KeyCode::Tab, KeyCode::Down, KeyCode::Tab, KeyCode::Enter] {
+            app.handle_key_event(key(code))
+                .expect("Failed to handle key event");
+        }
+
+        assert!(app.ui_state.picker.is_none());
+        let names: Vec<String> = app
+            .backend
+            .open_buffers()
+            .into_iter()
+            .map(|entry| entry.name)
+            .collect();
+        assert!(names.contains(&"a.rs".to_string()), "{names:?}");
+        assert!(names.contains(&"c.rs".to_string()), "{names:?}");
+        assert!(!names.contains(&"b.rs".to_string()), "{names:?}");
+        assert_eq!(app.backend.current_buffer_filename(), "c.rs");
+    }
+
+    #[test]
+    fn picker_keys_are_bound_in_their_own_section() {
+        let mut app =
+            app_with_config_and_file_contents("[picker_keymaps]\n\"ctrl+g\" = \"cancel\"", "text");
+        app.handle_operation(&Operation::OpenBufferPicker);
+
+        // The key replaced by the binding no longer closes the picker
+        app.handle_key_event(KeyEvent::new(KeyCode::Esc, KeyModifiers::NONE))
+            .expect("Failed to handle key event");
+        assert!(app.ui_state.picker.is_some());
+
+        app.handle_key_event(KeyEvent::new(KeyCode::Char('g'), KeyModifiers::CONTROL))
+            .expect("Failed to handle key event");
+        assert!(app.ui_state.picker.is_none());
+    }
+
+    #[test]
+    fn find_files_picker_toggles_ignored_files() {
+        let dir = tempfile::tempdir().expect("Failed to create directory");
+        std::fs::create_dir(dir.path().join("target")).unwrap();
+        for file in ["main.rs", "target/main", ".env"] {
+            std::fs::write(dir.path().join(file), file).unwrap();
+        }
+        std::fs::write(dir.path().join(".gitignore"), "/target\n").unwrap();
+        let mut app = App::build(super::Args {
+            config: None,
+            cwd: Some(dir.path().to_str().unwrap().to_string()),
+            file: None,
+        });
+        let toggle = KeyEvent::new(KeyCode::Char('i'), KeyModifiers::ALT);
+
+        app.handle_operation(&Operation::FindFilesInCWD);
+        for c in "main".chars() {
+            app.handle_key_event(KeyEvent::new(KeyCode::Char(c), KeyModifiers::NONE))
+                .expect("Failed to handle key event");
+        }
+        app.handle_key_event(KeyEvent::new(KeyCode::Down, KeyModifiers::NONE))
+            .expect("Failed to handle key event");
+        let picker = app.ui_state.picker.as_ref().unwrap();
+        assert_eq!(picker.includes_ignored_files, Some(false));
+        assert_eq!(
+            picker.selected().map(|item| item.label.as_str()),
+            Some("main.rs")
+        );
+
+        // The query is kept while the files are listed again
+        app.handle_key_event(toggle)
+            .expect("Failed to handle key event");
+        app.handle_key_event(KeyEvent::new(KeyCode::Down, KeyModifiers::NONE))
+            .expect("Failed to handle key event");
+        let picker = app.ui_state.picker.as_ref().unwrap();
+        assert_eq!(picker.includes_ignored_files, Some(true));
+        assert_eq!(picker.query.value(), "main");
+        assert_eq!(
+            picker.selected().map(|item| item.label.as_str()),
+            Some("target/main")
+        );
+
+        app.handle_key_event(toggle)
+            .expect("Failed to handle key event");
+        let picker = app.ui_state.picker.as_ref().unwrap();
+        assert_eq!(picker.includes_ignored_files, Some(false));
+    }
+
+    #[test]
+    fn find_text_picker_opens_file_at_chosen_line() {
+        let dir = tempfile::tempdir().expect("Failed to create directory");
+        std::fs::create_dir(dir.path().join("src")).unwrap();
+        std::fs::write(dir.path().join("main.rs"), "fn main() {\n    run();\n}").unwrap();
+        std::fs::write(dir.path().join("src/app.rs"), "// app\npub fn run() {}").unwrap();
+        let mut app = App::build(super::Args {
+            config: None,
+            cwd: Some(dir.path().to_str().unwrap().to_string()),
+            file: None,
+        });
+
+        app.handle_operation(&Operation::FindTextInCWD);
+        assert!(app.ui_state.picker.as_ref().unwrap().selected().is_none());
+        for c in "run(".chars() {
+            app.handle_key_event(KeyEvent::new(KeyCode::Char(c), KeyModifiers::NONE))
+                .expect("Failed to handle key event");
+        }
+        app.handle_key_event(KeyEvent::new(KeyCode::Down, KeyModifiers::NONE))
+            .expect("Failed to handle key event");
+        let picker = app.ui_state.picker.as_ref().unwrap();
+        assert_eq!(
+            picker.selected().map(|item| item.label.as_str()),
+            Some("src/app.rs:2: pub fn run() {}")
+        );
+
+        app.handle_key_event(KeyEvent::new(KeyCode::Enter, KeyModifiers::NONE))
+            .expect("Failed to handle key event");
+        assert!(app.ui_state.picker.is_none());
+        assert_eq!(app.backend.current_buffer_filename(), "app.rs");
+        let cursor = app.backend.cursor_position().expect("No buffer is open");
+        assert_eq!((cursor.line, cursor.offset), (1, 0));
+    }
+
+    #[test]
+    fn buffer_picker_switches_to_chosen_buffer() {
+        let mut app = app_with_file_contents("first");
+        app.handle_operation(&Operation::CreateNewBuffer);
+        app.backend.write_to_current_buffer("second").unwrap();
+
+        app.handle_key_event(KeyEvent::new(KeyCode::Char('b'), KeyModifiers::CONTROL))
+            .expect("Failed to handle key event");
+        let picker = app.ui_state.picker.as_ref().unwrap();
+        assert_eq!(
+            picker.selected().map(|item| item.label.clone()),
+            Some(app.backend.open_buffers()[0].name.clone())
+        );
+
+        app.handle_key_event(KeyEvent::new(KeyCode::Down, KeyModifiers::NONE))
+            .expect("Failed to handle key event");
+        let picker = app.ui_state.picker.as_ref().unwrap();
+        assert_eq!(
+            picker.selected().map(|item| item.label.as_str()),
+            Some("[No Name]*")
+        );
+
+        app.handle_key_event(KeyEvent::new(KeyCode::Up, KeyModifiers::NONE))
+            .expect("Failed to handle key event");
+        app.handle_key_event(KeyEvent::new(KeyCode::Enter, KeyModifiers::NONE))
+            .expect("Failed to handle key event");
+        assert!(app.ui_state.picker.is_none());
+        assert_eq!(app.backend.current_buffer_contents(), "first");
+    }
+
+    #[test]
+    fn opening_a_large_file_has_to_be_confirmed() {
+        let large = temp_file_with_contents(&"a".repeat(1024 * 1024 + 1));
+        let mut app = app_with_config("large_file_threshold_mb = 1");
+        let press = |app: &mut App, c: char| {
+            app.handle_key_event(KeyEvent::new(KeyCode::Char(c), KeyModifiers::NONE))
+                .expect("Failed to handle key event");
+        };
+
+        app.open_file_from_path(large.path().to_path_buf(), false);
+        let message = app.ui_state.status_message.clone().unwrap_or_default();
+        assert!(message.ends_with(LARGE_FILE_PROMPT));
+        press(&mut app, 'n');
+        assert!(app.backend.current_buffer().is_none());
+
+        app.open_file_from_path(large.path().to_path_buf(), false);
+        press(&mut app, 'y');
+        assert!(app.backend.current_buffer().is_some());
+        assert!(app.ui_state.awaiting_large_file.is_none());
+    }
+
+    #[test]
+    fn large_file_given_on_startup_has_to_be_confirmed() {
+        let large = temp_file_with_contents(&"a".repeat(1024 * 1024 + 1));
+        let config = temp_file_with_contents("large_file_threshold_mb = 1");
+        let mut app = App::build(super::Args {
+            config: Some(config.path().to_str().unwrap().to_string()),
+            cwd: None,
+            file: Some(large.path().to_str().unwrap().to_string()),
+        });
+        assert!(app.backend.current_buffer().is_none());
+        let message = app.ui_state.status_message.clone().unwrap_or_default();
+        assert!(message.ends_with(LARGE_FILE_PROMPT));
+
+        app.handle_key_event(KeyEvent::new(KeyCode::Char('y'), KeyModifiers::NONE))
+            .expect("Failed to handle key event");
+        assert_eq!(app.backend.current_buffer_contents().len(), 1024 * 1024 + 1);
+    }
+
+    #[test]
+    fn failing_to_open_scratch_buffer_on_startup_is_reported() {
+        // The parent of the scratch file is a regular file, so it can't be created
+        let parent = temp_file_with_contents("");
+        let scratch = parent.path().join("scratch.txt");
+        let app = app_with_config(&format!(
+            "scratch_buffer = true\nscratch_file = \"{}\"",
+            scratch.display()
+        ));
+
+        assert!(app.backend.current_buffer().is_none());
+        let message = app.ui_state.status_message.clone().unwrap_or_default();
+        assert!(
+            message.starts_with("Failed to create directory"),
+            "{message}"
+        );
+    }
+
+    #[test]
+    fn scratch_buffer_isnt_opened_with_a_file_given_on_startup() {
+        let dir = tempfile::tempdir().expect("Failed to create directory");
+        let scratch = dir.path().join("scratch.txt");
+        let large = temp_file_with_contents(&"a".repeat(1024 * 1024 + 1));
+        let config = temp_file_with_contents(&format!(
+            "scratch_buffer = true\nscratch_file = \"{}\"\nlarge_file_threshold_mb = 1",
+            scratch.display()
+        ));
+        let app = App::build(super::Args {
+            config: Some(config.path().to_str().unwrap().to_string()),
+            cwd: None,
+            file: Some(large.path().to_str().unwrap().to_string()),
+        });
+
+        // The large file waits for confirmation without the scratch buffer under it
+        assert!(app.ui_state.awaiting_large_file.is_some());
+        assert!(app.backend.current_buffer().is_none());
+        assert!(!scratch.exists());
+    }
+
+    #[test]
+    fn scratch_buffer_is_opened_from_the_configured_file() {
+        let dir = tempfile::tempdir().expect("Failed to create directory");
+        let scratch = dir.path().join("scratch.txt");
+        std::fs::write(&scratch, "notes").unwrap();
+        let app = app_with_config(&format!(
+            "scratch_buffer = true\nscratch_file = \"{}\"",
+            scratch.display()
+        ));
+        assert_eq!(app.backend.current_buffer_contents(), "notes");
+    }
+
+    #[test]
+    fn inserts_unicode_from_prompt() {
+        let mut app = app_with_file_contents("");
+        let enter_code_point = |app: &mut App, code_point: &str| {
+            app.handle_operation(&Operation::InsertUnicode);
+            for c in code_point.chars().chain(['\n']) {
+                let code = if c == '\n' {
+                    KeyCode::Enter
+                } else {
+                    KeyCode::Char(c)
+                };
+                app.handle_key_event(KeyEvent::new(code, KeyModifiers::NONE))
+                    .expect("Failed to handle key event");
+            }
+        };
+
+        enter_code_point(&mut app, "U+1F600");
+        assert_eq!(app.backend.current_buffer_contents(), "😀");
+        assert!(app.ui_state.file_input.is_none());
+
+        enter_code_point(&mut app, "U+ZZZ");
+        assert_eq!(app.backend.current_buffer_contents(), "😀");
+        assert_eq!(
+            app.ui_state.status_message.as_deref(),
+            Some("Invalid code point: U+ZZZ")
+        );
+    }
+
+    #[test]
+    fn workspace_root_has_to_be_a_directory() {
+        let dir = tempfile::tempdir().expect("Failed to create directory");
+        let file = temp_file_with_contents("");
+        let missing = dir.path().join("missing");
+
+        assert_eq!(
+            workspace_root(dir.path().to_str()),
+            Ok(dir.path().canonicalize().unwrap())
+        );
+        assert_eq!(workspace_root(None), Ok(std::env::current_dir().unwrap()));
+        assert!(workspace_root(file.path().to_str()).is_err());
+        assert!(workspace_root(missing.to_str()).is_err());
+    }
+
+    #[test]
+    fn force_quit_exits_with_unsaved_changes() {
+        let mut app = app_with_file_contents("abc");
+        app.handle_key_event(KeyEvent::new(KeyCode::Char('x'), KeyModifiers::NONE))
+            .expect("Failed to handle key event");
+        assert!(app.backend.has_unsaved_changes());
+
+        app.handle_operation(&Operation::ForceQuit);
+
+        assert!(app.exit);
+        assert!(app.ui_state.status_message.is_none());
+        assert!(app.ui_state.file_input.is_none());
+    }
+
+    #[test]
+    fn batched_events_after_quitting_are_ignored() {
+        let mut app = app_with_file_contents("");
+        let events = vec![
+            Event::Key(KeyEvent::new(KeyCode::Char('a'), KeyModifiers::NONE)),
+            Event::Key(KeyEvent::new(KeyCode::Char('q'), KeyModifiers::CONTROL)),
+            Event::Key(KeyEvent::new(KeyCode::Char('b'), KeyModifiers::NONE)),
+        ];
+
+        app.handle_event_batch(events)
+            .expect("Failed to handle events");
+
+        assert!(app.exit);
+        assert_eq!(app.backend.current_buffer_contents(), "a");
+    }
+
     #[test]
     fn handle_events_with_file_input_write_and_close() {
         let mut app = App::build_default();
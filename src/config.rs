@@ -1,14 +1,20 @@
+use chrono::format::{Item, StrftimeItems};
 use crossterm::event::{KeyCode, KeyModifiers};
 use toml::Table;
 
 use crate::key_shortcut::KeyShortcut;
-use crate::operations::Operation;
+use crate::operations::{InputAction, Operation};
 use std::{
     collections::{HashMap, HashSet},
-    fs,
+    fmt, fs,
+    hash::Hash,
     path::{Path, PathBuf},
 };
 
+/// Environment variable naming the config file used when none is passed
+/// with `--config`
+pub const CONFIG_PATH_ENV_VAR: &str = "PIKE_CONFIG";
+
 /// Returns the default configuration path for pike regardless
 /// of OS
 pub fn default_config_file_path() -> Result<PathBuf, String> {
@@ -28,10 +34,245 @@ pub fn default_config_dir_path() -> Result<PathBuf, String> {
         None => Err("Failed to get the configuration directory".to_string()),
     }
 }
+
+/// Returns the path of the file storing the session of the workspace in `cwd`.
+/// Sessions are kept in the data directory, one per workspace, named after the
+/// path of the workspace.
+pub fn session_file_path(cwd: &Path) -> Result<PathBuf, String> {
+    let mut path = dirs::data_dir().ok_or("Failed to get the data directory")?;
+    path.push("pike");
+    path.push("sessions");
+    let name = cwd
+        .to_string_lossy()
+        .replace(std::path::MAIN_SEPARATOR, "%");
+    path.push(format!("{name}.toml"));
+    Ok(path)
+}
+
+/// Returns the path of the file backing the scratch buffer, which is shared
+/// by all workspaces and kept in the data directory
+pub fn scratch_file_path() -> Result<PathBuf, String> {
+    let mut path = dirs::data_dir().ok_or("Failed to get the data directory")?;
+    path.push("pike");
+    path.push("scratch.txt");
+    Ok(path)
+}
+
+/// Default column at which paragraphs are wrapped when reflowing
+pub const DEFAULT_TEXT_WIDTH: usize = 80;
+
+/// Default number of columns a level of indentation takes
+pub const DEFAULT_INDENT_WIDTH: usize = 4;
+
+/// Default number of search results highlighted at once
+pub const DEFAULT_MAX_SEARCH_HIGHLIGHTS: usize = 1000;
+/// Size in megabytes above which opening a file has to be confirmed
+pub const DEFAULT_LARGE_FILE_THRESHOLD_MB: usize = 100;
+/// Default number of texts last copied or cut which can be pasted
+pub const DEFAULT_CLIPBOARD_RING_SIZE: usize = 10;
+
+/// Default format of the inserted date and time, as in ISO 8601
+pub const DEFAULT_DATETIME_FORMAT: &str = "%Y-%m-%dT%H:%M:%S";
+
+/// Default contents of the status bar, the name of the file followed by
+/// the modification and read-only indicators
+pub const DEFAULT_STATUS_BAR_FORMAT: &str = "{filename}{modified} {read_only}";
+
+/// Default contents of the status bar with modal editing, which start with the mode
+pub const DEFAULT_MODAL_STATUS_BAR_FORMAT: &str = "{mode} {filename}{modified} {read_only}";
+
+/// Separates the left-aligned part of the status bar format from the right-aligned one
+pub const STATUS_BAR_ALIGN_RIGHT: &str = "%=";
+
+/// Placeholders which can be used in the status bar format
+const STATUS_BAR_PLACEHOLDERS: &[&str] = &[
+    "filename",
+    "modified",
+    "read_only",
+    "line",
+    "col",
+    "percent",
+    "encoding",
+    "changes",
+    "mode",
+];
+
+/// Character used to indent lines
+#[derive(Debug, PartialEq, Eq, Clone, Copy, Default)]
+pub enum IndentStyle {
+    #[default]
+    Spaces,
+    Tabs,
+}
+
+impl IndentStyle {
+    pub fn from_string(s: &str) -> Result<IndentStyle, String> {
+        match s {
+            "spaces" => Ok(IndentStyle::Spaces),
+            "tabs" => Ok(IndentStyle::Tabs),
+            _ => Err(format!("Invalid indent style: {s}")),
+        }
+    }
+}
+
+/// Characters used to end lines
+#[derive(Debug, PartialEq, Eq, Clone, Copy, Default)]
+pub enum LineEnding {
+    #[default]
+    Lf,
+    CrLf,
+}
+
+impl LineEnding {
+    pub fn from_string(s: &str) -> Result<LineEnding, String> {
+        match s {
+            "lf" => Ok(LineEnding::Lf),
+            "crlf" => Ok(LineEnding::CrLf),
+            _ => Err(format!("Invalid line ending: {s}")),
+        }
+    }
+
+    pub fn as_str(&self) -> &'static str {
+        match self {
+            LineEnding::Lf => "\n",
+            LineEnding::CrLf => "\r\n",
+        }
+    }
+}
+
+/// Directory against which relative paths entered in the file input are resolved
+#[derive(Debug, PartialEq, Eq, Clone, Copy, Default)]
+pub enum PathBase {
+    #[default]
+    WorkingDirectory,
+    CurrentFile,
+}
+
+impl PathBase {
+    pub fn from_string(s: &str) -> Result<PathBase, String> {
+        match s {
+            "working_directory" => Ok(PathBase::WorkingDirectory),
+            "current_file" => Ok(PathBase::CurrentFile),
+            _ => Err(format!("Invalid relative path base: {s}")),
+        }
+    }
+}
+
+/// Settings affecting how a buffer is edited, which may differ between
+/// buffers, e.g. when adopted from the file being edited
+#[derive(Debug, PartialEq, Eq, Clone, Copy)]
+pub struct EditorSettings {
+    pub indent_style: IndentStyle,
+    pub indent_width: usize,
+}
+
+impl Default for EditorSettings {
+    fn default() -> EditorSettings {
+        EditorSettings {
+            indent_style: IndentStyle::default(),
+            indent_width: DEFAULT_INDENT_WIDTH,
+        }
+    }
+}
+
+/// Look of the gutter left of the buffer, which holds the line numbers
+/// and the marks of the lines
+#[derive(Debug, PartialEq, Eq, Clone, Copy)]
+pub struct GutterStyle {
+    /// Character drawn between the gutter and the text, if any
+    pub separator: Option<char>,
+    /// Columns left blank before the contents of the gutter
+    pub padding_left: usize,
+    /// Columns left blank after the contents of the gutter, before the separator
+    pub padding_right: usize,
+}
+
+impl Default for GutterStyle {
+    fn default() -> GutterStyle {
+        GutterStyle {
+            separator: None,
+            padding_left: 0,
+            padding_right: 1,
+        }
+    }
+}
+
 /// Editor configuration
 #[derive(Debug, PartialEq, Eq)]
 pub struct Config {
     pub key_mappings: HashMap<KeyShortcut, Operation>,
+    /// Additional key mappings, active only while no buffer is open
+    pub welcome_key_mappings: HashMap<KeyShortcut, Operation>,
+    /// Additional key mappings, active only in the normal mode of modal editing
+    pub normal_key_mappings: HashMap<KeyShortcut, Operation>,
+    /// Key mappings of the actions of the file input
+    pub file_input_key_mappings: HashMap<KeyShortcut, InputAction>,
+    /// Key mappings of the actions of the search input
+    pub search_key_mappings: HashMap<KeyShortcut, InputAction>,
+    /// Key mappings of the actions of the picker
+    pub picker_key_mappings: HashMap<KeyShortcut, InputAction>,
+    /// Column at which paragraphs are wrapped when reflowing
+    pub text_width: usize,
+    /// Settings used for buffers which don't override them
+    pub editor_settings: EditorSettings,
+    /// Whether to adopt the indentation of opened files instead
+    /// of the configured one
+    pub detect_indentation: bool,
+    /// Whether pasted blocks of lines are re-indented to the cursor's line
+    pub paste_reindent: bool,
+    /// Whether the indentation of pasted text is converted to the
+    /// indent style of the buffer
+    pub normalize_paste_indentation: bool,
+    /// Whether copying, cutting and surrounding the selection leave out
+    /// the whitespace at its ends
+    pub trim_selection: bool,
+    /// Whether the selection stays after copying it, until the cursor moves
+    pub keep_selection_after_copy: bool,
+    /// Number of texts last copied or cut which are kept to be pasted
+    pub clipboard_ring_size: usize,
+    /// Whether large scroll jumps are animated over a few frames
+    pub smooth_scroll: bool,
+    /// Whether a vertical scrollbar is displayed next to the buffer
+    pub scrollbar: bool,
+    /// Whether line numbers are displayed in a gutter left of the buffer
+    pub line_numbers: bool,
+    /// Separator and padding of the gutter
+    pub gutter_style: GutterStyle,
+    /// Maximum number of search results highlighted at once, the ones
+    /// nearest to the focused result are preferred
+    pub max_search_highlights: usize,
+    /// Size in megabytes above which opening a file has to be confirmed
+    pub large_file_threshold_mb: usize,
+    /// Whether a buffer opened from a symlink is bound to the link's target
+    /// rather than to the link itself
+    pub follow_symlinks: bool,
+    /// Words replaced with their expansions when typed
+    pub abbreviations: HashMap<String, String>,
+    /// Line ending used when normalizing the line endings of a buffer
+    pub line_ending: LineEnding,
+    /// Directory against which relative paths entered in the file input are resolved
+    pub relative_path_base: PathBase,
+    /// strftime-style format of the inserted date and time
+    pub datetime_format: String,
+    /// Whether the first edit of a file outside the workspace or owned
+    /// by root has to be confirmed
+    pub confirm_edit_outside_workspace: bool,
+    /// Template of the status bar contents, with placeholders in braces
+    pub status_bar_format: String,
+    /// Whether the session of the workspace is saved when quitting and
+    /// restored when starting without a file
+    pub restore_session: bool,
+    /// Whether a scratch buffer backed by a file in the data directory is
+    /// opened when starting without a file, and saved when quitting
+    pub scratch_buffer: bool,
+    /// File backing the scratch buffer, instead of the one in the data directory
+    pub scratch_file: Option<PathBuf>,
+    /// Whether pressing a ctrl-modified key which isn't bound to anything
+    /// is reported in the status bar
+    pub report_unbound_keys: bool,
+    /// Whether keys are commands in a normal mode and only typed into the
+    /// buffer in an insert mode, like in vim
+    pub modal_editing: bool,
 }
 
 #[allow(dead_code)]
@@ -45,30 +286,291 @@ impl Config {
             .map_err(|e| format!("Error parsing configuration file: {e}"))?;
 
         if let Some(keymap_table) = parsed.get("keymaps").and_then(|keys| keys.as_table()) {
-            let keymap_pairs = Config::keymap_pairs_from_toml_table(keymap_table)?;
+            let keymap_pairs =
+                Config::keymap_pairs_from_toml_table(keymap_table, Operation::from_string)?;
+            return_value.key_mappings = rebind(&return_value.key_mappings, keymap_pairs);
+        }
 
-            // Reverse the key_mappings (switch KeyShortcut and Operation)
-            let mut reversed_keymaps: HashMap<Operation, KeyShortcut> = return_value
-                .key_mappings
-                .iter()
-                .map(|(sh, op)| (op.clone(), sh.clone()))
-                .collect();
+        if let Some(welcome_table) = parsed
+            .get("welcome_keymaps")
+            .and_then(|keys| keys.as_table())
+        {
+            return_value.welcome_key_mappings =
+                Config::keymap_pairs_from_toml_table(welcome_table, Operation::from_string)?
+                    .into_iter()
+                    .map(|(op, sh)| (sh, op))
+                    .collect();
+        }
+
+        if let Some(normal_table) = parsed
+            .get("normal_keymaps")
+            .and_then(|keys| keys.as_table())
+        {
+            let keymap_pairs =
+                Config::keymap_pairs_from_toml_table(normal_table, Operation::from_string)?;
+            return_value.normal_key_mappings =
+                rebind(&return_value.normal_key_mappings, keymap_pairs);
+        }
+
+        if let Some(file_input_table) = parsed
+            .get("file_input_keymaps")
+            .and_then(|keys| keys.as_table())
+        {
+            let keymap_pairs =
+                Config::keymap_pairs_from_toml_table(file_input_table, InputAction::from_string)?;
+            return_value.file_input_key_mappings =
+                rebind(&return_value.file_input_key_mappings, keymap_pairs);
+        }
+
+        if let Some(search_table) = parsed
+            .get("search_keymaps")
+            .and_then(|keys| keys.as_table())
+        {
+            let keymap_pairs =
+                Config::keymap_pairs_from_toml_table(search_table, InputAction::from_string)?;
+            return_value.search_key_mappings =
+                rebind(&return_value.search_key_mappings, keymap_pairs);
+        }
+
+        if let Some(picker_table) = parsed
+            .get("picker_keymaps")
+            .and_then(|keys| keys.as_table())
+        {
+            let keymap_pairs =
+                Config::keymap_pairs_from_toml_table(picker_table, InputAction::from_string)?;
+            return_value.picker_key_mappings =
+                rebind(&return_value.picker_key_mappings, keymap_pairs);
+        }
+
+        if let Some(abbreviations) = parsed.get("abbreviations") {
+            return_value.abbreviations = Config::abbreviations_from_toml(abbreviations)?;
+        }
+
+        if let Some(text_width) = parsed.get("text_width") {
+            return_value.text_width = Config::positive_usize_from_toml(text_width, "text_width")?;
+        }
 
-            // Extend the reversed keymap with new keymap pairs
-            for (op, sh) in keymap_pairs {
-                reversed_keymaps.insert(op, sh);
+        if let Some(max_highlights) = parsed.get("max_search_highlights") {
+            return_value.max_search_highlights =
+                Config::positive_usize_from_toml(max_highlights, "max_search_highlights")?;
+        }
+
+        if let Some(threshold) = parsed.get("large_file_threshold_mb") {
+            return_value.large_file_threshold_mb =
+                Config::positive_usize_from_toml(threshold, "large_file_threshold_mb")?;
+        }
+
+        if let Some(follow_symlinks) = parsed.get("follow_symlinks") {
+            return_value.follow_symlinks =
+                Config::bool_from_toml(follow_symlinks, "follow_symlinks")?;
+        }
+
+        if let Some(indent_width) = parsed.get("indent_width") {
+            return_value.editor_settings.indent_width =
+                Config::positive_usize_from_toml(indent_width, "indent_width")?;
+        }
+
+        if let Some(indent_style) = parsed.get("indent_style") {
+            let indent_style = indent_style
+                .as_str()
+                .ok_or("indent_style has to be a string")?;
+            return_value.editor_settings.indent_style = IndentStyle::from_string(indent_style)?;
+        }
+
+        if let Some(base) = parsed.get("relative_path_base") {
+            let base = base
+                .as_str()
+                .ok_or("relative_path_base has to be a string")?;
+            return_value.relative_path_base = PathBase::from_string(base)?;
+        }
+
+        if let Some(line_ending) = parsed.get("line_ending") {
+            let line_ending = line_ending
+                .as_str()
+                .ok_or("line_ending has to be a string")?;
+            return_value.line_ending = LineEnding::from_string(line_ending)?;
+        }
+
+        if let Some(datetime_format) = parsed.get("datetime_format") {
+            let datetime_format = datetime_format
+                .as_str()
+                .ok_or("datetime_format has to be a string")?;
+            if StrftimeItems::new(datetime_format).any(|item| item == Item::Error) {
+                return Err(format!("Invalid datetime format: {datetime_format}"));
             }
+            return_value.datetime_format = datetime_format.to_string();
+        }
+
+        if let Some(status_bar_format) = parsed.get("status_bar_format") {
+            let status_bar_format = status_bar_format
+                .as_str()
+                .ok_or("status_bar_format has to be a string")?;
+            Config::validate_status_bar_format(status_bar_format)?;
+            return_value.status_bar_format = status_bar_format.to_string();
+        }
+
+        if let Some(detect_indentation) = parsed.get("detect_indentation") {
+            return_value.detect_indentation =
+                Config::bool_from_toml(detect_indentation, "detect_indentation")?;
+        }
+
+        if let Some(paste_reindent) = parsed.get("paste_reindent") {
+            return_value.paste_reindent = Config::bool_from_toml(paste_reindent, "paste_reindent")?;
+        }
+
+        if let Some(trim_selection) = parsed.get("trim_selection") {
+            return_value.trim_selection = Config::bool_from_toml(trim_selection, "trim_selection")?;
+        }
+
+        if let Some(ring_size) = parsed.get("clipboard_ring_size") {
+            return_value.clipboard_ring_size =
+                Config::positive_usize_from_toml(ring_size, "clipboard_ring_size")?;
+        }
+
+        if let Some(keep_selection) = parsed.get("keep_selection_after_copy") {
+            return_value.keep_selection_after_copy =
+                Config::bool_from_toml(keep_selection, "keep_selection_after_copy")?;
+        }
+
+        if let Some(normalize) = parsed.get("normalize_paste_indentation") {
+            return_value.normalize_paste_indentation =
+                Config::bool_from_toml(normalize, "normalize_paste_indentation")?;
+        }
+
+        if let Some(confirm_edit) = parsed.get("confirm_edit_outside_workspace") {
+            return_value.confirm_edit_outside_workspace =
+                Config::bool_from_toml(confirm_edit, "confirm_edit_outside_workspace")?;
+        }
+
+        if let Some(restore_session) = parsed.get("restore_session") {
+            return_value.restore_session =
+                Config::bool_from_toml(restore_session, "restore_session")?;
+        }
+
+        if let Some(modal_editing) = parsed.get("modal_editing") {
+            return_value.modal_editing = Config::bool_from_toml(modal_editing, "modal_editing")?;
+            if return_value.modal_editing && !parsed.contains_key("status_bar_format") {
+                return_value.status_bar_format = DEFAULT_MODAL_STATUS_BAR_FORMAT.to_string();
+            }
+        }
+
+        if let Some(scratch_buffer) = parsed.get("scratch_buffer") {
+            return_value.scratch_buffer = Config::bool_from_toml(scratch_buffer, "scratch_buffer")?;
+        }
 
-            // Rebuild the key_mappings with reversed keys and operations
-            return_value.key_mappings = reversed_keymaps
-                .into_iter()
-                .map(|(op, sh)| (sh, op))
-                .collect();
+        if let Some(scratch_file) = parsed.get("scratch_file") {
+            let scratch_file = scratch_file
+                .as_str()
+                .ok_or("scratch_file has to be a string")?;
+            return_value.scratch_file = Some(PathBuf::from(scratch_file));
+        }
+
+        if let Some(report_unbound_keys) = parsed.get("report_unbound_keys") {
+            return_value.report_unbound_keys =
+                Config::bool_from_toml(report_unbound_keys, "report_unbound_keys")?;
+        }
+
+        if let Some(smooth_scroll) = parsed.get("smooth_scroll") {
+            return_value.smooth_scroll = Config::bool_from_toml(smooth_scroll, "smooth_scroll")?;
+        }
+
+        if let Some(scrollbar) = parsed.get("scrollbar") {
+            return_value.scrollbar = Config::bool_from_toml(scrollbar, "scrollbar")?;
+        }
+
+        if let Some(line_numbers) = parsed.get("line_numbers") {
+            return_value.line_numbers = Config::bool_from_toml(line_numbers, "line_numbers")?;
+        }
+
+        if let Some(separator) = parsed.get("gutter_separator") {
+            let separator = separator
+                .as_str()
+                .ok_or("gutter_separator has to be a string")?;
+            let mut chars = separator.chars();
+            return_value.gutter_style.separator = match (chars.next(), chars.next()) {
+                (separator, None) => separator,
+                _ => return Err("gutter_separator has to be a single character".to_string()),
+            };
+        }
+
+        if let Some(padding) = parsed.get("gutter_padding_left") {
+            return_value.gutter_style.padding_left =
+                Config::usize_from_toml(padding, "gutter_padding_left")?;
+        }
+
+        if let Some(padding) = parsed.get("gutter_padding_right") {
+            return_value.gutter_style.padding_right =
+                Config::usize_from_toml(padding, "gutter_padding_right")?;
         }
 
         Ok(return_value)
     }
 
+    /// Reads a positive integer from a toml value, returning an error
+    /// mentioning `key` if the value is of a different type or not positive
+    fn positive_usize_from_toml(value: &toml::Value, key: &str) -> Result<usize, String> {
+        value
+            .as_integer()
+            .filter(|v| *v > 0)
+            .map(|v| v as usize)
+            .ok_or(format!("{key} has to be a positive integer"))
+    }
+
+    /// Reads a non-negative integer from a toml value, returning an error
+    /// mentioning `key` if the value is of a different type or negative
+    fn usize_from_toml(value: &toml::Value, key: &str) -> Result<usize, String> {
+        value
+            .as_integer()
+            .filter(|v| *v >= 0)
+            .map(|v| v as usize)
+            .ok_or(format!("{key} has to be a non-negative integer"))
+    }
+
+    /// Checks that all the placeholders in a status bar format are known
+    /// and closed
+    fn validate_status_bar_format(format: &str) -> Result<(), String> {
+        for placeholder in format.split('{').skip(1) {
+            let name = placeholder
+                .split_once('}')
+                .map(|(name, _)| name)
+                .ok_or(format!(
+                    "Unclosed placeholder in status bar format: {format}"
+                ))?;
+            if !STATUS_BAR_PLACEHOLDERS.contains(&name) {
+                return Err(format!("Unknown status bar placeholder: {{{name}}}"));
+            }
+        }
+        Ok(())
+    }
+
+    /// Reads the abbreviations from a toml table mapping words to strings
+    fn abbreviations_from_toml(value: &toml::Value) -> Result<HashMap<String, String>, String> {
+        let table = value.as_table().ok_or("abbreviations have to be a table")?;
+
+        table
+            .iter()
+            .map(|(abbreviation, expansion)| {
+                if abbreviation.is_empty()
+                    || !abbreviation
+                        .chars()
+                        .all(|c| c.is_alphanumeric() || c == '_')
+                {
+                    return Err(format!("Invalid abbreviation: {abbreviation:?}"));
+                }
+                let expansion = expansion
+                    .as_str()
+                    .ok_or(format!("Expansion of {abbreviation} has to be a string"))?;
+                Ok((abbreviation.clone(), expansion.to_string()))
+            })
+            .collect()
+    }
+
+    /// Reads a boolean from a toml value, returning an error mentioning
+    /// `key` if the value is of a different type
+    fn bool_from_toml(value: &toml::Value, key: &str) -> Result<bool, String> {
+        value.as_bool().ok_or(format!("{key} has to be a boolean"))
+    }
+
     /// Loads the configuration from the config file and returns it
     pub fn from_file(path: Option<&Path>) -> Result<Config, String> {
         match path {
@@ -83,17 +585,19 @@ impl Config {
 
     /// Creates a vector of pairs (shortcut, operation) to
     /// be inserted into the config's keymap section
-    /// over the default configuration
-    fn keymap_pairs_from_toml_table(
+    /// over the default configuration. The operations, or
+    /// input actions, are parsed with `parse`.
+    fn keymap_pairs_from_toml_table<T: Clone + Eq + Hash + fmt::Debug>(
         table: &Table,
-    ) -> Result<Vec<(Operation, KeyShortcut)>, String> {
-        let mut return_value = Vec::<(Operation, KeyShortcut)>::new();
+        parse: fn(&str) -> Result<T, String>,
+    ) -> Result<Vec<(T, KeyShortcut)>, String> {
+        let mut return_value = Vec::<(T, KeyShortcut)>::new();
         let mut seen_shortcuts = HashSet::<KeyShortcut>::new();
-        let mut seen_operations = HashSet::<Operation>::new();
+        let mut seen_operations = HashSet::<T>::new();
 
         for (shortcut, op) in table {
             let shortcut = KeyShortcut::from_string(shortcut)?;
-            let op = Operation::from_string(op.as_str().unwrap())?;
+            let op = parse(op.as_str().unwrap())?;
 
             if !seen_shortcuts.insert(shortcut.clone()) {
                 return Err(format!("Duplicate keybinding found: {:?}", shortcut));
@@ -110,6 +614,30 @@ impl Config {
     }
 }
 
+/// Returns `keymap` with the operations of `keymap_pairs` bound to their new
+/// shortcuts instead of the ones they were bound to
+fn rebind<T: Clone + Eq + Hash>(
+    keymap: &HashMap<KeyShortcut, T>,
+    keymap_pairs: Vec<(T, KeyShortcut)>,
+) -> HashMap<KeyShortcut, T> {
+    // Reverse the keymap (switch KeyShortcut and operation)
+    let mut reversed_keymap: HashMap<T, KeyShortcut> = keymap
+        .iter()
+        .map(|(sh, op)| (op.clone(), sh.clone()))
+        .collect();
+
+    // Extend the reversed keymap with new keymap pairs
+    for (op, sh) in keymap_pairs {
+        reversed_keymap.insert(op, sh);
+    }
+
+    // Rebuild the keymap with reversed keys and operations
+    reversed_keymap
+        .into_iter()
+        .map(|(op, sh)| (sh, op))
+        .collect()
+}
+
 impl Default for Config {
     fn default() -> Config {
         let key_mappings = HashMap::<KeyShortcut, Operation>::from([
@@ -137,6 +665,14 @@ impl Default for Config {
                 KeyShortcut::new(KeyCode::Char('f'), KeyModifiers::CONTROL),
                 Operation::SearchInCurrentBuffer,
             ),
+            (
+                KeyShortcut::new(KeyCode::Char('j'), KeyModifiers::CONTROL),
+                Operation::SearchAndReplaceInCurrentBuffer,
+            ),
+            (
+                KeyShortcut::new(KeyCode::Char('b'), KeyModifiers::CONTROL),
+                Operation::OpenBufferPicker,
+            ),
             (
                 KeyShortcut::new(KeyCode::Char('z'), KeyModifiers::CONTROL),
                 Operation::Undo,
@@ -149,9 +685,175 @@ impl Default for Config {
                 KeyShortcut::new(KeyCode::Char('q'), KeyModifiers::CONTROL),
                 Operation::Quit,
             ),
+            (
+                KeyShortcut::new(KeyCode::Char('q'), KeyModifiers::ALT),
+                Operation::ReflowParagraph,
+            ),
+            (
+                KeyShortcut::new(KeyCode::Char('h'), KeyModifiers::ALT),
+                Operation::ToggleHexView,
+            ),
+            (
+                KeyShortcut::new(KeyCode::Tab, KeyModifiers::NONE),
+                Operation::Indent,
+            ),
+            (
+                KeyShortcut::new(KeyCode::Char('s'), KeyModifiers::ALT),
+                Operation::Surround,
+            ),
+            (
+                KeyShortcut::new(KeyCode::Char('t'), KeyModifiers::CONTROL),
+                Operation::TransposeChars,
+            ),
+            (
+                KeyShortcut::new(KeyCode::Char('t'), KeyModifiers::ALT),
+                Operation::TransposeWords,
+            ),
+            (
+                KeyShortcut::new(KeyCode::Char('k'), KeyModifiers::CONTROL),
+                Operation::DeleteToLineEnd,
+            ),
+            (
+                KeyShortcut::new(KeyCode::Char('u'), KeyModifiers::CONTROL),
+                Operation::DeleteToLineStart,
+            ),
+            (
+                KeyShortcut::new(KeyCode::Char('c'), KeyModifiers::CONTROL),
+                Operation::Copy,
+            ),
+            (
+                KeyShortcut::new(KeyCode::Char('x'), KeyModifiers::CONTROL),
+                Operation::Cut,
+            ),
+            (
+                KeyShortcut::new(KeyCode::Char('v'), KeyModifiers::CONTROL),
+                Operation::Paste,
+            ),
+            (
+                KeyShortcut::new(KeyCode::F(1), KeyModifiers::NONE),
+                Operation::ShowHelp,
+            ),
+        ]);
+
+        let mut file_input_key_mappings = HashMap::<KeyShortcut, InputAction>::from([
+            (
+                KeyShortcut::new(KeyCode::Enter, KeyModifiers::NONE),
+                InputAction::Confirm,
+            ),
+            (
+                KeyShortcut::new(KeyCode::Esc, KeyModifiers::NONE),
+                InputAction::Cancel,
+            ),
         ]);
 
-        Config { key_mappings }
+        let mut search_key_mappings = file_input_key_mappings.clone();
+        let mut picker_key_mappings = file_input_key_mappings.clone();
+        file_input_key_mappings.extend([(
+            KeyShortcut::new(KeyCode::Tab, KeyModifiers::NONE),
+            InputAction::Complete,
+        )]);
+        search_key_mappings.extend([
+            (
+                KeyShortcut::new(KeyCode::Right, KeyModifiers::NONE),
+                InputAction::Next,
+            ),
+            (
+                KeyShortcut::new(KeyCode::Left, KeyModifiers::NONE),
+                InputAction::Previous,
+            ),
+            (
+                KeyShortcut::new(KeyCode::Char('c'), KeyModifiers::ALT),
+                InputAction::ToggleCaseInsensitive,
+            ),
+            (
+                KeyShortcut::new(KeyCode::Char('w'), KeyModifiers::ALT),
+                InputAction::ToggleWholeWord,
+            ),
+            (
+                KeyShortcut::new(KeyCode::Char('a'), KeyModifiers::ALT),
+                InputAction::ReplaceAll,
+            ),
+        ]);
+        picker_key_mappings.extend([
+            (
+                KeyShortcut::new(KeyCode::Down, KeyModifiers::NONE),
+                InputAction::Next,
+            ),
+            (
+                KeyShortcut::new(KeyCode::Up, KeyModifiers::NONE),
+                InputAction::Previous,
+            ),
+            (
+                KeyShortcut::new(KeyCode::Tab, KeyModifiers::NONE),
+                InputAction::ToggleMark,
+            ),
+            (
+                KeyShortcut::new(KeyCode::Char('i'), KeyModifiers::ALT),
+                InputAction::ToggleIgnoredFiles,
+            ),
+        ]);
+
+        let normal_key_mappings = HashMap::<KeyShortcut, Operation>::from([
+            (
+                KeyShortcut::new(KeyCode::Char('u'), KeyModifiers::NONE),
+                Operation::Undo,
+            ),
+            (
+                KeyShortcut::new(KeyCode::Char('y'), KeyModifiers::NONE),
+                Operation::Copy,
+            ),
+            (
+                KeyShortcut::new(KeyCode::Char('d'), KeyModifiers::NONE),
+                Operation::Cut,
+            ),
+            (
+                KeyShortcut::new(KeyCode::Char('p'), KeyModifiers::NONE),
+                Operation::Paste,
+            ),
+            (
+                KeyShortcut::new(KeyCode::Char('/'), KeyModifiers::NONE),
+                Operation::SearchInCurrentBuffer,
+            ),
+            (
+                KeyShortcut::new(KeyCode::Char('n'), KeyModifiers::NONE),
+                Operation::SearchNext,
+            ),
+        ]);
+
+        Config {
+            key_mappings,
+            welcome_key_mappings: HashMap::new(),
+            normal_key_mappings,
+            file_input_key_mappings,
+            search_key_mappings,
+            picker_key_mappings,
+            text_width: DEFAULT_TEXT_WIDTH,
+            editor_settings: EditorSettings::default(),
+            detect_indentation: true,
+            paste_reindent: true,
+            normalize_paste_indentation: false,
+            trim_selection: false,
+            keep_selection_after_copy: false,
+            clipboard_ring_size: DEFAULT_CLIPBOARD_RING_SIZE,
+            smooth_scroll: false,
+            scrollbar: false,
+            line_numbers: true,
+            gutter_style: GutterStyle::default(),
+            max_search_highlights: DEFAULT_MAX_SEARCH_HIGHLIGHTS,
+            large_file_threshold_mb: DEFAULT_LARGE_FILE_THRESHOLD_MB,
+            follow_symlinks: true,
+            abbreviations: HashMap::new(),
+            line_ending: LineEnding::default(),
+            relative_path_base: PathBase::default(),
+            datetime_format: DEFAULT_DATETIME_FORMAT.to_string(),
+            confirm_edit_outside_workspace: false,
+            status_bar_format: DEFAULT_STATUS_BAR_FORMAT.to_string(),
+            restore_session: false,
+            scratch_buffer: false,
+            scratch_file: None,
+            report_unbound_keys: false,
+            modal_editing: false,
+        }
     }
 }
 
@@ -161,9 +863,12 @@ mod config_test {
 
     use crossterm::event::{KeyCode, KeyModifiers};
 
-    use crate::operations::Operation;
+    use crate::operations::{InputAction, Operation};
 
-    use super::{Config, KeyShortcut};
+    use super::{
+        Config, EditorSettings, GutterStyle, IndentStyle, KeyShortcut, LineEnding, PathBase,
+        DEFAULT_CLIPBOARD_RING_SIZE, DEFAULT_MODAL_STATUS_BAR_FORMAT,
+    };
 
     #[test]
     fn from_toml_keymap_section_valid_case() {
@@ -203,6 +908,14 @@ mod config_test {
                 KeyShortcut::new(KeyCode::Char('f'), KeyModifiers::CONTROL),
                 Operation::SearchInCurrentBuffer,
             ),
+            (
+                KeyShortcut::new(KeyCode::Char('j'), KeyModifiers::CONTROL),
+                Operation::SearchAndReplaceInCurrentBuffer,
+            ),
+            (
+                KeyShortcut::new(KeyCode::Char('b'), KeyModifiers::CONTROL),
+                Operation::OpenBufferPicker,
+            ),
             (
                 KeyShortcut::new(KeyCode::Char('z'), KeyModifiers::CONTROL),
                 Operation::Undo,
@@ -215,6 +928,54 @@ mod config_test {
                 KeyShortcut::new(KeyCode::Char('q'), KeyModifiers::CONTROL),
                 Operation::Quit,
             ),
+            (
+                KeyShortcut::new(KeyCode::Char('q'), KeyModifiers::ALT),
+                Operation::ReflowParagraph,
+            ),
+            (
+                KeyShortcut::new(KeyCode::Char('h'), KeyModifiers::ALT),
+                Operation::ToggleHexView,
+            ),
+            (
+                KeyShortcut::new(KeyCode::Tab, KeyModifiers::NONE),
+                Operation::Indent,
+            ),
+            (
+                KeyShortcut::new(KeyCode::Char('s'), KeyModifiers::ALT),
+                Operation::Surround,
+            ),
+            (
+                KeyShortcut::new(KeyCode::Char('t'), KeyModifiers::CONTROL),
+                Operation::TransposeChars,
+            ),
+            (
+                KeyShortcut::new(KeyCode::Char('t'), KeyModifiers::ALT),
+                Operation::TransposeWords,
+            ),
+            (
+                KeyShortcut::new(KeyCode::Char('k'), KeyModifiers::CONTROL),
+                Operation::DeleteToLineEnd,
+            ),
+            (
+                KeyShortcut::new(KeyCode::Char('u'), KeyModifiers::CONTROL),
+                Operation::DeleteToLineStart,
+            ),
+            (
+                KeyShortcut::new(KeyCode::Char('c'), KeyModifiers::CONTROL),
+                Operation::Copy,
+            ),
+            (
+                KeyShortcut::new(KeyCode::Char('x'), KeyModifiers::CONTROL),
+                Operation::Cut,
+            ),
+            (
+                KeyShortcut::new(KeyCode::Char('v'), KeyModifiers::CONTROL),
+                Operation::Paste,
+            ),
+            (
+                KeyShortcut::new(KeyCode::F(1), KeyModifiers::NONE),
+                Operation::ShowHelp,
+            ),
         ]);
         assert_eq!(expected, actual);
     }
@@ -260,6 +1021,437 @@ mod config_test {
         }
     }
 
+    #[test]
+    fn from_toml_representation_large_file_threshold() {
+        let config = Config::from_toml_representation("large_file_threshold_mb = 500")
+            .expect("Failed to parse a valid threshold");
+        assert_eq!(config.large_file_threshold_mb, 500);
+        assert!(Config::from_toml_representation("large_file_threshold_mb = 0").is_err());
+    }
+
+    #[test]
+    fn from_toml_representation_modal_editing() {
+        assert!(!Config::default().modal_editing);
+        let config = Config::from_toml_representation(
+            r#"
+            modal_editing = true
+
+            [normal_keymaps]
+            "x" = "cut"
+            "#,
+        )
+        .expect("Failed to parse valid modal editing settings");
+        assert!(config.modal_editing);
+
+        // Binding an operation replaces its default key
+        let cut = KeyShortcut::new(KeyCode::Char('x'), KeyModifiers::NONE);
+        let default_cut = KeyShortcut::new(KeyCode::Char('d'), KeyModifiers::NONE);
+        assert_eq!(config.normal_key_mappings.get(&cut), Some(&Operation::Cut));
+        assert_eq!(config.normal_key_mappings.get(&default_cut), None);
+        assert_eq!(
+            config.normal_key_mappings.len(),
+            Config::default().normal_key_mappings.len()
+        );
+    }
+
+    #[test]
+    fn modal_editing_shows_mode_in_status_bar_by_default() {
+        let config = Config::from_toml_representation("modal_editing = true")
+            .expect("Failed to parse valid modal_editing setting");
+        assert_eq!(config.status_bar_format, DEFAULT_MODAL_STATUS_BAR_FORMAT);
+
+        let config = Config::from_toml_representation(
+            "modal_editing = true\nstatus_bar_format = \"{filename}\"",
+        )
+        .expect("Failed to parse valid modal_editing setting");
+        assert_eq!(config.status_bar_format, "{filename}");
+    }
+
+    #[test]
+    fn from_toml_representation_trim_selection() {
+        assert!(!Config::default().trim_selection);
+        let config = Config::from_toml_representation("trim_selection = true")
+            .expect("Failed to parse valid trim_selection setting");
+        assert!(config.trim_selection);
+    }
+
+    #[test]
+    fn from_toml_representation_clipboard_ring_size() {
+        assert_eq!(
+            Config::default().clipboard_ring_size,
+            DEFAULT_CLIPBOARD_RING_SIZE
+        );
+        let config = Config::from_toml_representation("clipboard_ring_size = 3")
+            .expect("Failed to parse valid clipboard_ring_size setting");
+        assert_eq!(config.clipboard_ring_size, 3);
+        assert!(Config::from_toml_representation("clipboard_ring_size = 0").is_err());
+    }
+
+    #[test]
+    fn from_toml_representation_keep_selection_after_copy() {
+        assert!(!Config::default().keep_selection_after_copy);
+        let config = Config::from_toml_representation("keep_selection_after_copy = true")
+            .expect("Failed to parse valid keep_selection_after_copy setting");
+        assert!(config.keep_selection_after_copy);
+        assert!(Config::from_toml_representation("keep_selection_after_copy = 1").is_err());
+    }
+
+    #[test]
+    fn from_toml_representation_scratch_buffer() {
+        assert!(!Config::default().scratch_buffer);
+        let config = Config::from_toml_representation("scratch_buffer = true")
+            .expect("Failed to parse valid scratch_buffer setting");
+        assert!(config.scratch_buffer);
+
+        assert_eq!(Config::default().scratch_file, None);
+        let config = Config::from_toml_representation("scratch_file = \"/tmp/notes.txt\"")
+            .expect("Failed to parse valid scratch_file setting");
+        assert_eq!(config.scratch_file, Some("/tmp/notes.txt".into()));
+        assert!(Config::from_toml_representation("scratch_file = 1").is_err());
+    }
+
+    #[test]
+    fn from_toml_representation_follow_symlinks() {
+        assert!(Config::default().follow_symlinks);
+        let config = Config::from_toml_representation("follow_symlinks = false")
+            .expect("Failed to parse valid follow_symlinks setting");
+        assert!(!config.follow_symlinks);
+    }
+
+    #[test]
+    fn from_toml_representation_max_search_highlights() {
+        let config = Config::from_toml_representation("max_search_highlights = 50")
+            .expect("Failed to parse a valid highlight limit");
+        assert_eq!(config.max_search_highlights, 50);
+
+        for s in ["max_search_highlights = 0", "max_search_highlights = true"] {
+            assert!(
+                Config::from_toml_representation(s).is_err(),
+                "Failed for: {s}"
+            );
+        }
+    }
+
+    #[test]
+    fn from_toml_representation_status_bar_format() {
+        let config =
+            Config::from_toml_representation("status_bar_format = \"{filename} %= {line}:{col}\"")
+                .expect("Failed to parse a valid status bar format");
+        assert_eq!(config.status_bar_format, "{filename} %= {line}:{col}");
+
+        for s in [
+            "status_bar_format = \"{name}\"",
+            "status_bar_format = \"{line\"",
+            "status_bar_format = 1",
+        ] {
+            assert!(
+                Config::from_toml_representation(s).is_err(),
+                "Failed for: {s}"
+            );
+        }
+    }
+
+    #[test]
+    fn from_toml_representation_text_width() {
+        let config = Config::from_toml_representation("text_width = 72")
+            .expect("Failed to parse a valid text width");
+        assert_eq!(config.text_width, 72);
+
+        for s in ["text_width = 0", "text_width = \"wide\""] {
+            assert!(
+                Config::from_toml_representation(s).is_err(),
+                "Failed for: {s}"
+            );
+        }
+    }
+
+    #[test]
+    fn from_toml_representation_indentation() {
+        let config = Config::from_toml_representation(
+            r#"
+            indent_style = "tabs"
+            indent_width = 8
+            detect_indentation = false
+            normalize_paste_indentation = true
+            "#,
+        )
+        .expect("Failed to parse valid indentation settings");
+        assert_eq!(
+            config.editor_settings,
+            EditorSettings {
+                indent_style: IndentStyle::Tabs,
+                indent_width: 8,
+            }
+        );
+        assert!(!config.detect_indentation);
+        assert!(config.normalize_paste_indentation);
+
+        for s in [
+            "indent_style = \"both\"",
+            "indent_width = 0",
+            "detect_indentation = \"yes\"",
+        ] {
+            assert!(
+                Config::from_toml_representation(s).is_err(),
+                "Failed for: {s}"
+            );
+        }
+    }
+
+    #[test]
+    fn from_toml_welcome_keymaps_section() {
+        let config = Config::from_toml_representation(
+            r#"
+            [welcome_keymaps]
+            "o" = "open_file"
+            "q" = "quit"
+            "#,
+        )
+        .expect("Failed to parse valid welcome keymaps");
+
+        assert_eq!(
+            config.welcome_key_mappings,
+            HashMap::from([
+                (
+                    KeyShortcut::new(KeyCode::Char('o'), KeyModifiers::NONE),
+                    Operation::OpenFile
+                ),
+                (
+                    KeyShortcut::new(KeyCode::Char('q'), KeyModifiers::NONE),
+                    Operation::Quit
+                ),
+            ])
+        );
+        assert_eq!(config.key_mappings, Config::default().key_mappings);
+    }
+
+    #[test]
+    fn from_toml_input_keymaps_sections() {
+        let config = Config::from_toml_representation(
+            r#"
+            [file_input_keymaps]
+            "ctrl+g" = "cancel"
+
+            [search_keymaps]
+            "ctrl+n" = "next"
+            "ctrl+p" = "previous"
+
+            [picker_keymaps]
+            "ctrl+t" = "toggle_mark"
+            "#,
+        )
+        .expect("Failed to parse valid input keymaps");
+
+        assert_eq!(
+            config.file_input_key_mappings,
+            HashMap::from([
+                (
+                    KeyShortcut::new(KeyCode::Enter, KeyModifiers::NONE),
+                    InputAction::Confirm
+                ),
+                (
+                    KeyShortcut::new(KeyCode::Char('g'), KeyModifiers::CONTROL),
+                    InputAction::Cancel
+                ),
+                (
+                    KeyShortcut::new(KeyCode::Tab, KeyModifiers::NONE),
+                    InputAction::Complete
+                ),
+            ])
+        );
+        assert_eq!(
+            config.search_key_mappings,
+            HashMap::from([
+                (
+                    KeyShortcut::new(KeyCode::Enter, KeyModifiers::NONE),
+                    InputAction::Confirm
+                ),
+                (
+                    KeyShortcut::new(KeyCode::Esc, KeyModifiers::NONE),
+                    InputAction::Cancel
+                ),
+                (
+                    KeyShortcut::new(KeyCode::Char('n'), KeyModifiers::CONTROL),
+                    InputAction::Next
+                ),
+                (
+                    KeyShortcut::new(KeyCode::Char('p'), KeyModifiers::CONTROL),
+                    InputAction::Previous
+                ),
+                (
+                    KeyShortcut::new(KeyCode::Char('c'), KeyModifiers::ALT),
+                    InputAction::ToggleCaseInsensitive
+                ),
+                (
+                    KeyShortcut::new(KeyCode::Char('w'), KeyModifiers::ALT),
+                    InputAction::ToggleWholeWord
+                ),
+                (
+                    KeyShortcut::new(KeyCode::Char('a'), KeyModifiers::ALT),
+                    InputAction::ReplaceAll
+                ),
+            ])
+        );
+        assert_eq!(
+            config.picker_key_mappings,
+            HashMap::from([
+                (
+                    KeyShortcut::new(KeyCode::Enter, KeyModifiers::NONE),
+                    InputAction::Confirm
+                ),
+                (
+                    KeyShortcut::new(KeyCode::Esc, KeyModifiers::NONE),
+                    InputAction::Cancel
+                ),
+                (
+                    KeyShortcut::new(KeyCode::Down, KeyModifiers::NONE),
+                    InputAction::Next
+                ),
+                (
+                    KeyShortcut::new(KeyCode::Up, KeyModifiers::NONE),
+                    InputAction::Previous
+                ),
+                (
+                    KeyShortcut::new(KeyCode::Char('t'), KeyModifiers::CONTROL),
+                    InputAction::ToggleMark
+                ),
+                (
+                    KeyShortcut::new(KeyCode::Char('i'), KeyModifiers::ALT),
+                    InputAction::ToggleIgnoredFiles
+                ),
+            ])
+        );
+
+        let invalid = "[search_keymaps]\n\"ctrl+n\" = \"open_file\"";
+        assert!(Config::from_toml_representation(invalid).is_err());
+    }
+
+    #[test]
+    fn from_toml_representation_scrolling() {
+        assert!(!Config::default().smooth_scroll);
+
+        let config = Config::from_toml_representation("smooth_scroll = true")
+            .expect("Failed to parse valid smooth_scroll setting");
+        assert!(config.smooth_scroll);
+
+        assert!(Config::from_toml_representation("smooth_scroll = 1").is_err());
+
+        let config = Config::from_toml_representation("scrollbar = true")
+            .expect("Failed to parse valid scrollbar setting");
+        assert!(config.scrollbar);
+
+        assert!(Config::default().line_numbers);
+        let config = Config::from_toml_representation("line_numbers = false")
+            .expect("Failed to parse valid line_numbers setting");
+        assert!(!config.line_numbers);
+    }
+
+    #[test]
+    fn from_toml_representation_gutter_style() {
+        assert_eq!(Config::default().gutter_style, GutterStyle::default());
+
+        let config = Config::from_toml_representation(
+            "gutter_separator = \"│\"\ngutter_padding_left = 2\ngutter_padding_right = 0",
+        )
+        .expect("Failed to parse valid gutter style");
+        assert_eq!(
+            config.gutter_style,
+            GutterStyle {
+                separator: Some('│'),
+                padding_left: 2,
+                padding_right: 0,
+            }
+        );
+
+        let config = Config::from_toml_representation("gutter_separator = \"\"")
+            .expect("Failed to parse empty gutter separator");
+        assert_eq!(config.gutter_style.separator, None);
+
+        for s in [
+            "gutter_separator = \"||\"",
+            "gutter_separator = 1",
+            "gutter_padding_left = -1",
+            "gutter_padding_right = \"1\"",
+        ] {
+            assert!(Config::from_toml_representation(s).is_err(), "{s}");
+        }
+    }
+
+    #[test]
+    fn from_toml_abbreviations_section() {
+        let config = Config::from_toml_representation(
+            r#"
+            [abbreviations]
+            teh = "the"
+            "#,
+        )
+        .expect("Failed to parse valid abbreviations");
+        assert_eq!(
+            config.abbreviations,
+            HashMap::from([("teh".to_string(), "the".to_string())])
+        );
+
+        for s in [
+            "abbreviations = 1",
+            "[abbreviations]\nteh = 1",
+            "[abbreviations]\n\"a b\" = \"c\"",
+        ] {
+            assert!(
+                Config::from_toml_representation(s).is_err(),
+                "Failed for: {s}"
+            );
+        }
+    }
+
+    #[test]
+    fn from_toml_representation_line_ending() {
+        assert_eq!(Config::default().line_ending, LineEnding::Lf);
+
+        let config = Config::from_toml_representation("line_ending = \"crlf\"")
+            .expect("Failed to parse valid line ending");
+        assert_eq!(config.line_ending, LineEnding::CrLf);
+
+        for s in ["line_ending = \"cr\"", "line_ending = 1"] {
+            assert!(
+                Config::from_toml_representation(s).is_err(),
+                "Failed for: {s}"
+            );
+        }
+    }
+
+    #[test]
+    fn from_toml_representation_relative_path_base() {
+        assert_eq!(
+            Config::default().relative_path_base,
+            PathBase::WorkingDirectory
+        );
+
+        let config = Config::from_toml_representation("relative_path_base = \"current_file\"")
+            .expect("Failed to parse valid relative path base");
+        assert_eq!(config.relative_path_base, PathBase::CurrentFile);
+
+        for s in ["relative_path_base = \"home\"", "relative_path_base = 1"] {
+            assert!(
+                Config::from_toml_representation(s).is_err(),
+                "Failed for: {s}"
+            );
+        }
+    }
+
+    #[test]
+    fn from_toml_representation_datetime_format() {
+        let config = Config::from_toml_representation("datetime_format = \"%d.%m.%Y\"")
+            .expect("Failed to parse valid datetime format");
+        assert_eq!(config.datetime_format, "%d.%m.%Y");
+
+        for s in ["datetime_format = \"%Q\"", "datetime_format = 1"] {
+            assert!(
+                Config::from_toml_representation(s).is_err(),
+                "Failed for: {s}"
+            );
+        }
+    }
+
     #[test]
     fn test_from_file_valid_case() {
         use std::io::Write;
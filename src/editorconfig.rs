@@ -0,0 +1,397 @@
+use std::fs;
+use std::path::Path;
+
+use crate::config::{IndentStyle, LineEnding};
+
+/// Name of the files looked up in the directories containing an opened file
+pub const EDITORCONFIG_FILE_NAME: &str = ".editorconfig";
+
+/// Properties of the EditorConfig format (<https://editorconfig.org>) supported
+/// by the editor. Properties not set by any matching section are None.
+#[derive(Debug, Default, Clone, Copy, PartialEq, Eq)]
+pub struct Properties {
+    pub indent_style: Option<IndentStyle>,
+    pub indent_size: Option<usize>,
+    pub tab_width: Option<usize>,
+    pub trim_trailing_whitespace: Option<bool>,
+    pub insert_final_newline: Option<bool>,
+    pub end_of_line: Option<LineEnding>,
+}
+
+impl Properties {
+    /// Returns the width of a level of indentation, which falls back to the
+    /// width of a tab, e.g. for `indent_size = tab`
+    pub fn indent_width(&self) -> Option<usize> {
+        self.indent_size.or(self.tab_width)
+    }
+
+    /// Returns `contents` as they should be saved, with trailing whitespace
+    /// trimmed and a final newline ending with `line_ending` inserted if the
+    /// properties ask for it
+    pub fn prepare_for_saving(&self, contents: &str, line_ending: LineEnding) -> String {
+        let mut prepared = if self.trim_trailing_whitespace == Some(true) {
+            contents
+                .split('\n')
+                .map(|line| match line.strip_suffix('\r') {
+                    Some(line) => format!("{}\r", line.trim_end()),
+                    None => line.trim_end().to_string(),
+                })
+                .collect::<Vec<_>>()
+                .join("\n")
+        } else {
+            contents.to_string()
+        };
+
+        if self.insert_final_newline == Some(true)
+            && !prepared.is_empty()
+            && !prepared.ends_with('\n')
+        {
+            prepared.push_str(line_ending.as_str());
+        }
+
+        prepared
+    }
+
+    /// Sets the property `key` to `value`, ignoring unknown properties and invalid
+    /// values. The special value `unset` removes a previously set property.
+    fn set(&mut self, key: &str, value: &str) {
+        let unset = value == "unset";
+        let number = value.parse::<usize>().ok().filter(|n| *n > 0);
+        let boolean = match value {
+            "true" => Some(true),
+            "false" => Some(false),
+            _ => None,
+        };
+
+        match key {
+            "indent_style" => {
+                self.indent_style = match value {
+                    "space" => Some(IndentStyle::Spaces),
+                    "tab" => Some(IndentStyle::Tabs),
+                    _ if unset => None,
+                    _ => return,
+                }
+            }
+            // A size of `tab` uses the tab width
+            "indent_size" if value == "tab" || unset => self.indent_size = None,
+            "indent_size" if number.is_some() => self.indent_size = number,
+            "tab_width" if number.is_some() || unset => self.tab_width = number,
+            "trim_trailing_whitespace" if boolean.is_some() || unset => {
+                self.trim_trailing_whitespace = boolean
+            }
+            "insert_final_newline" if boolean.is_some() || unset => {
+                self.insert_final_newline = boolean
+            }
+            "end_of_line" => {
+                self.end_of_line = match value {
+                    "lf" => Some(LineEnding::Lf),
+                    "crlf" => Some(LineEnding::CrLf),
+                    _ if unset => None,
+                    _ => return,
+                }
+            }
+            _ => {}
+        }
+    }
+}
+
+/// A section of an EditorConfig file, whose properties apply
+/// to the files matching its glob
+#[derive(Debug, PartialEq, Eq)]
+struct Section {
+    glob: String,
+    properties: Vec<(String, String)>,
+}
+
+impl Section {
+    /// Returns whether the section applies to the file at `path`, given
+    /// relative to the directory of the EditorConfig file with `/` separators.
+    /// A glob without a slash matches files of that name in any subdirectory.
+    fn matches(&self, path: &str) -> bool {
+        let glob = match self.glob.strip_prefix('/') {
+            Some(glob) => glob.to_string(),
+            None if self.glob.contains('/') => self.glob.clone(),
+            None => format!("**/{}", self.glob),
+        };
+
+        expand_braces(&glob).iter().any(|glob| {
+            let glob: Vec<char> = glob.chars().collect();
+            let path: Vec<char> = path.chars().collect();
+            glob_matches(&glob, &path)
+        })
+    }
+}
+
+/// Contents of an EditorConfig file
+#[derive(Debug, Default, PartialEq, Eq)]
+struct EditorConfig {
+    /// Whether files in parent directories are ignored
+    root: bool,
+    sections: Vec<Section>,
+}
+
+impl EditorConfig {
+    /// Parses an EditorConfig file. Lines which can't be parsed are skipped,
+    /// as are properties before the first section other than `root`.
+    fn parse(contents: &str) -> EditorConfig {
+        let mut editorconfig = EditorConfig::default();
+
+        for line in contents.lines().map(str::trim) {
+            if line.is_empty() || line.starts_with('#') || line.starts_with(';') {
+                continue;
+            }
+
+            if let Some(glob) = line.strip_prefix('[').and_then(|l| l.strip_suffix(']')) {
+                editorconfig.sections.push(Section {
+                    glob: glob.to_string(),
+                    properties: Vec::new(),
+                });
+                continue;
+            }
+
+            let Some((key, value)) = line.split_once('=') else {
+                continue;
+            };
+            let key = key.trim().to_lowercase();
+            let value = value.trim().to_lowercase();
+            match editorconfig.sections.last_mut() {
+                Some(section) => section.properties.push((key, value)),
+                None if key == "root" => editorconfig.root = value == "true",
+                None => {}
+            }
+        }
+
+        editorconfig
+    }
+}
+
+/// Finds the properties applying to the file at `path` in the EditorConfig files
+/// of its directory and the ones above it, up to the first one marked as root.
+/// Files nearer to `path` and later sections take precedence.
+pub fn properties_for(path: &Path) -> Properties {
+    let mut editorconfigs = Vec::new();
+    for dir in path.ancestors().skip(1) {
+        let Ok(contents) = fs::read_to_string(dir.join(EDITORCONFIG_FILE_NAME)) else {
+            continue;
+        };
+        let editorconfig = EditorConfig::parse(&contents);
+        let is_root = editorconfig.root;
+        editorconfigs.push((dir, editorconfig));
+        if is_root {
+            break;
+        }
+    }
+
+    let mut properties = Properties::default();
+    for (dir, editorconfig) in editorconfigs.iter().rev() {
+        let Ok(relative_path) = path.strip_prefix(dir) else {
+            continue;
+        };
+        let relative_path = relative_path
+            .components()
+            .map(|component| component.as_os_str().to_string_lossy())
+            .collect::<Vec<_>>()
+            .join("/");
+
+        for section in editorconfig
+            .sections
+            .iter()
+            .filter(|section| section.matches(&relative_path))
+        {
+            for (key, value) in &section.properties {
+                properties.set(key, value);
+            }
+        }
+    }
+
+    properties
+}
+
+/// Expands the first group of alternatives in braces, e.g. `*.{rs,toml}`,
+/// and the groups in the results, returning all the combinations
+fn expand_braces(glob: &str) -> Vec<String> {
+    let Some(open) = glob.find('{') else {
+        return vec![glob.to_string()];
+    };
+    let Some(close) = glob[open..].find('}').map(|close| open + close) else {
+        return vec![glob.to_string()];
+    };
+
+    let (prefix, alternatives, suffix) =
+        (&glob[..open], &glob[open + 1..close], &glob[close + 1..]);
+    alternatives
+        .split(',')
+        .flat_map(|alternative| expand_braces(&format!("{prefix}{alternative}{suffix}")))
+        .collect()
+}
+
+/// Matches `path` against a glob without braces. `*` matches anything but a `/`,
+/// `**` matches anything, `?` matches a single character other than `/` and
+/// brackets match a single character of a set, e.g. `[a-z]` or `[!0-9]`.
+fn glob_matches(glob: &[char], path: &[char]) -> bool {
+    match glob {
+        [] => path.is_empty(),
+        ['*', '*', rest @ ..] => {
+            // `**/` also matches no directories at all
+            rest.strip_prefix(&['/'])
+                .is_some_and(|rest| glob_matches(rest, path))
+                || (0..=path.len()).any(|skipped| glob_matches(rest, &path[skipped..]))
+        }
+        ['*', rest @ ..] => (0..=path.len())
+            .take_while(|skipped| !path[..*skipped].contains(&'/'))
+            .any(|skipped| glob_matches(rest, &path[skipped..])),
+        ['?', rest @ ..] => {
+            path.first().is_some_and(|c| *c != '/') && glob_matches(rest, &path[1..])
+        }
+        ['[', class @ ..] if class.contains(&']') => {
+            let end = class.iter().position(|c| *c == ']').unwrap_or_default();
+            let (negated, set) = match &class[..end] {
+                ['!', set @ ..] => (true, set),
+                set => (false, set),
+            };
+            path.first()
+                .is_some_and(|c| *c != '/' && class_contains(set, *c) != negated)
+                && glob_matches(&class[end + 1..], &path[1..])
+        }
+        ['\\', c, rest @ ..] | [c, rest @ ..] => {
+            path.first() == Some(c) && glob_matches(rest, &path[1..])
+        }
+    }
+}
+
+/// Returns whether the set of characters in brackets, e.g. `a-z_`, contains `c`
+fn class_contains(set: &[char], c: char) -> bool {
+    let mut index = 0;
+    while index < set.len() {
+        if index + 2 < set.len() && set[index + 1] == '-' {
+            if (set[index]..=set[index + 2]).contains(&c) {
+                return true;
+            }
+            index += 3;
+        } else {
+            if set[index] == c {
+                return true;
+            }
+            index += 1;
+        }
+    }
+    false
+}
+
+#[cfg(test)]
+mod tests {
+    use std::fs;
+
+    use super::{expand_braces, properties_for, EditorConfig, Properties, Section};
+    use crate::config::{IndentStyle, LineEnding};
+
+    fn section(glob: &str) -> Section {
+        Section {
+            glob: glob.to_string(),
+            properties: Vec::new(),
+        }
+    }
+
+    #[test]
+    fn test_section_globs() {
+        assert!(section("*").matches("main.rs"));
+        assert!(section("*.rs").matches("src/main.rs"));
+        assert!(!section("*.rs").matches("main.rst"));
+        assert!(section("src/*.rs").matches("src/main.rs"));
+        assert!(!section("src/*.rs").matches("src/ui/mod.rs"));
+        assert!(!section("/*.rs").matches("src/main.rs"));
+        assert!(section("src/**/*.rs").matches("src/main.rs"));
+        assert!(section("src/**/*.rs").matches("src/ui/mod.rs"));
+        assert!(section("*.{json,toml}").matches("Cargo.toml"));
+        assert!(!section("*.{json,toml}").matches("Cargo.lock"));
+        assert!(section("file?.[a-c]").matches("file1.b"));
+        assert!(!section("file?.[!a-c]").matches("file1.b"));
+        assert!(section("Makefile").matches("docs/Makefile"));
+    }
+
+    #[test]
+    fn test_expand_braces() {
+        assert_eq!(expand_braces("*.rs"), vec!["*.rs"]);
+        assert_eq!(
+            expand_braces("{a,b}.{c,d}"),
+            vec!["a.c", "a.d", "b.c", "b.d"]
+        );
+    }
+
+    #[test]
+    fn test_parse() {
+        let editorconfig = EditorConfig::parse(
+            "# comment\nroot = true\n\n[*.md]\nTrim_Trailing_Whitespace = False\n; comment\n",
+        );
+        assert_eq!(
+            editorconfig,
+            EditorConfig {
+                root: true,
+                sections: vec![Section {
+                    glob: "*.md".to_string(),
+                    properties: vec![("trim_trailing_whitespace".to_string(), "false".to_string())],
+                }],
+            }
+        );
+    }
+
+    #[test]
+    fn test_properties_for() {
+        let dir = tempfile::tempdir().expect("Failed to create directory");
+        let project = dir.path().join("project");
+        fs::create_dir_all(project.join("src")).unwrap();
+        fs::write(
+            dir.path().join(".editorconfig"),
+            "[*]\nend_of_line = crlf\ninsert_final_newline = true\n",
+        )
+        .unwrap();
+        fs::write(
+            project.join(".editorconfig"),
+            "[*]\nindent_style = space\nindent_size = 4\ntrim_trailing_whitespace = true\n\n\
+             [*.{yml,yaml}]\nindent_size = 2\n\n\
+             [src/**.go]\nindent_style = tab\nindent_size = tab\ntab_width = 8\n",
+        )
+        .unwrap();
+
+        assert_eq!(
+            properties_for(&project.join("config.yml")),
+            Properties {
+                indent_style: Some(IndentStyle::Spaces),
+                indent_size: Some(2),
+                tab_width: None,
+                trim_trailing_whitespace: Some(true),
+                insert_final_newline: Some(true),
+                end_of_line: Some(LineEnding::CrLf),
+            }
+        );
+
+        let go = properties_for(&project.join("src").join("main.go"));
+        assert_eq!(go.indent_style, Some(IndentStyle::Tabs));
+        assert_eq!(go.indent_width(), Some(8));
+
+        // A root file hides the ones above it
+        fs::write(project.join(".editorconfig"), "root = true\n").unwrap();
+        assert_eq!(
+            properties_for(&project.join("config.yml")),
+            Properties::default()
+        );
+    }
+
+    #[test]
+    fn test_prepare_for_saving() {
+        let properties = Properties {
+            trim_trailing_whitespace: Some(true),
+            insert_final_newline: Some(true),
+            ..Properties::default()
+        };
+        assert_eq!(
+            properties.prepare_for_saving("foo  \r\nbar\t", LineEnding::CrLf),
+            "foo\r\nbar\r\n"
+        );
+        assert_eq!(properties.prepare_for_saving("", LineEnding::Lf), "");
+        assert_eq!(
+            Properties::default().prepare_for_saving("foo  ", LineEnding::Lf),
+            "foo  "
+        );
+    }
+}
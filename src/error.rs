@@ -0,0 +1,112 @@
+use std::{error::Error, fmt, io, path::PathBuf};
+
+/// Errors which can occur when operating on the buffers of pike
+#[derive(Debug)]
+pub enum PikeError {
+    /// The operation needs a buffer, but none is open
+    NoBufferOpen,
+    /// The buffer has to be bound to a file, e.g. to be saved
+    BufferNotBound,
+    /// The file of the buffer can't be written to
+    ReadOnly(PathBuf),
+    /// The configuration file couldn't be read or is invalid
+    Config(String),
+    /// The workspace couldn't be created in the working directory
+    CreateWorkspace(scribe::errors::Error),
+    /// The parent directories of a new file couldn't be created
+    CreateDirectory(io::Error),
+    /// A new file couldn't be created
+    CreateFile(PathBuf, io::Error),
+    /// A file couldn't be opened in a buffer
+    OpenFile(io::Error),
+    /// A file couldn't be read
+    ReadFile(io::Error),
+    /// A buffer couldn't be saved to its file
+    SaveFile(PathBuf, io::Error),
+    /// Git couldn't be run or reported an error
+    Git(String),
+    /// The session file couldn't be located or is invalid
+    Session(String),
+    /// The input doesn't name a Unicode character
+    InvalidCodePoint(String),
+    /// The input isn't a valid regular expression
+    InvalidRegex(String),
+    /// A file is larger than the configured threshold, in megabytes, so opening
+    /// it has to be confirmed
+    FileTooLarge(PathBuf, u64),
+    /// A symlink points to a file which doesn't exist
+    BrokenSymlink(PathBuf),
+    /// The buffer is read-only, so its contents can't be changed
+    BufferReadOnly,
+    /// The first edit of the buffer has to be confirmed before changing it
+    EditNotConfirmed,
+}
+
+#[cfg(test)]
+impl PikeError {
+    /// Returns the kind of the underlying IO error, if the error was caused
+    /// by one, e.g. to tell a missing file from a lack of permissions
+    pub fn io_error_kind(&self) -> Option<io::ErrorKind> {
+        self.source()
+            .and_then(|source| source.downcast_ref::<io::Error>())
+            .map(io::Error::kind)
+    }
+}
+
+impl fmt::Display for PikeError {
+    fn fmt(&self, f: &mut fmt::Formatter<'_>) -> fmt::Result {
+        match self {
+            PikeError::NoBufferOpen => write!(f, "No buffer is currently open"),
+            PikeError::BufferNotBound => write!(f, "Buffer isn't bound to a file"),
+            PikeError::ReadOnly(path) => write!(f, "{} is read-only", path.display()),
+            PikeError::Config(e) => write!(f, "Error loading config: {e}"),
+            PikeError::CreateWorkspace(e) => write!(f, "Error creating workspace: {e}"),
+            PikeError::CreateDirectory(e) => write!(f, "Failed to create directory: {e}"),
+            PikeError::CreateFile(path, e) => {
+                write!(f, "Failed to create file: {} ({e})", path.display())
+            }
+            PikeError::OpenFile(_) => write!(f, "Error opening file"),
+            PikeError::ReadFile(e) => write!(f, "{e}"),
+            PikeError::SaveFile(path, e) => write!(f, "Failed to save {}: {e}", path.display()),
+            PikeError::Git(e) => write!(f, "Error running git: {e}"),
+            PikeError::Session(e) => write!(f, "Session error: {e}"),
+            PikeError::InvalidCodePoint(input) => write!(f, "Invalid code point: {input}"),
+            PikeError::InvalidRegex(pattern) => write!(f, "Invalid regex: {pattern}"),
+            PikeError::FileTooLarge(path, size_mb) => {
+                write!(f, "{} is a large file ({size_mb} MB)", path.display())
+            }
+            PikeError::BrokenSymlink(path) => {
+                write!(f, "{} is a broken symlink", path.display())
+            }
+            PikeError::BufferReadOnly => write!(f, "Buffer is read-only"),
+            PikeError::EditNotConfirmed => {
+                write!(f, "The first edit of the buffer hasn't been confirmed")
+            }
+        }
+    }
+}
+
+impl Error for PikeError {
+    fn source(&self) -> Option<&(dyn Error + 'static)> {
+        match self {
+            PikeError::CreateWorkspace(e) => Some(e),
+            PikeError::CreateDirectory(e)
+            | PikeError::CreateFile(_, e)
+            | PikeError::OpenFile(e)
+            | PikeError::ReadFile(e)
+            | PikeError::SaveFile(_, e) => Some(e),
+            PikeError::NoBufferOpen
+            | PikeError::BufferNotBound
+            | PikeError::ReadOnly(_)
+            | PikeError::Config(_)
+            | PikeError::Git(_)
+            | PikeError::Session(_)
+            | PikeError::InvalidCodePoint(_)
+            | PikeError::InvalidRegex(_)
+            | PikeError::FileTooLarge(_, _)
+            | PikeError::BrokenSymlink(_)
+            | PikeError::BufferReadOnly
+            | PikeError::EditNotConfirmed => None,
+        }
+    }
+}
@@ -47,6 +47,25 @@ impl KeyShortcut {
         Ok(shortcut)
     }
 
+    /// Returns the shortcut in the notation used by the config file,
+    /// e.g. ctrl+shift+p, so that it can be shown to the user
+    pub fn to_display_string(&self) -> String {
+        let modifiers = [
+            (KeyModifiers::CONTROL, "ctrl"),
+            (KeyModifiers::SHIFT, "shift"),
+            (KeyModifiers::ALT, "alt"),
+        ];
+        let mut elements: Vec<String> = modifiers
+            .iter()
+            .filter(|(modifier, _)| self.modifiers.contains(*modifier))
+            .map(|(_, name)| name.to_string())
+            .collect();
+        if self.code != KeyCode::Null {
+            elements.push(keycode_to_string(self.code));
+        }
+        elements.join("+")
+    }
+
     /// Returns true if the shortcut is empty, i.e. no key or modifier is set
     fn is_empty(&self) -> bool {
         self.code == KeyCode::Null && self.modifiers == KeyModifiers::empty()
@@ -106,6 +125,31 @@ fn keycode_from_string(s: &str) -> Result<KeyCode, String> {
     Ok(return_value)
 }
 
+/// Returns the string representation of a KeyCode, which is
+/// the inverse of `keycode_from_string` for the supported keys
+fn keycode_to_string(code: KeyCode) -> String {
+    match code {
+        KeyCode::Esc => "esc".to_string(),
+        KeyCode::Backspace => "backspace".to_string(),
+        KeyCode::Enter => "enter".to_string(),
+        KeyCode::Left => "left".to_string(),
+        KeyCode::Right => "right".to_string(),
+        KeyCode::Up => "up".to_string(),
+        KeyCode::Down => "down".to_string(),
+        KeyCode::Home => "home".to_string(),
+        KeyCode::End => "end".to_string(),
+        KeyCode::PageUp => "pageup".to_string(),
+        KeyCode::PageDown => "pagedown".to_string(),
+        KeyCode::Tab => "tab".to_string(),
+        KeyCode::BackTab => "backtab".to_string(),
+        KeyCode::Delete => "delete".to_string(),
+        KeyCode::Insert => "insert".to_string(),
+        KeyCode::F(n) => format!("f{n}"),
+        KeyCode::Char(c) => c.to_lowercase().to_string(),
+        other => format!("{other:?}").to_lowercase(),
+    }
+}
+
 #[cfg(test)]
 mod key_shortcut_test {
 
@@ -209,4 +253,61 @@ mod key_shortcut_test {
             assert!(KeyShortcut::from_string(s).is_err());
         }
     }
+
+    #[test]
+    fn to_display_string() {
+        let shortcuts_and_strings = vec![
+            (
+                KeyShortcut::new(KeyCode::Char('g'), KeyModifiers::CONTROL),
+                "ctrl+g",
+            ),
+            (
+                KeyShortcut::new(
+                    KeyCode::Char('Y'),
+                    KeyModifiers::SHIFT | KeyModifiers::CONTROL,
+                ),
+                "ctrl+shift+y",
+            ),
+            (
+                KeyShortcut::new(KeyCode::F(12), KeyModifiers::ALT),
+                "alt+f12",
+            ),
+            (
+                KeyShortcut::new(KeyCode::PageDown, KeyModifiers::empty()),
+                "pagedown",
+            ),
+        ];
+
+        for (shortcut, s) in shortcuts_and_strings {
+            assert_eq!(shortcut.to_display_string(), s);
+        }
+
+        // Displayed keybinds parse back, with letters in lower case
+        for (s, shortcut) in [
+            (
+                "ctrl+g",
+                KeyShortcut::new(KeyCode::Char('g'), KeyModifiers::CONTROL),
+            ),
+            (
+                "ctrl+shift+y",
+                KeyShortcut::new(
+                    KeyCode::Char('y'),
+                    KeyModifiers::SHIFT | KeyModifiers::CONTROL,
+                ),
+            ),
+            (
+                "alt+f12",
+                KeyShortcut::new(KeyCode::F(12), KeyModifiers::ALT),
+            ),
+            (
+                "pagedown",
+                KeyShortcut::new(KeyCode::PageDown, KeyModifiers::empty()),
+            ),
+        ] {
+            assert_eq!(
+                KeyShortcut::from_string(s).expect("Failed to parse displayed keybind"),
+                shortcut
+            );
+        }
+    }
 }
@@ -1,6 +1,9 @@
 mod app;
 mod config;
+mod editorconfig;
+mod error;
 mod key_shortcut;
+mod matching;
 mod operations;
 mod pike;
 mod test_util;
@@ -8,6 +11,8 @@ mod ui;
 mod welcome_pike;
 
 use clap::Parser;
+use crossterm::event::{DisableBracketedPaste, EnableBracketedPaste};
+use crossterm::execute;
 use std::io;
 
 use app::{App, Args};
@@ -15,9 +20,16 @@ use app::{App, Args};
 fn main() -> io::Result<()> {
     let args = Args::parse();
     let mut terminal = ratatui::init();
+    // Pasted text then arrives as a single event instead of key by key. Without
+    // it, pasting still works key by key, so a failure mustn't stop the editor.
+    let bracketed_paste = execute!(io::stdout(), EnableBracketedPaste).is_ok();
     let mut app = App::build(args);
-    app.run(&mut terminal)?;
+    let result = app.run(&mut terminal);
 
+    // Leave the raw mode even if the app failed
+    if bracketed_paste {
+        let _ = execute!(io::stdout(), DisableBracketedPaste);
+    }
     ratatui::restore();
-    Ok(())
+    result
 }
@@ -0,0 +1,239 @@
+use std::ops::Range;
+use std::path::Path;
+
+/// HTML elements which never have a closing tag, e.g. `<br>`
+const VOID_ELEMENTS: &[&str] = &[
+    "area", "base", "br", "col", "embed", "hr", "img", "input", "link", "meta", "source", "track",
+    "wbr",
+];
+
+/// Flavor of markup in which tags are matched
+#[derive(Debug, Clone, Copy, PartialEq, Eq)]
+pub enum Markup {
+    /// Tag names are case-insensitive and void elements aren't closed
+    Html,
+    Xml,
+}
+
+impl Markup {
+    /// Returns the markup of the file at `path` based on its extension,
+    /// or None if tags shouldn't be matched in it
+    pub fn of_file(path: &Path) -> Option<Markup> {
+        let extension = path.extension()?.to_str()?.to_lowercase();
+        match extension.as_str() {
+            "html" | "htm" => Some(Markup::Html),
+            "xhtml" | "xml" | "svg" => Some(Markup::Xml),
+            _ => None,
+        }
+    }
+
+    fn names_equal(&self, a: &str, b: &str) -> bool {
+        match self {
+            Markup::Html => a.eq_ignore_ascii_case(b),
+            Markup::Xml => a == b,
+        }
+    }
+}
+
+#[derive(Debug, Clone, Copy, PartialEq, Eq)]
+enum TagKind {
+    Opening,
+    Closing,
+    SelfClosing,
+}
+
+/// A tag found in the contents of a buffer, spanning `range` bytes
+/// from its `<` to right after its `>`
+#[derive(Debug, PartialEq, Eq)]
+struct Tag<'a> {
+    range: Range<usize>,
+    name: &'a str,
+    kind: TagKind,
+}
+
+/// Returns the byte range of the tag matching the one at `byte_offset`, e.g. of
+/// `</div>` for `<div>`, skipping nested elements of the same name. Self-closing
+/// tags and tags without a counterpart have no match.
+pub fn matching_tag(contents: &str, byte_offset: usize, markup: Markup) -> Option<Range<usize>> {
+    let tags = scan_tags(contents, markup);
+    let index = tags
+        .iter()
+        .position(|tag| tag.range.contains(&byte_offset))?;
+    let tag = &tags[index];
+
+    let (candidates, opening, closing): (Vec<&Tag>, _, _) = match tag.kind {
+        TagKind::Opening => (
+            tags[index + 1..].iter().collect(),
+            TagKind::Opening,
+            TagKind::Closing,
+        ),
+        // Look for the opening tag backwards, where the roles are swapped
+        TagKind::Closing => (
+            tags[..index].iter().rev().collect(),
+            TagKind::Closing,
+            TagKind::Opening,
+        ),
+        TagKind::SelfClosing => return None,
+    };
+
+    let mut depth = 0;
+    for candidate in candidates
+        .into_iter()
+        .filter(|candidate| markup.names_equal(candidate.name, tag.name))
+    {
+        if candidate.kind == opening {
+            depth += 1;
+        } else if candidate.kind == closing {
+            if depth == 0 {
+                return Some(candidate.range.clone());
+            }
+            depth -= 1;
+        }
+    }
+    None
+}
+
+/// Finds the tags in `contents` in order. Comments, CDATA sections, declarations
+/// like `<!DOCTYPE html>` and less-than signs which don't start a tag are skipped.
+fn scan_tags(contents: &str, markup: Markup) -> Vec<Tag<'_>> {
+    let mut tags = Vec::new();
+    let mut index = 0;
+
+    while let Some(found) = contents[index..].find('<') {
+        let start = index + found;
+        let rest = &contents[start..];
+
+        // Comments and CDATA sections may contain anything but their end
+        if let Some((_, end)) = [("<!--", "-->"), ("<![CDATA[", "]]>")]
+            .iter()
+            .find(|(start, _)| rest.starts_with(start))
+        {
+            index = rest
+                .find(end)
+                .map_or(contents.len(), |e| start + e + end.len());
+            continue;
+        }
+
+        let (kind, after_bracket) = match rest[1..].strip_prefix('/') {
+            Some(after_slash) => (TagKind::Closing, after_slash),
+            None => (TagKind::Opening, &rest[1..]),
+        };
+        let name_len = after_bracket
+            .find(|c: char| c.is_whitespace() || c == '/' || c == '>')
+            .unwrap_or(after_bracket.len());
+        let name = &after_bracket[..name_len];
+
+        let tag_len = tag_length(rest);
+        if !name.starts_with(|c: char| c.is_alphabetic()) || tag_len.is_none() {
+            index = start + 1;
+            continue;
+        }
+        let end = start + tag_len.unwrap_or_default();
+
+        let is_void = markup == Markup::Html
+            && VOID_ELEMENTS
+                .iter()
+                .any(|void| void.eq_ignore_ascii_case(name));
+        let kind = match kind {
+            TagKind::Opening if contents[..end - 1].ends_with('/') || is_void => {
+                TagKind::SelfClosing
+            }
+            kind => kind,
+        };
+
+        tags.push(Tag {
+            range: start..end,
+            name,
+            kind,
+        });
+        index = end;
+    }
+
+    tags
+}
+
+/// Returns the length of the tag at the start of `text` up to and including its
+/// `>`, which doesn't count inside quoted attribute values. None if the tag isn't
+/// closed before another one starts.
+fn tag_length(text: &str) -> Option<usize> {
+    let mut quote = None;
+    for (index, c) in text.char_indices().skip(1) {
+        match (quote, c) {
+            (Some(open), c) if c == open => quote = None,
+            (Some(_), _) => {}
+            (None, '"' | '\'') => quote = Some(c),
+            (None, '>') => return Some(index + 1),
+            (None, '<') => return None,
+            (None, _) => {}
+        }
+    }
+    None
+}
+
+#[cfg(test)]
+mod tests {
+    use std::path::Path;
+
+    use super::{matching_tag, scan_tags, Markup, TagKind};
+
+    /// Returns the text of the tag matching the first tag starting with `tag`
+    fn match_of<'a>(contents: &'a str, tag: &str, markup: Markup) -> Option<&'a str> {
+        let offset = contents.find(tag).expect("Tag not in contents");
+        matching_tag(contents, offset, markup).map(|range| &contents[range])
+    }
+
+    #[test]
+    fn test_markup_of_file() {
+        assert_eq!(Markup::of_file(Path::new("index.HTML")), Some(Markup::Html));
+        assert_eq!(Markup::of_file(Path::new("icon.svg")), Some(Markup::Xml));
+        assert_eq!(Markup::of_file(Path::new("main.rs")), None);
+        assert_eq!(Markup::of_file(Path::new("Makefile")), None);
+    }
+
+    #[test]
+    fn test_matching_tag_with_nesting() {
+        let contents = "<div id=\"a\"><div>\n<p>text</p></div>\n</div>";
+        assert_eq!(match_of(contents, "<div id", Markup::Html), Some("</div>"));
+        assert_eq!(matching_tag(contents, 36, Markup::Html), Some(0..12));
+        assert_eq!(match_of(contents, "</p>", Markup::Html), Some("<p>"));
+
+        // Any offset inside the tag works, up to its closing bracket
+        assert_eq!(matching_tag(contents, 11, Markup::Html), Some(36..42));
+        assert_eq!(matching_tag(contents, 12, Markup::Html), Some(29..35));
+        assert_eq!(match_of(contents, "text", Markup::Html), None);
+    }
+
+    #[test]
+    fn test_self_closing_and_void_tags() {
+        let contents = "<p><img src=\"a.png\"><br/><input></p>";
+        assert_eq!(match_of(contents, "<p>", Markup::Html), Some("</p>"));
+        assert_eq!(match_of(contents, "<br", Markup::Html), None);
+        assert_eq!(match_of(contents, "<img", Markup::Html), None);
+
+        // XML has no void elements
+        let contents = "<item><br></br></item>";
+        assert_eq!(match_of(contents, "<br>", Markup::Xml), Some("</br>"));
+        assert_eq!(match_of(contents, "<item>", Markup::Xml), Some("</item>"));
+    }
+
+    #[test]
+    fn test_names_are_case_sensitive_only_in_xml() {
+        assert_eq!(
+            match_of("<DIV></div>", "<DIV>", Markup::Html),
+            Some("</div>")
+        );
+        assert_eq!(match_of("<Item></item>", "<Item>", Markup::Xml), None);
+    }
+
+    #[test]
+    fn test_scan_tags_skips_non_tags() {
+        let contents = "<!DOCTYPE html><!-- <b> --><a title='1 > 0'>a < b</a><![CDATA[<c>]]>";
+        let tags = scan_tags(contents, Markup::Html);
+        let names: Vec<(&str, TagKind)> = tags.iter().map(|tag| (tag.name, tag.kind)).collect();
+        assert_eq!(
+            names,
+            vec![("a", TagKind::Opening), ("a", TagKind::Closing)]
+        );
+        assert_eq!(&contents[tags[0].range.clone()], "<a title='1 > 0'>");
+    }
+}
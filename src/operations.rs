@@ -1,3 +1,80 @@
+/// Alternate names of operations, familiar to users coming from other editors
+const OPERATION_ALIASES: &[(&str, &str)] = &[
+    ("w", "save"),
+    ("wa", "save_all"),
+    ("q", "quit"),
+    ("q!", "force_quit"),
+    ("e", "open_file"),
+    ("enew", "new_buffer"),
+    ("bn", "next_buffer"),
+    ("bp", "previous_buffer"),
+    ("b#", "alternate_buffer"),
+    ("u", "undo"),
+];
+
+/// Names of the operations in config files
+const OPERATION_NAMES: &[(&str, Operation)] = &[
+    ("open_file", Operation::OpenFile),
+    ("new_buffer", Operation::CreateNewBuffer),
+    ("previous_buffer", Operation::SwitchToPreviousBuffer),
+    ("next_buffer", Operation::SwitchToNextBuffer),
+    ("search_in_current_buffer", Operation::SearchInCurrentBuffer),
+    (
+        "search_and_replace_in_current_buffer",
+        Operation::SearchAndReplaceInCurrentBuffer,
+    ),
+    ("search_next", Operation::SearchNext),
+    ("search_prev", Operation::SearchPrev),
+    ("save", Operation::SaveBufferToFile),
+    ("undo", Operation::Undo),
+    ("redo", Operation::Redo),
+    ("quit", Operation::Quit),
+    ("force_quit", Operation::ForceQuit),
+    ("reflow_paragraph", Operation::ReflowParagraph),
+    ("toggle_line_mark", Operation::ToggleLineMark),
+    ("toggle_hex_view", Operation::ToggleHexView),
+    ("indent", Operation::Indent),
+    ("surround", Operation::Surround),
+    ("transpose_chars", Operation::TransposeChars),
+    ("transpose_words", Operation::TransposeWords),
+    ("save_all", Operation::SaveAll),
+    ("diff_with", Operation::DiffWith),
+    ("toggle_read_only", Operation::ToggleReadOnly),
+    ("normalize_line_endings", Operation::NormalizeLineEndings),
+    ("insert_date_time", Operation::InsertDateTime),
+    ("next_paragraph", Operation::NextParagraph),
+    ("previous_paragraph", Operation::PrevParagraph),
+    ("open_at_revision", Operation::OpenAtRevision),
+    ("save_session", Operation::SaveSession),
+    ("restore_session", Operation::RestoreSession),
+    ("go_to_last_edit", Operation::GoToLastEdit),
+    ("delete_to_line_end", Operation::DeleteToLineEnd),
+    ("delete_to_line_start", Operation::DeleteToLineStart),
+    ("insert_unicode", Operation::InsertUnicode),
+    ("scroll_line_right", Operation::ScrollLineRight),
+    ("scroll_line_left", Operation::ScrollLineLeft),
+    ("jump_to_matching_tag", Operation::JumpToMatchingTag),
+    ("keep_selection_matches", Operation::KeepSelectionMatches),
+    (
+        "remove_selection_matches",
+        Operation::RemoveSelectionMatches,
+    ),
+    ("alternate_buffer", Operation::SwitchToAlternateBuffer),
+    ("copy", Operation::Copy),
+    ("cut", Operation::Cut),
+    ("paste", Operation::Paste),
+    ("paste_cycle", Operation::PasteCycle),
+    ("show_help", Operation::ShowHelp),
+    ("clear_highlights", Operation::ClearHighlights),
+    ("open_buffer_picker", Operation::OpenBufferPicker),
+    ("find_files_in_cwd", Operation::FindFilesInCWD),
+    ("find_text_in_cwd", Operation::FindTextInCWD),
+    (
+        "toggle_relative_line_numbers",
+        Operation::ToggleRelativeLineNumbers,
+    ),
+];
+
 #[allow(dead_code, unused_variables, unused_mut)]
 /// Every keymappable operation within pike
 #[derive(Debug, PartialEq, Eq, Clone, Hash)]
@@ -7,28 +84,178 @@ pub enum Operation {
     SwitchToPreviousBuffer,
     SwitchToNextBuffer,
     SearchInCurrentBuffer,
+    SearchAndReplaceInCurrentBuffer,
+    SearchNext,
+    SearchPrev,
     SaveBufferToFile,
     Undo,
     Redo,
     Quit,
+    ForceQuit,
+    ReflowParagraph,
+    ToggleLineMark,
+    ToggleHexView,
+    Indent,
+    Surround,
+    TransposeChars,
+    TransposeWords,
+    SaveAll,
+    DiffWith,
+    ToggleReadOnly,
+    NormalizeLineEndings,
+    InsertDateTime,
+    NextParagraph,
+    PrevParagraph,
+    OpenAtRevision,
+    SaveSession,
+    RestoreSession,
+    GoToLastEdit,
+    DeleteToLineEnd,
+    DeleteToLineStart,
+    InsertUnicode,
+    ScrollLineRight,
+    ScrollLineLeft,
+    JumpToMatchingTag,
+    KeepSelectionMatches,
+    RemoveSelectionMatches,
+    SwitchToAlternateBuffer,
+    Copy,
+    Cut,
+    Paste,
+    PasteCycle,
+    ShowHelp,
+    ClearHighlights,
+    OpenBufferPicker,
+    FindFilesInCWD,
+    FindTextInCWD,
+    ToggleRelativeLineNumbers,
 }
 
 #[allow(dead_code, unused_variables, unused_mut)]
 impl Operation {
-    /// Creates a new Operation from a string from a config file
+    /// Creates a new Operation from a string from a config file,
+    /// which is either its name or one of its aliases
     pub fn from_string(query: &str) -> Result<Operation, String> {
-        let return_value = match query {
-            "open_file" => Operation::OpenFile,
-            "new_buffer" => Operation::CreateNewBuffer,
-            "previous_buffer" => Operation::SwitchToPreviousBuffer,
-            "next_buffer" => Operation::SwitchToNextBuffer,
-            "search_in_current_buffer" => Operation::SearchInCurrentBuffer,
-            "save" => Operation::SaveBufferToFile,
-            "undo" => Operation::Undo,
-            "redo" => Operation::Redo,
-            "quit" => Operation::Quit,
-            _ => return Err(format!("Invalid operation in config: {query}")),
-        };
-        Ok(return_value)
+        let name = OPERATION_ALIASES
+            .iter()
+            .find(|(alias, _)| *alias == query)
+            .map_or(query, |(_, name)| name);
+
+        OPERATION_NAMES
+            .iter()
+            .find(|(operation_name, _)| *operation_name == name)
+            .map(|(_, operation)| operation.clone())
+            .ok_or_else(|| format!("Invalid operation in config: {query}"))
+    }
+
+    /// Returns the name of the operation in config files
+    pub fn name(&self) -> &'static str {
+        OPERATION_NAMES
+            .iter()
+            .find(|(_, operation)| operation == self)
+            .map_or("", |(name, _)| name)
+    }
+
+    /// Returns the names of all the operations in config files
+    pub fn names() -> impl Iterator<Item = &'static str> {
+        OPERATION_NAMES.iter().map(|(name, _)| *name)
+    }
+
+    /// Whether the operation changes the contents of the current buffer
+    pub fn modifies_buffer(&self) -> bool {
+        matches!(
+            self,
+            Operation::Undo
+                | Operation::Redo
+                | Operation::SearchAndReplaceInCurrentBuffer
+                | Operation::ReflowParagraph
+                | Operation::Indent
+                | Operation::Surround
+                | Operation::TransposeChars
+                | Operation::TransposeWords
+                | Operation::NormalizeLineEndings
+                | Operation::InsertDateTime
+                | Operation::DeleteToLineEnd
+                | Operation::DeleteToLineStart
+                | Operation::InsertUnicode
+                | Operation::KeepSelectionMatches
+                | Operation::RemoveSelectionMatches
+                | Operation::Cut
+                | Operation::Paste
+                | Operation::PasteCycle
+        )
+    }
+}
+
+/// Actions of the inputs opened over the buffer, bound to keys separately for
+/// each input, as e.g. moving between search results has no use in the file input
+#[derive(Debug, PartialEq, Eq, Clone, Copy, Hash)]
+pub enum InputAction {
+    Confirm,
+    Cancel,
+    Next,
+    Previous,
+    /// Completes the entered path
+    Complete,
+    ToggleCaseInsensitive,
+    ToggleWholeWord,
+    /// Replaces all matches of the searched query at once
+    ReplaceAll,
+    /// Toggles listing hidden and ignored files in the file picker
+    ToggleIgnoredFiles,
+    /// Marks the selected item of the picker, or unmarks it
+    ToggleMark,
+}
+
+impl InputAction {
+    pub fn from_string(query: &str) -> Result<InputAction, String> {
+        match query {
+            "confirm" => Ok(InputAction::Confirm),
+            "cancel" => Ok(InputAction::Cancel),
+            "next" => Ok(InputAction::Next),
+            "previous" => Ok(InputAction::Previous),
+            "complete" => Ok(InputAction::Complete),
+            "toggle_case_insensitive" => Ok(InputAction::ToggleCaseInsensitive),
+            "toggle_whole_word" => Ok(InputAction::ToggleWholeWord),
+            "replace_all" => Ok(InputAction::ReplaceAll),
+            "toggle_ignored_files" => Ok(InputAction::ToggleIgnoredFiles),
+            "toggle_mark" => Ok(InputAction::ToggleMark),
+            _ => Err(format!("Invalid input action in config: {query}")),
+        }
+    }
+}
+
+#[cfg(test)]
+mod operations_test {
+    use super::Operation;
+
+    #[test]
+    fn from_string_accepts_aliases() {
+        assert_eq!(Operation::from_string("w"), Ok(Operation::SaveBufferToFile));
+        assert_eq!(Operation::from_string("q"), Ok(Operation::Quit));
+        assert_eq!(Operation::from_string("q!"), Ok(Operation::ForceQuit));
+        assert_eq!(
+            Operation::from_string("bn"),
+            Ok(Operation::SwitchToNextBuffer)
+        );
+        assert_eq!(
+            Operation::from_string("b#"),
+            Ok(Operation::SwitchToAlternateBuffer)
+        );
+        assert_eq!(
+            Operation::from_string("save"),
+            Ok(Operation::SaveBufferToFile)
+        );
+        assert!(Operation::from_string("x").is_err());
+    }
+
+    #[test]
+    fn name_is_the_inverse_of_from_string() {
+        for name in Operation::names() {
+            let operation = Operation::from_string(name).expect("Failed to parse operation name");
+            assert_eq!(operation.name(), name);
+        }
+        assert_eq!(Operation::Undo.name(), "undo");
+        assert_eq!(Operation::from_string("u").map(|op| op.name()), Ok("undo"));
     }
 }
@@ -1,14 +1,42 @@
-use std::fs::{self, File};
+use std::collections::{BTreeSet, HashMap, HashSet, VecDeque};
+use std::env;
+use std::ffi::OsString;
+use std::fs::{self, File, OpenOptions};
+use std::io;
+use std::ops::Range;
 use std::path::{Path, PathBuf};
+use std::process::Command;
 
 use crate::config;
-use crate::config::Config;
+use crate::config::{Config, EditorSettings, GutterStyle, IndentStyle, LineEnding, PathBase};
+use crate::editorconfig;
+use crate::error::PikeError;
 use crate::key_shortcut::KeyShortcut;
-use crate::operations::Operation;
+use crate::matching::{self, Markup};
+use crate::operations::{InputAction, Operation};
+use chrono::Local;
+use ignore::WalkBuilder;
+use regex::Regex;
 use scribe::buffer::Position as BufferPosition;
+use scribe::buffer::Range as BufferRange;
 use scribe::{Buffer, Workspace};
+use similar::{ChangeTag, TextDiff};
+use toml::{Table, Value};
 use unicode_segmentation::UnicodeSegmentation;
 
+/// Name under which buffers that aren't bound to a file are listed
+const UNBOUND_BUFFER_NAME: &str = "[No Name]";
+
+/// Maximum number of files listed when finding files in the working directory,
+/// so that it stays responsive if started in e.g. the home directory
+const MAX_LISTED_FILES: usize = 10_000;
+/// Maximum number of lines listed when finding text in the working directory
+const MAX_LISTED_TEXT_MATCHES: usize = 1000;
+/// Maximum number of bytes read by a single search for text in the working
+/// directory, so that a search stays responsive in large directories
+const MAX_SEARCHED_BYTES: u64 = 64 * 1024 * 1024;
+const BYTES_IN_MB: u64 = 1024 * 1024;
+
 /// Cursor history
 #[derive(Default)]
 struct CursorHistory {
@@ -17,7 +45,9 @@ struct CursorHistory {
 }
 
 impl CursorHistory {
-    /// Record a new cursor position on the undo stack.
+    /// Record a new cursor position on the undo stack. Every recorded position
+    /// has to be followed by a change of the buffer, as the stacks are popped
+    /// along with the buffer's own undo history and would fall out of step.
     fn record_undo_position(&mut self, pos: BufferPosition) {
         self.undo_stack.push(pos);
         // Once you record a new position, clear the redo stack.
@@ -25,11 +55,182 @@ impl CursorHistory {
     }
 }
 
+/// Marked lines of a buffer, along with the number of lines the buffer had when
+/// they were last adjusted, so that they can follow lines inserted or removed
 #[derive(Default)]
+struct LineMarks {
+    lines: BTreeSet<usize>,
+    line_count: usize,
+}
+
+impl LineMarks {
+    /// Shifts the marks after `line` by the change of the buffer's line count to
+    /// `line_count`, as lines were inserted or removed right after it. Marks on
+    /// removed lines are dropped.
+    fn follow_edit(&mut self, line: usize, line_count: usize) {
+        let previous = std::mem::replace(&mut self.line_count, line_count);
+        if previous == line_count {
+            return;
+        }
+        self.lines = self
+            .lines
+            .iter()
+            .filter_map(|&mark| match mark {
+                _ if mark <= line => Some(mark),
+                _ if line_count > previous => Some(mark + (line_count - previous)),
+                _ if mark > line + (previous - line_count) => Some(mark - (previous - line_count)),
+                _ => None,
+            })
+            .collect();
+    }
+}
+
+/// Returns the cursor history of `buffer`, creating it on its first use
+fn cursor_history_of<'a>(
+    histories: &'a mut HashMap<usize, CursorHistory>,
+    buffer: &Buffer,
+) -> &'a mut CursorHistory {
+    // Buffers added to the workspace always have an id
+    histories.entry(buffer.id.unwrap_or_default()).or_default()
+}
+
+/// A highlighted part of the buffer, spanning from `start` up to,
+/// but not including, `end`. Might span multiple lines.
+#[derive(Default, Clone)]
 pub struct Highlight {
     pub start: BufferPosition,
-    pub length: usize,
+    pub end: BufferPosition,
     pub is_selected: bool,
+    /// Whether the highlighted text is about to be replaced, which
+    /// takes precedence over it being selected
+    pub is_replace_target: bool,
+    /// Whether the highlight marks the selection instead of a search result
+    pub is_text_selection: bool,
+}
+
+/// Options of searching in the current buffer, toggled in the search input
+#[derive(Debug, Default, Clone, Copy, PartialEq, Eq)]
+pub struct SearchOptions {
+    /// Whether letters match regardless of their case
+    pub case_insensitive: bool,
+    /// Whether matches continuing a word on either side are skipped
+    pub whole_word: bool,
+}
+
+/// What the editor is doing, as far as the keys typed into it are concerned
+#[derive(Debug, Clone, Copy, PartialEq, Eq)]
+pub enum EditorMode {
+    /// No buffer is open, so the welcome screen is shown
+    Welcome,
+    /// Typed keys trigger the operations bound in the normal keymap of modal editing
+    Normal,
+    /// Typed text is inserted at the cursor
+    Insert,
+    /// Text is selected, e.g. to be copied or surrounded
+    Select,
+}
+
+impl EditorMode {
+    /// Returns the name of the mode shown in the status bar
+    pub fn name(&self) -> &'static str {
+        match self {
+            EditorMode::Welcome => "",
+            EditorMode::Normal => "NORMAL",
+            EditorMode::Insert => "INSERT",
+            EditorMode::Select => "SELECT",
+        }
+    }
+}
+
+/// State of the editor shown in the status bar, bundled so that it can be
+/// queried in one go, e.g. by tools integrating with pike
+#[derive(Debug, Clone, PartialEq, Eq)]
+pub struct EditorStateSummary {
+    pub filename: String,
+    pub is_modified: bool,
+    pub is_read_only: bool,
+    pub cursor: Option<BufferPosition>,
+    pub line_count: usize,
+    /// Number of selected graphemes, 0 without a selection
+    pub selection_length: usize,
+    pub mode: EditorMode,
+}
+
+/// An open buffer, as listed to switch to it
+#[derive(Debug, Clone, PartialEq, Eq)]
+pub struct BufferEntry {
+    pub id: usize,
+    /// Path of the buffer's file, relative to the working directory if it's
+    /// inside of it, or `[No Name]` if the buffer isn't bound to a file
+    pub name: String,
+    pub is_modified: bool,
+}
+
+/// A selected part of the current buffer. The anchor is where the selection
+/// started and stays in place, while the active end follows the cursor, so
+/// it might come before the anchor.
+#[derive(Debug, Clone, Copy, PartialEq, Eq)]
+pub struct Selection {
+    pub anchor: BufferPosition,
+    pub active: BufferPosition,
+}
+
+impl Selection {
+    /// Returns the start and (exclusive) end of the selection in document order
+    pub fn range(&self) -> (BufferPosition, BufferPosition) {
+        let anchor = (self.anchor.line, self.anchor.offset);
+        let active = (self.active.line, self.active.offset);
+        if anchor <= active {
+            (self.anchor, self.active)
+        } else {
+            (self.active, self.anchor)
+        }
+    }
+
+    /// Returns a highlight covering the selected text, in document order
+    /// even if the selection was made backwards
+    pub fn to_highlight(&self) -> Highlight {
+        let (start, end) = self.range();
+        Highlight {
+            start,
+            end,
+            is_text_selection: true,
+            ..Default::default()
+        }
+    }
+}
+
+/// How `Pike::filter_selection_lines` changes each selected line
+#[derive(Debug, Clone, Copy, PartialEq, Eq)]
+pub enum LineFilterMode {
+    /// Narrows the line down to the first match, or to its first
+    /// capture group if the regex has one
+    KeepMatch,
+    /// Removes the first match from the line
+    RemoveMatch,
+}
+
+/// A line of a diff between a file and the current buffer. Added lines
+/// are only in the buffer, removed ones only in the file.
+#[derive(Debug, Clone, PartialEq, Eq)]
+pub enum DiffLine {
+    Unchanged(String),
+    Added(String),
+    Removed(String),
+}
+
+/// Number of lines of a buffer ending with each line ending
+#[derive(Debug, Default, Clone, Copy, PartialEq, Eq)]
+pub struct LineEndingStats {
+    pub lf: usize,
+    pub crlf: usize,
+}
+
+impl LineEndingStats {
+    /// Returns whether both line endings are used
+    pub fn is_mixed(&self) -> bool {
+        self.lf > 0 && self.crlf > 0
+    }
 }
 
 /// Backend of the app
@@ -37,7 +238,41 @@ pub struct Highlight {
 pub struct Pike {
     workspace: Workspace,
     config: Config,
-    cursor_history: CursorHistory,
+    /// Cursor positions to restore on undo and redo, by buffer id
+    cursor_histories: HashMap<usize, CursorHistory>,
+    /// Settings overriding the configured ones for specific buffers, by buffer id
+    buffer_settings: HashMap<usize, EditorSettings>,
+    /// EditorConfig properties of the files of buffers, by buffer id
+    editorconfig_properties: HashMap<usize, editorconfig::Properties>,
+    /// Marked lines of each buffer, by buffer id
+    line_marks: HashMap<usize, LineMarks>,
+    /// Ids of the buffers which can't be edited
+    read_only_buffers: HashSet<usize>,
+    /// Ids of the buffers whose first edit has to be confirmed
+    unconfirmed_buffers: HashSet<usize>,
+    /// Selection in the current buffer
+    selection: Option<Selection>,
+    /// Cursor position right after the last expanded abbreviation
+    last_expansion: Option<BufferPosition>,
+    /// Position at which typing a delimiter doesn't expand an abbreviation,
+    /// set when an expansion is undone
+    suppressed_abbreviation: Option<BufferPosition>,
+    /// Cursor positions right after the last edit of each buffer, by buffer id
+    last_edits: HashMap<usize, BufferPosition>,
+    /// Id of the buffer which was current before the current one
+    alternate_buffer: Option<usize>,
+    /// Id of the buffer showing the keybind cheatsheet, if it's open
+    help_buffer: Option<usize>,
+    /// Texts last copied or cut within the editor, shared by all buffers,
+    /// the most recent first
+    registers: VecDeque<String>,
+    /// Id of the buffer the text of a register was last pasted into, where the
+    /// text starts and the index of the register, to replace it by cycling
+    last_paste: Option<(usize, BufferPosition, usize)>,
+    /// Lines of the current buffer changed since they were last taken
+    dirty_lines: Option<Range<usize>>,
+    /// Whether typed keys are commands of modal editing instead of text
+    normal_mode: bool,
 }
 
 #[allow(dead_code, unused_variables, unused_mut)]
@@ -46,50 +281,113 @@ impl Pike {
     pub fn build(
         cwd: PathBuf,
         cwf: Option<PathBuf>,
-        mut config_file: Option<PathBuf>,
-    ) -> Result<Pike, String> {
-        // If no config path is provided, check if the default config file exists
-        if config_file.is_none() {
-            let default_config_file_path = config::default_config_file_path();
-            if let Ok(default_config_path) = default_config_file_path {
-                if default_config_path.exists() {
-                    config_file = Some(default_config_path.to_path_buf());
-                }
-            }
-        }
+        config_file: Option<PathBuf>,
+    ) -> Result<Pike, PikeError> {
+        let config_file = config_file_path(config_file, env::var_os(config::CONFIG_PATH_ENV_VAR));
+
+        let workspace = Workspace::new(&cwd, None).map_err(PikeError::CreateWorkspace)?;
 
-        let mut workspace =
-            Workspace::new(&cwd, None).map_err(|e| format!("Error creating workspace: {}", e))?;
+        let mut pike = Pike {
+            workspace,
+            config: Config::from_file(config_file.as_deref()).map_err(PikeError::Config)?,
+            cursor_histories: HashMap::new(),
+            buffer_settings: HashMap::new(),
+            editorconfig_properties: HashMap::new(),
+            line_marks: HashMap::new(),
+            read_only_buffers: HashSet::new(),
+            unconfirmed_buffers: HashSet::new(),
+            selection: None,
+            last_expansion: None,
+            suppressed_abbreviation: None,
+            last_edits: HashMap::new(),
+            alternate_buffer: None,
+            help_buffer: None,
+            registers: VecDeque::new(),
+            last_paste: None,
+            dirty_lines: None,
+            normal_mode: false,
+        };
+        // Modal editing starts in the normal mode
+        pike.normal_mode = pike.config.modal_editing;
 
         if let Some(cwf) = cwf {
-            // Check if file exits, if not, create it
-            if !cwf.exists() {
-                if let Some(parent) = cwf.parent() {
-                    fs::create_dir_all(parent)
-                        .map_err(|e| format!("Failed to create directory: {}", e))?;
-                }
-                File::create(&cwf).map_err(|e| format!("Failed to create file: {}", e))?;
-            }
-            // Open the given file
-            workspace
-                .open_buffer(cwf.as_path())
-                .map_err(|_| "Error opening file")?;
+            // A broken symlink mustn't create its target, while a missing file is created
+            check_symlink_target(&cwf)?;
+            create_file_if_missing(&cwf)?;
+            pike.check_file_size(&cwf)?;
+            pike.load_file(&cwf, 0, 0)?;
         }
-        Ok(Pike {
-            workspace,
-            config: Config::from_file(config_file.as_deref())
-                .map_err(|e| format!("Error loading config: {}", e))?,
-            cursor_history: CursorHistory::default(),
-        })
+
+        Ok(pike)
     }
 
     /// Open a file, move its contents into the current buffer
     /// and set the cursor to the offset. If the offset is out of bounds,
-    /// the cursor will remain at the start of the file.
-    pub fn open_file(&mut self, path: &Path, line: usize, offset: usize) -> Result<(), String> {
-        self.workspace
-            .open_buffer(path)
-            .map_err(|_| "Error opening file".to_string())?;
+    /// the cursor will remain at the start of the file. If the file is
+    /// already open, its buffer is switched to instead and the cursor is
+    /// moved to the position, clamped to the contents.
+    pub fn open_file(&mut self, path: &Path, line: usize, offset: usize) -> Result<(), PikeError> {
+        self.open_file_at(path, Some((line, offset)))
+    }
+
+    /// Opens a file like `open_file`, placing the cursor at `position` if given.
+    /// Without a position, an already open buffer keeps its cursor.
+    fn open_file_at(
+        &mut self,
+        path: &Path,
+        position: Option<(usize, usize)>,
+    ) -> Result<(), PikeError> {
+        check_symlink_target(path)?;
+        self.selection = None;
+        let previous = self.current_buffer_id();
+        if self.switch_to_buffer_with_path(path) {
+            if let Some((line, offset)) = position {
+                self.move_cursor_to_clamped(line, offset);
+            }
+        } else {
+            let (line, offset) = position.unwrap_or_default();
+            self.check_file_size(path)?;
+            self.load_file(path, line, offset)?;
+        }
+        self.remember_alternate_buffer(previous);
+        Ok(())
+    }
+
+    /// Opens a file like `open_file`, but without checking its size, e.g. once
+    /// opening a large file has been confirmed
+    pub fn open_large_file(&mut self, path: &Path) -> Result<(), PikeError> {
+        check_symlink_target(path)?;
+        self.selection = None;
+        let previous = self.current_buffer_id();
+        if !self.switch_to_buffer_with_path(path) {
+            self.load_file(path, 0, 0)?;
+        }
+        self.remember_alternate_buffer(previous);
+        Ok(())
+    }
+
+    /// Returns an error if the file at `path` is larger than the configured
+    /// threshold, so that it isn't loaded into memory by accident.
+    /// Files which can't be inspected are left for opening to report.
+    fn check_file_size(&self, path: &Path) -> Result<(), PikeError> {
+        let Ok(metadata) = fs::metadata(path) else {
+            return Ok(());
+        };
+
+        if metadata.len() > self.config.large_file_threshold_mb as u64 * BYTES_IN_MB {
+            return Err(PikeError::FileTooLarge(
+                path.to_path_buf(),
+                metadata.len() / BYTES_IN_MB,
+            ));
+        }
+        Ok(())
+    }
+
+    /// Loads the file at `path` into a new buffer with the cursor at
+    /// `line` and `offset`, and sets the buffer up for editing
+    fn load_file(&mut self, path: &Path, line: usize, offset: usize) -> Result<(), PikeError> {
+        open_buffer(&mut self.workspace, path)?;
+        self.bind_current_buffer_to_symlink(path);
 
         self.workspace
             .current_buffer
@@ -97,75 +395,717 @@ impl Pike {
             .expect("Scribe's open_buffer should set a buffer")
             .cursor
             .move_to(BufferPosition { line, offset });
+        self.adopt_current_buffer_indentation();
+        self.apply_current_buffer_editorconfig();
+        self.detect_current_buffer_permissions();
 
         Ok(())
     }
 
-    /// Create a file if if does not exists and open it
-    pub fn create_and_open_file(&mut self, path: &Path) -> Result<(), String> {
-        if !path.exists() {
-            if let Some(parent) = path.parent() {
-                fs::create_dir_all(parent)
-                    .map_err(|e| format!("Failed to create directory: {}", e))?;
+    /// Open a file in a new buffer, even if it's already open in another one
+    pub fn open_file_in_new_buffer(&mut self, path: &Path) -> Result<(), PikeError> {
+        self.selection = None;
+        self.check_file_size(path)?;
+        let buffer = Buffer::from_file(path).map_err(|e| PikeError::OpenFile(e))?;
+        let previous = self.current_buffer_id();
+        self.workspace.add_buffer(buffer);
+        self.remember_alternate_buffer(previous);
+        self.adopt_current_buffer_indentation();
+        self.apply_current_buffer_editorconfig();
+        self.detect_current_buffer_permissions();
+
+        Ok(())
+    }
+
+    /// Binds the current buffer, opened from `path`, to the symlink at `path` instead
+    /// of to its target if symlinks aren't followed. Saving still writes through the
+    /// link to the target, while e.g. the displayed name and the EditorConfig sections
+    /// which apply are the link's.
+    fn bind_current_buffer_to_symlink(&mut self, path: &Path) {
+        let is_symlink = fs::symlink_metadata(path).is_ok_and(|meta| meta.file_type().is_symlink());
+        if self.config.follow_symlinks || !is_symlink {
+            return;
+        }
+        if let (Some(buffer), Ok(link)) = (
+            self.workspace.current_buffer.as_mut(),
+            std::path::absolute(path),
+        ) {
+            buffer.path = Some(link);
+        }
+        self.apply_current_buffer_editorconfig();
+    }
+
+    /// Opens the contents of the file at `path` as of the git revision `rev`, e.g.
+    /// `HEAD~1`, in a new buffer. The buffer is read-only and isn't bound to the
+    /// file, so that saving it can't overwrite the working tree.
+    pub fn open_file_from_git(&mut self, rev: &str, path: &Path) -> Result<(), PikeError> {
+        // A path starting with ./ is looked up relative to the working directory
+        let output = Command::new("git")
+            .arg("show")
+            .arg(format!("{rev}:./{}", path.display()))
+            .current_dir(self.cwd())
+            .output()
+            .map_err(|e| PikeError::Git(e.to_string()))?;
+        if !output.status.success() {
+            let stderr = String::from_utf8_lossy(&output.stderr);
+            return Err(PikeError::Git(stderr.trim().to_string()));
+        }
+
+        let mut buffer = Buffer::new();
+        buffer.insert(String::from_utf8_lossy(&output.stdout));
+        self.selection = None;
+        let previous = self.current_buffer_id();
+        self.workspace.add_buffer(buffer);
+        self.remember_alternate_buffer(previous);
+        self.adopt_current_buffer_indentation();
+        if let Some(id) = self.current_buffer().and_then(|buffer| buffer.id) {
+            self.read_only_buffers.insert(id);
+        }
+
+        Ok(())
+    }
+
+    /// Saves the open buffers bound to files along with their cursors to a session
+    /// file at `path`. The current buffer is saved first, followed by the others in
+    /// the order of switching to the next buffer. The current buffer stays current,
+    /// even though reading the cursors of the others means stepping through them.
+    pub fn save_session(&mut self, path: &Path) -> Result<(), PikeError> {
+        let current = self.current_buffer_id();
+        let mut buffers = Vec::new();
+        for _ in 0..self.workspace.buffer_paths().len() {
+            if let (Some(buffer_path), Some(cursor)) =
+                (self.current_buffer_path(), self.cursor_position())
+            {
+                let mut buffer = Table::new();
+                buffer.insert(
+                    "path".to_string(),
+                    Value::String(buffer_path.to_string_lossy().to_string()),
+                );
+                buffer.insert("line".to_string(), Value::Integer(cursor.line as i64));
+                buffer.insert("offset".to_string(), Value::Integer(cursor.offset as i64));
+                buffers.push(Value::Table(buffer));
             }
+            self.workspace.next_buffer();
+        }
+        if let Some(id) = current {
+            self.focus_buffer(id);
+        }
 
-            File::create(path).map_err(|e| {
-                format!(
-                    "Failed to create file: {} ({})",
-                    path.to_str()
-                        .expect("A path to file has to be valid unicode"),
-                    e
-                )
-            })?;
+        let mut session = Table::new();
+        session.insert("buffers".to_string(), Value::Array(buffers));
+
+        if let Some(parent) = path.parent() {
+            fs::create_dir_all(parent).map_err(|e| PikeError::CreateDirectory(e))?;
+        }
+        fs::write(path, session.to_string()).map_err(|e| PikeError::SaveFile(path.to_path_buf(), e))
+    }
+
+    /// Reopens the buffers saved to the session file at `path` and places their
+    /// cursors where they were, as far as the files haven't become shorter since.
+    /// Returns the errors of the files which weren't reopened, e.g. because they
+    /// no longer exist or are too large to open without confirmation.
+    pub fn load_session(&mut self, path: &Path) -> Result<Vec<PikeError>, PikeError> {
+        let contents = fs::read_to_string(path).map_err(|e| PikeError::ReadFile(e))?;
+        let session = contents
+            .parse::<Table>()
+            .map_err(|e| PikeError::Session(e.to_string()))?;
+        let buffers = match session.get("buffers") {
+            Some(buffers) => buffers
+                .as_array()
+                .ok_or(PikeError::Session(
+                    "buffers have to be an array".to_string(),
+                ))?
+                .as_slice(),
+            None => &[],
+        };
+
+        let mut restored = Vec::new();
+        let mut skipped = Vec::new();
+        for buffer in buffers {
+            let buffer_path = buffer
+                .get("path")
+                .and_then(|path| path.as_str())
+                .map(PathBuf::from)
+                .ok_or(PikeError::Session("a buffer has no path".to_string()))?;
+            let position = |key| {
+                buffer
+                    .get(key)
+                    .and_then(|value: &Value| value.as_integer())
+                    .map_or(0, |value| value.max(0) as usize)
+            };
+
+            if let Err(err) = self.open_existing_file(&buffer_path) {
+                skipped.push(err);
+                continue;
+            }
+            self.move_cursor_to_clamped(position("line"), position("offset"));
+            restored.push(buffer_path);
+        }
+
+        if let Some(first) = restored.first() {
+            self.switch_to_buffer_with_path(first);
+        }
+        Ok(skipped)
+    }
+
+    /// Moves the cursor to the given line and offset, or as close as possible
+    /// to them if they are past the end of the current buffer
+    fn move_cursor_to_clamped(&mut self, line: usize, offset: usize) {
+        let contents = self.current_buffer_contents();
+        let lines: Vec<&str> = buffer_lines(&contents).collect();
+        let line = line.min(lines.len() - 1);
+        let offset = offset.min(lines[line].graphemes(true).count());
+        self.move_cursor_to(BufferPosition { line, offset });
+    }
+
+    /// Opens the scratch buffer backed by the file at `path`, creating the file
+    /// first if it's missing, so that notes taken in it survive restarts
+    pub fn open_scratch_file(&mut self, path: &Path) -> Result<(), PikeError> {
+        self.create_and_open_file(path)
+    }
+
+    /// Saves the buffer of the scratch file at `path` if it's open and modified.
+    /// The current buffer stays the same.
+    pub fn save_scratch_file(&mut self, path: &Path) -> Result<(), PikeError> {
+        let previous = self.current_buffer_id();
+        if !self.switch_to_buffer_with_path(path) {
+            return Ok(());
+        }
+        let result = self.save_current_buffer_if_modified();
+        if let Some(previous) = previous {
+            self.focus_buffer(previous);
+        }
+        result.map_or(Ok(()), |result| result.map(|_| ()))
+    }
+
+    /// Returns the path of the session file of the workspace
+    pub fn session_file_path(&self) -> Result<PathBuf, PikeError> {
+        config::session_file_path(&self.cwd()).map_err(PikeError::Session)
+    }
+
+    /// Returns the index of the buffer bound to the file at `path` among the
+    /// buffers of the workspace, if there's one. Canonical paths are compared,
+    /// so different paths to the same file are matched.
+    pub fn find_buffer_by_path(&self, path: &Path) -> Option<usize> {
+        let path = path.canonicalize().ok()?;
+        self.workspace
+            .buffer_paths()
+            .iter()
+            .position(|buffer_path| {
+                buffer_path.and_then(|p| p.canonicalize().ok()).as_ref() == Some(&path)
+            })
+    }
+
+    /// Switches to the buffer bound to the file at `path`. Returns
+    /// whether such a buffer was found.
+    fn switch_to_buffer_with_path(&mut self, path: &Path) -> bool {
+        let Some(path) = self
+            .find_buffer_by_path(path)
+            .and_then(|_| path.canonicalize().ok())
+        else {
+            return false;
+        };
+
+        for _ in 0..self.workspace.buffer_paths().len() {
+            let current = self
+                .current_buffer()
+                .and_then(|buffer| buffer.path.as_ref())
+                .and_then(|p| p.canonicalize().ok());
+            if current.as_ref() == Some(&path) {
+                return true;
+            }
+            self.workspace.next_buffer();
+        }
+        false
+    }
+
+    /// Returns whether the current buffer can't be edited
+    pub fn is_current_buffer_read_only(&self) -> bool {
+        self.current_buffer()
+            .and_then(|buffer| buffer.id)
+            .is_some_and(|id| self.read_only_buffers.contains(&id))
+    }
+
+    /// Makes the current buffer read-only or editable again
+    pub fn toggle_current_buffer_read_only(&mut self) {
+        let Some(id) = self.current_buffer().and_then(|buffer| buffer.id) else {
+            return;
+        };
+        if !self.read_only_buffers.remove(&id) {
+            self.read_only_buffers.insert(id);
+        }
+    }
+
+    /// Makes the current buffer read-only if the file it was opened
+    /// from can't be written to. If configured, files outside the workspace
+    /// or owned by root require confirming their first edit instead.
+    fn detect_current_buffer_permissions(&mut self) {
+        let Some(buffer) = self.workspace.current_buffer.as_ref() else {
+            return;
+        };
+        let (Some(id), Some(path)) = (buffer.id, buffer.path.as_ref()) else {
+            return;
+        };
+
+        if !is_writable(path) {
+            self.read_only_buffers.insert(id);
+        } else if self.config.confirm_edit_outside_workspace
+            && (!is_inside(path, &self.workspace.path) || is_owned_by_root(path))
+        {
+            self.unconfirmed_buffers.insert(id);
+        }
+    }
+
+    /// Returns whether the current buffer hasn't been edited yet and
+    /// its first edit has to be confirmed
+    pub fn current_buffer_needs_edit_confirmation(&self) -> bool {
+        self.current_buffer()
+            .and_then(|buffer| buffer.id)
+            .is_some_and(|id| self.unconfirmed_buffers.contains(&id))
+    }
+
+    /// Returns an error if the contents of the current buffer mustn't be changed,
+    /// because it's read-only or its first edit hasn't been confirmed. Every
+    /// change to the contents of a buffer is checked with it.
+    fn check_current_buffer_editable(&self) -> Result<(), PikeError> {
+        if self.is_current_buffer_read_only() {
+            return Err(PikeError::BufferReadOnly);
+        }
+        if self.current_buffer_needs_edit_confirmation() {
+            return Err(PikeError::EditNotConfirmed);
         }
-        self.open_file(path, 0, 0)?;
         Ok(())
     }
 
+    /// Allows editing the current buffer without further confirmation
+    pub fn confirm_current_buffer_edits(&mut self) {
+        if let Some(id) = self.current_buffer().and_then(|buffer| buffer.id) {
+            self.unconfirmed_buffers.remove(&id);
+        }
+    }
+
+    /// Returns the settings of the current buffer, which are the configured
+    /// ones unless they were overridden for it
+    pub fn current_buffer_settings(&self) -> EditorSettings {
+        self.workspace
+            .current_buffer
+            .as_ref()
+            .and_then(|buffer| buffer.id)
+            .and_then(|id| self.buffer_settings.get(&id))
+            .copied()
+            .unwrap_or(self.config.editor_settings)
+    }
+
+    /// Detects the indentation used in `contents`. Whatever can't be
+    /// detected, e.g. the width of tab-indented contents, is taken
+    /// from the configured settings.
+    pub fn detect_indentation(&self, contents: &str) -> EditorSettings {
+        let mut settings = self.config.editor_settings;
+
+        let indents: Vec<&str> = contents
+            .lines()
+            .filter(|line| !line.trim().is_empty())
+            .map(|line| &line[..line.len() - line.trim_start().len()])
+            .collect();
+
+        let tab_indented = indents.iter().filter(|i| i.starts_with('\t')).count();
+        let space_indented = indents.iter().filter(|i| i.starts_with(' ')).count();
+        if tab_indented == 0 && space_indented == 0 {
+            return settings;
+        }
+
+        if tab_indented > space_indented {
+            settings.indent_style = IndentStyle::Tabs;
+            return settings;
+        }
+        settings.indent_style = IndentStyle::Spaces;
+
+        // The width is the most common increase of indentation between
+        // consecutive lines, which is robust against e.g. aligned comments
+        let mut increases = HashMap::<usize, usize>::new();
+        let mut previous_width = 0;
+        for indent in indents.iter().filter(|i| !i.contains('\t')) {
+            if indent.len() > previous_width {
+                *increases.entry(indent.len() - previous_width).or_default() += 1;
+            }
+            previous_width = indent.len();
+        }
+        if let Some((width, _)) = increases
+            .into_iter()
+            .max_by_key(|(width, count)| (*count, std::cmp::Reverse(*width)))
+        {
+            settings.indent_width = width;
+        }
+
+        settings
+    }
+
+    /// Overrides the settings of the current buffer with the indentation
+    /// detected in it, unless disabled or the buffer already has its own settings
+    fn adopt_current_buffer_indentation(&mut self) {
+        if !self.config.detect_indentation {
+            return;
+        }
+
+        let Some(buffer) = self.workspace.current_buffer.as_ref() else {
+            return;
+        };
+        let Some(id) = buffer.id else {
+            return;
+        };
+
+        if !self.buffer_settings.contains_key(&id) {
+            let settings = self.detect_indentation(&buffer.data());
+            self.buffer_settings.insert(id, settings);
+        }
+    }
+
+    /// Applies the EditorConfig properties of the current buffer's file. The
+    /// indentation they set takes precedence over the configured and detected one,
+    /// the rest is used when normalizing line endings and saving.
+    fn apply_current_buffer_editorconfig(&mut self) {
+        let Some(path) = self.current_buffer_path() else {
+            return;
+        };
+        let Some(id) = self.current_buffer().and_then(|buffer| buffer.id) else {
+            return;
+        };
+
+        let properties = editorconfig::properties_for(&path);
+        let mut settings = self.current_buffer_settings();
+        if let Some(indent_style) = properties.indent_style {
+            settings.indent_style = indent_style;
+        }
+        if let Some(indent_width) = properties.indent_width() {
+            settings.indent_width = indent_width;
+        }
+        self.buffer_settings.insert(id, settings);
+        self.editorconfig_properties.insert(id, properties);
+    }
+
+    /// Returns the EditorConfig properties of the current buffer's file
+    fn current_buffer_editorconfig(&self) -> editorconfig::Properties {
+        self.current_buffer()
+            .and_then(|buffer| buffer.id)
+            .and_then(|id| self.editorconfig_properties.get(&id))
+            .copied()
+            .unwrap_or_default()
+    }
+
+    /// Create a file if if does not exists and open it
+    pub fn create_and_open_file(&mut self, path: &Path) -> Result<(), PikeError> {
+        check_symlink_target(path)?;
+        create_file_if_missing(path)?;
+        self.open_file_at(path, None)
+    }
+
+    /// Open a file only if it already exists, e.g. when its path comes from
+    /// search results rather than being typed in to create a new file. Neither
+    /// the file nor its parent directories are ever created.
+    pub fn open_existing_file(&mut self, path: &Path) -> Result<(), PikeError> {
+        if !path.is_file() {
+            let error = io::Error::new(io::ErrorKind::NotFound, "no such file");
+            return Err(PikeError::OpenFile(error));
+        }
+        self.open_file_at(path, None)
+    }
+
     /// Writes `text` to current buffer
-    pub fn write_to_current_buffer(&mut self, text: &str) -> Result<(), String> {
+    pub fn write_to_current_buffer(&mut self, text: &str) -> Result<(), PikeError> {
+        self.check_current_buffer_editable()?;
         match &mut self.workspace.current_buffer {
+            Some(_) if text.is_empty() => Ok(()),
             Some(buffer) => {
+                self.selection = None;
+                self.last_expansion = None;
+
                 // Remember the cursor position before inserting
                 let start_position = buffer.cursor.position;
 
-                self.cursor_history.record_undo_position(start_position);
+                cursor_history_of(&mut self.cursor_histories, buffer)
+                    .record_undo_position(start_position);
 
                 buffer.insert(text);
+                buffer
+                    .cursor
+                    .move_to(position_after_insert(start_position, text));
 
-                // Calculate how many lines the inserted text spans
-                let lines: Vec<&str> = text.split('\n').collect();
-                let line_count = lines.len() - 1;
+                let lines = edited_lines(buffer, start_position.line, text);
+                self.mark_lines_dirty(lines);
+                self.record_last_edit();
+                Ok(())
+            }
+            None => Err(PikeError::NoBufferOpen),
+        }
+    }
 
-                // On the final line, check where it ends
-                let last_line_len = lines.last().map_or(0, |l| l.chars().count());
+    /// Writes a typed character to the current buffer. Typing a character which
+    /// can't be a part of a word right after an abbreviation replaces it with
+    /// its expansion, which is undone along with the character in a single step.
+    /// Typing the character again right after the undo doesn't expand it.
+    pub fn type_character(&mut self, ch: char) -> Result<(), PikeError> {
+        self.check_current_buffer_editable()?;
+        let suppressed = self.suppressed_abbreviation.take();
+        if is_word_character(ch) || (suppressed.is_some() && suppressed == self.cursor_position()) {
+            return self.write_to_current_buffer(&ch.to_string());
+        }
 
-                // If no newlines were inserted, just advance on the same line
-                // Otherwise, move down line_count lines, then set offset to the length of the last line
-                let new_line = start_position.line + line_count;
-                let new_offset = if line_count == 0 {
-                    start_position.offset + last_line_len
-                } else {
-                    last_line_len
-                };
+        let Some((start, expansion)) = self.abbreviation_before_cursor() else {
+            return self.write_to_current_buffer(&ch.to_string());
+        };
+        let buffer = self
+            .workspace
+            .current_buffer
+            .as_mut()
+            .expect("An abbreviation was found in the buffer");
+
+        let end = buffer.cursor.position;
+        cursor_history_of(&mut self.cursor_histories, buffer).record_undo_position(end);
+        self.selection = None;
+
+        let text = format!("{expansion}{ch}");
+        buffer.start_operation_group();
+        buffer.delete_range(BufferRange::new(start, end));
+        buffer.cursor.move_to(start);
+        buffer.insert(&text);
+        buffer.end_operation_group();
+
+        let position = position_after_insert(start, &text);
+        buffer.cursor.move_to(position);
+        self.last_expansion = Some(position);
+        let lines = edited_lines(buffer, start.line, &text);
+        self.mark_lines_dirty(lines);
+        self.record_last_edit();
 
-                buffer.cursor.move_to(scribe::buffer::Position {
-                    line: new_line,
-                    offset: new_offset,
-                });
+        Ok(())
+    }
 
-                Ok(())
+    /// Finds a configured abbreviation ending at the cursor and
+    /// returns its start along with its expansion
+    fn abbreviation_before_cursor(&self) -> Option<(BufferPosition, String)> {
+        if self.config.abbreviations.is_empty() {
+            return None;
+        }
+        let cursor = self.cursor_position()?;
+        let contents = self.current_buffer_contents();
+        let line = buffer_lines(&contents).nth(cursor.line)?;
+
+        let before_cursor: Vec<&str> = line.graphemes(true).take(cursor.offset).collect();
+        let word_length = before_cursor
+            .iter()
+            .rev()
+            .take_while(|g| g.chars().all(is_word_character))
+            .count();
+        let word = before_cursor[before_cursor.len() - word_length..].concat();
+
+        let expansion = self.config.abbreviations.get(&word)?;
+        let start = BufferPosition {
+            line: cursor.line,
+            offset: cursor.offset - word_length,
+        };
+        Some((start, expansion.clone()))
+    }
+
+    /// Inserts the current date and time at the cursor in the configured format
+    pub fn insert_datetime(&mut self) -> Result<(), PikeError> {
+        let datetime = Local::now()
+            .format(&self.config.datetime_format)
+            .to_string();
+        self.write_to_current_buffer(&datetime)
+    }
+
+    /// Inserts the character with the code point given in hex, e.g. `U+1F600`,
+    /// `0x1f600` or `1F600`, at the cursor
+    pub fn insert_code_point(&mut self, input: &str) -> Result<(), PikeError> {
+        let c = parse_code_point(input)
+            .ok_or_else(|| PikeError::InvalidCodePoint(input.trim().to_string()))?;
+        self.write_to_current_buffer(&c.to_string())
+    }
+
+    /// Inserts a level of indentation at the cursor, as configured for the current buffer
+    pub fn indent(&mut self) {
+        let settings = self.current_buffer_settings();
+        let indentation = match settings.indent_style {
+            IndentStyle::Spaces => " ".repeat(settings.indent_width),
+            IndentStyle::Tabs => String::from("\t"),
+        };
+
+        // Nothing to indent if no buffer is open
+        let _ = self.write_to_current_buffer(&indentation);
+    }
+
+    /// Pastes `text` at the cursor. Unless disabled in the config, a block of
+    /// lines is re-indented so that it lines up with the cursor's line
+    /// instead of keeping the indentation it was copied with. If configured,
+    /// the indentation is also converted to the buffer's indent style first.
+    pub fn paste(&mut self, text: &str) -> Result<(), PikeError> {
+        let text = if self.config.normalize_paste_indentation {
+            &convert_indentation(text, self.current_buffer_settings())
+        } else {
+            text
+        };
+        if !self.config.paste_reindent || !text.contains('\n') {
+            return self.write_to_current_buffer(text);
+        }
+
+        let pos = self.cursor_position().ok_or(PikeError::NoBufferOpen)?;
+        let contents = self.current_buffer_contents();
+        let line = contents.split('\n').nth(pos.line).unwrap_or_default();
+        let indent: String = line
+            .chars()
+            .take_while(|c| c.is_whitespace())
+            .take(pos.offset)
+            .collect();
+
+        self.write_to_current_buffer(&reindent_lines(text, &indent))
+    }
+
+    /// Copies the selection, or the cursor's line along with its line break
+    /// if nothing is selected, to the register. The selection is cleared
+    /// unless it's configured to be kept.
+    pub fn copy_selection(&mut self) {
+        if let Some((start, end)) = self.selection_or_current_line() {
+            if let Some(text) = self
+                .current_buffer()
+                .and_then(|buffer| buffer.read(&BufferRange::new(start, end)))
+            {
+                self.registers.push_front(text);
+                self.registers.truncate(self.config.clipboard_ring_size);
             }
-            None => Err("Trying to write to a non-existent buffer".to_string()),
         }
+        if !self.config.keep_selection_after_copy {
+            self.selection = None;
+        }
+    }
+
+    /// Moves the selection, or the cursor's line if nothing is selected,
+    /// to the register as a single undo step
+    pub fn cut_selection(&mut self) {
+        if self.check_current_buffer_editable().is_err() {
+            return;
+        }
+        let Some((start, end)) = self.selection_or_current_line() else {
+            return;
+        };
+        self.copy_selection();
+        let Some(buffer) = &mut self.workspace.current_buffer else {
+            return;
+        };
+        self.selection = None;
+        if start == end {
+            return;
+        }
+
+        cursor_history_of(&mut self.cursor_histories, buffer)
+            .record_undo_position(buffer.cursor.position);
+        buffer.delete_range(BufferRange::new(start, end));
+        buffer.cursor.move_to(start);
+
+        let lines = start.line..buffer.line_count().max(start.line + 1);
+        self.mark_lines_dirty(lines);
+        self.record_last_edit();
+    }
+
+    /// Pastes the text last copied or cut at the cursor as it is. Unlike with `paste`,
+    /// it isn't re-indented, as it was copied within the editor, e.g. as whole
+    /// lines which keep their own indentation.
+    pub fn paste_at_cursor(&mut self) -> Result<(), PikeError> {
+        let text = self.registers.front().cloned().unwrap_or_default();
+        let start = self.cursor_position();
+        self.write_to_current_buffer(&text)?;
+        self.last_paste = self
+            .current_buffer_id()
+            .zip(start)
+            .map(|(id, start)| (id, start, 0));
+        Ok(())
+    }
+
+    /// Replaces the text just pasted by `paste_at_cursor` with the text copied
+    /// or cut before it, as a single undo step. Cycling past the oldest text kept
+    /// starts over with the most recent one. Does nothing unless the cursor is
+    /// still right after the pasted text.
+    pub fn paste_cycle(&mut self) -> Result<(), PikeError> {
+        self.check_current_buffer_editable()?;
+        let Some((id, start, index)) = self.last_paste else {
+            return Ok(());
+        };
+        let Some(pasted) = self.registers.get(index) else {
+            return Ok(());
+        };
+        let end = position_after_insert(start, pasted);
+        let range = BufferRange::new(start, end);
+        let is_in_place = self.current_buffer_id() == Some(id)
+            && self.cursor_position() == Some(end)
+            && self.current_buffer().and_then(|buffer| buffer.read(&range)) == Some(pasted.clone());
+        if !is_in_place {
+            self.last_paste = None;
+            return Ok(());
+        }
+
+        let next = (index + 1) % self.registers.len();
+        let text = self.registers[next].clone();
+        let Some(buffer) = self.workspace.current_buffer.as_mut() else {
+            return Err(PikeError::NoBufferOpen);
+        };
+        self.selection = None;
+        cursor_history_of(&mut self.cursor_histories, buffer).record_undo_position(end);
+
+        buffer.start_operation_group();
+        buffer.delete_range(range);
+        buffer.cursor.move_to(start);
+        buffer.insert(&text);
+        buffer.end_operation_group();
+        buffer.cursor.move_to(position_after_insert(start, &text));
+
+        let lines = start.line..buffer.line_count().max(start.line + 1);
+        self.mark_lines_dirty(lines);
+        self.record_last_edit();
+        self.last_paste = Some((id, start, next));
+        Ok(())
+    }
+
+    /// Returns the start and end of the selection, or of the cursor's line
+    /// including its line break if nothing is selected
+    fn selection_or_current_line(&self) -> Option<(BufferPosition, BufferPosition)> {
+        if self.selection.is_some() {
+            return self.selection_to_operate_on();
+        }
+
+        let cursor = self.cursor_position()?;
+        let contents = self.current_buffer_contents();
+        let start = BufferPosition {
+            line: cursor.line,
+            offset: 0,
+        };
+        let end = if cursor.line + 1 < contents.split('\n').count() {
+            BufferPosition {
+                line: cursor.line + 1,
+                offset: 0,
+            }
+        } else {
+            let line = buffer_lines(&contents).nth(cursor.line).unwrap_or_default();
+            BufferPosition {
+                line: cursor.line,
+                offset: line.graphemes(true).count(),
+            }
+        };
+        Some((start, end))
     }
 
     /// Deletes a characted and moves the cursor left
     pub fn delete_character_from_current_buffer(&mut self) {
+        if self.check_current_buffer_editable().is_err() {
+            return;
+        }
         if let Some(buffer) = &mut self.workspace.current_buffer {
+            self.selection = None;
             let pos = buffer.cursor.position;
 
-            self.cursor_history.record_undo_position(pos);
+            // Nothing to delete at the start of the buffer
+            if pos.line == 0 && pos.offset == 0 {
+                return;
+            }
+            cursor_history_of(&mut self.cursor_histories, buffer).record_undo_position(pos);
 
             let data = buffer.data();
 
@@ -188,66 +1128,374 @@ impl Pike {
 
                 // Delete here so it removes the newline
                 buffer.delete();
+                let lines = pos.line - 1..buffer.line_count();
+                self.mark_lines_dirty(lines);
             } else if pos.offset > 0 {
                 buffer.cursor.move_left();
                 buffer.delete();
+                self.mark_lines_dirty(pos.line..pos.line + 1);
             }
+            self.record_last_edit();
         }
     }
 
-    /// Returns the contents of the currently opened buffer or
-    /// an empty string if none is open
-    pub fn current_buffer_contents(&self) -> String {
-        match self.current_buffer().as_ref() {
-            Some(buffer) => buffer.data(),
-            None => String::from(""),
+    /// Deletes the rest of the cursor's line after the cursor, keeping the line break
+    pub fn delete_to_line_end(&mut self) {
+        if let Some(pos) = self.cursor_position() {
+            let contents = self.current_buffer_contents();
+            let line = buffer_lines(&contents).nth(pos.line).unwrap_or_default();
+            self.delete_within_line(pos.line, pos.offset..line.graphemes(true).count());
         }
     }
 
-    /// Returns an absolute path to the current buffer or None
-    pub fn current_buffer_path(&self) -> Option<PathBuf> {
-        self.workspace
-            .current_buffer_path()
-            .map(|buf| self.workspace.path.join(buf))
+    /// Deletes the part of the cursor's line before the cursor
+    pub fn delete_to_line_start(&mut self) {
+        if let Some(pos) = self.cursor_position() {
+            self.delete_within_line(pos.line, 0..pos.offset);
+        }
     }
 
-    /// Returns the filename of the current buffer or an empty string
-    pub fn current_buffer_filename(&self) -> String {
-        match self.current_buffer_path() {
-            Some(path) => path
-                .file_name()
-                .and_then(|file_name| file_name.to_str())
-                .map(|s| s.to_string())
-                .expect("Failed to convert filename to string"),
-            None => String::from(""),
+    /// Deletes the graphemes at `offsets` of `line` as a single undo step and moves
+    /// the cursor to where they started. Deleting nothing isn't an undoable change.
+    fn delete_within_line(&mut self, line: usize, offsets: Range<usize>) {
+        if self.check_current_buffer_editable().is_err() {
+            return;
         }
+        let Some(buffer) = &mut self.workspace.current_buffer else {
+            return;
+        };
+        if offsets.is_empty() {
+            return;
+        }
+        self.selection = None;
+
+        cursor_history_of(&mut self.cursor_histories, buffer)
+            .record_undo_position(buffer.cursor.position);
+        let start = BufferPosition {
+            line,
+            offset: offsets.start,
+        };
+        let end = BufferPosition {
+            line,
+            offset: offsets.end,
+        };
+        buffer.delete_range(BufferRange::new(start, end));
+        buffer.cursor.move_to(start);
+
+        self.mark_lines_dirty(line..line + 1);
+        self.record_last_edit();
     }
 
-    /// Returns whether the current buffer has unsaved changes or
-    /// false if it's empty
-    pub fn has_unsaved_changes(&self) -> bool {
-        match &self.current_buffer() {
-            Some(buffer) => buffer.modified(),
-            None => false,
+    /// Remembers the cursor position as the position of the last edit of the
+    /// current buffer, to which `go_to_last_edit` jumps back
+    fn record_last_edit(&mut self) {
+        let Some(buffer) = self.workspace.current_buffer.as_ref() else {
+            return;
+        };
+        if let Some(id) = buffer.id {
+            self.last_edits.insert(id, buffer.cursor.position);
         }
     }
 
-    /// Returns the position of the cursor in the current buffer
-    /// or None if there isn't one
-    pub fn cursor_position(&self) -> Option<BufferPosition> {
-        self.workspace
-            .current_buffer
-            .as_ref()
-            .map(|buffer| buffer.cursor.position)
+    /// Returns the start and end of the tag matching the one under the cursor,
+    /// e.g. of `</div>` for `<div>`, if the current buffer's file is HTML or XML
+    pub fn matching_tag(&self) -> Option<(BufferPosition, BufferPosition)> {
+        let markup = Markup::of_file(&self.current_buffer_path()?)?;
+        let contents = self.current_buffer_contents();
+        let cursor = byte_offset_at_position(&contents, self.cursor_position()?);
+        let range = matching::matching_tag(&contents, cursor, markup)?;
+        Some((
+            position_at_byte_offset(&contents, range.start),
+            position_at_byte_offset(&contents, range.end),
+        ))
     }
 
-    /// Getter for the current buffer
-    pub fn current_buffer(&self) -> Option<&Buffer> {
-        self.workspace.current_buffer.as_ref()
+    /// Moves the cursor to the start of the tag matching the one under it
+    pub fn jump_to_matching_tag(&mut self) {
+        if let Some((start, _)) = self.matching_tag() {
+            self.selection = None;
+            self.move_cursor_to(start);
+        }
     }
 
-    /// Move the cursor up if possible, else do nothing
-    pub fn move_cursor_up(&mut self) {
+    /// Moves the cursor to where the current buffer was last edited, or as close
+    /// as possible if the buffer got shorter since, e.g. by undoing the edit
+    pub fn go_to_last_edit(&mut self) {
+        let last_edit = self
+            .current_buffer()
+            .and_then(|buffer| buffer.id)
+            .and_then(|id| self.last_edits.get(&id))
+            .copied();
+        if let Some(position) = last_edit {
+            self.selection = None;
+            self.move_cursor_to_clamped(position.line, position.offset);
+        }
+    }
+
+    /// Returns the contents of the currently opened buffer or
+    /// an empty string if none is open
+    pub fn current_buffer_contents(&self) -> String {
+        match self.current_buffer().as_ref() {
+            Some(buffer) => buffer.data(),
+            None => String::from(""),
+        }
+    }
+
+    /// Compares the file at `path` with the current buffer, line by line
+    pub fn diff_against(&self, path: &Path) -> Result<Vec<DiffLine>, PikeError> {
+        let other = fs::read_to_string(path).map_err(|e| PikeError::ReadFile(e))?;
+        Ok(diff_lines(&other, &self.current_buffer_contents()))
+    }
+
+    /// Counts the lines added and removed in the current buffer since it was
+    /// last saved, by comparing it with its file. Returns None if the buffer
+    /// isn't modified, isn't bound to a file or the file can't be read.
+    pub fn unsaved_change_stats(&self) -> Option<(usize, usize)> {
+        if !self.is_current_buffer_modified() {
+            return None;
+        }
+        let diff = self.diff_against(&self.current_buffer_path()?).ok()?;
+
+        let count = |pred: fn(&DiffLine) -> bool| diff.iter().filter(|line| pred(line)).count();
+        Some((
+            count(|line| matches!(line, DiffLine::Added(_))),
+            count(|line| matches!(line, DiffLine::Removed(_))),
+        ))
+    }
+
+    /// Counts the lines of the current buffer ending with each line ending
+    pub fn line_ending_stats(&self) -> LineEndingStats {
+        let contents = self.current_buffer_contents();
+        let crlf = contents.matches("\r\n").count();
+        LineEndingStats {
+            lf: contents.matches('\n').count() - crlf,
+            crlf,
+        }
+    }
+
+    /// Converts all line endings of the current buffer to `style`
+    /// in a single undo step
+    pub fn normalize_line_endings(&mut self, style: LineEnding) {
+        let contents = self.current_buffer_contents();
+        let lines: Vec<&str> = buffer_lines(&contents).collect();
+        let normalized = lines.join(style.as_str());
+        if normalized != contents {
+            self.replace_lines(0..lines.len(), &normalized);
+        }
+    }
+
+    /// Returns the line ending to which the current buffer is normalized, which is
+    /// the configured one unless the buffer's EditorConfig sets another one
+    pub fn line_ending(&self) -> LineEnding {
+        self.current_buffer_editorconfig()
+            .end_of_line
+            .unwrap_or(self.config.line_ending)
+    }
+
+    /// Returns the bytes of the current buffer as they are stored in its file,
+    /// without decoding them. A modified buffer, or one without a file, is
+    /// encoded as UTF-8 instead, as is one whose file can't be read.
+    pub fn current_buffer_bytes(&self) -> Vec<u8> {
+        let from_file = match self.current_buffer_path() {
+            Some(path) if !self.is_current_buffer_modified() => fs::read(path).ok(),
+            _ => None,
+        };
+        from_file.unwrap_or_else(|| self.current_buffer_contents().into_bytes())
+    }
+
+    /// Returns whether the current buffer looks like a binary file,
+    /// i.e. it contains a NUL byte
+    pub fn is_current_buffer_binary(&self) -> bool {
+        self.current_buffer()
+            .is_some_and(|buffer| buffer.data().contains('\0'))
+    }
+
+    /// Returns an absolute path to the current buffer or None
+    pub fn current_buffer_path(&self) -> Option<PathBuf> {
+        let path = self
+            .workspace
+            .path
+            .join(self.workspace.current_buffer_path()?);
+        if path.is_absolute() {
+            return Some(path);
+        }
+
+        // The workspace was created with a relative path, which is resolved against
+        // the working directory of the process, even if the file doesn't exist yet
+        let absolute = path
+            .canonicalize()
+            .or_else(|_| std::env::current_dir().map(|dir| dir.join(&path)))
+            .unwrap_or(path);
+        Some(absolute)
+    }
+
+    /// Resolves a path entered by the user against the working directory or the
+    /// directory of the current buffer's file, as configured. Absolute paths are
+    /// kept, and the working directory is used if the buffer isn't bound to a file.
+    pub fn resolve_path(&self, path: &Path) -> PathBuf {
+        let base = match self.config.relative_path_base {
+            PathBase::CurrentFile => self
+                .current_buffer_path()
+                .and_then(|file| file.parent().map(Path::to_path_buf)),
+            PathBase::WorkingDirectory => None,
+        };
+        base.unwrap_or_else(|| self.cwd()).join(path)
+    }
+
+    /// Completes the last component of a path entered by the user with the names
+    /// of the entries of its directory, as far as they have a common prefix. A
+    /// single matching directory gets a trailing slash. Hidden entries are only
+    /// completed once the component starts with a dot.
+    pub fn complete_path(&self, partial: &str) -> String {
+        let (dir, prefix) = match partial.rfind('/') {
+            Some(index) => partial.split_at(index + 1),
+            None => ("", partial),
+        };
+        let Ok(entries) = fs::read_dir(self.resolve_path(Path::new(dir))) else {
+            return partial.to_string();
+        };
+
+        let matches: Vec<(String, bool)> = entries
+            .filter_map(Result::ok)
+            .filter_map(|entry| {
+                let name = entry.file_name().into_string().ok()?;
+                let is_dir = entry.file_type().is_ok_and(|file_type| file_type.is_dir());
+                Some((name, is_dir))
+            })
+            .filter(|(name, _)| name.starts_with(prefix))
+            .filter(|(name, _)| prefix.starts_with('.') || !name.starts_with('.'))
+            .collect();
+
+        match matches.as_slice() {
+            [] => partial.to_string(),
+            [(name, true)] => format!("{dir}{name}/"),
+            [(name, false)] => format!("{dir}{name}"),
+            [(first, _), rest @ ..] => {
+                let common = rest.iter().fold(first.as_str(), |common, (name, _)| {
+                    common_prefix(common, name)
+                });
+                format!("{dir}{common}")
+            }
+        }
+    }
+
+    /// Returns the paths of the files in the working directory and its
+    /// subdirectories relative to it, sorted. Unless `include_ignored` is set,
+    /// hidden entries and the ones excluded by ignore files, e.g. `.gitignore`,
+    /// are skipped, so that e.g. build output doesn't use up the cap on the
+    /// listed files. The `.git` directory is always skipped.
+    pub fn files_in_cwd(&self, include_ignored: bool) -> Vec<String> {
+        let cwd = self.cwd();
+        let mut files: Vec<String> = WalkBuilder::new(&cwd)
+            .standard_filters(!include_ignored)
+            .require_git(false)
+            .filter_entry(|entry| entry.file_name() != ".git")
+            .build()
+            .filter_map(Result::ok)
+            .filter(|entry| {
+                entry
+                    .file_type()
+                    .is_some_and(|file_type| file_type.is_file())
+            })
+            .filter_map(|entry| {
+                let relative = entry.path().strip_prefix(&cwd).ok()?;
+                Some(relative.to_string_lossy().into_owned())
+            })
+            .take(MAX_LISTED_FILES)
+            .collect();
+
+        files.sort();
+        files
+    }
+
+    /// Returns a search for lines containing a query in the files listed by
+    /// `files_in_cwd`, yielding the path, index and contents of each matching
+    /// line. Files are read only when searching and binary files, files which
+    /// aren't valid UTF-8 or are larger than the large file threshold are
+    /// skipped. A search stops after `MAX_LISTED_TEXT_MATCHES` matches or
+    /// after reading `MAX_SEARCHED_BYTES`.
+    pub fn text_search_in_cwd(&self) -> impl Fn(&str) -> Vec<(String, usize, String)> + 'static {
+        let cwd = self.cwd();
+        let files = self.files_in_cwd(false);
+        let max_file_size = self.config.large_file_threshold_mb as u64 * BYTES_IN_MB;
+        move |query| {
+            let mut matches = Vec::new();
+            if query.is_empty() {
+                return matches;
+            }
+            let mut bytes_read = 0;
+            for file in &files {
+                let path = cwd.join(file);
+                let Ok(metadata) = fs::metadata(&path) else {
+                    continue;
+                };
+                if metadata.len() > max_file_size {
+                    continue;
+                }
+                if bytes_read + metadata.len() > MAX_SEARCHED_BYTES {
+                    break;
+                }
+                bytes_read += metadata.len();
+                let Ok(bytes) = fs::read(&path) else {
+                    continue;
+                };
+                if bytes.contains(&0) {
+                    continue;
+                }
+                let Ok(contents) = String::from_utf8(bytes) else {
+                    continue;
+                };
+                for (index, line) in contents.lines().enumerate() {
+                    if !line.contains(query) {
+                        continue;
+                    }
+                    matches.push((file.clone(), index, line.to_string()));
+                    if matches.len() == MAX_LISTED_TEXT_MATCHES {
+                        return matches;
+                    }
+                }
+            }
+            matches
+        }
+    }
+
+    /// Returns the filename of the current buffer or an empty string,
+    /// also if the filename isn't valid UTF-8
+    pub fn current_buffer_filename(&self) -> String {
+        self.current_buffer_path()
+            .and_then(|path| {
+                path.file_name()
+                    .and_then(|file_name| file_name.to_str())
+                    .map(|s| s.to_string())
+            })
+            .unwrap_or_default()
+    }
+
+    /// Returns whether the current buffer has unsaved changes or
+    /// false if it's empty
+    pub fn has_unsaved_changes(&self) -> bool {
+        match &self.current_buffer() {
+            Some(buffer) => buffer.modified(),
+            None => false,
+        }
+    }
+
+    /// Returns the position of the cursor in the current buffer
+    /// or None if there isn't one
+    pub fn cursor_position(&self) -> Option<BufferPosition> {
+        self.workspace
+            .current_buffer
+            .as_ref()
+            .map(|buffer| buffer.cursor.position)
+    }
+
+    /// Getter for the current buffer
+    pub fn current_buffer(&self) -> Option<&Buffer> {
+        self.workspace.current_buffer.as_ref()
+    }
+
+    /// Move the cursor up if possible, else do nothing
+    pub fn move_cursor_up(&mut self) {
         if let Some(buffer) = &mut self.workspace.current_buffer {
             buffer.cursor.move_up();
         }
@@ -288,10 +1536,7 @@ impl Pike {
 
             // Split the entire buffer by lines.
             let data = buffer.data();
-            let lines: Vec<&str> = data.lines().collect();
-            if lines.is_empty() {
-                return; // nothing to move around
-            }
+            let lines: Vec<&str> = buffer_lines(&data).collect();
 
             // If we're already at the very start of the file, do nothing.
             if pos.line == 0 && pos.offset == 0 {
@@ -339,12 +1584,7 @@ impl Pike {
 
             // Split the entire buffer by lines.
             let data = buffer.data();
-            let lines: Vec<&str> = data.lines().collect();
-
-            // If there's nothing in the buffer, no movement.
-            if lines.is_empty() {
-                return;
-            }
+            let lines: Vec<&str> = buffer_lines(&data).collect();
 
             let current_line_len = lines[pos.line].graphemes(true).count();
 
@@ -384,6 +1624,55 @@ impl Pike {
         }
     }
 
+    /// Move the cursor to the next blank line following a paragraph, skipping
+    /// the blank lines the cursor is on. Moves to the end of the buffer if
+    /// there is no such line.
+    pub fn move_to_next_paragraph(&mut self) {
+        if let Some(buffer) = &mut self.workspace.current_buffer {
+            let data = buffer.data();
+            let lines: Vec<&str> = buffer_lines(&data).collect();
+            let current_line = buffer.cursor.position.line;
+
+            let target = lines
+                .iter()
+                .enumerate()
+                .skip(current_line)
+                .skip_while(|(_, line)| is_blank(line))
+                .find(|(_, line)| is_blank(line))
+                .map(|(i, _)| BufferPosition { line: i, offset: 0 })
+                .unwrap_or_else(|| BufferPosition {
+                    line: lines.len() - 1,
+                    offset: lines[lines.len() - 1].graphemes(true).count(),
+                });
+
+            buffer.cursor.move_to(target);
+        }
+    }
+
+    /// Move the cursor to the previous blank line preceding a paragraph, skipping
+    /// the blank lines the cursor is on. Moves to the start of the buffer if
+    /// there is no such line.
+    pub fn move_to_prev_paragraph(&mut self) {
+        if let Some(buffer) = &mut self.workspace.current_buffer {
+            let data = buffer.data();
+            let lines: Vec<&str> = buffer_lines(&data).collect();
+            let current_line = buffer.cursor.position.line;
+
+            let target_line = lines[..=current_line]
+                .iter()
+                .enumerate()
+                .rev()
+                .skip_while(|(_, line)| is_blank(line))
+                .find(|(_, line)| is_blank(line))
+                .map_or(0, |(i, _)| i);
+
+            buffer.cursor.move_to(BufferPosition {
+                line: target_line,
+                offset: 0,
+            });
+        }
+    }
+
     /// Move the cursor right if possible, else do nothing
     pub fn move_cursor_right(&mut self) {
         if let Some(buffer) = &mut self.workspace.current_buffer {
@@ -398,782 +1687,4584 @@ impl Pike {
         }
     }
 
-    /// Returns the length of the current line
+    /// Move the cursor to the given byte offset in the current buffer, as reported
+    /// by external tools. An offset in the middle of a character moves the cursor
+    /// to its start, while one past the end of the buffer moves it to the end.
+    pub fn move_cursor_to_byte_offset(&mut self, byte_offset: usize) {
+        let contents = self.current_buffer_contents();
+        self.move_cursor_to(position_at_byte_offset(&contents, byte_offset));
+    }
+
+    /// Returns the current selection, if there's one
+    pub fn selection(&self) -> Option<Selection> {
+        self.selection
+    }
+
+    /// Returns the start and (exclusive) end of the selection in document order,
+    /// or None if nothing is selected
+    pub fn selection_range(&self) -> Option<(BufferPosition, BufferPosition)> {
+        self.selection.map(|selection| selection.range())
+    }
+
+    /// Returns the start and end of the selection shrunk to its first and last
+    /// non-whitespace characters, or None if nothing but whitespace is selected
+    pub fn trimmed_selection(&self) -> Option<(BufferPosition, BufferPosition)> {
+        let (start, end) = self.selection_range()?;
+        let contents = self.current_buffer_contents();
+        let start = byte_offset_at_position(&contents, start);
+        let selected = &contents[start..byte_offset_at_position(&contents, end)];
+
+        let trimmed = selected.trim();
+        if trimmed.is_empty() {
+            return None;
+        }
+        let start = start + selected.len() - selected.trim_start().len();
+        Some((
+            position_at_byte_offset(&contents, start),
+            position_at_byte_offset(&contents, start + trimmed.len()),
+        ))
+    }
+
+    /// Returns the range of the selection which copying, cutting and surrounding
+    /// act on, which is trimmed of whitespace if configured. A selection of nothing
+    /// but whitespace is taken as it is, so that acting on it isn't silently skipped.
+    fn selection_to_operate_on(&self) -> Option<(BufferPosition, BufferPosition)> {
+        if self.config.trim_selection {
+            self.trimmed_selection().or_else(|| self.selection_range())
+        } else {
+            self.selection_range()
+        }
+    }
+
+    /// Select the text from `start` to `end` in the current buffer, which may come
+    /// before `start`, and move the cursor to `end` so that extending the selection
+    /// continues from there. Returns false and keeps the selection as it was if
+    /// either position is outside of the buffer.
+    pub fn set_selection(&mut self, start: BufferPosition, end: BufferPosition) -> bool {
+        let Some(buffer) = self.workspace.current_buffer.as_mut() else {
+            return false;
+        };
+        let cursor = buffer.cursor.position;
+        if !(buffer.cursor.move_to(start) && buffer.cursor.move_to(end)) {
+            buffer.cursor.move_to(cursor);
+            return false;
+        }
+        self.selection = Some(Selection {
+            anchor: start,
+            active: end,
+        });
+        true
+    }
+
+    /// Returns the selected text, or None if nothing is selected
+    pub fn selected_text(&self) -> Option<String> {
+        let (start, end) = self.selection?.range();
+        if start == end {
+            return None;
+        }
+        self.current_buffer()?.read(&BufferRange::new(start, end))
+    }
+
+    /// Returns the word under the cursor, or the one right before it if the
+    /// cursor is just past its end, along with its start and length in graphemes.
+    /// Words are made of the same characters as abbreviations.
+    pub fn word_under_cursor(&self) -> Option<(String, BufferPosition, usize)> {
+        let cursor = self.cursor_position()?;
+        let contents = self.current_buffer_contents();
+        let line = buffer_lines(&contents).nth(cursor.line)?;
+        let graphemes: Vec<&str> = line.graphemes(true).collect();
+        let is_word = |g: &&str| g.chars().all(is_word_character);
+
+        let offset = if graphemes.get(cursor.offset).is_some_and(is_word) {
+            cursor.offset
+        } else if cursor.offset > 0 && graphemes.get(cursor.offset - 1).is_some_and(is_word) {
+            cursor.offset - 1
+        } else {
+            return None;
+        };
+
+        let start = offset
+            - graphemes[..offset]
+                .iter()
+                .rev()
+                .take_while(|g| is_word(g))
+                .count();
+        let length = graphemes[start..].iter().take_while(|g| is_word(g)).count();
+
+        Some((
+            graphemes[start..start + length].concat(),
+            BufferPosition {
+                line: cursor.line,
+                offset: start,
+            },
+            length,
+        ))
+    }
+
+    /// Start a new selection at the cursor
+    pub fn start_selection(&mut self) {
+        self.selection = self.cursor_position().map(|pos| Selection {
+            anchor: pos,
+            active: pos,
+        });
+    }
+
+    /// Move the active end of the selection to `pos`, starting the selection
+    /// at the cursor first if there isn't one
+    pub fn extend_selection_to(&mut self, pos: BufferPosition) {
+        if self.selection.is_none() {
+            self.start_selection();
+        }
+        if let Some(selection) = self.selection.as_mut() {
+            selection.active = pos;
+        }
+    }
+
+    /// Clear the selection, if there's one
+    pub fn clear_selection(&mut self) {
+        self.selection = None;
+    }
+
+    /// Wraps the selection in `open` and `close` as a single undo step and
+    /// places the cursor after `close`. Does nothing without a selection.
+    pub fn surround_selection(&mut self, open: char, close: char) {
+        if self.check_current_buffer_editable().is_err() {
+            return;
+        }
+        let Some((start, end)) = self.selection_to_operate_on() else {
+            return;
+        };
+        let Some(buffer) = self.workspace.current_buffer.as_mut() else {
+            return;
+        };
+
+        cursor_history_of(&mut self.cursor_histories, buffer)
+            .record_undo_position(buffer.cursor.position);
+
+        // Insert the closing character first so that it doesn't shift the start
+        buffer.start_operation_group();
+        buffer.cursor.move_to(end);
+        buffer.insert(close.to_string());
+        buffer.cursor.move_to(start);
+        buffer.insert(open.to_string());
+        buffer.end_operation_group();
+
+        let shift = if start.line == end.line { 2 } else { 1 };
+        buffer.cursor.move_to(BufferPosition {
+            line: end.line,
+            offset: end.offset + shift,
+        });
+        self.selection = None;
+        self.mark_lines_dirty(start.line..end.line + 1);
+        self.record_last_edit();
+    }
+
+    /// Changes each line touched by the selection according to the first match
+    /// of `pattern` in it, as a single undo step. Lines without a match are left
+    /// as they are. Does nothing without a selection.
+    pub fn filter_selection_lines(
+        &mut self,
+        pattern: &str,
+        mode: LineFilterMode,
+    ) -> Result<(), PikeError> {
+        let regex =
+            Regex::new(pattern).map_err(|_| PikeError::InvalidRegex(pattern.to_string()))?;
+        let Some((start, end)) = self.selection.map(|selection| selection.range()) else {
+            return Ok(());
+        };
+        // A selection of whole lines ends at the start of the line after them
+        let last_line = if end.offset == 0 && end.line > start.line {
+            end.line - 1
+        } else {
+            end.line
+        };
+
+        let contents = self.current_buffer_contents();
+        let filtered: Vec<String> = contents
+            .split('\n')
+            .skip(start.line)
+            .take(last_line + 1 - start.line)
+            .map(|line| {
+                let (text, line_ending) = match line.strip_suffix('\r') {
+                    Some(text) => (text, "\r"),
+                    None => (line, ""),
+                };
+                let filtered = match mode {
+                    LineFilterMode::KeepMatch => regex.captures(text).map(|captures| {
+                        let kept = captures.get(1).or_else(|| captures.get(0));
+                        kept.map_or("", |kept| kept.as_str()).to_string()
+                    }),
+                    LineFilterMode::RemoveMatch => regex
+                        .find(text)
+                        .map(|found| format!("{}{}", &text[..found.start()], &text[found.end()..])),
+                };
+                format!("{}{line_ending}", filtered.as_deref().unwrap_or(text))
+            })
+            .collect();
+
+        self.selection = None;
+        self.replace_lines(start.line..last_line + 1, &filtered.join("\n"));
+        self.move_cursor_to(BufferPosition {
+            line: start.line,
+            offset: 0,
+        });
+        Ok(())
+    }
+
+    /// Returns the number of lines in the current buffer, counted like
+    /// `str::lines` does - a trailing newline doesn't start another line.
+    /// Relies on the buffer's own line count instead of scanning its contents.
+    pub fn line_count(&self) -> usize {
+        let Some(buffer) = self.current_buffer() else {
+            return 0;
+        };
+
+        let line_count = buffer.line_count();
+        let last_line = line_count - 1;
+        let last_line_is_empty = buffer
+            .read(&BufferRange::new(
+                BufferPosition {
+                    line: last_line,
+                    offset: 0,
+                },
+                BufferPosition {
+                    line: last_line,
+                    offset: 1,
+                },
+            ))
+            .is_none_or(|text| text.is_empty());
+
+        if last_line_is_empty {
+            line_count - 1
+        } else {
+            line_count
+        }
+    }
+
+    /// Returns the number of lines the cursor can be placed on in the current
+    /// buffer, counted like `buffer_lines` does - unlike in `line_count`, a
+    /// trailing newline starts an empty last line.
+    pub fn navigable_line_count(&self) -> usize {
+        self.current_buffer()
+            .map_or(0, |buffer| buffer.line_count())
+    }
+
+    /// Returns the length of the current line in graphemes, which is how
+    /// cursor offsets are measured
     pub fn current_line_length(&self) -> usize {
         let current_line_number = self.cursor_position().map_or(0, |pos| pos.line);
-        match self
-            .current_buffer_contents()
-            .lines()
-            .nth(current_line_number)
-        {
-            Some(line) => line.len(),
+        match buffer_lines(&self.current_buffer_contents()).nth(current_line_number) {
+            Some(line) => line.graphemes(true).count(),
             None => 0,
         }
     }
 
     /// Create a new empty buffer not bound to a path and set it as the current buffer
     pub fn open_new_buffer(&mut self) {
+        self.selection = None;
         let buf = Buffer::new();
+        let previous = self.current_buffer_id();
         self.workspace.add_buffer(buf);
+        self.remember_alternate_buffer(previous);
     }
 
     /// Switch to the previous buffer
     pub fn previous_buffer(&mut self) {
+        self.selection = None;
+        let previous = self.current_buffer_id();
         self.workspace.previous_buffer();
-        // Clear the cursor history when switching buffers
-        self.cursor_history = CursorHistory::default();
+        self.remember_alternate_buffer(previous);
     }
 
     /// Switch to the next buffer
     pub fn next_buffer(&mut self) {
+        self.selection = None;
+        let previous = self.current_buffer_id();
         self.workspace.next_buffer();
-        // Clear the cursor history when switching buffers
-        self.cursor_history = CursorHistory::default();
+        self.remember_alternate_buffer(previous);
     }
 
-    /// Search for a query in the current buffer and return
-    /// the results in the form of a vec of offsets
-    pub fn search_in_current_buffer(&mut self, query: &str) -> Result<Vec<Highlight>, String> {
-        if let Some(buf) = self.workspace.current_buffer.as_mut() {
-            let results = buf
-                .search(query)
-                .into_iter()
-                .map(|pos| Highlight {
-                    start: pos,
-                    length: query.len(),
-                    is_selected: false,
-                })
-                .collect();
-            Ok(results)
-        } else {
-            Err("No buffer is currently open".to_string())
+    /// Returns the open buffers in the order in which they are cycled through,
+    /// starting after the current one, which comes last
+    pub fn open_buffers(&mut self) -> Vec<BufferEntry> {
+        let mut entries = Vec::new();
+        // Cycling through all the buffers ends up at the current one again
+        for _ in 0..self.workspace.buffer_paths().len() {
+            self.workspace.next_buffer();
+            let Some(id) = self.current_buffer_id() else {
+                continue;
+            };
+            let name = self
+                .workspace
+                .current_buffer_path()
+                .map_or(UNBOUND_BUFFER_NAME.to_string(), |path| {
+                    path.to_string_lossy().into_owned()
+                });
+            entries.push(BufferEntry {
+                id,
+                name,
+                is_modified: self.is_current_buffer_modified(),
+            });
         }
+        entries
     }
 
-    /// Save the current buffer to its file
-    pub fn save_current_buffer(&mut self) -> Result<(), String> {
-        match &mut self.workspace.current_buffer {
-            Some(buffer) => {
-                buffer.save().expect("Failed to save buffer");
-
-                Ok(())
-            }
-            None => Err("Trying to save a non-existent buffer".to_string()),
+    /// Switch to the open buffer with the given id. Returns whether it was found.
+    pub fn switch_to_buffer(&mut self, id: usize) -> bool {
+        let previous = self.current_buffer_id();
+        if !self.focus_buffer(id) {
+            return false;
         }
+        if previous != Some(id) {
+            self.selection = None;
+            self.remember_alternate_buffer(previous);
+        }
+        true
     }
 
-    /// Check if the current buffer has been modified
-    pub fn is_current_buffer_modified(&self) -> bool {
-        match self.current_buffer() {
-            Some(buffer) => buffer.modified(),
-            None => false,
+    /// Switch back to the buffer which was current before the current one,
+    /// like vim's `Ctrl-^`. Switching again returns to the current one.
+    pub fn switch_to_alternate_buffer(&mut self) {
+        let Some(alternate) = self.alternate_buffer else {
+            return;
+        };
+
+        let previous = self.current_buffer_id();
+        if self.focus_buffer(alternate) {
+            self.selection = None;
+            self.remember_alternate_buffer(previous);
         }
     }
 
-    /// Undo the last change in the current buffer and adjust the cursor position
-    pub fn undo(&mut self) {
-        if let Some(buf) = self.workspace.current_buffer.as_mut() {
-            // If there's a recorded position, pop it off
-            if let Some(prev_pos) = self.cursor_history.undo_stack.pop() {
-                // Push the current cursor position onto redo stack.
-                let current_pos = buf.cursor.position;
-                self.cursor_history.redo_stack.push(current_pos);
-
-                buf.undo();
-
-                // Move cursor to the old position
-                buf.cursor.move_to(prev_pos);
+    /// Makes the buffer with the given id the current one, returning
+    /// whether it's open
+    fn focus_buffer(&mut self, id: usize) -> bool {
+        for _ in 0..self.workspace.buffer_paths().len() {
+            if self.current_buffer_id() == Some(id) {
+                return true;
             }
+            self.workspace.next_buffer();
         }
+        false
     }
 
-    /// Redo the last change in the current buffer and adjust the cursor position
-    pub fn redo(&mut self) {
-        if let Some(buf) = self.workspace.current_buffer.as_mut() {
-            // If there's a position we previously popped off, pop it from redo
-            if let Some(pos) = self.cursor_history.redo_stack.pop() {
-                // Push the current cursor position onto undo stack
-                // so we can jump back if we undo the redo.
-                let current_pos = buf.cursor.position;
-                self.cursor_history.undo_stack.push(current_pos);
+    /// Opens a read-only buffer with a cheatsheet of the current keybinds, which
+    /// is regenerated each time. Showing help while in the help buffer closes it
+    /// and returns to the buffer which was current before.
+    pub fn show_help(&mut self) {
+        let previous = self.current_buffer_id();
+        if let Some(help) = self.help_buffer.take() {
+            if self.focus_buffer(help) {
+                self.read_only_buffers.remove(&help);
+                self.workspace.close_current_buffer();
+            }
+            if previous == Some(help) {
+                self.selection = None;
+                if let Some(alternate) = self.alternate_buffer.take() {
+                    self.focus_buffer(alternate);
+                }
+                return;
+            }
+            if let Some(previous) = previous {
+                self.focus_buffer(previous);
+            }
+        }
 
-                buf.redo();
+        let text = self.help_text();
+        self.open_new_buffer();
+        let Some(buffer) = self.workspace.current_buffer.as_mut() else {
+            return;
+        };
+        buffer.insert(text);
+        if let Some(id) = buffer.id {
+            self.read_only_buffers.insert(id);
+            self.help_buffer = Some(id);
+        }
+    }
 
-                // Move the cursor to the position after redo
-                buf.cursor.move_to(pos);
+    /// Generates the cheatsheet shown by `show_help`, listing the keybinds
+    /// by the names of their operations and then the unbound operations
+    pub fn help_text(&self) -> String {
+        let mut keybinds: Vec<(String, &str)> = self
+            .config
+            .key_mappings
+            .iter()
+            .map(|(shortcut, operation)| (shortcut.to_display_string(), operation.name()))
+            .collect();
+        keybinds.sort_by(|a, b| a.1.cmp(b.1).then_with(|| a.0.cmp(&b.0)));
+        let key_width = keybinds
+            .iter()
+            .map(|(key, _)| key.len())
+            .max()
+            .unwrap_or_default();
+
+        let mut text = String::from("Keybinds\n\n");
+        for (key, name) in &keybinds {
+            text.push_str(&format!("{key:<key_width$}  {name}\n"));
+        }
+
+        let mut unbound = Operation::names()
+            .filter(|name| !keybinds.iter().any(|(_, bound)| bound == name))
+            .peekable();
+        if unbound.peek().is_some() {
+            text.push_str("\nUnbound operations\n\n");
+            for name in unbound {
+                text.push_str(name);
+                text.push('\n');
             }
         }
+        text
     }
 
-    /// Returns the current working directory as a pathbuf
-    fn cwd(&self) -> PathBuf {
-        self.workspace.path.clone()
+    /// Returns the id of the current buffer, unique among the open buffers
+    fn current_buffer_id(&self) -> Option<usize> {
+        self.current_buffer().and_then(|buffer| buffer.id)
     }
 
-    /// Gets an operation corresponding to a key shortcut
-    pub fn get_keymap(&self, mapping: &KeyShortcut) -> Option<&Operation> {
-        self.config.key_mappings.get(mapping)
+    /// Remembers the buffer with the id `previous` as the alternate one,
+    /// if the current buffer is a different one since
+    fn remember_alternate_buffer(&mut self, previous: Option<usize>) {
+        if previous.is_some() && previous != self.current_buffer_id() {
+            self.alternate_buffer = previous;
+        }
     }
 
-    /// Sets a path for the current buffer
-    pub fn bind_current_buffer_to_path(&mut self, path: PathBuf) {
-        if let Some(buf) = self.workspace.current_buffer.as_mut() {
-            buf.path = Some(path);
+    /// Search for a query in the current buffer and return the results
+    /// as highlights. A `\n` in the query matches a line break, so the
+    /// results might span multiple lines.
+    pub fn search_in_current_buffer(
+        &mut self,
+        query: &str,
+        options: SearchOptions,
+    ) -> Result<Vec<Highlight>, PikeError> {
+        if let Some(buf) = self.workspace.current_buffer.as_ref() {
+            let query = unescape_search_query(query);
+            let results = find_matches(&buf.data(), &query, options)
+                .map(|(start, end)| search_result(start, end))
+                .collect();
+            Ok(results)
+        } else {
+            Err(PikeError::NoBufferOpen)
         }
     }
-}
 
-#[cfg(test)]
-mod pike_test {
-    use std::{
-        env, fs,
-        path::{Path, PathBuf},
-    };
+    /// Searches like `search_in_current_buffer`, but returns at most the configured
+    /// `max_search_highlights` results around the cursor, half of them before it if
+    /// there are enough. Matches past the last returned one aren't looked for. If no
+    /// match follows the cursor, the first ones are returned, as the search wraps around.
+    pub fn search_around_cursor(
+        &self,
+        query: &str,
+        options: SearchOptions,
+    ) -> Result<Vec<Highlight>, PikeError> {
+        let Some(buf) = self.workspace.current_buffer.as_ref() else {
+            return Err(PikeError::NoBufferOpen);
+        };
+        let max = self.config.max_search_highlights;
+        let cursor = buf.cursor.position;
+        let query = unescape_search_query(query);
+        let contents = buf.data();
+
+        let mut before = VecDeque::with_capacity(max);
+        let mut after = Vec::with_capacity(max);
+        let mut skipped_first = false;
+        for (start, end) in find_matches(&contents, &query, options) {
+            if (start.line, start.offset) < (cursor.line, cursor.offset) {
+                if before.len() == max {
+                    before.pop_front();
+                    skipped_first = true;
+                }
+                before.push_back(search_result(start, end));
+            } else {
+                after.push(search_result(start, end));
+                if after.len() == max {
+                    break;
+                }
+            }
+        }
 
-    use crate::{config::Config, test_util::temp_file_with_contents};
-    use scribe::buffer::Position;
+        if after.is_empty() && skipped_first {
+            return Ok(find_matches(&contents, &query, options)
+                .take(max)
+                .map(|(start, end)| search_result(start, end))
+                .collect());
+        }
 
-    use super::Pike;
+        let after_count = after.len().min(max - before.len().min(max / 2));
+        let before_count = before.len().min(max - after_count);
+        let mut results: Vec<Highlight> = before.split_off(before.len() - before_count).into();
+        results.extend(after.into_iter().take(after_count));
+        Ok(results)
+    }
 
-    /// Setup before a test, creates an instance of pike in
-    /// a temporary directory and returns them. Optionally takes
-    /// in the string contents to be injected into its config and
-    /// current working files.
-    fn tmp_pike_and_working_dir(
-        config_content: Option<&str>,
-        cwf_content: Option<&str>,
-    ) -> (Pike, PathBuf) {
-        let dir = env::temp_dir();
-        let cwd = PathBuf::from(dir.as_path())
-            .canonicalize()
-            .expect("Failed to canonicalize path");
+    /// Replaces the match of `find` starting at the cursor, or all of its matches if
+    /// `all` is set, with `replace` as a single undo step. Matches are found the same
+    /// way as by `search_in_current_buffer`, and a `\n` in `replace` stands for a line
+    /// break as well. Overlapping matches are only replaced once. Returns the number
+    /// of replaced matches.
+    pub fn replace_in_current_buffer(
+        &mut self,
+        find: &str,
+        replace: &str,
+        options: SearchOptions,
+        all: bool,
+    ) -> Result<usize, PikeError> {
+        self.check_current_buffer_editable()?;
+        let Some(buffer) = &mut self.workspace.current_buffer else {
+            return Err(PikeError::NoBufferOpen);
+        };
+        let find = unescape_search_query(find);
+        let replace = unescape_search_query(replace);
+        let cursor = buffer.cursor.position;
+
+        let mut targets: Vec<(BufferPosition, BufferPosition)> = Vec::new();
+        for (start, end) in find_matches(&buffer.data(), &find, options) {
+            let overlaps = targets.last().is_some_and(|(_, last_end)| {
+                (start.line, start.offset) < (last_end.line, last_end.offset)
+            });
+            if !overlaps && (all || start == cursor) {
+                targets.push((start, end));
+            }
+        }
+        let Some(&(first, _)) = targets.first() else {
+            return Ok(0);
+        };
+
+        self.selection = None;
+        cursor_history_of(&mut self.cursor_histories, buffer).record_undo_position(cursor);
+
+        // Replacing from the last match keeps the positions of the earlier ones valid
+        buffer.start_operation_group();
+        for (start, end) in targets.iter().rev() {
+            buffer.delete_range(BufferRange::new(*start, *end));
+            buffer.cursor.move_to(*start);
+            buffer.insert(&replace);
+        }
+        buffer.end_operation_group();
+        let lines = first.line..buffer.line_count().max(first.line + 1);
+
+        // A single replacement is stepped over, so that the replaced text isn't
+        // matched again, while replacing all matches keeps the cursor in place
+        if all {
+            self.move_cursor_to_clamped(cursor.line, cursor.offset);
+        } else {
+            self.move_cursor_to(position_after_insert(first, &replace));
+        }
+        self.mark_lines_dirty(lines);
+        self.record_last_edit();
+        Ok(targets.len())
+    }
+
+    /// Counts the matches of `query` in the current buffer, found the same way as by
+    /// `search_in_current_buffer`, without computing their positions. Returns 0 if no
+    /// buffer is open.
+    pub fn count_matches(&self, query: &str, options: SearchOptions) -> usize {
+        self.workspace.current_buffer.as_ref().map_or(0, |buf| {
+            let query = unescape_search_query(query);
+            match_indices(&buf.data(), &query, options).count()
+        })
+    }
+
+    /// Get the index of the first search result at or after the cursor, so that
+    /// searching from the middle of a file starts from there instead of from its top.
+    /// Returns None if no result follows the cursor, in which case the search
+    /// should wrap around to the first one.
+    pub fn first_result_from_cursor(&self, results: &[Highlight]) -> Option<usize> {
+        let cursor = self.cursor_position()?;
+        results.iter().position(|result| {
+            (result.start.line, result.start.offset) >= (cursor.line, cursor.offset)
+        })
+    }
+
+    /// Save the current buffer to its file
+    pub fn save_current_buffer(&mut self) -> Result<(), PikeError> {
+        self.prepare_current_buffer_for_saving();
+        let buffer = self
+            .workspace
+            .current_buffer
+            .as_mut()
+            .ok_or(PikeError::NoBufferOpen)?;
+        let path = buffer.path.clone().ok_or(PikeError::BufferNotBound)?;
+
+        buffer.save().map_err(|e| PikeError::SaveFile(path, e))
+    }
+
+    /// Save every modified buffer bound to a file. Returns the path of each saved
+    /// buffer or the reason it couldn't be saved, e.g. because it isn't bound to a
+    /// file. The current buffer stays the same.
+    pub fn save_all(&mut self) -> Vec<Result<PathBuf, PikeError>> {
+        let mut results = Vec::new();
+
+        for _ in 0..self.workspace.buffer_paths().len() {
+            if let Some(result) = self.save_current_buffer_if_modified() {
+                results.push(result);
+            }
+            // Cycling through all the buffers ends up at the current one again
+            self.workspace.next_buffer();
+        }
+
+        results
+    }
+
+    /// Saves the current buffer if it has been modified, returning its
+    /// path or the reason it couldn't be saved, or None if it's unmodified
+    fn save_current_buffer_if_modified(&mut self) -> Option<Result<PathBuf, PikeError>> {
+        let read_only = self.is_current_buffer_read_only();
+        if !self.current_buffer()?.modified() {
+            return None;
+        }
+        // Read-only buffers are left as they are, as they reject the changes
+        self.prepare_current_buffer_for_saving();
+
+        let buffer = self.workspace.current_buffer.as_mut()?;
+
+        let result = match buffer.path.clone() {
+            // Read-only buffers without a file, e.g. opened from git, are never saved
+            None if read_only => return None,
+            None => Err(PikeError::BufferNotBound),
+            Some(path) if read_only => Err(PikeError::ReadOnly(path)),
+            Some(path) => buffer
+                .save()
+                .map(|_| path.clone())
+                .map_err(|e| PikeError::SaveFile(path, e)),
+        };
+        Some(result)
+    }
+
+    /// Trims trailing whitespace and inserts a final newline as the EditorConfig of
+    /// the current buffer asks for, as a single undo step. The cursor stays where it
+    /// was, unless the whitespace it was in is trimmed.
+    fn prepare_current_buffer_for_saving(&mut self) {
+        let contents = self.current_buffer_contents();
+        let prepared = self
+            .current_buffer_editorconfig()
+            .prepare_for_saving(&contents, self.line_ending());
+        self.set_buffer_contents(&prepared);
+    }
+
+    /// Replaces all contents of the current buffer with `text` as a single undo
+    /// step, e.g. with the output of a formatter. The cursor stays as close to
+    /// its line and column as the new contents allow.
+    pub fn set_buffer_contents(&mut self, text: &str) {
+        let contents = self.current_buffer_contents();
+        if self.current_buffer().is_none() || text == contents {
+            return;
+        }
+
+        self.selection = None;
+        let cursor = self.cursor_position().unwrap_or_default();
+        self.replace_lines(0..contents.split('\n').count(), text);
+        self.move_cursor_to_clamped(cursor.line, cursor.offset);
+    }
+
+    /// Switch to the first modified buffer which isn't bound to a file,
+    /// returning whether there is one
+    pub fn select_unbound_modified_buffer(&mut self) -> bool {
+        let previous = self.current_buffer_id();
+        for _ in 0..self.workspace.buffer_paths().len() {
+            let is_unbound_and_modified = self
+                .current_buffer()
+                .is_some_and(|buffer| buffer.path.is_none() && buffer.modified());
+            if is_unbound_and_modified && !self.is_current_buffer_read_only() {
+                self.selection = None;
+                self.remember_alternate_buffer(previous);
+                return true;
+            }
+            self.workspace.next_buffer();
+        }
+        false
+    }
+
+    /// Set or clear a mark on a line of the current buffer
+    pub fn toggle_line_mark(&mut self, line: usize) {
+        let Some(buffer) = self.current_buffer() else {
+            return;
+        };
+        let Some(id) = buffer.id else {
+            return;
+        };
+        let line_count = buffer.line_count();
+
+        let marks = self.line_marks.entry(id).or_default();
+        marks.line_count = line_count;
+        if !marks.lines.remove(&line) {
+            marks.lines.insert(line);
+        }
+    }
+
+    /// Set or clear a mark on the line the cursor is on
+    pub fn toggle_mark_on_cursor_line(&mut self) {
+        if let Some(pos) = self.cursor_position() {
+            self.toggle_line_mark(pos.line);
+        }
+    }
+
+    /// Returns the marked lines of the current buffer in ascending order
+    pub fn current_buffer_line_marks(&self) -> Vec<usize> {
+        self.current_buffer()
+            .and_then(|buffer| buffer.id)
+            .and_then(|id| self.line_marks.get(&id))
+            .map(|marks| marks.lines.iter().copied().collect())
+            .unwrap_or_default()
+    }
+
+    /// Check if the current buffer has been modified
+    pub fn is_current_buffer_modified(&self) -> bool {
+        match self.current_buffer() {
+            Some(buffer) => buffer.modified(),
+            None => false,
+        }
+    }
+
+    /// Returns a summary of the state of the editor and of the current buffer
+    pub fn editor_state_summary(&self) -> EditorStateSummary {
+        let mode = if self.current_buffer().is_none() {
+            EditorMode::Welcome
+        } else if self.normal_mode {
+            EditorMode::Normal
+        } else if self.selection.is_some() {
+            EditorMode::Select
+        } else {
+            EditorMode::Insert
+        };
+
+        EditorStateSummary {
+            filename: self.current_buffer_filename(),
+            is_modified: self.is_current_buffer_modified(),
+            is_read_only: self.is_current_buffer_read_only(),
+            cursor: self.cursor_position(),
+            line_count: self.navigable_line_count(),
+            selection_length: self
+                .selected_text()
+                .map_or(0, |text| text.graphemes(true).count()),
+            mode,
+        }
+    }
+
+    /// Undo the last change in the current buffer and adjust the cursor position
+    pub fn undo(&mut self) {
+        if self.check_current_buffer_editable().is_err() {
+            return;
+        }
+        let last_expansion = self.last_expansion.take();
+        if let Some(buf) = self.workspace.current_buffer.as_mut() {
+            let cursor_history = cursor_history_of(&mut self.cursor_histories, buf);
+            // If there's a recorded position, pop it off
+            if let Some(prev_pos) = cursor_history.undo_stack.pop() {
+                // Push the current cursor position onto redo stack.
+                let current_pos = buf.cursor.position;
+                cursor_history.redo_stack.push(current_pos);
+
+                buf.undo();
+
+                // Move cursor to the old position
+                buf.cursor.move_to(prev_pos);
+                // The buffer doesn't tell where the undone change was, but it
+                // was made between the two cursor positions
+                let line_count = buf.line_count();
+                self.shift_line_marks(prev_pos.line.min(current_pos.line));
+                self.mark_lines_dirty(0..line_count);
+
+                // Don't expand the abbreviation again if the expansion was unwanted
+                if last_expansion == Some(current_pos) {
+                    self.suppressed_abbreviation = Some(prev_pos);
+                }
+            }
+        }
+    }
+
+    /// Redo the last change in the current buffer and adjust the cursor position
+    pub fn redo(&mut self) {
+        if self.check_current_buffer_editable().is_err() {
+            return;
+        }
+        if let Some(buf) = self.workspace.current_buffer.as_mut() {
+            let cursor_history = cursor_history_of(&mut self.cursor_histories, buf);
+            // If there's a position we previously popped off, pop it from redo
+            if let Some(pos) = cursor_history.redo_stack.pop() {
+                // Push the current cursor position onto undo stack
+                // so we can jump back if we undo the redo.
+                let current_pos = buf.cursor.position;
+                cursor_history.undo_stack.push(current_pos);
+
+                buf.redo();
+
+                // Move the cursor to the position after redo
+                buf.cursor.move_to(pos);
+                let line_count = buf.line_count();
+                self.shift_line_marks(pos.line.min(current_pos.line));
+                self.mark_lines_dirty(0..line_count);
+            }
+        }
+    }
+
+    /// Reflow the paragraph under the cursor so that none of its lines
+    /// exceed the configured text width
+    pub fn reflow_paragraph(&mut self) {
+        let width = self.config.text_width;
+        if let Some(lines) = self.paragraph_around_cursor() {
+            self.reflow(lines, width);
+        }
+    }
+
+    /// Returns the range of lines making up the block of non-blank lines
+    /// the cursor is placed in or None if it's on a blank line
+    fn paragraph_around_cursor(&self) -> Option<Range<usize>> {
+        let pos = self.cursor_position()?;
+        let data = self.current_buffer_contents();
+        let lines: Vec<&str> = data.split('\n').collect();
+        let is_blank = |line: usize| lines[line].trim().is_empty();
+
+        if pos.line >= lines.len() || is_blank(pos.line) {
+            return None;
+        }
+
+        let mut start = pos.line;
+        while start > 0 && !is_blank(start - 1) {
+            start -= 1;
+        }
+
+        let mut end = pos.line + 1;
+        while end < lines.len() && !is_blank(end) {
+            end += 1;
+        }
+
+        Some(start..end)
+    }
+
+    /// Re-wrap the given range of lines to `width` columns as a single undo step,
+    /// preserving the indentation and list marker of the first line
+    pub fn reflow(&mut self, lines: Range<usize>, width: usize) {
+        let data = self.current_buffer_contents();
+        let all_lines: Vec<&str> = data.split('\n').collect();
+        if lines.is_empty() || lines.end > all_lines.len() {
+            return;
+        }
+
+        let original = &all_lines[lines.clone()];
+        let reflowed = reflow_lines(original, width).join("\n");
+        if reflowed != original.join("\n") {
+            self.replace_lines(lines, &reflowed);
+        }
+    }
+
+    /// Swap the grapheme before the cursor with the one under it and move the
+    /// cursor past both. At the end of a line, the last two graphemes are swapped
+    /// instead. Does nothing at the start of a line.
+    pub fn transpose_chars(&mut self) {
+        self.transpose_in_current_line(transpose_graphemes);
+    }
+
+    /// Swap the word under or before the cursor with the next one and move the
+    /// cursor past both. If there's no next word on the line, the last two
+    /// words before the cursor are swapped instead.
+    pub fn transpose_words(&mut self) {
+        self.transpose_in_current_line(transpose_words);
+    }
+
+    /// Replace the cursor's line using `transpose`, which returns the new line
+    /// and cursor offset, or None if there's nothing to transpose
+    fn transpose_in_current_line(&mut self, transpose: fn(&str, usize) -> Option<(String, usize)>) {
+        let Some(pos) = self.cursor_position() else {
+            return;
+        };
+        let contents = self.current_buffer_contents();
+        let line = contents.split('\n').nth(pos.line).unwrap_or_default();
+
+        if let Some((transposed, offset)) = transpose(line, pos.offset) {
+            self.replace_lines(pos.line..pos.line + 1, &transposed);
+            self.move_cursor_to(BufferPosition {
+                line: pos.line,
+                offset,
+            });
+        }
+    }
+
+    /// Replace whole lines of the current buffer with `replacement`
+    /// as a single undo step
+    fn replace_lines(&mut self, lines: Range<usize>, replacement: &str) {
+        if self.check_current_buffer_editable().is_err() {
+            return;
+        }
+        if let Some(buffer) = &mut self.workspace.current_buffer {
+            let data = buffer.data();
+            let all_lines: Vec<&str> = data.split('\n').collect();
+
+            // Replacing the lines with the same text isn't an undoable change
+            if all_lines[lines.clone()].join("\n") == replacement {
+                return;
+            }
+
+            let last_line = lines.end - 1;
+            let start = BufferPosition {
+                line: lines.start,
+                offset: 0,
+            };
+            let end = BufferPosition {
+                line: last_line,
+                offset: all_lines[last_line].graphemes(true).count(),
+            };
+
+            cursor_history_of(&mut self.cursor_histories, buffer)
+                .record_undo_position(buffer.cursor.position);
+
+            buffer.start_operation_group();
+            buffer.delete_range(BufferRange::new(start, end));
+            buffer.cursor.move_to(start);
+            buffer.insert(replacement);
+            buffer.end_operation_group();
+
+            // Lines after the replaced ones only change if they are shifted
+            let replacement_lines = replacement.split('\n').count();
+            let dirty_end = if replacement_lines == lines.len() {
+                lines.end
+            } else {
+                buffer.line_count()
+            };
+            self.mark_lines_dirty(lines.start..dirty_end);
+            self.record_last_edit();
+        }
+    }
+
+    /// Moves the line marks of the current buffer along with the lines inserted
+    /// or removed by an edit starting on `line`
+    fn shift_line_marks(&mut self, line: usize) {
+        let Some(buffer) = self.workspace.current_buffer.as_ref() else {
+            return;
+        };
+        let line_count = buffer.line_count();
+        if let Some(marks) = buffer.id.and_then(|id| self.line_marks.get_mut(&id)) {
+            marks.follow_edit(line, line_count);
+        }
+    }
+
+    /// Extends the changed lines of the current buffer with `lines`, which start
+    /// on the line the edit started on. Line marks after it follow the edit.
+    fn mark_lines_dirty(&mut self, lines: Range<usize>) {
+        self.shift_line_marks(lines.start);
+        self.dirty_lines = Some(match self.dirty_lines.take() {
+            Some(dirty) => dirty.start.min(lines.start)..dirty.end.max(lines.end),
+            None => lines,
+        });
+    }
+
+    /// Returns the range of lines of the current buffer which have been changed
+    /// by edits since the last call, or None if there were no changes. Lines
+    /// shifted by inserting or removing others count as changed. Switching
+    /// buffers isn't tracked, as the whole new buffer has to be redrawn anyway.
+    pub fn take_dirty_lines(&mut self) -> Option<Range<usize>> {
+        self.dirty_lines.take()
+    }
+
+    /// Returns the current working directory as a pathbuf
+    pub fn cwd(&self) -> PathBuf {
+        self.workspace.path.clone()
+    }
+
+    /// Gets an operation corresponding to a key shortcut
+    pub fn get_keymap(&self, mapping: &KeyShortcut) -> Option<&Operation> {
+        self.config.key_mappings.get(mapping)
+    }
+
+    /// Gets an operation corresponding to a key shortcut while no buffer is open
+    pub fn get_welcome_keymap(&self, mapping: &KeyShortcut) -> Option<&Operation> {
+        self.config.welcome_key_mappings.get(mapping)
+    }
+
+    /// Returns whether typed keys are commands of modal editing instead of text
+    pub fn is_in_normal_mode(&self) -> bool {
+        self.normal_mode
+    }
+
+    /// Switches between the normal and the insert mode of modal editing.
+    /// Without modal editing, the editor always stays in the insert mode.
+    pub fn set_normal_mode(&mut self, normal: bool) {
+        self.normal_mode = normal && self.config.modal_editing;
+    }
+
+    /// Gets an operation corresponding to a key shortcut in the normal mode
+    pub fn get_normal_keymap(&self, mapping: &KeyShortcut) -> Option<&Operation> {
+        self.config.normal_key_mappings.get(mapping)
+    }
+
+    /// Gets the action of the file input corresponding to a key shortcut
+    pub fn get_file_input_keymap(&self, mapping: &KeyShortcut) -> Option<InputAction> {
+        self.config.file_input_key_mappings.get(mapping).copied()
+    }
+
+    /// Gets the action of the search input corresponding to a key shortcut
+    pub fn get_search_keymap(&self, mapping: &KeyShortcut) -> Option<InputAction> {
+        self.config.search_key_mappings.get(mapping).copied()
+    }
+
+    /// Gets the action of the picker corresponding to a key shortcut
+    pub fn get_picker_keymap(&self, mapping: &KeyShortcut) -> Option<InputAction> {
+        self.config.picker_key_mappings.get(mapping).copied()
+    }
+
+    /// Returns whether keys are commands until the insert mode is entered
+    pub fn modal_editing(&self) -> bool {
+        self.config.modal_editing
+    }
+
+    /// Returns the path of the file backing the scratch buffer, which is the
+    /// configured one or else the one in the data directory
+    pub fn scratch_file_path(&self) -> Result<PathBuf, String> {
+        match &self.config.scratch_file {
+            Some(path) => Ok(path.clone()),
+            None => config::scratch_file_path(),
+        }
+    }
+
+    /// Returns whether a scratch buffer is opened when starting without
+    /// a file and saved when quitting
+    pub fn opens_scratch_buffer(&self) -> bool {
+        self.config.scratch_buffer
+    }
+
+    /// Returns whether the session should be saved when quitting and
+    /// restored when starting without a file
+    pub fn restores_session(&self) -> bool {
+        self.config.restore_session
+    }
+
+    /// Returns whether pressing an unbound ctrl-modified key should be reported
+    pub fn reports_unbound_keys(&self) -> bool {
+        self.config.report_unbound_keys
+    }
+
+    /// Returns whether large scroll jumps should be animated
+    pub fn smooth_scroll(&self) -> bool {
+        self.config.smooth_scroll
+    }
+
+    /// Returns the template of the status bar contents
+    pub fn status_bar_format(&self) -> &str {
+        &self.config.status_bar_format
+    }
+
+    /// Returns the maximum number of search results highlighted at once
+    pub fn max_search_highlights(&self) -> usize {
+        self.config.max_search_highlights
+    }
+
+    /// Returns whether a scrollbar should be displayed next to the buffer
+    pub fn show_scrollbar(&self) -> bool {
+        self.config.scrollbar
+    }
+
+    /// Returns whether line numbers should be displayed left of the buffer
+    pub fn show_line_numbers(&self) -> bool {
+        self.config.line_numbers
+    }
+
+    /// Returns the configured separator and padding of the gutter
+    pub fn gutter_style(&self) -> GutterStyle {
+        self.config.gutter_style
+    }
+
+    /// Sets a path for the current buffer
+    pub fn bind_current_buffer_to_path(&mut self, path: PathBuf) {
+        if let Some(buf) = self.workspace.current_buffer.as_mut() {
+            buf.path = Some(path);
+        }
+    }
+}
+
+/// Parses a hexadecimal code point, optionally prefixed with `U+` or `0x`,
+/// into the character it encodes. Surrogates and values above U+10FFFF
+/// aren't characters.
+fn parse_code_point(input: &str) -> Option<char> {
+    let input = input.trim();
+    let hex = ["U+", "u+", "0x", "0X"]
+        .iter()
+        .find_map(|prefix| input.strip_prefix(prefix))
+        .unwrap_or(input);
+
+    // `from_str_radix` would also accept a sign
+    if hex.is_empty() || !hex.chars().all(|c| c.is_ascii_hexdigit()) {
+        return None;
+    }
+    u32::from_str_radix(hex, 16).ok().and_then(char::from_u32)
+}
+
+/// Picks the config file to load: the one passed with `--config`, then the one
+/// named by the environment variable, whose value is `env_path`, and finally
+/// the default one if it exists. None means the default configuration is used.
+fn config_file_path(config_file: Option<PathBuf>, env_path: Option<OsString>) -> Option<PathBuf> {
+    if config_file.is_some() {
+        return config_file;
+    }
+    if let Some(env_path) = env_path.filter(|path| !path.is_empty()) {
+        return Some(PathBuf::from(env_path));
+    }
+
+    config::default_config_file_path()
+        .ok()
+        .filter(|path| path.exists())
+}
+
+/// Returns the longest prefix `a` shares with `b`
+fn common_prefix<'a>(a: &'a str, b: &str) -> &'a str {
+    let len = a
+        .char_indices()
+        .zip(b.chars())
+        .find(|((_, a), b)| a != b)
+        .map_or(a.len().min(b.len()), |((index, _), _)| index);
+    &a[..len]
+}
+
+/// Returns whether the characters of `query` appear in `candidate` in order,
+/// ignoring case, e.g. "srcap" in "src/app.rs"
+pub fn fuzzy_matches(query: &str, candidate: &str) -> bool {
+    let mut candidate = candidate.chars().flat_map(char::to_lowercase);
+    query
+        .chars()
+        .flat_map(char::to_lowercase)
+        .all(|ch| candidate.any(|c| c == ch))
+}
+
+/// Transposes the graphemes around `offset` in `line`, returning the new
+/// line and the offset the cursor should be moved to
+fn transpose_graphemes(line: &str, offset: usize) -> Option<(String, usize)> {
+    let mut graphemes: Vec<&str> = line.graphemes(true).collect();
+    if offset == 0 || graphemes.len() < 2 || offset > graphemes.len() {
+        return None;
+    }
+
+    // At the end of the line there's nothing under the cursor,
+    // so the two graphemes before it are swapped
+    let second = offset.min(graphemes.len() - 1);
+    graphemes.swap(second - 1, second);
+
+    Some((graphemes.concat(), second + 1))
+}
+
+/// Transposes the word under or before `offset` in `line` with the next one,
+/// returning the new line and the offset the cursor should be moved to
+fn transpose_words(line: &str, offset: usize) -> Option<(String, usize)> {
+    // Byte ranges of the words in the line
+    let words: Vec<Range<usize>> = line
+        .split_word_bound_indices()
+        .filter(|(_, word)| word.chars().any(char::is_alphanumeric))
+        .map(|(start, word)| start..start + word.len())
+        .collect();
+
+    let cursor = line
+        .grapheme_indices(true)
+        .nth(offset)
+        .map_or(line.len(), |(index, _)| index);
+
+    // The word under the cursor, or else the last one before it
+    let current = words
+        .iter()
+        .rposition(|word| word.start <= cursor)
+        .or((!words.is_empty()).then_some(0))?;
+    let first = if current + 1 < words.len() {
+        current
+    } else {
+        current.checked_sub(1)?
+    };
+    let (first, second) = (&words[first], &words[first + 1]);
+
+    let transposed = format!(
+        "{}{}{}{}{}",
+        &line[..first.start],
+        &line[second.clone()],
+        &line[first.end..second.start],
+        &line[first.clone()],
+        &line[second.end..]
+    );
+    let new_offset = line[..second.end].graphemes(true).count();
+
+    Some((transposed, new_offset))
+}
+
+/// Returns the pair of characters to surround a selection with when `ch` is
+/// typed - a bracket pair for either of its brackets, else `ch` on both sides
+pub fn surround_pair(ch: char) -> (char, char) {
+    match ch {
+        '(' | ')' => ('(', ')'),
+        '[' | ']' => ('[', ']'),
+        '{' | '}' => ('{', '}'),
+        '<' | '>' => ('<', '>'),
+        _ => (ch, ch),
+    }
+}
+
+/// Returns an error if `path` is a symlink to a file which doesn't exist, so
+/// that opening it doesn't create the file in place of the missing target
+fn check_symlink_target(path: &Path) -> Result<(), PikeError> {
+    let is_symlink = fs::symlink_metadata(path).is_ok_and(|meta| meta.file_type().is_symlink());
+    if is_symlink && !path.exists() {
+        return Err(PikeError::BrokenSymlink(path.to_path_buf()));
+    }
+    Ok(())
+}
+
+/// Creates an empty file at `path` along with its parent directories,
+/// unless it already exists
+fn create_file_if_missing(path: &Path) -> Result<(), PikeError> {
+    if path.exists() {
+        return Ok(());
+    }
+    if let Some(parent) = path.parent() {
+        fs::create_dir_all(parent).map_err(|e| PikeError::CreateDirectory(e))?;
+    }
+    File::create(path).map_err(|e| PikeError::CreateFile(path.to_path_buf(), e))?;
+    Ok(())
+}
+
+/// Opens the file at `path` in a buffer of the workspace. The file is opened
+/// beforehand as well, so that the cause of a failure can be reported.
+fn open_buffer(workspace: &mut Workspace, path: &Path) -> Result<(), PikeError> {
+    let to_error = |e| PikeError::OpenFile(e);
+    File::open(path).map_err(to_error)?;
+    workspace
+        .open_buffer(path)
+        .map_err(|e| to_error(io::Error::other(e.to_string())))
+}
+
+/// Converts a byte offset in `contents` to a position, snapping it to the
+/// start of the grapheme it falls into
+fn position_at_byte_offset(contents: &str, byte_offset: usize) -> BufferPosition {
+    let mut position = BufferPosition { line: 0, offset: 0 };
+
+    for (index, grapheme) in contents.grapheme_indices(true) {
+        if index + grapheme.len() > byte_offset {
+            break;
+        }
+        if grapheme.ends_with('\n') {
+            position.line += 1;
+            position.offset = 0;
+        } else {
+            position.offset += 1;
+        }
+    }
+
+    position
+}
+
+/// Converts a position in `contents` to a byte offset. A position past the end
+/// of its line is clamped to the line's end.
+fn byte_offset_at_position(contents: &str, position: BufferPosition) -> usize {
+    let mut current = BufferPosition { line: 0, offset: 0 };
+
+    for (index, grapheme) in contents.grapheme_indices(true) {
+        let line_ends = grapheme.ends_with('\n');
+        if current.line == position.line && (current.offset == position.offset || line_ends) {
+            return index;
+        }
+        if line_ends {
+            current.line += 1;
+            current.offset = 0;
+        } else {
+            current.offset += 1;
+        }
+    }
+
+    contents.len()
+}
+
+/// Returns where the cursor ends up after inserting `text` at `start`
+fn position_after_insert(start: BufferPosition, text: &str) -> BufferPosition {
+    // Calculate how many lines the inserted text spans
+    let lines: Vec<&str> = text.split('\n').collect();
+    let line_count = lines.len() - 1;
+
+    // On the final line, check where it ends
+    let last_line_len = lines.last().map_or(0, |l| l.chars().count());
+
+    // If no newlines were inserted, just advance on the same line
+    // Otherwise, move down line_count lines, then set offset to the length of the last line
+    let offset = if line_count == 0 {
+        start.offset + last_line_len
+    } else {
+        last_line_len
+    };
+
+    BufferPosition {
+        line: start.line + line_count,
+        offset,
+    }
+}
+
+/// Returns whether `ch` can be a part of a word, e.g. an abbreviation
+fn is_word_character(ch: char) -> bool {
+    ch.is_alphanumeric() || ch == '_'
+}
+
+/// Splits the contents of a buffer into lines. This is the line model used
+/// throughout the editor: unlike with `str::lines`, a trailing newline starts
+/// an empty last line, which the cursor can be placed on.
+pub fn buffer_lines(contents: &str) -> impl Iterator<Item = &str> {
+    contents
+        .split('\n')
+        .map(|line| line.strip_suffix('\r').unwrap_or(line))
+}
+
+/// Returns the lines of `buffer` changed by inserting `text` at `line`,
+/// which are all the following ones if the text spans multiple lines
+fn edited_lines(buffer: &Buffer, line: usize, text: &str) -> Range<usize> {
+    if text.contains('\n') {
+        line..buffer.line_count()
+    } else {
+        line..line + 1
+    }
+}
+
+/// Whether a line has no other characters than whitespace
+fn is_blank(line: &str) -> bool {
+    line.trim().is_empty()
+}
+
+/// Diffs two texts by lines, from `old` to `new`
+fn diff_lines(old: &str, new: &str) -> Vec<DiffLine> {
+    let old: Vec<&str> = buffer_lines(old).collect();
+    let new: Vec<&str> = buffer_lines(new).collect();
+
+    TextDiff::from_slices(&old, &new)
+        .iter_all_changes()
+        .map(|change| {
+            let line = change.value().to_string();
+            match change.tag() {
+                ChangeTag::Equal => DiffLine::Unchanged(line),
+                ChangeTag::Insert => DiffLine::Added(line),
+                ChangeTag::Delete => DiffLine::Removed(line),
+            }
+        })
+        .collect()
+}
+
+/// Returns whether `path` is inside the `dir` directory
+fn is_inside(path: &Path, dir: &Path) -> bool {
+    match (path.canonicalize(), dir.canonicalize()) {
+        (Ok(path), Ok(dir)) => path.starts_with(dir),
+        _ => false,
+    }
+}
+
+/// Returns whether the file at `path` is owned by root
+#[cfg(unix)]
+fn is_owned_by_root(path: &Path) -> bool {
+    use std::os::unix::fs::MetadataExt;
+    fs::metadata(path).is_ok_and(|metadata| metadata.uid() == 0)
+}
+
+#[cfg(not(unix))]
+fn is_owned_by_root(_path: &Path) -> bool {
+    false
+}
+
+/// Returns whether the file at `path` can be written to. Checks the
+/// permission bits as well as opening the file for writing, as the
+/// latter succeeds for privileged users regardless of them.
+fn is_writable(path: &Path) -> bool {
+    let permits_writing = fs::metadata(path)
+        .map(|metadata| !metadata.permissions().readonly())
+        .unwrap_or(false);
+
+    permits_writing && OpenOptions::new().append(true).open(path).is_ok()
+}
+
+/// Replaces the escape sequences allowed in search queries with the
+/// characters they stand for: `\n` with a line break and `\\` with a
+/// single backslash. Any other backslash is kept as is.
+fn unescape_search_query(query: &str) -> String {
+    let mut unescaped = String::with_capacity(query.len());
+    let mut chars = query.chars().peekable();
+
+    while let Some(ch) = chars.next() {
+        match (ch, chars.peek()) {
+            ('\\', Some('n')) => {
+                unescaped.push('\n');
+                chars.next();
+            }
+            ('\\', Some('\\')) => {
+                unescaped.push('\\');
+                chars.next();
+            }
+            _ => unescaped.push(ch),
+        }
+    }
+
+    unescaped
+}
+
+/// Returns the byte indices at which occurrences of `query` start in `contents`,
+/// including overlapping ones. An empty query doesn't match anything.
+fn match_indices<'a>(
+    contents: &'a str,
+    query: &'a str,
+    options: SearchOptions,
+) -> impl Iterator<Item = usize> + 'a {
+    contents
+        .char_indices()
+        .map(|(index, _)| index)
+        .filter(move |index| {
+            if query.is_empty() {
+                return false;
+            }
+            match match_length(&contents[*index..], query, options.case_insensitive) {
+                Some(length) => {
+                    !options.whole_word || is_whole_word(contents, *index..*index + length)
+                }
+                None => false,
+            }
+        })
+}
+
+/// Returns the length in bytes of the occurrence of `query` at the start of `text`,
+/// if there's one. Without case sensitivity, characters are compared by their
+/// lowercase forms, so the occurrence might differ in length from the query.
+fn match_length(text: &str, query: &str, case_insensitive: bool) -> Option<usize> {
+    if !case_insensitive {
+        return text.starts_with(query).then_some(query.len());
+    }
+
+    let mut text_chars = text.char_indices();
+    for query_char in query.chars() {
+        let (_, text_char) = text_chars.next()?;
+        if !text_char.to_lowercase().eq(query_char.to_lowercase()) {
+            return None;
+        }
+    }
+    Some(text_chars.next().map_or(text.len(), |(index, _)| index))
+}
+
+/// Returns whether the occurrence spanning `range` of `contents` doesn't continue
+/// a word on either side, e.g. `cat` in `a cat.` but not in `concatenate`
+fn is_whole_word(contents: &str, range: Range<usize>) -> bool {
+    let occurrence = &contents[range.clone()];
+    let joined = |outer: Option<char>, inner: Option<char>| {
+        outer.is_some_and(is_word_character) && inner.is_some_and(is_word_character)
+    };
+    !joined(
+        contents[..range.start].chars().next_back(),
+        occurrence.chars().next(),
+    ) && !joined(
+        contents[range.end..].chars().next(),
+        occurrence.chars().next_back(),
+    )
+}
+
+/// Returns a search result spanning from `start` to `end`, which isn't focused
+fn search_result(start: BufferPosition, end: BufferPosition) -> Highlight {
+    Highlight {
+        start,
+        end,
+        is_selected: false,
+        is_replace_target: false,
+        is_text_selection: false,
+    }
+}
+
+/// Finds the occurrences of `query` in `contents`, which may span multiple
+/// lines, and yields their start and (exclusive) end positions. Occurrences are
+/// found lazily, so that the search can be stopped early.
+fn find_matches<'a>(
+    contents: &'a str,
+    query: &'a str,
+    options: SearchOptions,
+) -> impl Iterator<Item = (BufferPosition, BufferPosition)> + 'a {
+    let query_line_breaks = query.matches('\n').count();
+    let query_last_line = query.rsplit('\n').next().unwrap_or_default();
+    let query_last_line_width = query_last_line.graphemes(true).count();
+
+    // Lines and graphemes are counted from the previous match onwards, so that
+    // many matches on a long line don't make the line be counted over and over
+    let mut line = 0;
+    let mut line_start = 0;
+    let mut counted_until = 0;
+    let mut graphemes = contents.grapheme_indices(true).peekable();
+    let mut graphemes_line_start = 0;
+    let mut offset = 0;
+    match_indices(contents, query, options).map(move |index| {
+        // Count the lines up to the match
+        for (position, ch) in contents[counted_until..index].char_indices() {
+            if ch == '\n' {
+                line += 1;
+                line_start = counted_until + position + 1;
+            }
+        }
+        counted_until = index;
+
+        // Count the graphemes starting before the match on its line
+        if graphemes_line_start != line_start {
+            graphemes = contents[line_start..].grapheme_indices(true).peekable();
+            graphemes_line_start = line_start;
+            offset = 0;
+        }
+        while graphemes
+            .next_if(|(start, _)| line_start + start < index)
+            .is_some()
+        {
+            offset += 1;
+        }
+
+        let start = BufferPosition { line, offset };
+        let end = if query_line_breaks == 0 {
+            BufferPosition {
+                line,
+                offset: offset + query_last_line_width,
+            }
+        } else {
+            BufferPosition {
+                line: line + query_line_breaks,
+                offset: query_last_line_width,
+            }
+        };
+        (start, end)
+    })
+}
+
+/// Rewrites the leading whitespace of each line of `text` in the indent style of
+/// `settings`, keeping the width it takes. Tabs advance to the next multiple of
+/// the indent width. With tabs, a width which isn't a multiple of the indent
+/// width is padded with spaces.
+fn convert_indentation(text: &str, settings: EditorSettings) -> String {
+    let width = settings.indent_width;
+
+    text.split('\n')
+        .map(|line| {
+            let content = line.trim_start_matches([' ', '\t']);
+            let columns =
+                line[..line.len() - content.len()]
+                    .chars()
+                    .fold(0, |columns, c| match c {
+                        '\t' => (columns / width + 1) * width,
+                        _ => columns + 1,
+                    });
+            let indentation = match settings.indent_style {
+                IndentStyle::Spaces => " ".repeat(columns),
+                IndentStyle::Tabs => "\t".repeat(columns / width) + &" ".repeat(columns % width),
+            };
+            indentation + content
+        })
+        .collect::<Vec<String>>()
+        .join("\n")
+}
+
+/// Strips the indentation common to all lines but the first from `text`
+/// and indents these lines with `indent` instead. The first line is
+/// meant to be inserted after an existing indentation, so it only loses
+/// as much of the common indentation as it has.
+fn reindent_lines(text: &str, indent: &str) -> String {
+    // Measured in characters, as lines may be indented with multi-byte whitespace
+    let indent_len = |line: &str| line.chars().take_while(|c| c.is_whitespace()).count();
+    let strip_indent = |line: &str, len: usize| -> String { line.chars().skip(len).collect() };
+
+    let mut lines = text.split('\n');
+    let first_line = lines.next().unwrap_or_default();
+    let rest: Vec<&str> = lines.collect();
+
+    let common_indent = rest
+        .iter()
+        .filter(|line| !line.trim().is_empty())
+        .map(|line| indent_len(line))
+        .min()
+        .unwrap_or(0);
+
+    let first_line = strip_indent(first_line, common_indent.min(indent_len(first_line)));
+    let rest = rest.iter().map(|line| {
+        if line.trim().is_empty() {
+            String::new()
+        } else {
+            format!("{indent}{}", strip_indent(line, common_indent))
+        }
+    });
+
+    std::iter::once(first_line)
+        .chain(rest)
+        .collect::<Vec<String>>()
+        .join("\n")
+}
+
+/// Splits a line into its indentation, an optional list marker
+/// (`- `, `* `, `+ `, `> ` or `1. `) and the remaining text
+fn split_line_prefix(line: &str) -> (&str, &str, &str) {
+    let (indent, rest) = line.split_at(line.len() - line.trim_start().len());
+
+    let marker_len = ["- ", "* ", "+ ", "> "]
+        .iter()
+        .find(|marker| rest.starts_with(*marker))
+        .map(|marker| marker.len())
+        .or_else(|| {
+            let digits = rest.chars().take_while(char::is_ascii_digit).count();
+            (digits > 0 && rest[digits..].starts_with(". ")).then_some(digits + 2)
+        })
+        .unwrap_or(0);
+
+    let (marker, text) = rest.split_at(marker_len);
+    (indent, marker, text)
+}
+
+/// Greedily wraps the words of `lines` so that the resulting lines fit in
+/// `width` columns where possible. The first line keeps its prefix and the
+/// following ones are aligned with the text after it.
+fn reflow_lines(lines: &[&str], width: usize) -> Vec<String> {
+    let (indent, marker, first_line_text) = split_line_prefix(lines[0]);
+    let continuation = format!("{}{}", indent, " ".repeat(marker.len()));
+
+    let words = first_line_text
+        .split_whitespace()
+        .chain(lines[1..].iter().flat_map(|line| line.split_whitespace()));
+
+    let mut result = Vec::new();
+    let mut current = format!("{indent}{marker}");
+    let mut current_width = current.graphemes(true).count();
+    let mut has_words = false;
+
+    for word in words {
+        let word_width = word.graphemes(true).count();
+        if has_words && current_width + 1 + word_width > width {
+            result.push(current);
+            current = continuation.clone();
+            current_width = continuation.graphemes(true).count();
+            has_words = false;
+        }
+
+        if has_words {
+            current.push(' ');
+            current_width += 1;
+        }
+        current.push_str(word);
+        current_width += word_width;
+        has_words = true;
+    }
+    result.push(current);
+
+    result
+}
+
+#[cfg(test)]
+mod pike_test {
+    use std::{
+        env, fs, io,
+        path::{Path, PathBuf},
+        process::Command,
+    };
+
+    use crate::{
+        config::{Config, EditorSettings, IndentStyle, LineEnding, DEFAULT_INDENT_WIDTH},
+        error::PikeError,
+        test_util::temp_file_with_contents,
+    };
+    use scribe::buffer::Position;
+
+    use super::{
+        buffer_lines, config_file_path, convert_indentation, fuzzy_matches, reindent_lines,
+        surround_pair, transpose_graphemes, transpose_words, unescape_search_query, DiffLine,
+        EditorMode, EditorStateSummary, LineEndingStats, LineFilterMode, Pike, SearchOptions,
+    };
+
+    /// Setup before a test, creates an instance of pike in
+    /// a temporary directory and returns them. Optionally takes
+    /// in the string contents to be injected into its config and
+    /// current working files.
+    fn tmp_pike_and_working_dir(
+        config_content: Option<&str>,
+        cwf_content: Option<&str>,
+    ) -> (Pike, PathBuf) {
+        let dir = env::temp_dir();
+        let cwd = PathBuf::from(dir.as_path())
+            .canonicalize()
+            .expect("Failed to canonicalize path");
         let cwf = cwf_content.map(temp_file_with_contents);
         let config_file = config_content.map(temp_file_with_contents);
         let cwf_path = cwf.as_ref().map(|f| f.path().to_path_buf());
         let config_path = config_file.as_ref().map(|f| f.path().to_path_buf());
 
-        (
-            Pike::build(cwd.clone(), cwf_path, config_path).expect("Failed to build Pike"),
-            cwd,
-        )
+        (
+            Pike::build(cwd.clone(), cwf_path, config_path).expect("Failed to build Pike"),
+            cwd,
+        )
+    }
+
+    /// Canonicalizes two paths and asserts their equality
+    fn assert_paths(path1: &Path, path2: &Path) {
+        assert_eq!(
+            path1.canonicalize().expect("Failed to canonicalize path"),
+            path2.canonicalize().expect("Failed to canonicalize path")
+        );
+    }
+
+    #[test]
+    fn test_config_file_path_precedence() {
+        let explicit = temp_file_with_contents("datetime_format = \"explicit\"");
+        let from_env = temp_file_with_contents("datetime_format = \"env\"");
+        let env_value = || Some(from_env.path().as_os_str().to_os_string());
+
+        assert_eq!(
+            config_file_path(Some(explicit.path().to_path_buf()), env_value()),
+            Some(explicit.path().to_path_buf())
+        );
+        let config_file = config_file_path(None, env_value());
+        assert_eq!(config_file.as_deref(), Some(from_env.path()));
+
+        // The config is loaded from the file named by the variable
+        let pike = Pike::build(env::temp_dir(), None, config_file).expect("Failed to build Pike");
+        assert_eq!(pike.config.datetime_format, "env");
+
+        // An empty variable counts as unset
+        assert_ne!(
+            config_file_path(None, Some("".into())),
+            Some(PathBuf::new())
+        );
+    }
+
+    #[test]
+    fn test_current_buffer_path_in_relative_workspace() {
+        let mut pike = Pike::build(PathBuf::from("src"), None, None).expect("Failed to build Pike");
+        pike.open_new_buffer();
+        pike.bind_current_buffer_to_path(PathBuf::from("new.txt"));
+
+        let path = pike.current_buffer_path().unwrap();
+        assert!(path.is_absolute());
+        assert_eq!(
+            path,
+            env::current_dir().unwrap().join("src").join("new.txt")
+        );
+        assert_eq!(pike.current_buffer_filename(), "new.txt");
+    }
+
+    #[cfg(unix)]
+    #[test]
+    fn test_current_buffer_filename_not_utf8() {
+        use std::ffi::OsStr;
+        use std::os::unix::ffi::OsStrExt;
+
+        let (mut pike, cwd) = tmp_pike_and_working_dir(None, None);
+        pike.open_new_buffer();
+        pike.bind_current_buffer_to_path(cwd.join(OsStr::from_bytes(b"\xff.txt")));
+
+        assert_eq!(pike.current_buffer_filename(), "");
+    }
+
+    #[test]
+    fn test_resolve_path() {
+        let workspace = tempfile::tempdir().expect("Failed to create directory");
+        let cwd = workspace.path().canonicalize().unwrap();
+        let nested = cwd.join("src").join("ui");
+        fs::create_dir_all(&nested).unwrap();
+        fs::write(nested.join("mod.rs"), "").unwrap();
+        let config = temp_file_with_contents("relative_path_base = \"current_file\"");
+
+        for (config, base) in [(None, &cwd), (Some(config.path().to_path_buf()), &nested)] {
+            let mut pike = Pike::build(cwd.clone(), None, config).expect("Failed to build Pike");
+            assert_eq!(pike.resolve_path(Path::new("a.rs")), cwd.join("a.rs"));
+
+            pike.open_file(&nested.join("mod.rs"), 0, 0).unwrap();
+            assert_eq!(pike.resolve_path(Path::new("a.rs")), base.join("a.rs"));
+            assert_eq!(
+                pike.resolve_path(Path::new("/b.rs")),
+                PathBuf::from("/b.rs")
+            );
+        }
+    }
+
+    #[test]
+    fn test_complete_path() {
+        let workspace = tempfile::tempdir().expect("Failed to create directory");
+        let cwd = workspace.path().to_path_buf();
+        fs::create_dir_all(cwd.join("module")).unwrap();
+        for file in ["main.rs", "mod.rs", "module/lib.rs", ".hidden"] {
+            fs::write(cwd.join(file), "").unwrap();
+        }
+        let pike = Pike::build(cwd, None, None).expect("Failed to build Pike");
+
+        assert_eq!(pike.complete_path("m"), "m");
+        assert_eq!(pike.complete_path("ma"), "main.rs");
+        assert_eq!(pike.complete_path("mo"), "mod");
+        assert_eq!(pike.complete_path("modu"), "module/");
+        assert_eq!(pike.complete_path("module/"), "module/lib.rs");
+        assert_eq!(pike.complete_path(""), "m");
+        assert_eq!(pike.complete_path("."), ".hidden");
+        assert_eq!(pike.complete_path("x"), "x");
+        assert_eq!(pike.complete_path("missing/x"), "missing/x");
+    }
+
+    #[test]
+    fn test_files_in_cwd() {
+        let workspace = tempfile::tempdir().expect("Failed to create directory");
+        let cwd = workspace.path().to_path_buf();
+        fs::create_dir_all(cwd.join("src/ui")).unwrap();
+        fs::create_dir_all(cwd.join(".git")).unwrap();
+        fs::create_dir_all(cwd.join("target/debug")).unwrap();
+        for file in [
+            "main.rs",
+            "src/app.rs",
+            "src/ui/picker.rs",
+            "src/notes.log",
+            ".hidden",
+            ".git/HEAD",
+            "target/debug/pike",
+        ] {
+            fs::write(cwd.join(file), "").unwrap();
+        }
+        fs::write(cwd.join(".gitignore"), "/target\n*.log\n").unwrap();
+        let pike = Pike::build(cwd, None, None).expect("Failed to build Pike");
+
+        assert_eq!(
+            pike.files_in_cwd(false),
+            vec!["main.rs", "src/app.rs", "src/ui/picker.rs"]
+        );
+        assert_eq!(
+            pike.files_in_cwd(true),
+            vec![
+                ".gitignore",
+                ".hidden",
+                "main.rs",
+                "src/app.rs",
+                "src/notes.log",
+                "src/ui/picker.rs",
+                "target/debug/pike",
+            ]
+        );
+    }
+
+    #[test]
+    fn test_text_search_in_cwd() {
+        let workspace = tempfile::tempdir().expect("Failed to create directory");
+        let cwd = workspace.path().to_path_buf();
+        fs::create_dir_all(cwd.join("target")).unwrap();
+        fs::write(cwd.join("main.rs"), "fn main() {\n    run();\n}").unwrap();
+        fs::write(cwd.join("image.png"), b"run\0\x89").unwrap();
+        fs::write(cwd.join("target/out.rs"), "run();").unwrap();
+        fs::write(cwd.join(".gitignore"), "/target\n").unwrap();
+        let pike = Pike::build(cwd.clone(), None, None).expect("Failed to build Pike");
+
+        let search = pike.text_search_in_cwd();
+        assert!(search("").is_empty());
+        // Files are read when searching, not when the search is created
+        fs::write(cwd.join("lib.rs"), "pub fn run() {}").unwrap();
+        fs::write(
+            cwd.join("main.rs"),
+            "fn main() {\n    run();\n    run();\n}",
+        )
+        .unwrap();
+        assert_eq!(
+            search("run("),
+            vec![
+                ("main.rs".to_string(), 1, "    run();".to_string()),
+                ("main.rs".to_string(), 2, "    run();".to_string()),
+            ]
+        );
+    }
+
+    #[test]
+    fn test_fuzzy_matches() {
+        assert!(fuzzy_matches("", "main.rs"));
+        assert!(fuzzy_matches("srcap", "src/app.rs"));
+        assert!(fuzzy_matches("APP", "src/app.rs"));
+        assert!(!fuzzy_matches("pa", "app"));
+        assert!(!fuzzy_matches("appx", "src/app.rs"));
+    }
+
+    #[test]
+    fn test_build_with_large_file() {
+        let large = temp_file_with_contents(&"a".repeat(1024 * 1024 + 1));
+        let config = temp_file_with_contents("large_file_threshold_mb = 1");
+        let result = Pike::build(
+            env::temp_dir(),
+            Some(large.path().to_path_buf()),
+            Some(config.path().to_path_buf()),
+        );
+        assert!(matches!(result, Err(PikeError::FileTooLarge(path, 1)) if path == large.path()));
+    }
+
+    #[test]
+    fn test_build_minimal_args() {
+        let (pike, cwd) = tmp_pike_and_working_dir(None, None);
+
+        assert_eq!(pike.workspace.path, cwd);
+        assert!(pike.current_buffer().is_none());
+        assert!(pike.config == Config::default());
+    }
+
+    #[test]
+    fn test_build_max_args() {
+        let config_content = r#"
+            [keymaps]
+            "ctrl+a" = "save"
+        "#;
+        let file_content = "hello, world!";
+        let (pike, cwd) = tmp_pike_and_working_dir(Some(config_content), Some(file_content));
+
+        assert_eq!(pike.workspace.path, cwd);
+        assert_eq!(
+            pike.workspace
+                .current_buffer
+                .expect("Current buffer shouldn't be empty when set")
+                .data(),
+            "hello, world!"
+        );
+        let expected_config =
+            Config::from_toml_representation(config_content).expect("Failed to parse config");
+        assert_eq!(pike.config, expected_config);
+    }
+
+    #[test]
+    fn test_open_zero_offset() {
+        let file = temp_file_with_contents("Hello, world!");
+        let mut pike = tmp_pike_and_working_dir(None, None).0;
+        pike.open_file(file.path(), 0, 0)
+            .expect("Failed to open file");
+
+        assert_eq!(
+            pike.workspace
+                .current_buffer_path()
+                .expect("Buffer should be set after opening a file")
+                .file_name()
+                .expect("File should have a name"),
+            file.path().file_name().expect("File should have a name")
+        );
+
+        assert_eq!(
+            pike.workspace
+                .current_buffer
+                .expect("Buffer should be set after opening a file")
+                .data(),
+            "Hello, world!"
+        );
+    }
+
+    #[test]
+    fn test_open_file_non_zero_offset() {
+        let file_contents = r#"
+            Hello,
+            World
+            "#;
+        let file = temp_file_with_contents(file_contents);
+        let mut pike = tmp_pike_and_working_dir(None, None).0;
+        pike.open_file(file.path(), 1, 2)
+            .expect("Could not open file");
+
+        assert_eq!(
+            pike.workspace
+                .current_buffer_path()
+                .expect("Buffer should be set after opening a file")
+                .file_name()
+                .expect("File should have a name"),
+            file.path().file_name().expect("File should have a name")
+        );
+
+        assert_eq!(
+            pike.workspace
+                .current_buffer
+                .expect("Should have an open buffer!")
+                .cursor
+                .position,
+            Position { line: 1, offset: 2 }
+        );
+    }
+
+    #[test]
+    fn test_open_file_out_of_bounds_offset() {
+        let file_contents = r#"
+            Hello,
+            World
+            "#;
+        let file = temp_file_with_contents(file_contents);
+        let mut pike = tmp_pike_and_working_dir(None, None).0;
+        pike.open_file(file.path(), 2, 100)
+            .expect("Could not open file");
+
+        assert_eq!(
+            pike.workspace
+                .current_buffer
+                .expect("Should have an open buffer!")
+                .cursor
+                .position,
+            Position { line: 0, offset: 0 }
+        );
+    }
+
+    #[test]
+    fn test_write_to_buffer() {
+        let mut pike = tmp_pike_and_working_dir(None, Some("")).0;
+        pike.write_to_current_buffer("Hello, world!")
+            .expect("Failed to write to buffer");
+
+        assert_eq!(
+            pike.workspace
+                .current_buffer
+                .expect("Should have an open buffer!")
+                .data(),
+            "Hello, world!"
+        );
+    }
+
+    #[test]
+    fn test_write_to_unbound_buffer() {
+        let mut pike = tmp_pike_and_working_dir(None, None).0;
+        pike.open_new_buffer();
+        let result = pike.write_to_current_buffer("Hello, world!");
+        assert!(result.is_ok());
+        assert_eq!(pike.current_buffer_contents(), "Hello, world!");
+        pike.write_to_current_buffer(" Its me!")
+            .expect("Failed to write to buffer");
+        assert_eq!(pike.current_buffer_contents(), "Hello, world! Its me!");
+    }
+
+    #[test]
+    fn test_save_current_buffer() {
+        let file = temp_file_with_contents("Hello, world!");
+        let mut pike = tmp_pike_and_working_dir(None, None).0;
+
+        pike.open_file(file.path(), 0, 0)
+            .expect("Failed to open file");
+        pike.save_current_buffer().expect("Failed to save buffer");
+
+        let contents = fs::read_to_string(file.path()).expect("Failed to read file");
+        assert_eq!(contents, "Hello, world!");
+    }
+
+    #[test]
+    fn test_save_buffer_no_path() {
+        let mut pike = tmp_pike_and_working_dir(None, None).0;
+        pike.open_new_buffer();
+        // The UI asks for a path before saving an unbound buffer
+        assert!(matches!(
+            pike.save_current_buffer(),
+            Err(PikeError::BufferNotBound)
+        ));
+    }
+
+    #[test]
+    fn test_open_file_errors_tell_their_cause() {
+        let (mut pike, cwd) = tmp_pike_and_working_dir(None, None);
+        let missing = cwd.join("missing/file.txt");
+
+        let err = pike
+            .open_file(&missing, 0, 0)
+            .expect_err("Opening a missing file should fail");
+        assert_eq!(err.io_error_kind(), Some(std::io::ErrorKind::NotFound));
+        assert!(err.to_string().starts_with("Error opening file"));
+
+        assert!(matches!(
+            pike.write_to_current_buffer("text"),
+            Err(PikeError::NoBufferOpen)
+        ));
+    }
+
+    #[test]
+    fn test_current_buffer_contents_has_buffer() {
+        let file = temp_file_with_contents("Hello, world!");
+        let mut pike = tmp_pike_and_working_dir(None, None).0;
+        pike.open_file(file.path(), 0, 0)
+            .expect("Failed to open file");
+
+        assert_eq!(pike.current_buffer_contents(), "Hello, world!");
+    }
+
+    #[test]
+    fn test_current_buffer_contents_no_buffer() {
+        let pike = tmp_pike_and_working_dir(None, None).0;
+
+        assert_eq!(pike.current_buffer_contents(), "");
+    }
+
+    #[test]
+    fn test_current_buffer_fname_has_buffer() {
+        let file = temp_file_with_contents("Hello, world!");
+        let mut pike = tmp_pike_and_working_dir(None, None).0;
+        pike.open_file(file.path(), 0, 0)
+            .expect("Failed to open file");
+
+        assert_eq!(
+            pike.current_buffer_filename(),
+            file.path().file_name().unwrap().to_str().unwrap()
+        );
+    }
+
+    #[test]
+    fn test_current_buffer_fname_no_buffer() {
+        let pike = tmp_pike_and_working_dir(None, None).0;
+
+        assert_eq!(pike.current_buffer_filename(), "");
+    }
+
+    #[test]
+    fn test_has_unsaved_changes_has_changes() {
+        let file = temp_file_with_contents("Hello, world!");
+        let mut pike = tmp_pike_and_working_dir(None, None).0;
+        pike.open_file(file.path(), 0, 0)
+            .expect("Failed to open file");
+        pike.write_to_current_buffer("belo")
+            .expect("Failed to write to file");
+
+        assert!(pike.has_unsaved_changes());
+    }
+
+    #[test]
+    fn test_has_unsaved_changes_no_changes() {
+        let file = temp_file_with_contents("Hello, world!");
+        let mut pike = tmp_pike_and_working_dir(None, None).0;
+        pike.open_file(file.path(), 0, 0)
+            .expect("Failed to open file");
+
+        assert!(!pike.has_unsaved_changes());
+    }
+
+    #[test]
+    fn test_has_unsaved_changes_new_buffer() {
+        let mut pike = tmp_pike_and_working_dir(None, None).0;
+        pike.open_new_buffer();
+        assert!(pike.has_unsaved_changes());
+    }
+
+    /// When moving down to a shorter line, the
+    /// cursor position should be clamped to its length
+    #[test]
+    fn test_move_cursor_down_shorter_line() {
+        let contents = r#"Hello!
+
+        This is a test."#;
+        let (mut pike, _) = tmp_pike_and_working_dir(None, Some(contents));
+        for _ in 0..5 {
+            pike.move_cursor_right();
+        }
+
+        pike.move_cursor_down();
+        assert_eq!(
+            pike.cursor_position(),
+            Some(Position { line: 1, offset: 0 })
+        );
+    }
+
+    /// The cursor should not move out of the bounds of the current
+    /// buffer
+    #[test]
+    fn test_move_cursor_out_of_bounds() {
+        let contents = "a";
+        let (mut pike, _) = tmp_pike_and_working_dir(None, Some(contents));
+
+        pike.move_cursor_right();
+        assert_eq!(
+            pike.cursor_position(),
+            // This makes sense, since inserting does not move the cursor right
+            Some(Position { line: 0, offset: 1 })
+        );
+
+        // Two times to the left to test for going too far to the left
+        pike.move_cursor_left();
+        pike.move_cursor_left();
+        assert_eq!(
+            pike.cursor_position(),
+            Some(Position { line: 0, offset: 0 })
+        );
+
+        pike.move_cursor_down();
+        assert_eq!(
+            pike.cursor_position(),
+            Some(Position { line: 0, offset: 0 })
+        );
+
+        pike.move_cursor_up();
+        assert_eq!(
+            pike.cursor_position(),
+            Some(Position { line: 0, offset: 0 })
+        );
+    }
+
+    #[test]
+    fn test_move_cursor_left_by_word() {
+        let contents = "aaa aaa";
+        let (mut pike, _) = tmp_pike_and_working_dir(None, Some(contents));
+
+        pike.move_cursor_to(Position { line: 0, offset: 4 });
+
+        pike.move_cursor_left_by_word();
+
+        assert_eq!(
+            pike.cursor_position(),
+            Some(Position { line: 0, offset: 0 })
+        );
+    }
+
+    #[test]
+    fn test_move_cursor_right_by_word() {
+        let contents = "aaa aaa";
+        let (mut pike, _) = tmp_pike_and_working_dir(None, Some(contents));
+
+        pike.move_cursor_to(Position { line: 0, offset: 0 });
+
+        pike.move_cursor_right_by_word();
+
+        assert_eq!(
+            pike.cursor_position(),
+            Some(Position { line: 0, offset: 3 })
+        );
+    }
+
+    #[test]
+    fn test_move_cursor_left_by_word_with_unicode() {
+        let contents = "aaa ę aaa";
+        let (mut pike, _) = tmp_pike_and_working_dir(None, Some(contents));
+
+        pike.move_cursor_to(Position { line: 0, offset: 6 });
+
+        pike.move_cursor_left_by_word();
+
+        assert_eq!(
+            pike.cursor_position(),
+            Some(Position { line: 0, offset: 4 })
+        );
+
+        pike.move_cursor_left_by_word();
+
+        assert_eq!(
+            pike.cursor_position(),
+            Some(Position { line: 0, offset: 0 })
+        );
+    }
+
+    #[test]
+    fn test_move_cursor_right_by_word_with_unicode() {
+        let contents = "aaa ę aaa";
+        let (mut pike, _) = tmp_pike_and_working_dir(None, Some(contents));
+
+        pike.move_cursor_to(Position { line: 0, offset: 0 });
+
+        pike.move_cursor_right_by_word();
+
+        assert_eq!(
+            pike.cursor_position(),
+            Some(Position { line: 0, offset: 3 })
+        );
+
+        pike.move_cursor_right_by_word();
+
+        assert_eq!(
+            pike.cursor_position(),
+            Some(Position { line: 0, offset: 5 })
+        );
+    }
+
+    #[test]
+    fn test_move_cursor_right_and_left_with_combining_unicode() {
+        let contents = "ęęę ęęę";
+        let (mut pike, _) = tmp_pike_and_working_dir(None, Some(contents));
+
+        pike.move_cursor_to(Position { line: 0, offset: 0 });
+
+        pike.move_cursor_right_by_word();
+
+        assert_eq!(
+            pike.cursor_position(),
+            Some(Position { line: 0, offset: 3 })
+        );
+
+        pike.move_cursor_right_by_word();
+        assert_eq!(
+            pike.cursor_position(),
+            Some(Position { line: 0, offset: 7 })
+        );
+
+        pike.move_cursor_left_by_word();
+
+        assert_eq!(
+            pike.cursor_position(),
+            Some(Position { line: 0, offset: 4 })
+        );
+
+        pike.move_cursor_left_by_word();
+
+        assert_eq!(
+            pike.cursor_position(),
+            Some(Position { line: 0, offset: 0 })
+        );
+    }
+
+    #[test]
+    fn test_current_line_length_buffer_exists() {
+        let contents = ["Hello!", ""].join("\n");
+        let (mut pike, _) = tmp_pike_and_working_dir(None, Some(contents.as_str()));
+
+        assert_eq!(pike.current_line_length(), 6);
+
+        pike.move_cursor_down();
+        assert_eq!(pike.current_line_length(), 0);
+    }
+
+    #[test]
+    fn test_current_line_length_counts_graphemes() {
+        let (mut pike, _) = tmp_pike_and_working_dir(None, Some("héllo 😀\ne\u{301}"));
+        assert_eq!(pike.current_line_length(), 7);
+        // The end of the line is at the same offset as the cursor measures it
+        pike.move_cursor_to_end_of_line();
+        assert_eq!(
+            pike.cursor_position(),
+            Some(Position { line: 0, offset: 7 })
+        );
+
+        // A combining accent is part of the character before it
+        pike.move_cursor_down();
+        assert_eq!(pike.current_line_length(), 1);
+    }
+
+    #[test]
+    fn test_current_line_length_no_buffer() {
+        let pike = tmp_pike_and_working_dir(None, None).0;
+
+        assert_eq!(pike.current_line_length(), 0);
+    }
+
+    #[test]
+    fn test_create_and_open_file_doesnt_exist() {
+        let (mut pike, cwd) = tmp_pike_and_working_dir(None, None);
+        let file_path = cwd.join("test.txt");
+
+        pike.create_and_open_file(&file_path)
+            .expect("Failed to create and open file");
+
+        assert_paths(
+            &pike
+                .current_buffer_path()
+                .expect("Buffer should be set after opening a file"),
+            &file_path,
+        );
+    }
+
+    #[test]
+    fn test_opening_open_file_switches_to_its_buffer() {
+        let (mut pike, cwd) = tmp_pike_and_working_dir(None, None);
+        let file_path = cwd.join("test.txt");
+        fs::write(&file_path, "contents").expect("Failed to write file");
+
+        pike.open_file(&file_path, 0, 3)
+            .expect("Failed to open file");
+        pike.open_new_buffer();
+        assert_eq!(pike.find_buffer_by_path(&file_path), Some(0));
+
+        pike.create_and_open_file(&cwd.join(".").join("test.txt"))
+            .expect("Failed to open file");
+        assert_eq!(pike.workspace.buffer_paths().len(), 2);
+        assert_eq!(pike.current_buffer_contents(), "contents");
+        assert_eq!(
+            pike.cursor_position(),
+            Some(Position { line: 0, offset: 3 })
+        );
+
+        pike.open_file_in_new_buffer(&file_path)
+            .expect("Failed to open file");
+        assert_eq!(pike.workspace.buffer_paths().len(), 3);
+    }
+
+    #[test]
+    fn test_opening_open_file_at_position_moves_cursor() {
+        let (mut pike, cwd) = tmp_pike_and_working_dir(None, None);
+        let file_path = cwd.join("test.txt");
+        fs::write(&file_path, "first\nsecond\nthird").expect("Failed to write file");
+
+        pike.open_file(&file_path, 0, 3)
+            .expect("Failed to open file");
+        pike.open_new_buffer();
+        pike.open_file(&file_path, 1, 2)
+            .expect("Failed to open file");
+        assert_eq!(pike.workspace.buffer_paths().len(), 2);
+        assert_eq!(
+            pike.cursor_position(),
+            Some(Position { line: 1, offset: 2 })
+        );
+
+        // Out of bounds positions are clamped to the contents
+        pike.open_file(&file_path, 10, 100)
+            .expect("Failed to open file");
+        assert_eq!(
+            pike.cursor_position(),
+            Some(Position { line: 2, offset: 5 })
+        );
+    }
+
+    #[test]
+    fn test_open_existing_file_doesnt_create_it() {
+        let file = temp_file_with_contents("Hello, world!");
+        let (mut pike, cwd) = tmp_pike_and_working_dir(None, None);
+        let missing = cwd.join("not-created").join("test.txt");
+
+        let err = pike
+            .open_existing_file(&missing)
+            .expect_err("Opened a missing file");
+        assert_eq!(err.io_error_kind(), Some(io::ErrorKind::NotFound));
+        assert!(!missing.parent().unwrap().exists());
+        assert!(pike.current_buffer().is_none());
+
+        pike.open_existing_file(file.path())
+            .expect("Failed to open file");
+        assert_eq!(pike.current_buffer_contents(), "Hello, world!");
+    }
+
+    #[test]
+    fn test_editorconfig_is_applied() {
+        let workspace = tempfile::tempdir().expect("Failed to create directory");
+        let cwd = workspace.path().to_path_buf();
+        fs::write(
+            cwd.join(".editorconfig"),
+            "root = true\n\n[*]\ntrim_trailing_whitespace = true\ninsert_final_newline = true\n\n\
+             [*.yml]\nindent_size = 2\nend_of_line = crlf\n",
+        )
+        .unwrap();
+        let (yaml, text) = (cwd.join("a.yml"), cwd.join("b.txt"));
+        fs::write(&yaml, "a:\n    b: 1").unwrap();
+        fs::write(&text, "foo  \nbar").unwrap();
+
+        let mut pike = Pike::build(cwd.clone(), None, None).expect("Failed to build Pike");
+        pike.open_file(&yaml, 0, 0).unwrap();
+        // The detected indentation is overridden
+        assert_eq!(pike.current_buffer_settings().indent_width, 2);
+        assert_eq!(pike.line_ending(), LineEnding::CrLf);
+
+        pike.open_file(&text, 0, 5).unwrap();
+        assert_eq!(pike.line_ending(), LineEnding::Lf);
+        pike.write_to_current_buffer("!").unwrap();
+        pike.save_current_buffer().unwrap();
+        assert_eq!(fs::read_to_string(&text).unwrap(), "foo  !\nbar\n");
+        assert_eq!(
+            pike.cursor_position(),
+            Some(Position { line: 0, offset: 6 })
+        );
+
+        pike.write_to_current_buffer(" ").unwrap();
+        pike.save_current_buffer().unwrap();
+        assert_eq!(fs::read_to_string(&text).unwrap(), "foo  !\nbar\n");
+    }
+
+    #[test]
+    fn test_save_and_load_session() {
+        let workspace = tempfile::tempdir().expect("Failed to create directory");
+        let cwd = workspace.path().to_path_buf();
+        let (first, second, third) = (cwd.join("a.txt"), cwd.join("b.txt"), cwd.join("c.txt"));
+        fs::write(&first, "one\ntwo\nthree").unwrap();
+        fs::write(&second, "four\nfive").unwrap();
+        fs::write(&third, "six").unwrap();
+        let session = cwd.join("sessions").join("session.toml");
+
+        let mut pike = Pike::build(cwd.clone(), None, None).expect("Failed to build Pike");
+        pike.open_file(&first, 2, 4).unwrap();
+        pike.open_file(&third, 0, 0).unwrap();
+        pike.open_file(&second, 1, 2).unwrap();
+        pike.open_new_buffer();
+        pike.previous_buffer();
+        pike.save_session(&session).expect("Failed to save session");
+        assert_paths(&pike.current_buffer_path().unwrap(), &second);
+
+        // The first file gets shorter and the third one is deleted meanwhile
+        fs::write(&first, "one").unwrap();
+        fs::remove_file(&third).unwrap();
+
+        let mut pike = Pike::build(cwd.clone(), None, None).expect("Failed to build Pike");
+        let skipped = pike.load_session(&session).expect("Failed to load session");
+        assert!(
+            matches!(skipped.as_slice(), [err @ PikeError::OpenFile(_)]
+                if err.io_error_kind() == Some(io::ErrorKind::NotFound)),
+            "{skipped:?}"
+        );
+        assert_eq!(pike.workspace.buffer_paths().len(), 2);
+
+        assert_paths(&pike.current_buffer_path().unwrap(), &second);
+        assert_eq!(
+            pike.cursor_position(),
+            Some(Position { line: 1, offset: 2 })
+        );
+        pike.next_buffer();
+        assert_paths(&pike.current_buffer_path().unwrap(), &first);
+        assert_eq!(
+            pike.cursor_position(),
+            Some(Position { line: 0, offset: 3 })
+        );
+
+        fs::write(&session, "buffers = 1").unwrap();
+        assert!(matches!(
+            pike.load_session(&session),
+            Err(PikeError::Session(_))
+        ));
+
+        // Large files are left for opening once confirmed
+        fs::write(&first, "a".repeat(2 * 1024 * 1024 + 1)).unwrap();
+        pike.save_session(&session).expect("Failed to save session");
+        let config = cwd.join("config.toml");
+        fs::write(&config, "large_file_threshold_mb = 1").unwrap();
+        let mut pike = Pike::build(cwd.clone(), None, Some(config)).expect("Failed to build Pike");
+        let skipped = pike.load_session(&session).expect("Failed to load session");
+        assert!(
+            matches!(skipped.as_slice(), [PikeError::FileTooLarge(path, 2)] if *path == first),
+            "{skipped:?}"
+        );
+        assert_paths(&pike.current_buffer_path().unwrap(), &second);
+    }
+
+    #[test]
+    fn test_scratch_file_persists_between_runs() {
+        let workspace = tempfile::tempdir().expect("Failed to create directory");
+        let cwd = workspace.path().to_path_buf();
+        let scratch = cwd.join("data").join("scratch.txt");
+        let other = cwd.join("other.txt");
+        fs::write(&other, "other").unwrap();
+
+        let mut pike = Pike::build(cwd.clone(), None, None).expect("Failed to build Pike");
+        pike.open_scratch_file(&scratch).unwrap();
+        assert!(scratch.is_file());
+        pike.write_to_current_buffer("note").unwrap();
+        pike.open_file(&other, 0, 0).unwrap();
+        pike.save_scratch_file(&scratch).unwrap();
+
+        // Saving the scratch file doesn't move away from the current buffer
+        assert_paths(&pike.current_buffer_path().unwrap(), &other);
+        assert_eq!(fs::read_to_string(&scratch).unwrap(), "note");
+
+        let mut pike = Pike::build(cwd, None, None).expect("Failed to build Pike");
+        pike.open_scratch_file(&scratch).unwrap();
+        assert_eq!(pike.current_buffer_contents(), "note");
+    }
+
+    #[test]
+    fn test_create_and_open_file_nested() {
+        let (mut pike, cwd) = tmp_pike_and_working_dir(None, None);
+        let file_path = cwd.join("nested").join("test.txt");
+
+        pike.create_and_open_file(&file_path)
+            .expect("Failed to create and open file");
+
+        assert_paths(
+            &pike
+                .current_buffer_path()
+                .expect("Buffer should be set after opening a file"),
+            &file_path,
+        );
+    }
+
+    #[test]
+    fn test_create_and_open_file_exists() {
+        let file = temp_file_with_contents("Hello, world!");
+        let (mut pike, _) = tmp_pike_and_working_dir(None, None);
+
+        pike.create_and_open_file(file.path())
+            .expect("Failed to create and open file");
+
+        assert_paths(
+            &pike
+                .current_buffer_path()
+                .expect("Buffer should be set after opening a file"),
+            file.path(),
+        );
+    }
+
+    #[test]
+    fn test_open_new_buffer() {
+        let file = temp_file_with_contents("Hello, world!");
+        let (mut pike, _) = tmp_pike_and_working_dir(None, None);
+
+        pike.open_file(file.path(), 0, 0)
+            .expect("Failed to open file");
+        assert_eq!(pike.workspace.buffer_paths().len(), 1);
+
+        // Should be empty with no path
+        pike.open_new_buffer();
+        assert_eq!(pike.current_buffer_contents(), "");
+        assert!(pike
+            .current_buffer()
+            .expect("A buffer should be open")
+            .path
+            .is_none());
+        assert_eq!(pike.workspace.buffer_paths().len(), 2);
+    }
+
+    #[test]
+    fn test_bind_current_buffer_to_path() {
+        let file_contents = "Hello, world!";
+        let (mut pike, dir) = tmp_pike_and_working_dir(None, None);
+        assert!(pike.current_buffer_path().is_none());
+        pike.open_new_buffer();
+        pike.write_to_current_buffer(file_contents)
+            .expect("Failed to write to current buffer");
+
+        let file_path = dir.join(Path::new("new_file.txt"));
+        pike.bind_current_buffer_to_path(file_path.clone());
+
+        assert!(pike.save_current_buffer().is_ok());
+
+        let contents_from_file =
+            fs::read_to_string(file_path).expect("std::fs failed to read from file");
+        assert_eq!(file_contents, contents_from_file)
+    }
+
+    #[test]
+    fn test_moving_cursor_when_inserting_many_characters() {
+        let file = temp_file_with_contents("Hello, world!");
+        let (mut pike, _) = tmp_pike_and_working_dir(None, None);
+
+        pike.open_file(file.path(), 0, 0)
+            .expect("Failed to open file");
+
+        pike.move_cursor_to(Position {
+            line: 0,
+            offset: 13,
+        });
+        pike.write_to_current_buffer(" Its me!")
+            .expect("Failed to write to buffer");
+
+        assert_eq!(pike.current_buffer_contents(), "Hello, world! Its me!");
+        assert_eq!(
+            pike.cursor_position(),
+            Some(Position {
+                line: 0,
+                offset: 21
+            })
+        );
+    }
+
+    #[test]
+    fn test_abbreviation_expands_and_can_be_undone() {
+        let config = "[abbreviations]\nteh = \"the\"";
+        let (mut pike, _) = tmp_pike_and_working_dir(Some(config), Some(""));
+        let type_str = |pike: &mut Pike, s: &str| {
+            for ch in s.chars() {
+                pike.type_character(ch).expect("Failed to type");
+            }
+        };
+
+        type_str(&mut pike, "teh tehx teh.");
+        assert_eq!(pike.current_buffer_contents(), "the tehx the.");
+        assert_eq!(
+            pike.cursor_position(),
+            Some(Position {
+                line: 0,
+                offset: 13
+            })
+        );
+
+        pike.undo();
+        assert_eq!(pike.current_buffer_contents(), "the tehx teh");
+        type_str(&mut pike, " teh ");
+        assert_eq!(pike.current_buffer_contents(), "the tehx teh the ");
+    }
+
+    #[test]
+    fn test_normalize_mixed_line_endings() {
+        let (mut pike, _) = tmp_pike_and_working_dir(None, Some("one\r\ntwo\nthree\r\n"));
+        assert_eq!(pike.line_ending_stats(), LineEndingStats { lf: 1, crlf: 2 });
+        assert!(pike.line_ending_stats().is_mixed());
+
+        pike.normalize_line_endings(LineEnding::CrLf);
+        assert_eq!(pike.current_buffer_contents(), "one\r\ntwo\r\nthree\r\n");
+        assert!(!pike.line_ending_stats().is_mixed());
+
+        pike.normalize_line_endings(LineEnding::Lf);
+        assert_eq!(pike.current_buffer_contents(), "one\ntwo\nthree\n");
+        assert_eq!(pike.line_ending_stats(), LineEndingStats { lf: 3, crlf: 0 });
+
+        pike.undo();
+        assert_eq!(pike.current_buffer_contents(), "one\r\ntwo\r\nthree\r\n");
+    }
+
+    #[test]
+    fn test_insert_datetime_in_configured_format() {
+        let config = "datetime_format = \"date: %Y\"";
+        let (mut pike, _) = tmp_pike_and_working_dir(Some(config), Some(""));
+
+        pike.insert_datetime().expect("Failed to insert date");
+        let expected = format!("date: {}", chrono::Local::now().format("%Y"));
+        assert_eq!(pike.current_buffer_contents(), expected);
+
+        pike.undo();
+        assert_eq!(pike.current_buffer_contents(), "");
+    }
+
+    #[test]
+    fn test_large_files_are_not_opened_without_confirmation() {
+        let workspace = tempfile::tempdir().expect("Failed to create directory");
+        let (large, small) = (
+            workspace.path().join("large"),
+            workspace.path().join("small"),
+        );
+        fs::write(&large, "a".repeat(2 * 1024 * 1024 + 1)).unwrap();
+        fs::write(&small, "a".repeat(1024)).unwrap();
+        let (mut pike, _) = tmp_pike_and_working_dir(Some("large_file_threshold_mb = 1"), None);
+
+        assert!(matches!(
+            pike.open_file(&large, 0, 0),
+            Err(PikeError::FileTooLarge(path, 2)) if path == large
+        ));
+        assert!(pike.current_buffer().is_none());
+
+        pike.open_file(&small, 0, 0).unwrap();
+        pike.open_large_file(&large).unwrap();
+        assert_eq!(pike.current_buffer_contents().len(), 2 * 1024 * 1024 + 1);
+    }
+
+    #[test]
+    fn test_switch_to_alternate_buffer() {
+        let (mut pike, _) = tmp_pike_and_working_dir(None, Some("first"));
+        pike.switch_to_alternate_buffer();
+        assert_eq!(pike.current_buffer_contents(), "first");
+
+        for contents in ["second", "third"] {
+            pike.open_new_buffer();
+            pike.write_to_current_buffer(contents).unwrap();
+        }
+        pike.switch_to_alternate_buffer();
+        assert_eq!(pike.current_buffer_contents(), "second");
+        pike.switch_to_alternate_buffer();
+        assert_eq!(pike.current_buffer_contents(), "third");
+
+        // Cycling through the buffers updates the alternate as well
+        pike.next_buffer();
+        assert_eq!(pike.current_buffer_contents(), "first");
+        pike.switch_to_alternate_buffer();
+        assert_eq!(pike.current_buffer_contents(), "third");
+        pike.switch_to_alternate_buffer();
+        assert_eq!(pike.current_buffer_contents(), "first");
+    }
+
+    #[test]
+    fn test_open_buffers_and_switch_to_buffer() {
+        let (mut pike, _) = tmp_pike_and_working_dir(None, Some("first"));
+        let filename = pike.current_buffer_filename();
+        pike.open_new_buffer();
+        pike.write_to_current_buffer("second").unwrap();
+
+        let entries = pike.open_buffers();
+        let names: Vec<(&str, bool)> = entries
+            .iter()
+            .map(|entry| (entry.name.as_str(), entry.is_modified))
+            .collect();
+        assert_eq!(names, vec![(filename.as_str(), false), ("[No Name]", true)]);
+        assert_eq!(pike.current_buffer_contents(), "second");
+
+        assert!(pike.switch_to_buffer(entries[0].id));
+        assert_eq!(pike.current_buffer_contents(), "first");
+        pike.switch_to_alternate_buffer();
+        assert_eq!(pike.current_buffer_contents(), "second");
+        assert!(!pike.switch_to_buffer(entries[1].id + 100));
+        assert_eq!(pike.current_buffer_contents(), "second");
+    }
+
+    #[test]
+    fn test_show_help_toggles_a_read_only_cheatsheet() {
+        let config = "[keymaps]\n\"ctrl+g\" = \"show_help\"";
+        let (mut pike, _) = tmp_pike_and_working_dir(Some(config), Some("first"));
+        pike.show_help();
+
+        let help = pike.current_buffer_contents();
+        assert!(pike.is_current_buffer_read_only());
+        assert!(help
+            .lines()
+            .any(|line| line.split_whitespace().eq(["ctrl+g", "show_help"])));
+        // Binding the operation to another key replaces its default one
+        assert!(!help.contains("f1"));
+        let (_, unbound) = help
+            .split_once("Unbound operations")
+            .expect("Some operations are unbound by default");
+        assert!(unbound.lines().any(|line| line == "insert_unicode"));
+
+        // The cheatsheet is regenerated when shown from another buffer
+        pike.open_new_buffer();
+        pike.show_help();
+        assert_eq!(pike.current_buffer_contents(), help);
+        assert_eq!(pike.workspace.buffer_paths().len(), 3);
+
+        pike.show_help();
+        assert_eq!(pike.current_buffer_contents(), "");
+        assert!(!pike.is_current_buffer_read_only());
+        assert_eq!(pike.workspace.buffer_paths().len(), 2);
+    }
+
+    #[test]
+    fn test_filter_selection_lines() {
+        let contents = "header\nid=1 name=ada\r\nid=2 name=bob\nno fields\nid=3 name=eve";
+        let (mut pike, _) = tmp_pike_and_working_dir(None, Some(contents));
+        let select_lines = |pike: &mut Pike| {
+            pike.move_cursor_to(Position { line: 1, offset: 3 });
+            pike.extend_selection_to(Position { line: 4, offset: 0 });
+        };
+
+        select_lines(&mut pike);
+        pike.filter_selection_lines(r"name=(\w+)", LineFilterMode::KeepMatch)
+            .unwrap();
+        assert_eq!(
+            pike.current_buffer_contents(),
+            "header\nada\r\nbob\nno fields\nid=3 name=eve"
+        );
+        assert_eq!(pike.selection(), None);
+
+        pike.undo();
+        assert_eq!(pike.current_buffer_contents(), contents);
+
+        select_lines(&mut pike);
+        pike.filter_selection_lines(r"id=\d+ ", LineFilterMode::RemoveMatch)
+            .unwrap();
+        assert_eq!(
+            pike.current_buffer_contents(),
+            "header\nname=ada\r\nname=bob\nno fields\nid=3 name=eve"
+        );
+
+        select_lines(&mut pike);
+        assert!(matches!(
+            pike.filter_selection_lines("(", LineFilterMode::KeepMatch),
+            Err(PikeError::InvalidRegex(_))
+        ));
+    }
+
+    #[test]
+    fn test_jump_to_matching_tag() {
+        let workspace = tempfile::tempdir().expect("Failed to create directory");
+        let (html, text) = (
+            workspace.path().join("a.html"),
+            workspace.path().join("a.txt"),
+        );
+        let contents = "<ul>\n  <li>żółw</li>\n</ul>";
+        fs::write(&html, contents).unwrap();
+        fs::write(&text, contents).unwrap();
+        let mut pike =
+            Pike::build(workspace.path().to_path_buf(), None, None).expect("Failed to build Pike");
+
+        pike.open_file(&html, 1, 4).unwrap();
+        assert_eq!(
+            pike.matching_tag(),
+            Some((
+                Position {
+                    line: 1,
+                    offset: 10
+                },
+                Position {
+                    line: 1,
+                    offset: 15
+                }
+            ))
+        );
+        pike.jump_to_matching_tag();
+        assert_eq!(
+            pike.cursor_position(),
+            Some(Position {
+                line: 1,
+                offset: 10
+            })
+        );
+        pike.jump_to_matching_tag();
+        assert_eq!(
+            pike.cursor_position(),
+            Some(Position { line: 1, offset: 2 })
+        );
+
+        // Outside of a tag, the cursor stays put
+        pike.move_cursor_to(Position { line: 1, offset: 7 });
+        pike.jump_to_matching_tag();
+        assert_eq!(
+            pike.cursor_position(),
+            Some(Position { line: 1, offset: 7 })
+        );
+
+        // Tags are only matched in markup files
+        pike.open_file(&text, 0, 0).unwrap();
+        assert_eq!(pike.matching_tag(), None);
+    }
+
+    #[test]
+    fn test_set_buffer_contents() {
+        let (mut pike, _) = tmp_pike_and_working_dir(None, Some("fn main() {\n    let x=1;\n}"));
+        pike.move_cursor_to(Position {
+            line: 1,
+            offset: 12,
+        });
+
+        pike.set_buffer_contents("fn main() {\n    let x = 1;\n}\n");
+        assert_eq!(
+            pike.current_buffer_contents(),
+            "fn main() {\n    let x = 1;\n}\n"
+        );
+        assert_eq!(
+            pike.cursor_position(),
+            Some(Position {
+                line: 1,
+                offset: 12
+            })
+        );
+
+        // The cursor is clamped to the new contents
+        pike.set_buffer_contents("short");
+        assert_eq!(
+            pike.cursor_position(),
+            Some(Position { line: 0, offset: 5 })
+        );
+
+        // Each swap is undone in one step
+        pike.undo();
+        assert_eq!(
+            pike.current_buffer_contents(),
+            "fn main() {\n    let x = 1;\n}\n"
+        );
+        pike.undo();
+        assert_eq!(
+            pike.current_buffer_contents(),
+            "fn main() {\n    let x=1;\n}"
+        );
+    }
+
+    #[test]
+    fn test_insert_code_point() {
+        let (mut pike, _) = tmp_pike_and_working_dir(None, Some(""));
+
+        pike.insert_code_point("U+1F600").unwrap();
+        pike.insert_code_point(" 0xe9 ").unwrap();
+        pike.insert_code_point("2192").unwrap();
+        assert_eq!(pike.current_buffer_contents(), "😀é→");
+
+        for invalid in ["", "U+", "+41", "U+D800", "110000", "smile"] {
+            assert!(matches!(
+                pike.insert_code_point(invalid),
+                Err(PikeError::InvalidCodePoint(_))
+            ));
+        }
+        assert_eq!(pike.current_buffer_contents(), "😀é→");
+    }
+
+    #[test]
+    fn test_move_cursor_to_byte_offset() {
+        let (mut pike, _) = tmp_pike_and_working_dir(None, Some("zażółć\ngęśle\u{301}\r\nx"));
+        let position_at = |pike: &mut Pike, byte_offset| {
+            pike.move_cursor_to_byte_offset(byte_offset);
+            pike.cursor_position().map(|pos| (pos.line, pos.offset))
+        };
+
+        assert_eq!(position_at(&mut pike, 0), Some((0, 0)));
+        // The middle of 'ż' snaps to its start
+        assert_eq!(position_at(&mut pike, 3), Some((0, 2)));
+        assert_eq!(position_at(&mut pike, 4), Some((0, 3)));
+        assert_eq!(position_at(&mut pike, 11), Some((1, 0)));
+        assert_eq!(position_at(&mut pike, 13), Some((1, 1)));
+        // 'e' with a combining accent is a single grapheme
+        assert_eq!(position_at(&mut pike, 18), Some((1, 4)));
+        assert_eq!(position_at(&mut pike, 20), Some((1, 5)));
+        assert_eq!(position_at(&mut pike, 21), Some((1, 5)));
+        assert_eq!(position_at(&mut pike, 22), Some((2, 0)));
+        assert_eq!(position_at(&mut pike, 100), Some((2, 1)));
+    }
+
+    #[test]
+    fn test_delete_to_line_end_and_start() {
+        let (mut pike, _) = tmp_pike_and_working_dir(None, Some("foo bar\r\n\nzażółć"));
+        let contents_after = |pike: &mut Pike, delete: fn(&mut Pike), line, offset| {
+            pike.move_cursor_to(Position { line, offset });
+            delete(pike);
+            (pike.current_buffer_contents(), pike.cursor_position())
+        };
+
+        // Nothing is deleted at the line boundaries and on empty lines
+        for (delete, line, offset) in [
+            (Pike::delete_to_line_end as fn(&mut Pike), 0, 7),
+            (Pike::delete_to_line_start, 0, 0),
+            (Pike::delete_to_line_end, 1, 0),
+            (Pike::delete_to_line_start, 1, 0),
+        ] {
+            assert_eq!(
+                contents_after(&mut pike, delete, line, offset),
+                (
+                    "foo bar\r\n\nzażółć".to_string(),
+                    Some(Position { line, offset })
+                )
+            );
+        }
+
+        assert_eq!(
+            contents_after(&mut pike, Pike::delete_to_line_end, 0, 3),
+            (
+                "foo\r\n\nzażółć".to_string(),
+                Some(Position { line: 0, offset: 3 })
+            )
+        );
+        assert_eq!(
+            contents_after(&mut pike, Pike::delete_to_line_start, 2, 4),
+            (
+                "foo\r\n\nłć".to_string(),
+                Some(Position { line: 2, offset: 0 })
+            )
+        );
+
+        pike.undo();
+        assert_eq!(pike.current_buffer_contents(), "foo\r\n\nzażółć");
+        pike.undo();
+        assert_eq!(pike.current_buffer_contents(), "foo bar\r\n\nzażółć");
+    }
+
+    #[test]
+    fn test_go_to_last_edit() {
+        let (mut pike, _) = tmp_pike_and_working_dir(None, Some("foo\nbar\nbaz"));
+        pike.go_to_last_edit();
+        assert_eq!(
+            pike.cursor_position(),
+            Some(Position { line: 0, offset: 0 })
+        );
+
+        pike.move_cursor_to(Position { line: 1, offset: 3 });
+        pike.write_to_current_buffer("!").unwrap();
+        pike.move_cursor_to(Position { line: 2, offset: 0 });
+        pike.move_to_next_paragraph();
+        pike.go_to_last_edit();
+        assert_eq!(
+            pike.cursor_position(),
+            Some(Position { line: 1, offset: 4 })
+        );
+
+        pike.move_cursor_to(Position { line: 0, offset: 2 });
+        pike.delete_character_from_current_buffer();
+        pike.move_cursor_to(Position { line: 2, offset: 3 });
+        pike.go_to_last_edit();
+        assert_eq!(
+            pike.cursor_position(),
+            Some(Position { line: 0, offset: 1 })
+        );
+
+        // Edits replacing lines or spanning several places count as well
+        pike.set_selection(
+            Position { line: 2, offset: 0 },
+            Position { line: 2, offset: 3 },
+        );
+        pike.surround_selection('(', ')');
+        pike.move_cursor_to(Position { line: 0, offset: 0 });
+        pike.go_to_last_edit();
+        assert_eq!(
+            pike.cursor_position(),
+            Some(Position { line: 2, offset: 5 })
+        );
+
+        pike.move_cursor_to(Position { line: 1, offset: 4 });
+        pike.transpose_chars();
+        pike.move_cursor_to(Position { line: 0, offset: 0 });
+        pike.go_to_last_edit();
+        assert_eq!(pike.cursor_position().map(|pos| pos.line), Some(1));
     }
 
-    /// Canonicalizes two paths and asserts their equality
-    fn assert_paths(path1: &Path, path2: &Path) {
+    #[test]
+    fn test_move_by_paragraph() {
+        let (mut pike, _) = tmp_pike_and_working_dir(None, Some("a\nb\n\n  \n\nc\n\nd\ne"));
+        let line_after = |pike: &mut Pike, move_by_paragraph: fn(&mut Pike)| {
+            move_by_paragraph(pike);
+            pike.cursor_position().map(|pos| (pos.line, pos.offset))
+        };
+
+        // Consecutive blank lines, also ones with whitespace, are a single stop
         assert_eq!(
-            path1.canonicalize().expect("Failed to canonicalize path"),
-            path2.canonicalize().expect("Failed to canonicalize path")
+            line_after(&mut pike, Pike::move_to_next_paragraph),
+            Some((2, 0))
+        );
+        assert_eq!(
+            line_after(&mut pike, Pike::move_to_next_paragraph),
+            Some((6, 0))
+        );
+        assert_eq!(
+            line_after(&mut pike, Pike::move_to_next_paragraph),
+            Some((8, 1))
+        );
+        assert_eq!(
+            line_after(&mut pike, Pike::move_to_next_paragraph),
+            Some((8, 1))
+        );
+
+        assert_eq!(
+            line_after(&mut pike, Pike::move_to_prev_paragraph),
+            Some((6, 0))
+        );
+        assert_eq!(
+            line_after(&mut pike, Pike::move_to_prev_paragraph),
+            Some((4, 0))
+        );
+        assert_eq!(
+            line_after(&mut pike, Pike::move_to_prev_paragraph),
+            Some((0, 0))
+        );
+        assert_eq!(
+            line_after(&mut pike, Pike::move_to_prev_paragraph),
+            Some((0, 0))
         );
     }
 
     #[test]
-    fn test_build_minimal_args() {
-        let (pike, cwd) = tmp_pike_and_working_dir(None, None);
+    fn test_word_under_cursor() {
+        let (mut pike, _) = tmp_pike_and_working_dir(None, Some("let źdźbło_2 = x;\n  "));
+        let word_at = |pike: &mut Pike, offset| {
+            pike.move_cursor_to(Position { line: 0, offset });
+            pike.word_under_cursor()
+                .map(|(word, start, length)| (word, start.offset, length))
+        };
+
+        assert_eq!(word_at(&mut pike, 0), Some(("let".to_string(), 0, 3)));
+        // Right past the end of a word still counts as being on it
+        assert_eq!(word_at(&mut pike, 3), Some(("let".to_string(), 0, 3)));
+        assert_eq!(word_at(&mut pike, 6), Some(("źdźbło_2".to_string(), 4, 8)));
+        assert_eq!(word_at(&mut pike, 14), None);
+        assert_eq!(word_at(&mut pike, 16), Some(("x".to_string(), 15, 1)));
+
+        pike.move_cursor_to(Position { line: 1, offset: 1 });
+        assert_eq!(pike.word_under_cursor(), None);
+    }
 
-        assert_eq!(pike.workspace.path, cwd);
-        assert!(pike.current_buffer().is_none());
-        assert!(pike.config == Config::default());
+    #[test]
+    fn test_selected_text() {
+        let (mut pike, _) = tmp_pike_and_working_dir(None, Some("zażółć\ngęślą jaźń"));
+        assert_eq!(pike.selected_text(), None);
+
+        pike.move_cursor_to(Position { line: 1, offset: 2 });
+        pike.start_selection();
+        assert_eq!(pike.selected_text(), None);
+
+        pike.extend_selection_to(Position { line: 0, offset: 4 });
+        assert_eq!(pike.selected_text().as_deref(), Some("łć\ngę"));
+
+        pike.extend_selection_to(Position {
+            line: 1,
+            offset: 10,
+        });
+        assert_eq!(pike.selected_text().as_deref(), Some("ślą jaźń"));
     }
 
     #[test]
-    fn test_build_max_args() {
-        let config_content = r#"
-            [keymaps]
-            "ctrl+a" = "save"
-        "#;
-        let file_content = "hello, world!";
-        let (pike, cwd) = tmp_pike_and_working_dir(Some(config_content), Some(file_content));
+    fn test_undo() {
+        let file = temp_file_with_contents("Hello, world!");
+        let (mut pike, _) = tmp_pike_and_working_dir(None, None);
 
-        assert_eq!(pike.workspace.path, cwd);
+        pike.open_file(file.path(), 0, 0)
+            .expect("Failed to open file");
+
+        pike.move_cursor_to(Position {
+            line: 0,
+            offset: 13,
+        });
+        pike.write_to_current_buffer("!")
+            .expect("Failed to write to buffer");
+
+        assert_eq!(pike.current_buffer_contents(), "Hello, world!!");
         assert_eq!(
-            pike.workspace
-                .current_buffer
-                .expect("Current buffer shouldn't be empty when set")
-                .data(),
-            "hello, world!"
+            pike.cursor_position(),
+            Some(Position {
+                line: 0,
+                offset: 14
+            })
+        );
+
+        pike.undo();
+        assert_eq!(pike.current_buffer_contents(), "Hello, world!");
+        assert_eq!(
+            pike.cursor_position(),
+            Some(Position {
+                line: 0,
+                offset: 13
+            })
         );
-        let expected_config =
-            Config::from_toml_representation(config_content).expect("Failed to parse config");
-        assert_eq!(pike.config, expected_config);
     }
 
     #[test]
-    fn test_open_zero_offset() {
+    fn test_edit_after_undo_clears_redo() {
+        let (mut pike, _) = tmp_pike_and_working_dir(None, Some("a"));
+        pike.move_cursor_to_end_of_line();
+        pike.write_to_current_buffer("b").unwrap();
+        pike.write_to_current_buffer("c").unwrap();
+
+        pike.undo();
+        pike.undo();
+        pike.write_to_current_buffer("x").unwrap();
+        pike.redo();
+
+        assert_eq!(pike.current_buffer_contents(), "ax");
+        assert_eq!(
+            pike.cursor_position(),
+            Some(Position { line: 0, offset: 2 })
+        );
+
+        pike.undo();
+        assert_eq!(pike.current_buffer_contents(), "a");
+        assert_eq!(
+            pike.cursor_position(),
+            Some(Position { line: 0, offset: 1 })
+        );
+    }
+
+    #[test]
+    fn test_edits_changing_nothing_are_not_undone() {
+        let (mut pike, _) = tmp_pike_and_working_dir(None, Some("aa b"));
+        pike.move_cursor_to_end_of_line();
+        pike.write_to_current_buffer("c").unwrap();
+
+        // Neither deleting at the start of the buffer nor swapping equal
+        // characters changes the buffer
+        pike.move_cursor_to(Position { line: 0, offset: 0 });
+        pike.delete_character_from_current_buffer();
+        pike.move_cursor_to(Position { line: 0, offset: 1 });
+        pike.transpose_chars();
+        pike.write_to_current_buffer("").unwrap();
+
+        pike.undo();
+        assert_eq!(pike.current_buffer_contents(), "aa b");
+        assert_eq!(
+            pike.cursor_position(),
+            Some(Position { line: 0, offset: 4 })
+        );
+    }
+
+    #[test]
+    fn test_undo_history_is_kept_per_buffer() {
+        let (mut pike, _) = tmp_pike_and_working_dir(None, Some("first"));
+        let second = temp_file_with_contents("second");
+        pike.move_cursor_to_end_of_line();
+        pike.write_to_current_buffer("!").unwrap();
+
+        pike.open_file(second.path(), 0, 0).unwrap();
+        pike.write_to_current_buffer("the ").unwrap();
+
+        pike.next_buffer();
+        assert_eq!(pike.current_buffer_contents(), "first!");
+        pike.undo();
+        assert_eq!(pike.current_buffer_contents(), "first");
+        assert_eq!(
+            pike.cursor_position(),
+            Some(Position { line: 0, offset: 5 })
+        );
+
+        pike.previous_buffer();
+        assert_eq!(pike.current_buffer_contents(), "the second");
+        pike.undo();
+        assert_eq!(pike.current_buffer_contents(), "second");
+        assert_eq!(
+            pike.cursor_position(),
+            Some(Position { line: 0, offset: 0 })
+        );
+
+        pike.next_buffer();
+        pike.redo();
+        assert_eq!(pike.current_buffer_contents(), "first!");
+    }
+
+    /// Runs git with the given arguments in `dir`, panicking if it fails
+    fn run_git(dir: &Path, args: &[&str]) {
+        let status = Command::new("git")
+            .args(["-c", "user.name=pike", "-c", "user.email=pike@example.com"])
+            .args(args)
+            .current_dir(dir)
+            .status()
+            .expect("Failed to run git");
+        assert!(status.success(), "git {args:?} failed");
+    }
+
+    #[test]
+    fn test_open_file_from_git() {
+        let repo = tempfile::tempdir().expect("Failed to create directory");
+        let file = repo.path().join("file.txt");
+        run_git(repo.path(), &["init", "-q"]);
+        fs::write(&file, "old\n").unwrap();
+        run_git(repo.path(), &["add", "file.txt"]);
+        run_git(repo.path(), &["commit", "-q", "-m", "Add a file"]);
+        fs::write(&file, "new\n").unwrap();
+
+        let mut pike =
+            Pike::build(repo.path().to_path_buf(), None, None).expect("Failed to build Pike");
+        pike.open_file_from_git("HEAD", Path::new("file.txt"))
+            .expect("Failed to open the file from git");
+
+        assert_eq!(pike.current_buffer_contents(), "old\n");
+        assert!(pike.is_current_buffer_read_only());
+        assert_eq!(pike.current_buffer_path(), None);
+        // The read-only buffer is neither saved nor asked a path for
+        assert!(pike.save_all().is_empty());
+        assert!(!pike.select_unbound_modified_buffer());
+        assert_eq!(fs::read_to_string(&file).unwrap(), "new\n");
+
+        assert!(matches!(
+            pike.open_file_from_git("no-such-revision", Path::new("file.txt")),
+            Err(PikeError::Git(_))
+        ));
+    }
+
+    #[test]
+    fn test_edits_report_dirty_lines() {
+        let (mut pike, _) = tmp_pike_and_working_dir(None, Some("a\nb\nc\nd"));
+        assert_eq!(pike.take_dirty_lines(), None);
+
+        pike.move_cursor_to(Position { line: 1, offset: 1 });
+        pike.write_to_current_buffer("x").unwrap();
+        pike.move_cursor_to(Position { line: 2, offset: 0 });
+        pike.write_to_current_buffer("y").unwrap();
+        assert_eq!(pike.take_dirty_lines(), Some(1..3));
+        assert_eq!(pike.take_dirty_lines(), None);
+
+        // Breaking a line shifts all the following ones
+        pike.move_cursor_to(Position { line: 1, offset: 0 });
+        pike.write_to_current_buffer("\n").unwrap();
+        assert_eq!(pike.take_dirty_lines(), Some(1..5));
+
+        // Joining lines shifts the following ones back
+        pike.delete_character_from_current_buffer();
+        assert_eq!(pike.take_dirty_lines(), Some(1..4));
+
+        pike.move_cursor_to(Position { line: 3, offset: 1 });
+        pike.delete_character_from_current_buffer();
+        assert_eq!(pike.take_dirty_lines(), Some(3..4));
+
+        pike.move_cursor_to(Position { line: 2, offset: 1 });
+        pike.transpose_chars();
+        assert_eq!(pike.take_dirty_lines(), Some(2..3));
+
+        pike.undo();
+        assert_eq!(pike.take_dirty_lines(), Some(0..4));
+
+        // Moving the cursor doesn't change anything
+        pike.move_cursor_down();
+        assert_eq!(pike.take_dirty_lines(), None);
+    }
+
+    #[test]
+    fn test_redo() {
         let file = temp_file_with_contents("Hello, world!");
-        let mut pike = tmp_pike_and_working_dir(None, None).0;
+        let (mut pike, _) = tmp_pike_and_working_dir(None, None);
+
         pike.open_file(file.path(), 0, 0)
             .expect("Failed to open file");
 
+        pike.move_cursor_to(Position {
+            line: 0,
+            offset: 13,
+        });
+        pike.write_to_current_buffer("!")
+            .expect("Failed to write to buffer");
+
+        assert_eq!(pike.current_buffer_contents(), "Hello, world!!");
         assert_eq!(
-            pike.workspace
-                .current_buffer_path()
-                .expect("Buffer should be set after opening a file")
-                .file_name()
-                .expect("File should have a name"),
-            file.path().file_name().expect("File should have a name")
+            pike.cursor_position(),
+            Some(Position {
+                line: 0,
+                offset: 14
+            })
         );
 
+        pike.undo();
+        assert_eq!(pike.current_buffer_contents(), "Hello, world!");
         assert_eq!(
-            pike.workspace
-                .current_buffer
-                .expect("Buffer should be set after opening a file")
-                .data(),
-            "Hello, world!"
+            pike.cursor_position(),
+            Some(Position {
+                line: 0,
+                offset: 13
+            })
+        );
+
+        pike.redo();
+        assert_eq!(pike.current_buffer_contents(), "Hello, world!!");
+        assert_eq!(
+            pike.cursor_position(),
+            Some(Position {
+                line: 0,
+                offset: 14
+            })
         );
     }
 
     #[test]
-    fn test_open_file_non_zero_offset() {
-        let file_contents = r#"
-            Hello,
-            World
-            "#;
-        let file = temp_file_with_contents(file_contents);
-        let mut pike = tmp_pike_and_working_dir(None, None).0;
-        pike.open_file(file.path(), 1, 2)
-            .expect("Could not open file");
+    fn test_search_in_current_buffer() {
+        let file_contents = "Hello, world!";
+        let (mut pike, _) = tmp_pike_and_working_dir(None, Some(file_contents));
+
+        let results = pike
+            .search_in_current_buffer("world", SearchOptions::default())
+            .expect("No buffer is currently open");
 
+        assert_eq!(results.len(), 1);
+        assert_eq!(results[0].start, Position { line: 0, offset: 7 });
         assert_eq!(
-            pike.workspace
-                .current_buffer_path()
-                .expect("Buffer should be set after opening a file")
-                .file_name()
-                .expect("File should have a name"),
-            file.path().file_name().expect("File should have a name")
+            results[0].end,
+            Position {
+                line: 0,
+                offset: 12
+            }
         );
+    }
+
+    #[test]
+    fn test_search_in_current_buffer_across_lines() {
+        let file_contents = "foo\nbar\nfoo\nbaz";
+        let (mut pike, _) = tmp_pike_and_working_dir(None, Some(file_contents));
+
+        let results = pike
+            .search_in_current_buffer("o\\nba", SearchOptions::default())
+            .expect("No buffer is currently open");
+
+        assert_eq!(results.len(), 2);
+        assert_eq!(results[0].start, Position { line: 0, offset: 2 });
+        assert_eq!(results[0].end, Position { line: 1, offset: 2 });
+        assert_eq!(results[1].start, Position { line: 2, offset: 2 });
+        assert_eq!(results[1].end, Position { line: 3, offset: 2 });
+    }
+
+    #[test]
+    fn test_search_positions_of_many_matches_on_a_line() {
+        let file_contents = "żaża e\u{301}a\na ża\n\naa";
+        let (mut pike, _) = tmp_pike_and_working_dir(None, Some(file_contents));
 
+        let results = pike
+            .search_in_current_buffer("a", SearchOptions::default())
+            .expect("No buffer is currently open");
+
+        let starts: Vec<(usize, usize)> = results
+            .iter()
+            .map(|result| (result.start.line, result.start.offset))
+            .collect();
         assert_eq!(
-            pike.workspace
-                .current_buffer
-                .expect("Should have an open buffer!")
-                .cursor
-                .position,
-            Position { line: 1, offset: 2 }
+            starts,
+            vec![(0, 1), (0, 3), (0, 6), (1, 0), (1, 3), (3, 0), (3, 1)]
         );
     }
 
     #[test]
-    fn test_open_file_out_of_bounds_offset() {
-        let file_contents = r#"
-            Hello,
-            World
-            "#;
-        let file = temp_file_with_contents(file_contents);
-        let mut pike = tmp_pike_and_working_dir(None, None).0;
-        pike.open_file(file.path(), 2, 100)
-            .expect("Could not open file");
+    fn test_search_around_cursor_is_capped() {
+        let file_contents = "foo\n".repeat(10);
+        let config = "max_search_highlights = 3";
+        let (mut pike, _) = tmp_pike_and_working_dir(Some(config), Some(&file_contents));
+        let result_lines = |pike: &Pike| -> Vec<usize> {
+            pike.search_around_cursor("foo", SearchOptions::default())
+                .expect("No buffer is currently open")
+                .iter()
+                .map(|result| result.start.line)
+                .collect()
+        };
+
+        assert_eq!(result_lines(&pike), vec![0, 1, 2]);
+        pike.move_cursor_to(Position { line: 5, offset: 0 });
+        assert_eq!(result_lines(&pike), vec![4, 5, 6]);
+        // Near the end, more of the results before the cursor are taken
+        pike.move_cursor_to(Position { line: 9, offset: 0 });
+        assert_eq!(result_lines(&pike), vec![7, 8, 9]);
+        // Without results after the cursor, the search wraps around to the top
+        pike.move_cursor_to(Position { line: 9, offset: 2 });
+        assert_eq!(result_lines(&pike), vec![0, 1, 2]);
+    }
+
+    #[test]
+    fn test_count_matches() {
+        let (pike, _) = tmp_pike_and_working_dir(None, None);
+        assert_eq!(pike.count_matches("foo", SearchOptions::default()), 0);
+
+        let file_contents = "foo\nbar foo\nfoofoo\naaa";
+        let (mut pike, _) = tmp_pike_and_working_dir(None, Some(file_contents));
+        let cursor = pike.cursor_position();
+        for query in ["foo", "o\\nba", "aa", "\\n", "", "missing"] {
+            let results = pike
+                .search_in_current_buffer(query, SearchOptions::default())
+                .expect("No buffer is currently open");
+            assert_eq!(
+                pike.count_matches(query, SearchOptions::default()),
+                results.len(),
+                "{query}"
+            );
+        }
+        assert_eq!(pike.count_matches("foo", SearchOptions::default()), 4);
+        assert_eq!(pike.cursor_position(), cursor);
+    }
 
+    #[test]
+    fn test_search_options() {
+        let file_contents = "Foo foo\nfood FOO_ foo.";
+        let (mut pike, _) = tmp_pike_and_working_dir(None, Some(file_contents));
+        let starts =
+            |pike: &mut Pike, query: &str, options: SearchOptions| -> Vec<(usize, usize)> {
+                pike.search_in_current_buffer(query, options)
+                    .expect("No buffer is currently open")
+                    .iter()
+                    .map(|result| (result.start.line, result.start.offset))
+                    .collect()
+            };
+
+        let case_insensitive = SearchOptions {
+            case_insensitive: true,
+            ..Default::default()
+        };
+        let whole_word = SearchOptions {
+            whole_word: true,
+            ..Default::default()
+        };
+        assert_eq!(
+            starts(&mut pike, "foo", SearchOptions::default()),
+            vec![(0, 4), (1, 0), (1, 10)]
+        );
         assert_eq!(
-            pike.workspace
-                .current_buffer
-                .expect("Should have an open buffer!")
-                .cursor
-                .position,
-            Position { line: 0, offset: 0 }
+            starts(&mut pike, "foo", case_insensitive),
+            vec![(0, 0), (0, 4), (1, 0), (1, 5), (1, 10)]
+        );
+        assert_eq!(starts(&mut pike, "foo", whole_word), vec![(0, 4), (1, 10)]);
+        assert_eq!(
+            starts(
+                &mut pike,
+                "FOO",
+                SearchOptions {
+                    case_insensitive: true,
+                    whole_word: true
+                }
+            ),
+            vec![(0, 0), (0, 4), (1, 10)]
         );
+
+        // Only the word characters at the ends of the query need a boundary
+        assert_eq!(starts(&mut pike, "o\\nf", whole_word), vec![]);
+        assert_eq!(starts(&mut pike, " foo", whole_word), vec![(0, 3), (1, 9)]);
+        assert_eq!(pike.count_matches("foo", case_insensitive), 5);
     }
 
     #[test]
-    fn test_write_to_buffer() {
-        let mut pike = tmp_pike_and_working_dir(None, Some("")).0;
-        pike.write_to_current_buffer("Hello, world!")
-            .expect("Failed to write to buffer");
+    fn test_replace_in_current_buffer() {
+        let file_contents = "foo bar\nfoofoo\nbar foo";
+        let (mut pike, _) = tmp_pike_and_working_dir(None, Some(file_contents));
+        let options = SearchOptions::default();
 
+        // Only a match starting at the cursor is replaced, after which the cursor
+        // moves past the replacement
+        pike.move_cursor_to(Position { line: 1, offset: 1 });
         assert_eq!(
-            pike.workspace
-                .current_buffer
-                .expect("Should have an open buffer!")
-                .data(),
-            "Hello, world!"
+            pike.replace_in_current_buffer("foo", "x", options, false)
+                .ok(),
+            Some(0)
+        );
+        pike.move_cursor_to(Position { line: 1, offset: 3 });
+        assert_eq!(
+            pike.replace_in_current_buffer("foo", "a\\nb", options, false)
+                .ok(),
+            Some(1)
+        );
+        assert_eq!(pike.current_buffer_contents(), "foo bar\nfooa\nb\nbar foo");
+        assert_eq!(
+            pike.cursor_position(),
+            Some(Position { line: 2, offset: 1 })
+        );
+
+        // Replacing all matches is undone at once and keeps the cursor
+        assert_eq!(
+            pike.replace_in_current_buffer("foo", "qux", options, true)
+                .ok(),
+            Some(3)
+        );
+        assert_eq!(pike.current_buffer_contents(), "qux bar\nquxa\nb\nbar qux");
+        assert_eq!(
+            pike.cursor_position(),
+            Some(Position { line: 2, offset: 1 })
         );
+        pike.undo();
+        assert_eq!(pike.current_buffer_contents(), "foo bar\nfooa\nb\nbar foo");
+
+        // Overlapping matches are replaced once
+        let (mut pike, _) = tmp_pike_and_working_dir(None, Some("aaaa a"));
+        assert_eq!(
+            pike.replace_in_current_buffer("aa", "b", options, true)
+                .ok(),
+            Some(2)
+        );
+        assert_eq!(pike.current_buffer_contents(), "bb a");
     }
 
     #[test]
-    fn test_write_to_unbound_buffer() {
-        let mut pike = tmp_pike_and_working_dir(None, None).0;
-        pike.open_new_buffer();
-        let result = pike.write_to_current_buffer("Hello, world!");
-        assert!(result.is_ok());
-        assert_eq!(pike.current_buffer_contents(), "Hello, world!");
-        pike.write_to_current_buffer(" Its me!")
-            .expect("Failed to write to buffer");
-        assert_eq!(pike.current_buffer_contents(), "Hello, world! Its me!");
+    fn test_unescape_search_query() {
+        assert_eq!(unescape_search_query("foo"), "foo");
+        assert_eq!(unescape_search_query("foo\\nbar"), "foo\nbar");
+        assert_eq!(unescape_search_query("foo\\\\nbar"), "foo\\nbar");
+        assert_eq!(unescape_search_query("a\\b"), "a\\b");
     }
 
     #[test]
-    fn test_save_current_buffer() {
-        let file = temp_file_with_contents("Hello, world!");
-        let mut pike = tmp_pike_and_working_dir(None, None).0;
+    fn test_first_result_from_cursor() {
+        let file_contents = "foo\nbar foo\nfoo";
+        let (mut pike, _) = tmp_pike_and_working_dir(None, Some(file_contents));
+        let results = pike
+            .search_in_current_buffer("foo", SearchOptions::default())
+            .expect("No buffer is currently open");
 
-        pike.open_file(file.path(), 0, 0)
-            .expect("Failed to open file");
-        pike.save_current_buffer().expect("Failed to save buffer");
+        assert_eq!(pike.first_result_from_cursor(&results), Some(0));
 
-        let contents = fs::read_to_string(file.path()).expect("Failed to read file");
-        assert_eq!(contents, "Hello, world!");
+        pike.move_cursor_to(Position { line: 1, offset: 2 });
+        assert_eq!(pike.first_result_from_cursor(&results), Some(1));
+
+        pike.move_cursor_to(Position { line: 1, offset: 4 });
+        assert_eq!(pike.first_result_from_cursor(&results), Some(1));
+
+        pike.move_cursor_to(Position { line: 2, offset: 1 });
+        assert_eq!(pike.first_result_from_cursor(&results), None);
     }
 
     #[test]
-    #[should_panic]
-    fn test_save_buffer_no_path() {
-        let mut pike = tmp_pike_and_working_dir(None, None).0;
-        pike.open_new_buffer();
-        // This situation should not happen as it's handled in the UI, so a panic here
-        // is expected
-        let _ = pike.save_current_buffer();
+    fn test_detect_indentation_tabs() {
+        let (pike, _) = tmp_pike_and_working_dir(None, None);
+        let contents = "fn main() {\n\tif x {\n\t\ty();\n\t}\n}\n";
+
+        assert_eq!(
+            pike.detect_indentation(contents),
+            EditorSettings {
+                indent_style: IndentStyle::Tabs,
+                indent_width: DEFAULT_INDENT_WIDTH,
+            }
+        );
     }
 
     #[test]
-    fn test_current_buffer_contents_has_buffer() {
-        let file = temp_file_with_contents("Hello, world!");
-        let mut pike = tmp_pike_and_working_dir(None, None).0;
-        pike.open_file(file.path(), 0, 0)
-            .expect("Failed to open file");
+    fn test_detect_indentation_two_spaces() {
+        let (pike, _) = tmp_pike_and_working_dir(None, None);
+        let contents = "a:\n  b:\n    c: 1\n    d: 2\n  e:\n    f: 3\n";
 
-        assert_eq!(pike.current_buffer_contents(), "Hello, world!");
+        assert_eq!(
+            pike.detect_indentation(contents),
+            EditorSettings {
+                indent_style: IndentStyle::Spaces,
+                indent_width: 2,
+            }
+        );
     }
 
     #[test]
-    fn test_current_buffer_contents_no_buffer() {
-        let pike = tmp_pike_and_working_dir(None, None).0;
+    fn test_detect_indentation_falls_back_to_config() {
+        let (pike, _) =
+            tmp_pike_and_working_dir(Some("indent_style = \"tabs\"\nindent_width = 3"), None);
 
-        assert_eq!(pike.current_buffer_contents(), "");
+        assert_eq!(
+            pike.detect_indentation("no\nindentation\nhere"),
+            EditorSettings {
+                indent_style: IndentStyle::Tabs,
+                indent_width: 3,
+            }
+        );
     }
 
     #[test]
-    fn test_current_buffer_fname_has_buffer() {
-        let file = temp_file_with_contents("Hello, world!");
-        let mut pike = tmp_pike_and_working_dir(None, None).0;
-        pike.open_file(file.path(), 0, 0)
-            .expect("Failed to open file");
+    fn test_opened_file_adopts_its_indentation() {
+        let (pike, _) = tmp_pike_and_working_dir(None, Some("a\n  b\n    c"));
+        assert_eq!(pike.current_buffer_settings().indent_width, 2);
 
+        let config = "detect_indentation = false";
+        let (pike, _) = tmp_pike_and_working_dir(Some(config), Some("a\n  b\n    c"));
         assert_eq!(
-            pike.current_buffer_filename(),
-            file.path().file_name().unwrap().to_str().unwrap()
+            pike.current_buffer_settings().indent_width,
+            DEFAULT_INDENT_WIDTH
         );
     }
 
     #[test]
-    fn test_current_buffer_fname_no_buffer() {
-        let pike = tmp_pike_and_working_dir(None, None).0;
+    fn test_line_marks_are_toggled_per_buffer() {
+        let (mut pike, _) = tmp_pike_and_working_dir(None, Some("one\ntwo\nthree"));
 
-        assert_eq!(pike.current_buffer_filename(), "");
+        pike.toggle_line_mark(2);
+        pike.move_cursor_to(Position { line: 1, offset: 0 });
+        pike.toggle_mark_on_cursor_line();
+        assert_eq!(pike.current_buffer_line_marks(), vec![1, 2]);
+
+        pike.toggle_line_mark(2);
+        assert_eq!(pike.current_buffer_line_marks(), vec![1]);
+
+        pike.open_new_buffer();
+        assert!(pike.current_buffer_line_marks().is_empty());
+
+        pike.previous_buffer();
+        assert_eq!(pike.current_buffer_line_marks(), vec![1]);
     }
 
     #[test]
-    fn test_has_unsaved_changes_has_changes() {
-        let file = temp_file_with_contents("Hello, world!");
-        let mut pike = tmp_pike_and_working_dir(None, None).0;
-        pike.open_file(file.path(), 0, 0)
-            .expect("Failed to open file");
-        pike.write_to_current_buffer("belo")
-            .expect("Failed to write to file");
+    fn test_line_marks_follow_edits() {
+        let (mut pike, _) = tmp_pike_and_working_dir(None, Some("one\ntwo\nthree\nfour"));
+        pike.toggle_line_mark(0);
+        pike.toggle_line_mark(2);
+        pike.toggle_line_mark(3);
+
+        // Lines inserted above the marks move them down
+        pike.move_cursor_to(Position { line: 1, offset: 0 });
+        pike.write_to_current_buffer("new\nlines\n")
+            .expect("Failed to write");
+        assert_eq!(pike.current_buffer_line_marks(), vec![0, 4, 5]);
+
+        // Undoing the insertion moves them back
+        pike.undo();
+        assert_eq!(pike.current_buffer_line_marks(), vec![0, 2, 3]);
 
-        assert!(pike.has_unsaved_changes());
+        // Marks on removed lines are dropped, the ones after them move up
+        pike.set_selection(
+            Position { line: 1, offset: 3 },
+            Position { line: 2, offset: 5 },
+        );
+        pike.cut_selection();
+        assert_eq!(pike.current_buffer_contents(), "one\ntwo\nfour");
+        assert_eq!(pike.current_buffer_line_marks(), vec![0, 2]);
     }
 
     #[test]
-    fn test_has_unsaved_changes_no_changes() {
-        let file = temp_file_with_contents("Hello, world!");
-        let mut pike = tmp_pike_and_working_dir(None, None).0;
-        pike.open_file(file.path(), 0, 0)
-            .expect("Failed to open file");
+    fn test_is_current_buffer_binary() {
+        let (pike, _) = tmp_pike_and_working_dir(None, Some("plain text"));
+        assert!(!pike.is_current_buffer_binary());
 
-        assert!(!pike.has_unsaved_changes());
+        let (pike, _) = tmp_pike_and_working_dir(None, Some("ELF\0\0\u{1}"));
+        assert!(pike.is_current_buffer_binary());
     }
 
     #[test]
-    fn test_has_unsaved_changes_new_buffer() {
-        let mut pike = tmp_pike_and_working_dir(None, None).0;
-        pike.open_new_buffer();
-        assert!(pike.has_unsaved_changes());
+    fn test_current_buffer_bytes_are_read_from_its_file() {
+        let (mut pike, _) = tmp_pike_and_working_dir(None, Some("text"));
+        // Bytes which aren't valid UTF-8 are shown as they are
+        let raw = [0x7f, b'E', b'L', b'F', 0xff, 0xfe, 0x00];
+        fs::write(pike.current_buffer_path().unwrap(), raw).unwrap();
+        assert_eq!(pike.current_buffer_bytes(), raw);
+
+        // Unsaved changes aren't in the file yet
+        pike.write_to_current_buffer("é").unwrap();
+        assert_eq!(pike.current_buffer_bytes(), "étext".as_bytes());
     }
 
-    /// When moving down to a shorter line, the
-    /// cursor position should be clamped to its length
     #[test]
-    fn test_move_cursor_down_shorter_line() {
-        let contents = r#"Hello!
+    fn test_indent_follows_buffer_settings() {
+        let (mut pike, _) = tmp_pike_and_working_dir(None, Some("a\n  b\n"));
+        pike.move_cursor_to(Position { line: 2, offset: 0 });
+        pike.indent();
+        assert_eq!(pike.current_buffer_contents(), "a\n  b\n  ");
+
+        let config = "indent_style = \"tabs\"";
+        let (mut pike, _) = tmp_pike_and_working_dir(Some(config), Some(""));
+        pike.indent();
+        assert_eq!(pike.current_buffer_contents(), "\t");
+    }
 
-        This is a test."#;
+    #[test]
+    fn test_set_selection() {
+        let (mut pike, _) = tmp_pike_and_working_dir(None, Some("first\nsecond line"));
+        let start = Position { line: 0, offset: 2 };
+        let end = Position { line: 1, offset: 6 };
+
+        assert!(pike.set_selection(start, end));
+        assert_eq!(pike.selection_range(), Some((start, end)));
+        assert_eq!(pike.cursor_position(), Some(end));
+        assert_eq!(pike.selected_text().as_deref(), Some("rst\nsecond"));
+
+        // A reversed range reads back in document order, with the cursor at its start
+        assert!(pike.set_selection(end, start));
+        assert_eq!(pike.selection_range(), Some((start, end)));
+        assert_eq!(pike.cursor_position(), Some(start));
+        assert_eq!(pike.selected_text().as_deref(), Some("rst\nsecond"));
+
+        // Positions outside of the buffer leave the selection and the cursor alone
+        assert!(!pike.set_selection(start, Position { line: 5, offset: 0 }));
+        assert_eq!(pike.selection_range(), Some((start, end)));
+        assert_eq!(pike.cursor_position(), Some(start));
+
+        pike.clear_selection();
+        assert_eq!(pike.selection_range(), None);
+    }
+
+    #[test]
+    fn test_trimmed_selection() {
+        let contents = "a \t word\t \n  here \n";
         let (mut pike, _) = tmp_pike_and_working_dir(None, Some(contents));
-        for _ in 0..5 {
-            pike.move_cursor_right();
-        }
+        assert_eq!(pike.trimmed_selection(), None);
 
-        pike.move_cursor_down();
+        // Padded with spaces and tabs on both ends, including a line break
+        assert!(pike.set_selection(
+            Position { line: 0, offset: 1 },
+            Position { line: 2, offset: 0 },
+        ));
         assert_eq!(
-            pike.cursor_position(),
-            Some(Position { line: 1, offset: 0 })
+            pike.trimmed_selection(),
+            Some((
+                Position { line: 0, offset: 4 },
+                Position { line: 1, offset: 6 }
+            ))
+        );
+
+        // Nothing is left of a selection of whitespace
+        pike.set_selection(
+            Position { line: 0, offset: 1 },
+            Position { line: 0, offset: 4 },
         );
+        assert_eq!(pike.trimmed_selection(), None);
     }
 
-    /// The cursor should not move out of the bounds of the current
-    /// buffer
     #[test]
-    fn test_move_cursor_out_of_bounds() {
-        let contents = "a";
+    fn test_trim_selection_when_copying_and_surrounding() {
+        let contents = "\t word  here \n";
+        let (start, end) = (
+            Position { line: 0, offset: 0 },
+            Position { line: 1, offset: 0 },
+        );
         let (mut pike, _) = tmp_pike_and_working_dir(None, Some(contents));
+        pike.set_selection(start, end);
+        pike.copy_selection();
+        pike.move_cursor_to(end);
+        pike.paste_at_cursor().expect("Failed to paste");
+        assert_eq!(
+            pike.current_buffer_contents(),
+            format!("{contents}{contents}")
+        );
 
-        pike.move_cursor_right();
+        let (mut pike, _) = tmp_pike_and_working_dir(Some("trim_selection = true"), Some(contents));
+        pike.set_selection(start, end);
+        pike.copy_selection();
+        pike.move_cursor_to(end);
+        pike.paste_at_cursor().expect("Failed to paste");
         assert_eq!(
-            pike.cursor_position(),
-            // This makes sense, since inserting does not move the cursor right
-            Some(Position { line: 0, offset: 1 })
+            pike.current_buffer_contents(),
+            format!("{contents}word  here")
         );
 
-        // Two times to the left to test for going too far to the left
-        pike.move_cursor_left();
-        pike.move_cursor_left();
+        pike.set_selection(start, end);
+        pike.surround_selection('(', ')');
+        assert_eq!(
+            pike.current_buffer_contents(),
+            "\t (word  here) \nword  here"
+        );
+    }
+
+    #[test]
+    fn test_cut_with_trim_selection() {
+        let contents = "  one two  \n";
+        let (mut pike, _) = tmp_pike_and_working_dir(Some("trim_selection = true"), Some(contents));
+        pike.set_selection(
+            Position { line: 0, offset: 0 },
+            Position { line: 0, offset: 9 },
+        );
+        pike.cut_selection();
+        assert_eq!(pike.current_buffer_contents(), "    \n");
+
+        // A selection of nothing but whitespace is cut as it is
+        pike.set_selection(
+            Position { line: 0, offset: 1 },
+            Position { line: 0, offset: 3 },
+        );
+        pike.cut_selection();
+        assert_eq!(pike.current_buffer_contents(), "  \n");
+        pike.move_cursor_to(Position { line: 1, offset: 0 });
+        pike.paste_at_cursor().expect("Failed to paste");
+        assert_eq!(pike.current_buffer_contents(), "  \n  ");
+    }
+
+    #[test]
+    fn test_surround_selection_is_a_single_undo_step() {
+        let (mut pike, _) = tmp_pike_and_working_dir(None, Some("a word here"));
+        pike.move_cursor_to(Position { line: 0, offset: 6 });
+        pike.start_selection();
+        pike.move_cursor_to(Position { line: 0, offset: 2 });
+        pike.extend_selection_to(Position { line: 0, offset: 2 });
+
+        pike.surround_selection('(', ')');
+        assert_eq!(pike.current_buffer_contents(), "a (word) here");
         assert_eq!(
             pike.cursor_position(),
-            Some(Position { line: 0, offset: 0 })
+            Some(Position { line: 0, offset: 8 })
         );
+        assert_eq!(pike.selection(), None);
 
-        pike.move_cursor_down();
+        pike.undo();
+        assert_eq!(pike.current_buffer_contents(), "a word here");
+    }
+
+    #[test]
+    fn test_cut_and_paste_selection() {
+        let (mut pike, _) = tmp_pike_and_working_dir(None, Some("a word here"));
+        pike.move_cursor_to(Position { line: 0, offset: 2 });
+        pike.start_selection();
+        pike.move_cursor_to(Position { line: 0, offset: 7 });
+        pike.extend_selection_to(Position { line: 0, offset: 7 });
+
+        pike.cut_selection();
+        assert_eq!(pike.current_buffer_contents(), "a here");
         assert_eq!(
             pike.cursor_position(),
-            Some(Position { line: 0, offset: 0 })
+            Some(Position { line: 0, offset: 2 })
         );
+        assert_eq!(pike.selection(), None);
 
-        pike.move_cursor_up();
+        pike.move_cursor_to(Position { line: 0, offset: 6 });
+        pike.paste_at_cursor().expect("Failed to paste");
+        assert_eq!(pike.current_buffer_contents(), "a hereword ");
         assert_eq!(
             pike.cursor_position(),
-            Some(Position { line: 0, offset: 0 })
+            Some(Position {
+                line: 0,
+                offset: 11
+            })
         );
+
+        pike.undo();
+        assert_eq!(pike.current_buffer_contents(), "a here");
+        pike.undo();
+        assert_eq!(pike.current_buffer_contents(), "a word here");
     }
 
     #[test]
-    fn test_move_cursor_left_by_word() {
-        let contents = "aaa aaa";
+    fn test_cut_and_paste_indented_lines() {
+        let contents = "fn main() {\n    let a = 1;\n        nested();\n}\n";
         let (mut pike, _) = tmp_pike_and_working_dir(None, Some(contents));
+        pike.set_selection(
+            Position { line: 1, offset: 0 },
+            Position { line: 3, offset: 0 },
+        );
+        pike.cut_selection();
+        assert_eq!(pike.current_buffer_contents(), "fn main() {\n}\n");
 
-        pike.move_cursor_to(Position { line: 0, offset: 4 });
-
-        pike.move_cursor_left_by_word();
-
+        pike.move_cursor_to(Position { line: 2, offset: 0 });
+        pike.paste_at_cursor().expect("Failed to paste");
         assert_eq!(
-            pike.cursor_position(),
-            Some(Position { line: 0, offset: 0 })
+            pike.current_buffer_contents(),
+            "fn main() {\n}\n    let a = 1;\n        nested();\n"
         );
     }
 
     #[test]
-    fn test_move_cursor_right_by_word() {
-        let contents = "aaa aaa";
-        let (mut pike, _) = tmp_pike_and_working_dir(None, Some(contents));
+    fn test_paste_cycle_recovers_each_cut_line() {
+        let (mut pike, _) = tmp_pike_and_working_dir(None, Some("one\ntwo\nthree\nfour\n"));
+        pike.cut_selection();
+        pike.cut_selection();
+        pike.cut_selection();
+        assert_eq!(pike.current_buffer_contents(), "four\n");
+
+        pike.paste_at_cursor().expect("Failed to paste");
+        assert_eq!(pike.current_buffer_contents(), "three\nfour\n");
+        pike.paste_cycle().expect("Failed to cycle paste");
+        assert_eq!(pike.current_buffer_contents(), "two\nfour\n");
+        pike.paste_cycle().expect("Failed to cycle paste");
+        assert_eq!(pike.current_buffer_contents(), "one\nfour\n");
+        // Starts over with the text cut last
+        pike.paste_cycle().expect("Failed to cycle paste");
+        assert_eq!(pike.current_buffer_contents(), "three\nfour\n");
+
+        // A single undo step brings back the text pasted before cycling
+        pike.undo();
+        assert_eq!(pike.current_buffer_contents(), "one\nfour\n");
 
+        // Cycling needs the cursor right after the pasted text
         pike.move_cursor_to(Position { line: 0, offset: 0 });
+        pike.paste_cycle().expect("Failed to cycle paste");
+        assert_eq!(pike.current_buffer_contents(), "one\nfour\n");
+    }
 
-        pike.move_cursor_right_by_word();
+    #[test]
+    fn test_copy_and_cut_current_line_without_selection() {
+        let (mut pike, _) = tmp_pike_and_working_dir(None, Some("one\ntwo\nthree"));
+        pike.move_cursor_to(Position { line: 1, offset: 1 });
+        pike.copy_selection();
+        assert_eq!(pike.current_buffer_contents(), "one\ntwo\nthree");
+
+        pike.move_cursor_to(Position { line: 0, offset: 0 });
+        pike.paste_at_cursor().expect("Failed to paste");
+        assert_eq!(pike.current_buffer_contents(), "two\none\ntwo\nthree");
+
+        // The last line has no line break to take along
+        pike.move_cursor_to(Position { line: 3, offset: 2 });
+        pike.cut_selection();
+        assert_eq!(pike.current_buffer_contents(), "two\none\ntwo\n");
+        pike.paste_at_cursor().expect("Failed to paste");
+        assert_eq!(pike.current_buffer_contents(), "two\none\ntwo\nthree");
+    }
+
+    #[test]
+    fn test_surround_selection_across_lines() {
+        let (mut pike, _) = tmp_pike_and_working_dir(None, Some("one\ntwo"));
+        pike.start_selection();
+        pike.extend_selection_to(Position { line: 1, offset: 3 });
 
+        pike.surround_selection('"', '"');
+        assert_eq!(pike.current_buffer_contents(), "\"one\ntwo\"");
         assert_eq!(
             pike.cursor_position(),
-            Some(Position { line: 0, offset: 3 })
+            Some(Position { line: 1, offset: 4 })
         );
     }
 
     #[test]
-    fn test_move_cursor_left_by_word_with_unicode() {
-        let contents = "aaa ę aaa";
-        let (mut pike, _) = tmp_pike_and_working_dir(None, Some(contents));
-
-        pike.move_cursor_to(Position { line: 0, offset: 6 });
+    fn test_transpose_chars_boundaries() {
+        let (mut pike, _) = tmp_pike_and_working_dir(None, Some("abc\nd"));
 
-        pike.move_cursor_left_by_word();
+        // Start of the buffer and of a line
+        pike.transpose_chars();
+        assert_eq!(pike.current_buffer_contents(), "abc\nd");
 
+        pike.move_cursor_to(Position { line: 0, offset: 1 });
+        pike.transpose_chars();
+        assert_eq!(pike.current_buffer_contents(), "bac\nd");
         assert_eq!(
             pike.cursor_position(),
-            Some(Position { line: 0, offset: 4 })
+            Some(Position { line: 0, offset: 2 })
         );
 
-        pike.move_cursor_left_by_word();
-
+        // End of the line
+        pike.move_cursor_to(Position { line: 0, offset: 3 });
+        pike.transpose_chars();
+        assert_eq!(pike.current_buffer_contents(), "bca\nd");
         assert_eq!(
             pike.cursor_position(),
-            Some(Position { line: 0, offset: 0 })
+            Some(Position { line: 0, offset: 3 })
         );
+
+        // Too short a line
+        pike.move_cursor_to(Position { line: 1, offset: 1 });
+        pike.transpose_chars();
+        assert_eq!(pike.current_buffer_contents(), "bca\nd");
+
+        pike.undo();
+        assert_eq!(pike.current_buffer_contents(), "bac\nd");
     }
 
     #[test]
-    fn test_move_cursor_right_by_word_with_unicode() {
-        let contents = "aaa ę aaa";
-        let (mut pike, _) = tmp_pike_and_working_dir(None, Some(contents));
+    fn test_transpose_chars_is_grapheme_correct() {
+        assert_eq!(
+            transpose_graphemes("ae\u{301}o", 1),
+            Some(("e\u{301}ao".to_string(), 2))
+        );
+    }
 
-        pike.move_cursor_to(Position { line: 0, offset: 0 });
+    #[test]
+    fn test_transpose_words_boundaries() {
+        // Start of the line
+        assert_eq!(
+            transpose_words("foo bar baz", 0),
+            Some(("bar foo baz".to_string(), 7))
+        );
+        // Inside a word
+        assert_eq!(
+            transpose_words("foo bar baz", 5),
+            Some(("foo baz bar".to_string(), 11))
+        );
+        // End of the line swaps the last two words
+        assert_eq!(
+            transpose_words("foo, bar!", 9),
+            Some(("bar, foo!".to_string(), 8))
+        );
+        assert_eq!(transpose_words("foo", 1), None);
+        assert_eq!(transpose_words("", 0), None);
+    }
 
-        pike.move_cursor_right_by_word();
+    #[test]
+    fn test_transpose_words_is_a_single_undo_step() {
+        let (mut pike, _) = tmp_pike_and_working_dir(None, Some("hello world"));
 
+        pike.transpose_words();
+        assert_eq!(pike.current_buffer_contents(), "world hello");
         assert_eq!(
             pike.cursor_position(),
-            Some(Position { line: 0, offset: 3 })
+            Some(Position {
+                line: 0,
+                offset: 11
+            })
         );
 
-        pike.move_cursor_right_by_word();
+        pike.undo();
+        assert_eq!(pike.current_buffer_contents(), "hello world");
+    }
+
+    #[test]
+    fn test_line_count_with_and_without_final_newline() {
+        for contents in ["", "one", "one\ntwo", "one\ntwo\n", "one\n\n", "\n"] {
+            let (pike, _) = tmp_pike_and_working_dir(None, Some(contents));
+            assert_eq!(
+                pike.line_count(),
+                contents.lines().count(),
+                "Failed for: {contents:?}"
+            );
+        }
+
+        let (pike, _) = tmp_pike_and_working_dir(None, None);
+        assert_eq!(pike.line_count(), 0);
+    }
+
+    #[test]
+    fn test_navigable_line_count_includes_empty_last_line() {
+        for (contents, count) in [
+            ("", 1),
+            ("one", 1),
+            ("one\ntwo", 2),
+            ("one\ntwo\n", 3),
+            ("one\n\n", 3),
+            ("\n", 2),
+            ("one\r\ntwo\r\n", 3),
+        ] {
+            let (pike, _) = tmp_pike_and_working_dir(None, Some(contents));
+            assert_eq!(
+                pike.navigable_line_count(),
+                count,
+                "Failed for: {contents:?}"
+            );
+            assert_eq!(pike.navigable_line_count(), buffer_lines(contents).count());
+        }
+
+        // The cursor can be placed on the empty last line, which the status
+        // bar counts its position against
+        let (mut pike, _) = tmp_pike_and_working_dir(None, Some("a\n"));
+        pike.move_cursor_to(Position { line: 1, offset: 0 });
+        let summary = pike.editor_state_summary();
+        assert_eq!(summary.cursor.map(|cursor| cursor.line + 1), Some(2));
+        assert_eq!(summary.line_count, 2);
+
+        let (pike, _) = tmp_pike_and_working_dir(None, None);
+        assert_eq!(pike.navigable_line_count(), 0);
+    }
+
+    #[test]
+    fn test_editor_state_summary() {
+        let (pike, _) = tmp_pike_and_working_dir(None, None);
+        let summary = pike.editor_state_summary();
+        assert_eq!(summary.mode, EditorMode::Welcome);
+        assert_eq!((summary.cursor, summary.line_count), (None, 0));
+
+        let workspace = tempfile::tempdir().expect("Failed to create directory");
+        let path = workspace.path().join("notes.txt");
+        fs::write(&path, "zażółć\ngęślą\njaźń").unwrap();
+        let mut pike = Pike::build(workspace.path().to_path_buf(), Some(path), None)
+            .expect("Failed to build Pike");
+
+        pike.write_to_current_buffer("x").unwrap();
+        pike.move_cursor_to(Position { line: 0, offset: 4 });
+        pike.start_selection();
+        pike.extend_selection_to(Position { line: 1, offset: 2 });
+        pike.move_cursor_to(Position { line: 1, offset: 2 });
 
         assert_eq!(
-            pike.cursor_position(),
-            Some(Position { line: 0, offset: 5 })
+            pike.editor_state_summary(),
+            EditorStateSummary {
+                filename: "notes.txt".to_string(),
+                is_modified: true,
+                is_read_only: false,
+                cursor: Some(Position { line: 1, offset: 2 }),
+                line_count: 3,
+                selection_length: 6,
+                mode: EditorMode::Select,
+            }
         );
     }
 
     #[test]
-    fn test_move_cursor_right_and_left_with_combining_unicode() {
-        let contents = "ęęę ęęę";
-        let (mut pike, _) = tmp_pike_and_working_dir(None, Some(contents));
-
-        pike.move_cursor_to(Position { line: 0, offset: 0 });
-
-        pike.move_cursor_right_by_word();
+    fn test_navigation_in_file_ending_with_newline() {
+        let (mut pike, _) = tmp_pike_and_working_dir(None, Some("one two\n"));
 
+        pike.move_cursor_down();
         assert_eq!(
             pike.cursor_position(),
-            Some(Position { line: 0, offset: 3 })
+            Some(Position { line: 1, offset: 0 })
         );
+        assert_eq!(pike.current_line_length(), 0);
 
+        // The empty last line is the end of the file
+        pike.move_cursor_down();
         pike.move_cursor_right_by_word();
         assert_eq!(
             pike.cursor_position(),
-            Some(Position { line: 0, offset: 7 })
+            Some(Position { line: 1, offset: 0 })
         );
 
         pike.move_cursor_left_by_word();
-
         assert_eq!(
             pike.cursor_position(),
             Some(Position { line: 0, offset: 4 })
         );
+        assert_eq!(pike.current_line_length(), 7);
 
-        pike.move_cursor_left_by_word();
-
+        pike.move_cursor_to(Position { line: 0, offset: 7 });
+        pike.move_cursor_right_by_word();
         assert_eq!(
             pike.cursor_position(),
-            Some(Position { line: 0, offset: 0 })
+            Some(Position { line: 1, offset: 0 })
         );
     }
 
     #[test]
-    fn test_current_line_length_buffer_exists() {
-        let contents = ["Hello!", ""].join("\n");
-        let (mut pike, _) = tmp_pike_and_working_dir(None, Some(contents.as_str()));
+    fn test_buffer_lines() {
+        let lines = |contents| buffer_lines(contents).collect::<Vec<&str>>();
 
-        assert_eq!(pike.current_line_length(), 6);
-
-        pike.move_cursor_down();
-        assert_eq!(pike.current_line_length(), 0);
+        assert_eq!(lines(""), vec![""]);
+        assert_eq!(lines("one\ntwo"), vec!["one", "two"]);
+        assert_eq!(lines("one\ntwo\n"), vec!["one", "two", ""]);
+        assert_eq!(lines("one\r\ntwo\r\n"), vec!["one", "two", ""]);
     }
 
     #[test]
-    fn test_current_line_length_no_buffer() {
-        let pike = tmp_pike_and_working_dir(None, None).0;
+    fn test_save_all_saves_modified_bound_buffers() {
+        let (mut pike, _) = tmp_pike_and_working_dir(None, Some("first"));
+        let first_path = pike.current_buffer_path().expect("Buffer is bound");
+        pike.write_to_current_buffer("1 ").expect("Failed to write");
 
-        assert_eq!(pike.current_line_length(), 0);
+        let second = temp_file_with_contents("second");
+        pike.open_file(second.path(), 0, 0)
+            .expect("Failed to open file");
+
+        let third = temp_file_with_contents("third");
+        pike.open_file(third.path(), 0, 0)
+            .expect("Failed to open file");
+        pike.write_to_current_buffer("3 ").expect("Failed to write");
+
+        pike.open_new_buffer();
+        pike.write_to_current_buffer("unbound")
+            .expect("Failed to write");
+
+        let results = pike.save_all();
+
+        assert_eq!(results.iter().filter(|result| result.is_ok()).count(), 2);
+        assert_eq!(results.iter().filter(|result| result.is_err()).count(), 1);
+        assert_eq!(
+            fs::read_to_string(&first_path).expect("Failed to read file"),
+            "1 first"
+        );
+        assert_eq!(
+            fs::read_to_string(third.path()).expect("Failed to read file"),
+            "3 third"
+        );
+        assert_eq!(pike.current_buffer_contents(), "unbound");
+
+        pike.next_buffer();
+        assert!(pike.select_unbound_modified_buffer());
+        assert_eq!(pike.current_buffer_contents(), "unbound");
     }
 
     #[test]
-    fn test_create_and_open_file_doesnt_exist() {
-        let (mut pike, cwd) = tmp_pike_and_working_dir(None, None);
-        let file_path = cwd.join("test.txt");
+    fn test_diff_against_file() {
+        let (pike, _) = tmp_pike_and_working_dir(None, Some("one\n2\nthree\nfour"));
+        let original = temp_file_with_contents("one\ntwo\nthree");
 
-        pike.create_and_open_file(&file_path)
-            .expect("Failed to create and open file");
+        let diff = pike.diff_against(original.path()).expect("Failed to diff");
 
-        assert_paths(
-            &pike
-                .current_buffer_path()
-                .expect("Buffer should be set after opening a file"),
-            &file_path,
+        assert_eq!(
+            diff,
+            vec![
+                DiffLine::Unchanged("one".to_string()),
+                DiffLine::Removed("two".to_string()),
+                DiffLine::Added("2".to_string()),
+                DiffLine::Unchanged("three".to_string()),
+                DiffLine::Added("four".to_string()),
+            ]
         );
+        assert!(pike.diff_against(Path::new("does/not/exist")).is_err());
     }
 
     #[test]
-    fn test_create_and_open_file_nested() {
-        let (mut pike, cwd) = tmp_pike_and_working_dir(None, None);
-        let file_path = cwd.join("nested").join("test.txt");
+    fn test_unsaved_change_stats() {
+        let workspace = tempfile::tempdir().expect("Failed to create directory");
+        let file = workspace.path().join("file.txt");
+        fs::write(&file, "one\ntwo\nthree").unwrap();
+        let mut pike =
+            Pike::build(workspace.path().to_path_buf(), None, None).expect("Failed to build Pike");
+        assert_eq!(pike.unsaved_change_stats(), None);
+
+        pike.open_file(&file, 1, 3).unwrap();
+        assert_eq!(pike.unsaved_change_stats(), None);
+        pike.write_to_current_buffer("!\nnew").unwrap();
+        assert_eq!(pike.unsaved_change_stats(), Some((2, 1)));
+
+        pike.save_current_buffer().unwrap();
+        assert_eq!(pike.unsaved_change_stats(), None);
+
+        // Unbound buffers have nothing to compare with
+        pike.open_new_buffer();
+        pike.write_to_current_buffer("foo").unwrap();
+        assert_eq!(pike.unsaved_change_stats(), None);
+    }
 
-        pike.create_and_open_file(&file_path)
-            .expect("Failed to create and open file");
+    #[test]
+    fn test_surround_pair() {
+        assert_eq!(surround_pair('('), ('(', ')'));
+        assert_eq!(surround_pair(')'), ('(', ')'));
+        assert_eq!(surround_pair('{'), ('{', '}'));
+        assert_eq!(surround_pair('*'), ('*', '*'));
+    }
 
-        assert_paths(
-            &pike
-                .current_buffer_path()
-                .expect("Buffer should be set after opening a file"),
-            &file_path,
+    #[test]
+    fn test_convert_indentation() {
+        let spaces = EditorSettings {
+            indent_style: IndentStyle::Spaces,
+            indent_width: 4,
+        };
+        let tabs = EditorSettings {
+            indent_style: IndentStyle::Tabs,
+            ..spaces
+        };
+
+        assert_eq!(
+            convert_indentation("\ta\n  \tb\n\t\t c\td", spaces),
+            "    a\n    b\n         c\td"
+        );
+        assert_eq!(
+            convert_indentation("    a\n  b\n\t c\n", tabs),
+            "\ta\n  b\n\t c\n"
         );
     }
 
     #[test]
-    fn test_create_and_open_file_exists() {
-        let file = temp_file_with_contents("Hello, world!");
-        let (mut pike, _) = tmp_pike_and_working_dir(None, None);
+    fn test_paste_converts_tabs_into_spaces() {
+        let contents = "fn main() {\n    \n}";
+        let copied = "\tif x {\n\t\ty();\n\t}";
+        let config = "normalize_paste_indentation = true";
+
+        let (mut pike, _) = tmp_pike_and_working_dir(Some(config), Some(contents));
+        pike.move_cursor_to(Position { line: 1, offset: 4 });
+        pike.paste(copied).expect("Failed to paste");
+        assert_eq!(
+            pike.current_buffer_contents(),
+            "fn main() {\n    if x {\n        y();\n    }\n}"
+        );
+    }
 
-        pike.create_and_open_file(file.path())
-            .expect("Failed to create and open file");
+    #[test]
+    fn test_paste_reindents_nested_block() {
+        let contents = "fn main() {\n    \n}";
+        let copied = "        if x {\n            y();\n        }";
 
-        assert_paths(
-            &pike
-                .current_buffer_path()
-                .expect("Buffer should be set after opening a file"),
-            file.path(),
+        let (mut pike, _) = tmp_pike_and_working_dir(None, Some(contents));
+        pike.move_cursor_to(Position { line: 1, offset: 4 });
+        pike.paste(copied).expect("Failed to paste");
+        assert_eq!(
+            pike.current_buffer_contents(),
+            "fn main() {\n    if x {\n        y();\n    }\n}"
+        );
+        assert_eq!(
+            pike.cursor_position(),
+            Some(Position { line: 3, offset: 5 })
+        );
+
+        let config = "paste_reindent = false";
+        let (mut pike, _) = tmp_pike_and_working_dir(Some(config), Some(contents));
+        pike.move_cursor_to(Position { line: 1, offset: 4 });
+        pike.paste(copied).expect("Failed to paste");
+        assert_eq!(
+            pike.current_buffer_contents(),
+            format!("fn main() {{\n    {copied}\n}}")
         );
     }
 
     #[test]
-    fn test_open_new_buffer() {
-        let file = temp_file_with_contents("Hello, world!");
-        let (mut pike, _) = tmp_pike_and_working_dir(None, None);
-
-        pike.open_file(file.path(), 0, 0)
-            .expect("Failed to open file");
-        assert_eq!(pike.workspace.buffer_paths().len(), 1);
+    fn test_reindent_lines_copied_from_the_middle_of_a_line() {
+        assert_eq!(
+            reindent_lines("if x {\n        y();\n\n    }", "  "),
+            "if x {\n      y();\n\n  }"
+        );
+    }
 
-        // Should be empty with no path
-        pike.open_new_buffer();
-        assert_eq!(pike.current_buffer_contents(), "");
-        assert!(pike
-            .current_buffer()
-            .expect("A buffer should be open")
-            .path
-            .is_none());
-        assert_eq!(pike.workspace.buffer_paths().len(), 2);
+    #[test]
+    fn test_reindent_lines_with_multi_byte_whitespace() {
+        // An ideographic space is a single character taking up three bytes
+        assert_eq!(
+            reindent_lines("a\n\u{3000}\u{3000}b\n\u{3000}c", "\t"),
+            "a\n\t\u{3000}b\n\tc"
+        );
+        assert_eq!(reindent_lines("\u{3000}a\n\u{3000}b", ""), "a\nb");
     }
 
     #[test]
-    fn test_bind_current_buffer_to_path() {
-        let file_contents = "Hello, world!";
-        let (mut pike, dir) = tmp_pike_and_working_dir(None, None);
-        assert!(pike.current_buffer_path().is_none());
-        pike.open_new_buffer();
-        pike.write_to_current_buffer(file_contents)
-            .expect("Failed to write to current buffer");
+    fn test_paste_after_multi_byte_indentation() {
+        let (mut pike, _) = tmp_pike_and_working_dir(None, Some("\u{3000}\u{3000}x"));
+        pike.move_cursor_to(Position { line: 0, offset: 2 });
+        pike.paste("a\n  b").expect("Failed to paste");
+        assert_eq!(
+            pike.current_buffer_contents(),
+            "\u{3000}\u{3000}a\n\u{3000}\u{3000}bx"
+        );
+    }
 
-        let file_path = dir.join(Path::new("new_file.txt"));
-        pike.bind_current_buffer_to_path(file_path.clone());
+    #[cfg(unix)]
+    #[test]
+    fn test_open_symlink() {
+        let workspace = tempfile::tempdir().expect("Failed to create directory");
+        let cwd = workspace.path().canonicalize().unwrap();
+        let target = cwd.join("target.txt");
+        let link = cwd.join("link.txt");
+        fs::write(&target, "target").unwrap();
+        std::os::unix::fs::symlink(&target, &link).unwrap();
+        let config = temp_file_with_contents("follow_symlinks = false");
+
+        for (config, bound_path) in [(None, &target), (Some(config.path().to_path_buf()), &link)] {
+            let mut pike = Pike::build(cwd.clone(), None, config).expect("Failed to build Pike");
+            pike.open_file(&link, 0, 0).expect("Failed to open symlink");
+            assert_eq!(pike.current_buffer_contents(), "target");
+            assert_eq!(pike.current_buffer_path().as_ref(), Some(bound_path));
+
+            // Saving writes to the target, keeping the link in place
+            pike.write_to_current_buffer("saved ").unwrap();
+            pike.save_current_buffer().expect("Failed to save");
+            assert_eq!(fs::read_to_string(&target).unwrap(), "saved target");
+            assert!(fs::symlink_metadata(&link)
+                .unwrap()
+                .file_type()
+                .is_symlink());
+            fs::write(&target, "target").unwrap();
+        }
+    }
 
-        assert!(pike.save_current_buffer().is_ok());
+    #[cfg(unix)]
+    #[test]
+    fn test_open_broken_symlink() {
+        let workspace = tempfile::tempdir().expect("Failed to create directory");
+        let cwd = workspace.path().canonicalize().unwrap();
+        let link = cwd.join("link.txt");
+        std::os::unix::fs::symlink(cwd.join("missing.txt"), &link).unwrap();
+
+        let mut pike = Pike::build(cwd.clone(), None, None).expect("Failed to build Pike");
+        for result in [
+            pike.open_file(&link, 0, 0),
+            pike.open_large_file(&link),
+            pike.create_and_open_file(&link),
+        ] {
+            assert!(matches!(result, Err(PikeError::BrokenSymlink(path)) if path == link));
+        }
+        assert!(!cwd.join("missing.txt").exists());
+        assert!(pike.current_buffer().is_none());
+    }
 
-        let contents_from_file =
-            fs::read_to_string(file_path).expect("std::fs failed to read from file");
-        assert_eq!(file_contents, contents_from_file)
+    #[cfg(unix)]
+    #[test]
+    fn test_build_with_symlink() {
+        let workspace = tempfile::tempdir().expect("Failed to create directory");
+        let cwd = workspace.path().canonicalize().unwrap();
+        let target = cwd.join("target.txt");
+        let link = cwd.join("link.txt");
+        let broken = cwd.join("broken.txt");
+        fs::write(&target, "target").unwrap();
+        std::os::unix::fs::symlink(&target, &link).unwrap();
+        std::os::unix::fs::symlink(cwd.join("missing.txt"), &broken).unwrap();
+
+        let result = Pike::build(cwd.clone(), Some(broken.clone()), None);
+        assert!(matches!(result, Err(PikeError::BrokenSymlink(path)) if path == broken));
+        assert!(!cwd.join("missing.txt").exists());
+
+        let config = temp_file_with_contents("follow_symlinks = false");
+        let pike = Pike::build(cwd, Some(link.clone()), Some(config.path().to_path_buf()))
+            .expect("Failed to build Pike");
+        assert_eq!(pike.current_buffer_contents(), "target");
+        assert_eq!(pike.current_buffer_path(), Some(link));
     }
 
     #[test]
-    fn test_moving_cursor_when_inserting_many_characters() {
-        let file = temp_file_with_contents("Hello, world!");
+    fn test_open_read_only_file() {
         let (mut pike, _) = tmp_pike_and_working_dir(None, None);
 
+        let file = temp_file_with_contents("read only");
+        let mut permissions = fs::metadata(file.path())
+            .expect("Failed to read metadata")
+            .permissions();
+        permissions.set_readonly(true);
+        fs::set_permissions(file.path(), permissions).expect("Failed to set permissions");
+
         pike.open_file(file.path(), 0, 0)
             .expect("Failed to open file");
+        assert!(pike.is_current_buffer_read_only());
 
-        pike.move_cursor_to(Position {
-            line: 0,
-            offset: 13,
-        });
-        pike.write_to_current_buffer(" Its me!")
-            .expect("Failed to write to buffer");
-
-        assert_eq!(pike.current_buffer_contents(), "Hello, world! Its me!");
-        assert_eq!(
-            pike.cursor_position(),
-            Some(Position {
-                line: 0,
-                offset: 21
-            })
-        );
+        let writable = temp_file_with_contents("writable");
+        pike.open_file(writable.path(), 0, 0)
+            .expect("Failed to open file");
+        assert!(!pike.is_current_buffer_read_only());
     }
 
     #[test]
-    fn test_undo() {
-        let file = temp_file_with_contents("Hello, world!");
-        let (mut pike, _) = tmp_pike_and_working_dir(None, None);
+    fn test_read_only_toggle_is_kept_per_buffer() {
+        let (mut pike, _) = tmp_pike_and_working_dir(None, Some("first"));
+        pike.toggle_current_buffer_read_only();
+        assert!(pike.is_current_buffer_read_only());
 
-        pike.open_file(file.path(), 0, 0)
-            .expect("Failed to open file");
-
-        pike.move_cursor_to(Position {
-            line: 0,
-            offset: 13,
-        });
-        pike.write_to_current_buffer("!")
-            .expect("Failed to write to buffer");
+        pike.open_new_buffer();
+        assert!(!pike.is_current_buffer_read_only());
 
-        assert_eq!(pike.current_buffer_contents(), "Hello, world!!");
-        assert_eq!(
-            pike.cursor_position(),
-            Some(Position {
-                line: 0,
-                offset: 14
-            })
-        );
+        pike.previous_buffer();
+        assert!(pike.is_current_buffer_read_only());
+        pike.toggle_current_buffer_read_only();
+        assert!(!pike.is_current_buffer_read_only());
+    }
 
-        pike.undo();
-        assert_eq!(pike.current_buffer_contents(), "Hello, world!");
-        assert_eq!(
-            pike.cursor_position(),
-            Some(Position {
-                line: 0,
-                offset: 13
-            })
-        );
+    #[test]
+    fn test_read_only_buffer_rejects_edits() {
+        let (mut pike, _) = tmp_pike_and_working_dir(None, Some("first\nsecond"));
+        pike.toggle_current_buffer_read_only();
+
+        assert!(matches!(
+            pike.paste("pasted\n"),
+            Err(PikeError::BufferReadOnly)
+        ));
+        assert!(matches!(
+            pike.type_character('a'),
+            Err(PikeError::BufferReadOnly)
+        ));
+        assert!(matches!(
+            pike.replace_in_current_buffer("first", "1st", SearchOptions::default(), true),
+            Err(PikeError::BufferReadOnly)
+        ));
+        pike.cut_selection();
+        pike.set_buffer_contents("replaced");
+        pike.normalize_line_endings(LineEnding::CrLf);
+        assert_eq!(pike.current_buffer_contents(), "first\nsecond");
+
+        pike.toggle_current_buffer_read_only();
+        pike.paste("pasted\n").expect("Failed to paste");
+        assert_eq!(pike.current_buffer_contents(), "pasted\nfirst\nsecond");
     }
 
     #[test]
-    fn test_redo() {
-        let file = temp_file_with_contents("Hello, world!");
-        let (mut pike, _) = tmp_pike_and_working_dir(None, None);
+    fn test_edits_outside_workspace_need_confirmation() {
+        let workspace = tempfile::tempdir().expect("Failed to create directory");
+        let outside = temp_file_with_contents("outside");
+        let config = temp_file_with_contents("confirm_edit_outside_workspace = true");
+
+        let mut pike = Pike::build(
+            workspace.path().to_path_buf(),
+            Some(outside.path().to_path_buf()),
+            Some(config.path().to_path_buf()),
+        )
+        .expect("Failed to build Pike");
+        assert!(pike.current_buffer_needs_edit_confirmation());
+
+        // Edits are rejected until the first one is confirmed
+        assert!(matches!(
+            pike.paste("pasted "),
+            Err(PikeError::EditNotConfirmed)
+        ));
+        pike.move_cursor_to(Position { line: 0, offset: 3 });
+        pike.delete_character_from_current_buffer();
+        pike.set_selection(
+            Position { line: 0, offset: 0 },
+            Position { line: 0, offset: 3 },
+        );
+        pike.surround_selection('(', ')');
+        pike.set_buffer_contents("replaced");
+        assert_eq!(pike.current_buffer_contents(), "outside");
 
-        pike.open_file(file.path(), 0, 0)
+        pike.confirm_current_buffer_edits();
+        assert!(!pike.current_buffer_needs_edit_confirmation());
+        pike.move_cursor_to(Position { line: 0, offset: 0 });
+        pike.paste("pasted ").expect("Failed to paste");
+        assert_eq!(pike.current_buffer_contents(), "pasted outside");
+
+        // Confirmation is disabled by default
+        let (mut pike, _) = tmp_pike_and_working_dir(None, None);
+        pike.open_file(outside.path(), 0, 0)
             .expect("Failed to open file");
+        assert!(!pike.current_buffer_needs_edit_confirmation());
+    }
 
-        pike.move_cursor_to(Position {
-            line: 0,
-            offset: 13,
-        });
-        pike.write_to_current_buffer("!")
-            .expect("Failed to write to buffer");
+    #[test]
+    fn test_reflow_long_line_and_back() {
+        let contents = "aaa bbb ccc ddd eee";
+        let (mut pike, _) = tmp_pike_and_working_dir(None, Some(contents));
 
-        assert_eq!(pike.current_buffer_contents(), "Hello, world!!");
-        assert_eq!(
-            pike.cursor_position(),
-            Some(Position {
-                line: 0,
-                offset: 14
-            })
-        );
+        pike.reflow(0..1, 7);
+        assert_eq!(pike.current_buffer_contents(), "aaa bbb\nccc ddd\neee");
 
-        pike.undo();
-        assert_eq!(pike.current_buffer_contents(), "Hello, world!");
-        assert_eq!(
-            pike.cursor_position(),
-            Some(Position {
-                line: 0,
-                offset: 13
-            })
-        );
+        pike.reflow(0..3, 80);
+        assert_eq!(pike.current_buffer_contents(), contents);
+    }
 
-        pike.redo();
-        assert_eq!(pike.current_buffer_contents(), "Hello, world!!");
+    #[test]
+    fn test_reflow_preserves_indentation_and_list_marker() {
+        let contents = "  - one two three four";
+        let (mut pike, _) = tmp_pike_and_working_dir(None, Some(contents));
+
+        pike.reflow(0..1, 14);
         assert_eq!(
-            pike.cursor_position(),
-            Some(Position {
-                line: 0,
-                offset: 14
-            })
+            pike.current_buffer_contents(),
+            "  - one two\n    three four"
         );
     }
 
     #[test]
-    fn test_search_in_current_buffer() {
-        let file_contents = "Hello, world!";
-        let (mut pike, _) = tmp_pike_and_working_dir(None, Some(file_contents));
+    fn test_reflow_paragraph_is_a_single_undo_step() {
+        let contents = "first paragraph here\n\nsecond one stays untouched";
+        let config = "text_width = 10";
+        let (mut pike, _) = tmp_pike_and_working_dir(Some(config), Some(contents));
 
-        let results = pike
-            .search_in_current_buffer("world")
-            .expect("No buffer is currently open");
+        pike.reflow_paragraph();
+        assert_eq!(
+            pike.current_buffer_contents(),
+            "first\nparagraph\nhere\n\nsecond one stays untouched"
+        );
 
-        assert_eq!(results.len(), 1);
-        assert_eq!(results[0].start, Position { line: 0, offset: 7 });
-        assert_eq!(results[0].length, 5);
+        pike.undo();
+        assert_eq!(pike.current_buffer_contents(), contents);
     }
 
     #[test]
@@ -47,3 +47,49 @@ pub mod ui {
         line
     }
 }
+
+#[cfg(test)]
+pub mod cursor {
+    use ratatui::layout::{Position as TerminalPosition, Rect};
+    use scribe::buffer::Position as BufferPosition;
+    use tui_input::Input;
+
+    use crate::ui::UIState;
+
+    /// Scroll the displayed buffer so that the cursor is visible, the same way
+    /// rendering it does, and return the position the cursor would be rendered at
+    pub fn buffer_cursor_render_position(
+        ui_state: &mut UIState,
+        area: Rect,
+        cursor_position: Option<BufferPosition>,
+    ) -> TerminalPosition {
+        if let Some(cp) = cursor_position {
+            ui_state.buffer_state.update_x_offset(area, cp.offset);
+            ui_state.buffer_state.update_y_offset(area, cp.line);
+        }
+
+        ui_state.calculate_cursor_for_buffer(area, cursor_position)
+    }
+
+    /// Assert the position the cursor is rendered at when the buffer is displayed in `area`
+    pub fn assert_buffer_cursor_render_pos(
+        ui_state: &mut UIState,
+        area: Rect,
+        cursor_position: Option<BufferPosition>,
+        expected: (u16, u16),
+    ) {
+        let pos = buffer_cursor_render_position(ui_state, area, cursor_position);
+        assert_eq!(pos, expected.into());
+    }
+
+    /// Assert the position the cursor is rendered at when `input` is displayed in `area`
+    pub fn assert_input_cursor_render_pos(
+        ui_state: &UIState,
+        input: &Input,
+        area: Rect,
+        expected: (u16, u16),
+    ) {
+        let pos = ui_state.calculate_cursor_for_file_input(input, area);
+        assert_eq!(pos, expected.into());
+    }
+}
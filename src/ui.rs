@@ -1,3 +1,4 @@
+use crossterm::event::KeyEvent;
 use ratatui::{
     buffer::Buffer,
     layout::{Position as TerminalPosition, Rect},
@@ -7,35 +8,266 @@ use ratatui::{
 };
 use scribe::buffer::Position as BufferPosition;
 use std::rc::Rc;
-use std::{cmp::min, path::PathBuf};
+use std::{cmp::min, collections::HashSet, fmt::Display, marker::PhantomData, path::PathBuf};
 use tui_input::{Input, InputRequest};
+use unicode_segmentation::UnicodeSegmentation;
 
-use crate::pike::Highlight;
+use crate::config::{GutterStyle, STATUS_BAR_ALIGN_RIGHT};
+use crate::operations::Operation;
+use crate::pike::{
+    buffer_lines, DiffLine, EditorStateSummary, Highlight, LineFilterMode, SearchOptions,
+};
 
-/// We would like to have some struct which can be rendered
-/// as a list with given callbacks to be executed when something is
-/// selected (similarly to telescope.nvim) so that we can reuse
-/// it when searching for files or word occurrences in the cwd
-/// As of now, I can't look that far into the future without writing
-/// some code to know what fields this should keep and how it should
-/// behave, so it's empty
-#[allow(dead_code)]
-struct Picker {}
+/// What the app does with the item chosen in a picker
+#[derive(Debug, Clone, PartialEq, Eq)]
+pub enum PickerAction {
+    /// Opens the file at the path, relative to the working directory
+    OpenFile(PathBuf),
+    /// Opens the file at the path, relative to the working directory, with the
+    /// cursor at the start of the line with the index
+    OpenFileAtLine(PathBuf, usize),
+    /// Switches to the open buffer with the id
+    FocusBuffer(usize),
+}
+
+/// An item listed in the pickers of the app, along with what choosing it does
+#[derive(Debug, Clone, PartialEq, Eq)]
+pub struct PickerItem {
+    pub label: String,
+    pub action: PickerAction,
+}
+
+impl Display for PickerItem {
+    fn fmt(&self, f: &mut std::fmt::Formatter<'_>) -> std::fmt::Result {
+        f.write_str(&self.label)
+    }
+}
+
+/// Returns the items matching a query of a picker, best matches first
+type PickerSearch<T> = Box<dyn Fn(&str) -> Vec<T>>;
+
+/// Returns what the app should do with the items chosen in a picker
+type PickerConfirm<T> = Box<dyn Fn(Vec<&T>) -> Vec<PickerAction>>;
+
+/// A list of items narrowed down by a query typed in below it, from which
+/// one or several marked ones are chosen, similarly to telescope.nvim.
+/// It's reused for e.g. finding files in the working directory.
+pub struct Picker<T> {
+    pub query: Input,
+    results: Vec<T>,
+    selected: usize,
+    /// Indices of the marked results, which are chosen instead of the selected
+    /// one if there are any. They're cleared when the query changes.
+    marked: HashSet<usize>,
+    title: &'static str,
+    search: PickerSearch<T>,
+    /// Invoked with the chosen items, returns what the app should do with them
+    on_confirm: PickerConfirm<T>,
+    /// Whether the listed files include hidden and ignored ones,
+    /// None if the picker doesn't list files
+    pub includes_ignored_files: Option<bool>,
+}
+
+impl<T: Display> Picker<T> {
+    /// Creates a picker listing the items matching an empty query
+    pub fn new(
+        title: &'static str,
+        search: impl Fn(&str) -> Vec<T> + 'static,
+        on_confirm: impl Fn(Vec<&T>) -> Vec<PickerAction> + 'static,
+    ) -> Self {
+        Picker {
+            query: Input::default(),
+            results: search(""),
+            selected: 0,
+            marked: HashSet::new(),
+            title,
+            search: Box::new(search),
+            on_confirm: Box::new(on_confirm),
+            includes_ignored_files: None,
+        }
+    }
+
+    /// Replaces the query and lists the items matching it, selecting the first one
+    pub fn set_query(&mut self, query: &str) {
+        self.query = query.into();
+        self.results = (self.search)(query);
+        self.selected = 0;
+        self.marked.clear();
+    }
+
+    /// Edits the query and lists the items matching it, selecting the first one
+    pub fn handle(&mut self, request: InputRequest) {
+        self.query.handle(request);
+        self.results = (self.search)(self.query.value());
+        self.selected = 0;
+        self.marked.clear();
+    }
+
+    pub fn selected(&self) -> Option<&T> {
+        self.results.get(self.selected)
+    }
+
+    pub fn move_selection_up(&mut self) {
+        self.selected = self.selected.saturating_sub(1);
+    }
+
+    pub fn move_selection_down(&mut self) {
+        if self.selected + 1 < self.results.len() {
+            self.selected += 1;
+        }
+    }
+
+    /// Marks the selected item, or unmarks it if it's marked, and moves the
+    /// selection down to the next item
+    pub fn toggle_mark(&mut self) {
+        if self.selected().is_none() {
+            return;
+        }
+        if !self.marked.remove(&self.selected) {
+            self.marked.insert(self.selected);
+        }
+        self.move_selection_down();
+    }
+
+    /// Returns the marked items in the order they're listed in
+    pub fn marked(&self) -> Vec<&T> {
+        let mut indices: Vec<usize> = self.marked.iter().copied().collect();
+        indices.sort_unstable();
+        indices
+            .into_iter()
+            .filter_map(|index| self.results.get(index))
+            .collect()
+    }
+
+    /// Returns what to do with the marked items, or with the selected one if none
+    /// are marked. Nothing is done if nothing matches the query.
+    pub fn confirm(&self) -> Vec<PickerAction> {
+        let chosen = if self.marked.is_empty() {
+            self.selected().into_iter().collect()
+        } else {
+            self.marked()
+        };
+        if chosen.is_empty() {
+            return Vec::new();
+        }
+        (self.on_confirm)(chosen)
+    }
+
+    /// Returns the area of the query input when the picker is rendered in `area`
+    pub fn query_area(area: Rect) -> Rect {
+        let height = area.height.min(PICKER_QUERY_HEIGHT);
+        Rect {
+            y: area.bottom() - height,
+            height,
+            ..area
+        }
+    }
+}
+
+/// Widget rendering a picker as a list of its results above its query input
+pub struct PickerWidget<T> {
+    items: PhantomData<T>,
+}
+
+impl<T> Default for PickerWidget<T> {
+    fn default() -> Self {
+        PickerWidget { items: PhantomData }
+    }
+}
+
+impl<T: Display> StatefulWidget for PickerWidget<T> {
+    type State = Picker<T>;
+
+    fn render(self, area: Rect, buf: &mut Buffer, state: &mut Self::State) {
+        let query_area = Picker::<T>::query_area(area);
+        let list_area = Rect {
+            height: area.height - query_area.height,
+            ..area
+        };
+
+        widgets::Clear.render(list_area, buf);
+        let items: Vec<widgets::ListItem> = state
+            .results
+            .iter()
+            .enumerate()
+            .map(|(index, item)| {
+                if state.marked.is_empty() {
+                    widgets::ListItem::new(item.to_string())
+                } else if state.marked.contains(&index) {
+                    widgets::ListItem::new(format!("{PICKER_MARK_GLYPH} {item}"))
+                        .style(Style::default().fg(PICKER_MARKED_FG))
+                } else {
+                    widgets::ListItem::new(format!("  {item}"))
+                }
+            })
+            .collect();
+        let list = widgets::List::new(items)
+            .block(
+                widgets::Block::new()
+                    .borders(widgets::Borders::TOP)
+                    .title(state.title),
+            )
+            .highlight_style(Style::default().fg(Color::Black).bg(PICKER_SELECTED_BG));
+        let mut list_state = widgets::ListState::default().with_selected(Some(state.selected));
+        StatefulWidget::render(list, list_area, buf, &mut list_state);
+
+        widgets::Clear.render(query_area, buf);
+        let input = widgets::Paragraph::new(state.query.to_text())
+            .block(widgets::Block::new().borders(widgets::Borders::all()));
+        input.render(query_area, buf);
+    }
+}
 
 const HIGHLIGHT_BG_SELECTED: Color = Color::Rgb(245, 206, 88);
 const HIGHLIGHT_BG_UNSELECTED: Color = Color::Rgb(240, 137, 48);
+const HIGHLIGHT_BG_REPLACE_TARGET: Color = Color::Rgb(224, 90, 90);
+const DIFF_FG_ADDED: Color = Color::Green;
+const DIFF_FG_REMOVED: Color = Color::Red;
+const SEARCH_FG_NO_MATCHES: Color = Color::Red;
+const MATCHING_TAG_BG: Color = Color::DarkGray;
+const SELECTION_BG: Color = Color::Rgb(130, 170, 230);
+const LINE_NUMBER_FG: Color = Color::DarkGray;
+const LINE_MARK_FG: Color = Color::Rgb(245, 206, 88);
+const PICKER_SELECTED_BG: Color = SELECTION_BG;
+const PICKER_MARKED_FG: Color = Color::Rgb(245, 206, 88);
+
+/// Height of the query input of a picker, including its borders
+const PICKER_QUERY_HEIGHT: u16 = 3;
+
+/// Shown in the gutter next to marked lines
+const LINE_MARK_GLYPH: &str = "●";
+/// Shown before the marked items of a picker
+const PICKER_MARK_GLYPH: &str = "+";
+
+/// Number of frames over which a smooth scroll reaches its target
+const SMOOTH_SCROLL_FRAMES: usize = 3;
 
 pub enum CursorCalculationMode<'a> {
     FileInput(&'a Input),
     Buffer,
 }
 
-/// Two ways a file input can serve in the app - either when opening
-/// a new file by path or saving an unbound buffer
+/// Ways a file input can serve in the app - opening a new file by path,
+/// saving an unbound buffer, choosing a file to diff the buffer against
+/// or choosing a file to open as of a git revision
 #[derive(Debug, Clone, PartialEq)]
+#[allow(clippy::enum_variant_names)]
 pub enum FileInputRole {
     GetOpenPath,
     GetSavePath,
+    GetDiffPath,
+    GetRevisionPath,
+    GetCodePoint,
+    GetLineFilter(LineFilterMode),
+}
+
+/// The second field of the search input when searching and replacing
+#[derive(Default, Clone)]
+pub struct ReplaceInputState {
+    pub input: Input,
+    /// Whether typed text goes to the replacement rather than to the searched
+    /// query, which is the case once the query has been searched for
+    pub is_focused: bool,
 }
 
 /// Holds an input and an indicator of its role
@@ -64,6 +296,18 @@ impl From<(&str, FileInputRole)> for FileInputState {
     }
 }
 
+/// An edit of the current buffer which waits for its first edit to be confirmed,
+/// and which is made once it is
+#[derive(Debug, Clone, PartialEq)]
+pub enum PendingEdit {
+    /// A key typed into the buffer
+    Key(KeyEvent),
+    /// An operation which modifies the buffer
+    Operation(Operation),
+    /// Text pasted into the buffer
+    Paste(String),
+}
+
 /// Holds the information about the current state of the UI
 /// of the app.
 #[allow(dead_code)]
@@ -75,6 +319,39 @@ pub struct UIState {
     /// and opening a new file
     pub file_input: Option<FileInputState>,
     pub search_input: Option<Input>,
+    /// Number of matches of the last searched query, None until the
+    /// query in the search input is searched for
+    pub search_match_count: Option<usize>,
+    /// Options the search input searches with, kept between searches
+    pub search_options: SearchOptions,
+    /// Replacement of the searched query, shown under the search input while
+    /// searching and replacing
+    pub replace_input: Option<ReplaceInputState>,
+    /// Picker shown over the whole UI, e.g. to find a file to open
+    pub picker: Option<Picker<PickerItem>>,
+    /// Query last searched for, which the search input is opened with and
+    /// which searching for the next or previous match reuses
+    pub last_search_query: Option<String>,
+    /// Whether the gutter numbers lines relative to the cursor's line
+    pub relative_line_numbers: bool,
+    /// Whether the next typed character is used to surround the selection
+    pub awaiting_surround: bool,
+    /// Edit waiting for the next key press to confirm or decline editing the
+    /// current buffer
+    pub awaiting_edit_confirmation: Option<PendingEdit>,
+    /// Path of a large file waiting for the next key press to confirm opening it
+    pub awaiting_large_file: Option<PathBuf>,
+    /// State of the read-only hex view of the current buffer, if it's open
+    pub hex_view: Option<HexViewState>,
+    /// State of the read-only diff of the current buffer against a file, if it's open
+    pub diff_view: Option<DiffViewState>,
+    /// A one-off message displayed to the user until the next key press
+    pub status_message: Option<String>,
+    /// Lines added and removed in the current buffer since it was last saved,
+    /// as last computed
+    pub unsaved_changes: Option<(usize, usize)>,
+    /// Whether `unsaved_changes` has to be recomputed once the user is idle
+    pub unsaved_changes_stale: bool,
 }
 
 impl UIState {
@@ -128,12 +405,15 @@ impl UIState {
         if let Some(cursor_pos) = cursor_pos {
             let (max_x, max_y) = Self::max_rect_position(&area);
             let (base_x, base_y) = Self::base_rect_position(&area);
+            let base_x = base_x.saturating_add(self.buffer_state.gutter_width());
 
-            let x_offset = self.buffer_state.offset.x as u16;
-            let y_offset = self.buffer_state.offset.y as u16;
+            // Subtract the offsets before narrowing down to terminal
+            // coordinates, so that extremely long lines don't overflow them
+            let visible_x = cursor_pos.offset.saturating_sub(self.buffer_state.offset.x);
+            let visible_y = cursor_pos.line.saturating_sub(self.buffer_state.offset.y);
 
-            let x = (base_x + cursor_pos.offset as u16).saturating_sub(x_offset);
-            let y = (base_y + cursor_pos.line as u16).saturating_sub(y_offset);
+            let x = base_x.saturating_add(visible_x.min(u16::MAX as usize) as u16);
+            let y = base_y.saturating_add(visible_y.min(u16::MAX as usize) as u16);
 
             TerminalPosition {
                 x: min(x, max_x),
@@ -154,11 +434,11 @@ impl UIState {
         (area.x, area.y)
     }
 
-    /// Update the state of the buffer with the given highlights
+    /// Update the state of the buffer with the given highlights and focus the first one
     pub fn update_highlights(&mut self, highlights: Vec<Highlight>) {
         self.buffer_state.highlight_state.highlights = highlights;
-        let focused_highlight = self.buffer_state.highlight_state.focused;
-        self.buffer_state.highlight_state.highlights[focused_highlight].is_selected = true;
+        self.buffer_state.highlight_state.focused = 0;
+        self.focus_highlight(0);
     }
 
     /// Get the position of the currently focused highlight
@@ -168,30 +448,42 @@ impl UIState {
         highlight.start
     }
 
-    /// Change the focus to the next highlight
-    pub fn focus_next_highlight(&mut self) {
-        let highlights = &mut self.buffer_state.highlight_state.highlights;
+    /// Move the focus to the highlight with the given index, if it exists
+    pub fn focus_highlight(&mut self, index: usize) {
+        let state = &mut self.buffer_state.highlight_state;
+
+        if let Some(highlight) = state.highlights.get_mut(state.focused) {
+            highlight.is_selected = false;
+        }
+
+        if let Some(highlight) = state.highlights.get_mut(index) {
+            highlight.is_selected = true;
+            state.focused = index;
+        }
+    }
+
+    /// Change the focus to the next highlight. Returns whether the focus
+    /// wrapped around from the last highlight to the first one.
+    pub fn focus_next_highlight(&mut self) -> bool {
         let currently_focused = self.buffer_state.highlight_state.focused;
-        let n_of_highlights = highlights.len();
+        let n_of_highlights = self.buffer_state.highlight_state.highlights.len();
 
-        highlights[currently_focused].is_selected = false;
+        let next_highlight = (currently_focused + 1) % n_of_highlights;
+        self.focus_highlight(next_highlight);
 
-        let next_highlight = currently_focused.wrapping_add(1) % n_of_highlights;
-        highlights[next_highlight].is_selected = true;
-        self.buffer_state.highlight_state.focused = next_highlight;
+        next_highlight <= currently_focused
     }
 
-    /// Change the focus to the previous highlight
-    pub fn focus_prev_highlight(&mut self) {
-        let highlights = &mut self.buffer_state.highlight_state.highlights;
+    /// Change the focus to the previous highlight. Returns whether the focus
+    /// wrapped around from the first highlight to the last one.
+    pub fn focus_prev_highlight(&mut self) -> bool {
         let currently_focused = self.buffer_state.highlight_state.focused;
-        let n_of_highlights = highlights.len();
+        let n_of_highlights = self.buffer_state.highlight_state.highlights.len();
 
-        highlights[currently_focused].is_selected = false;
+        let prev_highlight = (currently_focused + n_of_highlights - 1) % n_of_highlights;
+        self.focus_highlight(prev_highlight);
 
-        let prev_highlight = currently_focused.wrapping_sub(1) % n_of_highlights;
-        highlights[prev_highlight].is_selected = true;
-        self.buffer_state.highlight_state.focused = prev_highlight;
+        prev_highlight >= currently_focused
     }
 
     /// Clear all highlights from the buffer
@@ -227,11 +519,80 @@ pub struct HighlightState {
     pub focused: usize,
 }
 
+impl HighlightState {
+    /// Marks the highlight with the given index as the one about to be
+    /// replaced, or clears the mark if `index` is None
+    pub fn set_replace_target(&mut self, index: Option<usize>) {
+        for (i, highlight) in self.highlights.iter_mut().enumerate() {
+            highlight.is_replace_target = Some(i) == index;
+        }
+    }
+
+    /// Returns the lines on which the highlights start, along with
+    /// whether the highlight is the focused one
+    pub fn highlighted_lines(&self) -> impl Iterator<Item = (usize, bool)> + '_ {
+        self.highlights
+            .iter()
+            .map(|highlight| (highlight.start.line, highlight.is_selected))
+    }
+
+    /// Marks the lines of the highlights on the track of a vertical scrollbar
+    /// rendered in the rightmost column of `area`, at positions proportional
+    /// to where they are in all `line_count` lines of the buffer. The track
+    /// excludes the arrows at both ends of the scrollbar.
+    pub fn render_scrollbar_markers(&self, line_count: usize, area: Rect, buf: &mut Buffer) {
+        let track_height = area.height.saturating_sub(2) as usize;
+        if track_height == 0 || line_count == 0 || area.width == 0 {
+            return;
+        }
+
+        let x = area.right() - 1;
+        // Render the focused marker last, so it's not covered by the others
+        let mut lines: Vec<(usize, bool)> = self.highlighted_lines().collect();
+        lines.sort_by_key(|(_, is_selected)| *is_selected);
+
+        for (line, is_selected) in lines {
+            let row = min(line * track_height / line_count, track_height - 1);
+            let bg = if is_selected {
+                HIGHLIGHT_BG_SELECTED
+            } else {
+                HIGHLIGHT_BG_UNSELECTED
+            };
+            if let Some(cell) = buf.cell_mut((x, area.y + 1 + row as u16)) {
+                cell.set_bg(bg);
+            }
+        }
+    }
+}
+
 #[derive(Default)]
 /// Represents the state of a buffer display, including its contents, cursor position, and offset.
 pub struct BufferDisplayState {
     pub offset: BufferDisplayOffset,
     pub highlight_state: HighlightState,
+    /// Whether jumps of the y offset are spread over a few frames
+    pub smooth_scroll: bool,
+    /// Lines the y offset moves by each frame of an ongoing smooth scroll
+    scroll_step: Option<usize>,
+    /// Cursor position at which the view was last scrolled sideways by hand.
+    /// The x offset doesn't follow the cursor until it moves away from it.
+    panned_at: Option<BufferPosition>,
+    /// Highlight of the selected text, rendered along with the search results
+    pub selection: Option<Highlight>,
+    /// Whether line numbers are displayed in a gutter left of the buffer
+    pub line_numbers: bool,
+    /// Whether the gutter numbers lines by their distance from the cursor's
+    /// line, which shows its own number
+    pub relative_line_numbers: bool,
+    /// Separator and padding of the gutter
+    pub gutter_style: GutterStyle,
+    /// Marked lines of the buffer, in ascending order. If there are any, the
+    /// gutter has a column in which they're marked with a glyph.
+    pub line_marks: Vec<usize>,
+    /// Columns taken up by the gutter in the last rendered frame, which is
+    /// wide enough for the marks column and the largest visible line number
+    /// along with the padding and the separator around them
+    gutter_width: u16,
 }
 
 #[allow(dead_code)]
@@ -240,12 +601,175 @@ impl BufferDisplayState {
         BufferDisplayState {
             offset,
             highlight_state: HighlightState::default(),
+            smooth_scroll: false,
+            scroll_step: None,
+            panned_at: None,
+            selection: None,
+            line_numbers: false,
+            relative_line_numbers: false,
+            gutter_style: GutterStyle::default(),
+            line_marks: Vec::new(),
+            gutter_width: 0,
         }
     }
 
+    /// Scrolls the view `columns` to the right without moving the cursor
+    /// at `cursor`. The offset is clamped to the visible lines when rendering.
+    pub fn scroll_right(&mut self, columns: usize, cursor: Option<BufferPosition>) {
+        self.offset.x = self.offset.x.saturating_add(columns);
+        self.panned_at = cursor;
+    }
+
+    /// Scrolls the view `columns` to the left without moving the cursor at `cursor`
+    pub fn scroll_left(&mut self, columns: usize, cursor: Option<BufferPosition>) {
+        self.offset.x = self.offset.x.saturating_sub(columns);
+        self.panned_at = cursor;
+    }
+
+    /// Returns the columns taken up by the gutter in the last rendered frame
+    pub fn gutter_width(&self) -> u16 {
+        self.gutter_width
+    }
+
+    /// Returns the part of `area` in which the text is rendered, right of the gutter
+    fn text_area(&self, area: Rect) -> Rect {
+        let gutter_width = self.gutter_width.min(area.width);
+        Rect {
+            x: area.x + gutter_width,
+            width: area.width - gutter_width,
+            ..area
+        }
+    }
+
+    /// Returns the columns of the gutter taken up by the line numbers, wide enough
+    /// for the largest line number visible in `area` out of `line_count` lines
+    fn line_number_digits(&self, area: Rect, line_count: usize) -> usize {
+        if !self.line_numbers {
+            return 0;
+        }
+        let last_visible = line_count.min(self.offset.y + area.height as usize);
+        last_visible.max(1).to_string().len()
+    }
+
+    /// Sizes the gutter for the marks column and the largest line number
+    /// visible in `area`, out of the buffer's `line_count` lines, along with
+    /// the configured padding and separator
+    fn update_gutter_width(&mut self, area: Rect, line_count: usize) {
+        let marks = usize::from(!self.line_marks.is_empty());
+        let digits = self.line_number_digits(area, line_count);
+        let style = self.gutter_style;
+        self.gutter_width = if marks + digits > 0 {
+            let separator = usize::from(style.separator.is_some());
+            let width = style.padding_left + marks + digits + style.padding_right + separator;
+            width.min(area.width as usize) as u16
+        } else {
+            0
+        };
+    }
+
+    /// Renders the glyphs of the marked lines and the 1-based numbers of the
+    /// visible lines, right-aligned in the gutter. With relative line numbers,
+    /// lines other than the one at `cursor_line` show their distance from it.
+    fn render_gutter(
+        &self,
+        area: Rect,
+        buf: &mut Buffer,
+        line_count: usize,
+        cursor_line: Option<usize>,
+    ) {
+        if self.gutter_width == 0 {
+            return;
+        }
+        let digits = self.line_number_digits(area, line_count);
+        let gutter_end = area.x + self.gutter_width;
+        let visible_lines = self.offset.y..line_count.min(self.offset.y + area.height as usize);
+        for (row, line) in visible_lines.enumerate() {
+            let y = area.y + row as u16;
+            let mut x = area.x.saturating_add(self.gutter_style.padding_left as u16);
+            if !self.line_marks.is_empty() && x < gutter_end {
+                if self.line_marks.binary_search(&line).is_ok() {
+                    buf.set_string(x, y, LINE_MARK_GLYPH, Style::default().fg(LINE_MARK_FG));
+                }
+                x += 1;
+            }
+            if digits > 0 {
+                let number = match cursor_line {
+                    Some(cursor_line) if self.relative_line_numbers && line != cursor_line => {
+                        line.abs_diff(cursor_line)
+                    }
+                    _ => line + 1,
+                };
+                buf.set_stringn(
+                    x,
+                    y,
+                    format!("{number:>digits$}"),
+                    gutter_end.saturating_sub(x) as usize,
+                    Style::default().fg(LINE_NUMBER_FG),
+                );
+            }
+            if let Some(separator) = self.gutter_style.separator {
+                buf.set_string(
+                    gutter_end - 1,
+                    y,
+                    separator.to_string(),
+                    Style::default().fg(LINE_NUMBER_FG),
+                );
+            }
+        }
+    }
+
+    /// Marks the part of the buffer from `start` up to `end` which is visible
+    /// in `area`, e.g. the tag matching the one under the cursor
+    pub fn render_matching_tag(
+        &self,
+        area: Rect,
+        buf: &mut Buffer,
+        start: BufferPosition,
+        end: BufferPosition,
+    ) {
+        let area = self.text_area(area);
+        let visible_lines = self.offset.y..self.offset.y + area.height as usize;
+        for line in (start.line..=end.line).filter(|line| visible_lines.contains(line)) {
+            let from = if line == start.line { start.offset } else { 0 };
+            let to = if line == end.line {
+                end.offset
+            } else {
+                usize::MAX
+            };
+            let from = from.saturating_sub(self.offset.x);
+            let to = to.saturating_sub(self.offset.x).min(area.width as usize);
+            if from >= to {
+                continue;
+            }
+
+            let marked = Rect::new(
+                area.x + from as u16,
+                area.y + (line - self.offset.y) as u16,
+                (to - from) as u16,
+                1,
+            );
+            buf.set_style(marked, Style::default().bg(MATCHING_TAG_BG));
+        }
+    }
+
+    /// Keeps a hand-scrolled x offset from going past the point where the end
+    /// of the longest visible line is at the right edge of the area
+    fn clamp_x_offset(&mut self, area: Rect, contents: &str) {
+        let longest = buffer_lines(contents)
+            .skip(self.offset.y)
+            .take(area.height as usize)
+            .map(|line| line.chars().count())
+            .max()
+            .unwrap_or_default();
+        self.offset.x = self
+            .offset
+            .x
+            .min(longest.saturating_sub(area.width as usize));
+    }
+
     /// Updates the x offset of the buffer so that the cursor is always visible
     pub fn update_x_offset(&mut self, area: Rect, cursor_offset_x: usize) {
-        let too_far_right = cursor_offset_x as u16 >= self.offset.x as u16 + area.width;
+        let too_far_right = cursor_offset_x >= self.offset.x + area.width as usize;
         if too_far_right {
             self.offset.x = cursor_offset_x
                 .saturating_sub(area.width as usize)
@@ -256,73 +780,113 @@ impl BufferDisplayState {
         self.offset.x = self.offset.x.min(cursor_offset_x);
     }
 
-    /// Updates the y offset of the buffer so that the cursor is always visible
+    /// Updates the y offset of the buffer so that the cursor is always visible.
+    /// With smooth scrolling, jumps of more than a line only take a step towards
+    /// it, and the rest is done by the next frames.
     pub fn update_y_offset(&mut self, area: Rect, cursor_line: usize) {
-        let too_far_down = cursor_line as u16 >= self.offset.y as u16 + area.height;
+        let mut target = self.offset.y;
+        let too_far_down = cursor_line >= target + area.height as usize;
         if too_far_down {
-            self.offset.y = cursor_line
+            target = cursor_line
                 .saturating_sub(area.height as usize)
                 .saturating_add(1);
         }
 
         // Ensure offset.y is never greater than cursor_y
-        self.offset.y = self.offset.y.min(cursor_line);
+        target = target.min(cursor_line);
+
+        let distance = target.abs_diff(self.offset.y);
+        if !self.smooth_scroll || distance <= 1 {
+            self.offset.y = target;
+            self.scroll_step = None;
+            return;
+        }
+
+        let step = *self
+            .scroll_step
+            .get_or_insert(distance.div_ceil(SMOOTH_SCROLL_FRAMES));
+        if target > self.offset.y {
+            self.offset.y += step.min(distance);
+        } else {
+            self.offset.y -= step.min(distance);
+        }
+        if self.offset.y == target {
+            self.scroll_step = None;
+        }
     }
 
-    /// Shifts the content of the buffer down by the offset and returns the resulting string.
-    /// Basically removes the first self.offset.y lines and joins the remaining ones.
-    fn shift_contents_down(&mut self, contents: String) -> String {
-        contents
-            .lines()
-            .skip(self.offset.y)
-            .collect::<Vec<&str>>()
-            .join("\n")
+    /// Returns whether a smooth scroll is in progress, so more frames
+    /// need to be rendered to finish it
+    pub fn is_scrolling(&self) -> bool {
+        self.scroll_step.is_some()
     }
 
-    /// Shifts the content of the buffer to the right by the offset and returns the resulting
-    /// string. Basically, takes every line and removes line[0:self.offset.x] from it, then
-    /// joins and returns them.
-    fn shift_contents_right(&mut self, contents: String) -> String {
-        contents
-            .lines()
+    /// Shifts the contents of the buffer down and to the right by the offset and returns
+    /// the part of them which fits in `area`. Only the visible window of the visible lines
+    /// is copied, so rendering doesn't slow down with extremely long lines.
+    fn shift_contents(&self, contents: &str, area: Rect) -> String {
+        buffer_lines(contents)
+            .skip(self.offset.y)
+            .take(area.height as usize)
             .map(|line| {
-                let line = line.chars().skip(self.offset.x).collect::<String>();
-                line
+                line.chars()
+                    .skip(self.offset.x)
+                    .take(area.width as usize)
+                    .collect::<String>()
             })
             .collect::<Vec<String>>()
             .join("\n")
     }
 
-    /// Shifts the contents of the buffer down and to the right by the offset and returns the
-    /// resulting string.
-    fn shift_contents(&mut self, contents: String) -> String {
-        let down_shifted = self.shift_contents_down(contents);
-        self.shift_contents_right(down_shifted)
-    }
-
     /// Adds highlights to the given contents and returns a Text widget with the highlights applied.
     pub fn add_highlights<'a>(&self, contents: &'a str, highlights: &[Highlight]) -> Text<'a> {
         let mut highlighted_content = vec![];
-        let contents_to_lines = contents.lines().collect::<Vec<&str>>();
+        let contents_to_lines = buffer_lines(contents).collect::<Vec<&str>>();
 
         for (line_index, line_text) in contents_to_lines.iter().enumerate() {
             let mut line = Vec::new();
             let mut current_pos = 0;
 
-            // Find all highlights in this line, considering the offset
+            let buffer_line = line_index + self.offset.y;
+
+            // Find all highlights covering this line, considering the offset.
+            // A highlight spanning multiple lines covers the whole of the lines
+            // between its first and last one.
             for highlight in highlights
                 .iter()
-                .filter(|h| h.start.line == line_index + self.offset.y)
+                .filter(|h| h.start.line <= buffer_line && buffer_line <= h.end.line)
             {
-                let highlight_start = highlight.start.offset.saturating_sub(self.offset.x);
-                let highlight_end = (highlight_start + highlight.length).min(line_text.len());
+                let start = if highlight.start.line == buffer_line {
+                    highlight.start.offset
+                } else {
+                    0
+                };
+                let end = if highlight.end.line == buffer_line {
+                    highlight.end.offset
+                } else {
+                    usize::MAX
+                };
+
+                // Offsets count graphemes, while the line is sliced by bytes
+                let highlight_start =
+                    byte_index_of_grapheme(line_text, start.saturating_sub(self.offset.x))
+                        .max(current_pos);
+                let highlight_end =
+                    byte_index_of_grapheme(line_text, end.saturating_sub(self.offset.x));
+                if highlight_start >= highlight_end {
+                    continue;
+                }
 
                 if highlight_start > current_pos {
                     // Add unhighlighted text before the highlight
                     line.push(Span::raw(&line_text[current_pos..highlight_start]));
                 }
 
-                let highlight_bg = if highlight.is_selected {
+                let highlight_bg = if highlight.is_replace_target {
+                    HIGHLIGHT_BG_REPLACE_TARGET
+                } else if highlight.is_text_selection {
+                    SELECTION_BG
+                } else if highlight.is_selected {
                     HIGHLIGHT_BG_SELECTED
                 } else {
                     HIGHLIGHT_BG_UNSELECTED
@@ -351,16 +915,144 @@ impl BufferDisplayState {
         Text::from(highlighted_content)
     }
 
+    /// Returns the rendered search results along with the selection, ordered
+    /// by where they start as `add_highlights` expects
+    fn highlights_with_selection(&self) -> Vec<Highlight> {
+        let mut highlights = self.highlight_state.highlights.clone();
+        highlights.extend(self.selection.clone());
+        highlights.sort_by_key(|highlight| (highlight.start.line, highlight.start.offset));
+        highlights
+    }
+
     /// Prepares a paragraph widget with the given contents, applying highlights if present.
+    /// Control characters are displayed as visible symbols, so that they can't mess up
+    /// the terminal.
     fn prepare_paragraph_widget<'a>(&mut self, contents: &'a str) -> Paragraph<'a> {
-        let paragraph_widget = if !self.highlight_state.highlights.is_empty() {
-            let text_widget = self.add_highlights(contents, &self.highlight_state.highlights);
-            Paragraph::new(text_widget)
+        let highlights = self.highlights_with_selection();
+        let mut text_widget = if !highlights.is_empty() {
+            self.add_highlights(contents, &highlights)
         } else {
-            let text_widget = Text::from(contents);
-            Paragraph::new(text_widget)
+            Text::from(contents)
         };
-        paragraph_widget
+
+        for span in text_widget
+            .lines
+            .iter_mut()
+            .flat_map(|line| line.spans.iter_mut())
+        {
+            if let Some(visible) = visible_control_characters(&span.content) {
+                span.content = visible.into();
+            }
+        }
+        Paragraph::new(text_widget)
+    }
+}
+
+/// Returns the byte index in `line` at which the grapheme at `offset` starts,
+/// or the length of the line if it has fewer graphemes
+fn byte_index_of_grapheme(line: &str, offset: usize) -> usize {
+    line.grapheme_indices(true)
+        .nth(offset)
+        .map_or(line.len(), |(index, _)| index)
+}
+
+/// Replaces the control characters in `text` with the symbols standing for them,
+/// e.g. `␛` for an escape. The symbols take a single column just like the characters
+/// they replace are counted as, so the cursor stays where it's expected. Tabs are left
+/// as they are, as they're part of regular indentation. Returns None if there are no
+/// control characters to replace.
+fn visible_control_characters(text: &str) -> Option<String> {
+    let is_replaced = |c: char| c.is_control() && c != '\t';
+    if !text.chars().any(is_replaced) {
+        return None;
+    }
+
+    let visible = text
+        .chars()
+        .map(|c| match c as u32 {
+            _ if !is_replaced(c) => c,
+            // The Control Pictures block follows the order of the C0 controls
+            code @ 0x00..=0x1f => char::from_u32(0x2400 + code).unwrap_or(c),
+            0x7f => '\u{2421}',
+            _ => char::REPLACEMENT_CHARACTER,
+        })
+        .collect();
+    Some(visible)
+}
+
+/// Information about the current buffer which can be shown in the status bar
+pub struct StatusBarFields {
+    pub state: EditorStateSummary,
+    /// Lines added and removed since the buffer was last saved
+    pub unsaved_changes: Option<(usize, usize)>,
+}
+
+impl StatusBarFields {
+    /// Fills in the placeholders of a status bar format, returning the left- and
+    /// right-aligned parts of the status bar. Placeholders which don't apply, e.g.
+    /// the cursor position when no buffer is open, are left empty.
+    pub fn format(&self, format: &str) -> (String, String) {
+        let state = &self.state;
+        let (line, col, percent) = match state.cursor {
+            Some(cursor) => (
+                (cursor.line + 1).to_string(),
+                (cursor.offset + 1).to_string(),
+                format!("{}%", (cursor.line + 1) * 100 / state.line_count.max(1)),
+            ),
+            None => Default::default(),
+        };
+        let changes = match self.unsaved_changes {
+            Some((added, removed)) => format!("+{added} -{removed}"),
+            None => String::new(),
+        };
+
+        let value = |placeholder: &str| -> Option<&str> {
+            Some(match placeholder {
+                "filename" => &state.filename,
+                "modified" if state.is_modified => "*",
+                "read_only" if state.is_read_only => "[RO]",
+                "modified" | "read_only" => "",
+                "line" => &line,
+                "col" => &col,
+                "percent" => &percent,
+                "encoding" => "utf-8",
+                "changes" => &changes,
+                "mode" => state.mode.name(),
+                _ => return None,
+            })
+        };
+
+        // Placeholders are expanded in a single pass, so that braces in the substituted
+        // text, e.g. in a filename, aren't expanded again. Fields left empty mustn't
+        // leave the spaces around them behind at the ends of the section.
+        let fill = |section: &str| {
+            let mut filled = String::new();
+            let mut rest = section;
+            while let Some(start) = rest.find('{') {
+                filled.push_str(&rest[..start]);
+                rest = &rest[start..];
+                let expanded = rest
+                    .find('}')
+                    .and_then(|end| Some((value(&rest[1..end])?, end)));
+                match expanded {
+                    Some((text, end)) => {
+                        filled.push_str(text);
+                        rest = &rest[end + 1..];
+                    }
+                    None => {
+                        filled.push('{');
+                        rest = &rest[1..];
+                    }
+                }
+            }
+            filled.push_str(rest);
+            filled.trim().to_string()
+        };
+
+        match format.split_once(STATUS_BAR_ALIGN_RIGHT) {
+            Some((left, right)) => (fill(left), fill(right)),
+            None => (fill(format), String::new()),
+        }
     }
 }
 
@@ -390,17 +1082,33 @@ impl StatefulWidget for BufferDisplayWidget<'_> {
         buf: &mut ratatui::prelude::Buffer,
         state: &mut Self::State,
     ) {
-        // Update offsets to keep cursor visible
+        // The gutter's width depends on the lines scrolled to, while the
+        // text area left for the x offset depends on the gutter's width
         if let Some(pos) = self.cursor_position {
-            state.update_x_offset(area, pos.offset);
             state.update_y_offset(area, pos.line);
         }
+        let line_count = buffer_lines(self.buffer_contents).count();
+        state.update_gutter_width(area, line_count);
+        let text_area = state.text_area(area);
+
+        // Update offsets to keep cursor visible, unless the view has been
+        // scrolled sideways by hand and the cursor hasn't moved since
+        if let Some(pos) = self.cursor_position {
+            if state.panned_at == Some(pos) {
+                state.clamp_x_offset(text_area, self.buffer_contents);
+            } else {
+                state.panned_at = None;
+                state.update_x_offset(text_area, pos.offset);
+            }
+        }
         // Shift contents based on offset
-        let shifted_contents = state.shift_contents(self.buffer_contents.to_string());
+        let shifted_contents = state.shift_contents(self.buffer_contents, text_area);
         // Render the text using Paragraph
 
         let paragraph_widget = state.prepare_paragraph_widget(&shifted_contents);
-        paragraph_widget.render(area, buf);
+        paragraph_widget.render(text_area, buf);
+        let cursor_line = self.cursor_position.map(|pos| pos.line);
+        state.render_gutter(area, buf, line_count, cursor_line);
     }
 }
 
@@ -415,47 +1123,445 @@ impl StatefulWidget for FileInput {
     type State = FileInputState;
 
     fn render(self, area: Rect, buf: &mut Buffer, state: &mut Self::State) {
+        let title = match state.role {
+            FileInputRole::GetRevisionPath => "Enter revision:relative file path",
+            FileInputRole::GetCodePoint => "Enter code point, e.g. U+1F600",
+            FileInputRole::GetLineFilter(LineFilterMode::KeepMatch) => "Keep in each line: regex",
+            FileInputRole::GetLineFilter(LineFilterMode::RemoveMatch) => {
+                "Remove from each line: regex"
+            }
+            _ => "Enter relative file path",
+        };
         let widget = widgets::Paragraph::new(state.input.to_text()).block(
             widgets::Block::new()
                 .borders(widgets::Borders::all())
-                .title("Enter relative file path"),
+                .title(title),
         );
         widget.render(area, buf)
     }
 }
 
+/// A widget for displaying the search input, optionally with a message
+/// about the search (e.g. that it wrapped around) in its top right corner.
+/// The input is colored red if the searched query has no matches.
 #[derive(Default)]
-pub struct SearchInput {}
+pub struct SearchInput {
+    message: Option<String>,
+    match_count: Option<usize>,
+    options: SearchOptions,
+}
+
+impl SearchInput {
+    pub fn new(
+        message: Option<String>,
+        match_count: Option<usize>,
+        options: SearchOptions,
+    ) -> Self {
+        SearchInput {
+            message,
+            match_count,
+            options,
+        }
+    }
+
+    /// Returns the title of the input, listing the enabled search options
+    fn title(&self) -> String {
+        let options: Vec<&str> = [
+            (self.options.case_insensitive, "ignoring case"),
+            (self.options.whole_word, "whole words"),
+        ]
+        .into_iter()
+        .filter_map(|(enabled, name)| enabled.then_some(name))
+        .collect();
+
+        if options.is_empty() {
+            "Search for: ".to_string()
+        } else {
+            format!("Search for ({}): ", options.join(", "))
+        }
+    }
+}
 
 impl StatefulWidget for SearchInput {
     type State = Input;
 
     fn render(self, area: Rect, buf: &mut Buffer, state: &mut Self::State) {
-        let widget = widgets::Paragraph::new(state.to_text()).block(
+        let mut block = widgets::Block::new()
+            .borders(widgets::Borders::all())
+            .title(self.title());
+        if self.match_count == Some(0) {
+            let style = Style::default().fg(SEARCH_FG_NO_MATCHES);
+            block = block.border_style(style).title_style(style);
+        }
+        if let Some(message) = self.message {
+            block = block.title(Line::from(message).right_aligned());
+        }
+
+        let widget = widgets::Paragraph::new(state.to_text()).block(block);
+        widget.render(area, buf)
+    }
+}
+
+/// A widget for displaying the replacement entered under the search input
+#[derive(Default)]
+pub struct ReplaceInput {}
+
+impl StatefulWidget for ReplaceInput {
+    type State = ReplaceInputState;
+
+    fn render(self, area: Rect, buf: &mut Buffer, state: &mut Self::State) {
+        let widget = widgets::Paragraph::new(state.input.to_text()).block(
             widgets::Block::new()
                 .borders(widgets::Borders::all())
-                .title("Search for: "),
+                .title("Replace with: "),
         );
         widget.render(area, buf)
     }
 }
 
+/// Number of bytes displayed in a single row of the hex view
+pub const HEX_BYTES_PER_ROW: usize = 16;
+
+/// Width of the offset column of the hex view, including the separating spaces
+const HEX_OFFSET_WIDTH: u16 = 10;
+
+/// State of the hex view - the shown bytes, the byte under the cursor and the
+/// first displayed row
+#[derive(Default, Debug, Clone, PartialEq)]
+pub struct HexViewState {
+    pub bytes: Vec<u8>,
+    pub cursor: usize,
+    pub first_row: usize,
+}
+
+impl HexViewState {
+    pub fn new(bytes: Vec<u8>) -> Self {
+        Self {
+            bytes,
+            cursor: 0,
+            first_row: 0,
+        }
+    }
+
+    /// Move the cursor one byte to the left, if possible
+    pub fn move_left(&mut self) {
+        self.cursor = self.cursor.saturating_sub(1);
+    }
+
+    /// Move the cursor one byte to the right, if possible
+    pub fn move_right(&mut self, len: usize) {
+        self.cursor = min(self.cursor + 1, len.saturating_sub(1));
+    }
+
+    /// Move the cursor one row up, if possible
+    pub fn move_up(&mut self) {
+        if self.cursor >= HEX_BYTES_PER_ROW {
+            self.cursor -= HEX_BYTES_PER_ROW;
+        }
+    }
+
+    /// Move the cursor one row down, if possible
+    pub fn move_down(&mut self, len: usize) {
+        if self.cursor + HEX_BYTES_PER_ROW < len {
+            self.cursor += HEX_BYTES_PER_ROW;
+        }
+    }
+
+    /// Move the cursor to the first byte of its row
+    pub fn move_to_start_of_row(&mut self) {
+        self.cursor -= self.cursor % HEX_BYTES_PER_ROW;
+    }
+
+    /// Move the cursor to the last byte of its row
+    pub fn move_to_end_of_row(&mut self, len: usize) {
+        let row_end = self.cursor - self.cursor % HEX_BYTES_PER_ROW + HEX_BYTES_PER_ROW - 1;
+        self.cursor = min(row_end, len.saturating_sub(1));
+    }
+
+    /// Updates the first displayed row so that the cursor is always visible
+    fn update_first_row(&mut self, area: Rect) {
+        let cursor_row = self.cursor / HEX_BYTES_PER_ROW;
+        let height = area.height as usize;
+
+        if cursor_row >= self.first_row + height {
+            self.first_row = (cursor_row + 1).saturating_sub(height);
+        }
+        self.first_row = self.first_row.min(cursor_row);
+    }
+
+    /// Calculate the position of the cursor, placed over the hex
+    /// representation of the byte it's on
+    pub fn cursor_render_position(&self, area: Rect) -> TerminalPosition {
+        let row = (self.cursor / HEX_BYTES_PER_ROW).saturating_sub(self.first_row) as u16;
+        let column = (self.cursor % HEX_BYTES_PER_ROW) as u16 * 3;
+
+        TerminalPosition::new(area.x + HEX_OFFSET_WIDTH + column, area.y + row)
+    }
+}
+
+/// Formats a row of a hex dump: the offset of its first byte, the bytes
+/// in hex and their ASCII representation, with non-printable bytes as dots
+pub fn hex_dump_row(offset: usize, bytes: &[u8]) -> String {
+    let hex: String = bytes.iter().map(|b| format!("{b:02x} ")).collect();
+    let ascii: String = bytes
+        .iter()
+        .map(|b| {
+            if b.is_ascii_graphic() || *b == b' ' {
+                *b as char
+            } else {
+                '.'
+            }
+        })
+        .collect();
+
+    format!(
+        "{offset:08x}  {hex:<width$} |{ascii}|",
+        width = HEX_BYTES_PER_ROW * 3
+    )
+}
+
+/// Widget displaying raw bytes as a classic hex dump. A read-only
+/// alternative to `BufferDisplayWidget`.
+pub struct HexDumpWidget;
+
+impl StatefulWidget for HexDumpWidget {
+    type State = HexViewState;
+
+    fn render(self, area: Rect, buf: &mut Buffer, state: &mut Self::State) {
+        state.update_first_row(area);
+
+        let lines: Vec<Line> = state
+            .bytes
+            .chunks(HEX_BYTES_PER_ROW)
+            .enumerate()
+            .skip(state.first_row)
+            .take(area.height as usize)
+            .map(|(row, bytes)| Line::from(hex_dump_row(row * HEX_BYTES_PER_ROW, bytes)))
+            .collect();
+
+        Paragraph::new(lines).render(area, buf);
+    }
+}
+
+/// State of the diff view - the diffed lines and the first displayed one
+#[derive(Default, Debug, Clone, PartialEq)]
+pub struct DiffViewState {
+    pub lines: Vec<DiffLine>,
+    pub first_line: usize,
+}
+
+impl DiffViewState {
+    pub fn new(lines: Vec<DiffLine>) -> Self {
+        Self {
+            lines,
+            first_line: 0,
+        }
+    }
+
+    /// Scroll the view one line up, if possible
+    pub fn scroll_up(&mut self) {
+        self.first_line = self.first_line.saturating_sub(1);
+    }
+
+    /// Scroll the view one line down, if possible
+    pub fn scroll_down(&mut self) {
+        self.first_line = min(self.first_line + 1, self.lines.len().saturating_sub(1));
+    }
+}
+
+/// Converts a line of a diff to a line prefixed and colored
+/// according to whether it was added, removed or left unchanged
+fn diff_line_to_line(diff_line: &DiffLine) -> Line<'_> {
+    match diff_line {
+        DiffLine::Unchanged(line) => Line::from(format!("  {line}")),
+        DiffLine::Added(line) => {
+            Line::styled(format!("+ {line}"), Style::default().fg(DIFF_FG_ADDED))
+        }
+        DiffLine::Removed(line) => {
+            Line::styled(format!("- {line}"), Style::default().fg(DIFF_FG_REMOVED))
+        }
+    }
+}
+
+/// Widget displaying an inline diff of the current buffer against a file
+pub struct DiffWidget;
+
+impl StatefulWidget for DiffWidget {
+    type State = DiffViewState;
+
+    fn render(self, area: Rect, buf: &mut Buffer, state: &mut Self::State) {
+        let lines: Vec<Line> = state
+            .lines
+            .iter()
+            .skip(state.first_line)
+            .take(area.height as usize)
+            .map(diff_line_to_line)
+            .collect();
+
+        Paragraph::new(lines).render(area, buf);
+    }
+}
+
 #[cfg(test)]
 mod tests {
     use crate::{
-        test_util::ui::{n_spaces, nth_line_from_terminal_buffer, vertical_border},
-        ui::{BufferDisplayState, FileInputRole, FileInputState},
+        config::GutterStyle,
+        pike::DiffLine,
+        test_util::{
+            cursor::assert_buffer_cursor_render_pos,
+            ui::{n_spaces, nth_line_from_terminal_buffer, vertical_border},
+        },
+        ui::{
+            hex_dump_row, BufferDisplayOffset, BufferDisplayState, BufferDisplayWidget,
+            DiffViewState, DiffWidget, FileInputRole, FileInputState, HexDumpWidget, HexViewState,
+            StatusBarFields, UIState,
+        },
     };
     use ratatui::style::{Color, Modifier, Style};
-    use ratatui::{buffer::Buffer, layout::Rect, widgets::StatefulWidget};
+    use ratatui::{
+        buffer::Buffer,
+        layout::{Position as TerminalPosition, Rect},
+        widgets::StatefulWidget,
+    };
     use scribe::buffer::Position as BufferPosition;
     use tui_input::InputRequest;
 
-    use crate::pike::Highlight;
+    use crate::pike::{EditorMode, EditorStateSummary, Highlight, SearchOptions, Selection};
 
-    use super::FileInput;
+    use super::{
+        FileInput, Picker, PickerAction, PickerWidget, SearchInput, HIGHLIGHT_BG_UNSELECTED,
+        MATCHING_TAG_BG, PICKER_MARKED_FG, PICKER_SELECTED_BG, SEARCH_FG_NO_MATCHES, SELECTION_BG,
+    };
     // TODO: could move some BufferDisplay tests here for clarity
 
+    #[test]
+    fn search_input_is_red_without_matches() {
+        let area = Rect::new(0, 0, 20, 3);
+        for (match_count, is_red) in [(None, false), (Some(2), false), (Some(0), true)] {
+            let mut buf = Buffer::empty(area);
+            let mut input = "foo".into();
+            SearchInput::new(None, match_count, SearchOptions::default())
+                .render(area, &mut buf, &mut input);
+
+            for position in [(0, 0), (0, 1), (1, 0)] {
+                let fg = buf.cell(position).unwrap().fg;
+                assert_eq!(fg == SEARCH_FG_NO_MATCHES, is_red, "{match_count:?}");
+            }
+        }
+    }
+
+    #[test]
+    fn search_input_title_lists_enabled_options() {
+        let area = Rect::new(0, 0, 50, 3);
+        let mut buf = Buffer::empty(area);
+        let mut input = "foo".into();
+        let options = SearchOptions {
+            case_insensitive: true,
+            whole_word: true,
+        };
+        SearchInput::new(None, None, options).render(area, &mut buf, &mut input);
+
+        let title: String = (1..49)
+            .map(|x| buf.cell((x, 0)).unwrap().symbol())
+            .collect();
+        assert!(title.starts_with("Search for (ignoring case, whole words): "));
+    }
+
+    /// Picker of the given words, keeping those which contain the query
+    fn picker_of(words: &'static [&'static str]) -> Picker<String> {
+        Picker::new(
+            "Words",
+            |query: &str| {
+                words
+                    .iter()
+                    .filter(|word| word.contains(query))
+                    .map(|word| word.to_string())
+                    .collect()
+            },
+            |words: Vec<&String>| {
+                words
+                    .into_iter()
+                    .map(|word| PickerAction::OpenFile(word.into()))
+                    .collect()
+            },
+        )
+    }
+
+    #[test]
+    fn picker_renders_results_above_query() {
+        let area = Rect::new(0, 0, 12, 6);
+        let mut buf = Buffer::empty(area);
+        let mut picker = picker_of(&["apple", "banana", "cherry"]);
+        picker.handle(InputRequest::InsertChar('a'));
+        PickerWidget::default().render(area, &mut buf, &mut picker);
+
+        assert!(nth_line_from_terminal_buffer(&buf, 0).contains("Words"));
+        assert_eq!(nth_line_from_terminal_buffer(&buf, 1), "apple       ");
+        assert_eq!(nth_line_from_terminal_buffer(&buf, 2), "banana      ");
+        assert_eq!(
+            nth_line_from_terminal_buffer(&buf, 4),
+            vertical_border() + "a" + &n_spaces(9) + &vertical_border()
+        );
+        assert_eq!(buf.cell((0, 1)).unwrap().bg, PICKER_SELECTED_BG);
+        assert_ne!(buf.cell((0, 2)).unwrap().bg, PICKER_SELECTED_BG);
+    }
+
+    #[test]
+    fn picker_selection_moves_within_results() {
+        let mut picker = picker_of(&["apple", "banana", "cherry"]);
+        picker.move_selection_up();
+        assert_eq!(picker.selected().map(String::as_str), Some("apple"));
+
+        picker.move_selection_down();
+        picker.move_selection_down();
+        picker.move_selection_down();
+        assert_eq!(
+            picker.confirm(),
+            vec![PickerAction::OpenFile("cherry".into())]
+        );
+
+        // Typing in the query starts over from its first match
+        picker.handle(InputRequest::InsertChar('n'));
+        assert_eq!(picker.selected().map(String::as_str), Some("banana"));
+
+        picker.handle(InputRequest::InsertChar('x'));
+        assert_eq!(picker.confirm(), Vec::new());
+    }
+
+    #[test]
+    fn picker_confirms_all_marked_items() {
+        let mut picker = picker_of(&["apple", "banana", "cherry"]);
+        picker.move_selection_down();
+        picker.move_selection_down();
+        picker.toggle_mark();
+        picker.move_selection_up();
+        picker.move_selection_up();
+        picker.toggle_mark();
+        assert_eq!(picker.selected().map(String::as_str), Some("banana"));
+        assert_eq!(picker.marked(), vec!["apple", "cherry"]);
+        assert_eq!(
+            picker.confirm(),
+            vec![
+                PickerAction::OpenFile("apple".into()),
+                PickerAction::OpenFile("cherry".into()),
+            ]
+        );
+
+        let area = Rect::new(0, 0, 12, 6);
+        let mut buf = Buffer::empty(area);
+        PickerWidget::default().render(area, &mut buf, &mut picker);
+        assert_eq!(nth_line_from_terminal_buffer(&buf, 1), "+ apple     ");
+        assert_eq!(nth_line_from_terminal_buffer(&buf, 2), "  banana    ");
+        assert_eq!(buf.cell((2, 1)).unwrap().fg, PICKER_MARKED_FG);
+
+        // Unmarking an item leaves the other marks, a new query clears them
+        picker.move_selection_up();
+        picker.toggle_mark();
+        assert_eq!(picker.marked(), vec!["cherry"]);
+        picker.handle(InputRequest::InsertChar('a'));
+        assert!(picker.marked().is_empty());
+    }
+
     #[test]
     fn file_input_displays_input() {
         let mut input_state: FileInputState = ("hello", FileInputRole::GetSavePath).into();
@@ -486,6 +1592,467 @@ mod tests {
         );
     }
 
+    #[test]
+    fn cursor_is_rendered_relative_to_the_scrolled_buffer() {
+        let mut ui_state = UIState::default();
+        let area = Rect::new(0, 0, 5, 2);
+
+        assert_buffer_cursor_render_pos(&mut ui_state, area, None, (0, 0));
+        assert_buffer_cursor_render_pos(
+            &mut ui_state,
+            area,
+            Some(BufferPosition { line: 1, offset: 3 }),
+            (3, 1),
+        );
+
+        // Moving past the visible area scrolls the buffer, keeping the cursor on its edge
+        assert_buffer_cursor_render_pos(
+            &mut ui_state,
+            area,
+            Some(BufferPosition { line: 4, offset: 7 }),
+            (4, 1),
+        );
+        assert_eq!(ui_state.buffer_state.offset.x, 3);
+        assert_eq!(ui_state.buffer_state.offset.y, 3);
+    }
+
+    /// UIState with `n` unselected highlights in consecutive lines
+    fn ui_state_with_highlights(n: usize) -> UIState {
+        let mut ui_state = UIState::default();
+        let highlights = (0..n)
+            .map(|line| Highlight {
+                start: BufferPosition { line, offset: 0 },
+                end: BufferPosition { line, offset: 1 },
+                is_selected: false,
+                is_replace_target: false,
+                is_text_selection: false,
+            })
+            .collect();
+        ui_state.update_highlights(highlights);
+        ui_state
+    }
+
+    #[test]
+    fn focus_next_highlight_reports_wrapping() {
+        let mut ui_state = ui_state_with_highlights(3);
+
+        assert!(!ui_state.focus_next_highlight());
+        assert!(!ui_state.focus_next_highlight());
+        assert_eq!(ui_state.buffer_state.highlight_state.focused, 2);
+
+        assert!(ui_state.focus_next_highlight());
+        assert_eq!(ui_state.buffer_state.highlight_state.focused, 0);
+
+        let selected: Vec<bool> = ui_state
+            .buffer_state
+            .highlight_state
+            .highlights
+            .iter()
+            .map(|h| h.is_selected)
+            .collect();
+        assert_eq!(selected, vec![true, false, false]);
+    }
+
+    #[test]
+    fn focus_prev_highlight_reports_wrapping() {
+        let mut ui_state = ui_state_with_highlights(3);
+
+        assert!(ui_state.focus_prev_highlight());
+        assert_eq!(ui_state.buffer_state.highlight_state.focused, 2);
+
+        assert!(!ui_state.focus_prev_highlight());
+        assert_eq!(ui_state.buffer_state.highlight_state.focused, 1);
+    }
+
+    #[test]
+    fn scrollbar_markers_are_proportional_to_highlighted_lines() {
+        let mut ui_state = UIState::default();
+        let highlights = [0, 5, 9]
+            .into_iter()
+            .map(|line| Highlight {
+                start: BufferPosition { line, offset: 0 },
+                end: BufferPosition { line, offset: 1 },
+                is_selected: false,
+                is_replace_target: false,
+                is_text_selection: false,
+            })
+            .collect();
+        ui_state.update_highlights(highlights);
+        ui_state.focus_highlight(1);
+
+        let area = Rect::new(0, 0, 3, 7);
+        let mut buffer = Buffer::empty(area);
+        ui_state
+            .buffer_state
+            .highlight_state
+            .render_scrollbar_markers(10, area, &mut buffer);
+
+        let backgrounds: Vec<Color> = (0..7).map(|y| buffer[(2, y)].bg).collect();
+        let unselected = Color::Rgb(240, 137, 48);
+        let selected = Color::Rgb(245, 206, 88);
+        assert_eq!(
+            backgrounds,
+            vec![
+                Color::Reset,
+                unselected,
+                Color::Reset,
+                selected,
+                Color::Reset,
+                unselected,
+                Color::Reset
+            ]
+        );
+        assert_eq!(buffer[(1, 3)].bg, Color::Reset);
+    }
+
+    #[test]
+    fn matching_tag_is_marked_where_visible() {
+        let state = BufferDisplayState::new(BufferDisplayOffset::new(2, 1));
+        let area = Rect::new(1, 1, 6, 3);
+        let mut buffer = Buffer::empty(Rect::new(0, 0, 8, 4));
+
+        // A tag spanning lines 1 to 2 of the buffer, partly scrolled out of view
+        state.render_matching_tag(
+            area,
+            &mut buffer,
+            BufferPosition { line: 1, offset: 4 },
+            BufferPosition { line: 2, offset: 4 },
+        );
+
+        let marked: Vec<(u16, u16)> = (0..4)
+            .flat_map(|y| (0..8).map(move |x| (x, y)))
+            .filter(|&(x, y)| buffer[(x, y)].bg == MATCHING_TAG_BG)
+            .collect();
+        assert_eq!(marked, vec![(3, 1), (4, 1), (5, 1), (6, 1), (1, 2), (2, 2)]);
+    }
+
+    #[test]
+    fn hex_dump_row_formats_offset_bytes_and_ascii() {
+        assert_eq!(
+            hex_dump_row(0x20, b"pike\0\n!"),
+            format!(
+                "00000020  70 69 6b 65 00 0a 21  {}|pike..!|",
+                " ".repeat(27)
+            )
+        );
+    }
+
+    #[test]
+    fn hex_view_cursor_moves_by_bytes_and_rows() {
+        let len = 40;
+        let mut state = HexViewState::default();
+
+        state.move_left();
+        assert_eq!(state.cursor, 0);
+        state.move_right(len);
+        assert_eq!(state.cursor, 1);
+        state.move_down(len);
+        state.move_down(len);
+        assert_eq!(state.cursor, 33);
+        // The last row is too short to move down from 33
+        state.move_down(len);
+        assert_eq!(state.cursor, 33);
+        state.move_to_end_of_row(len);
+        assert_eq!(state.cursor, 39);
+        state.move_to_start_of_row();
+        assert_eq!(state.cursor, 32);
+        state.move_up();
+        assert_eq!(state.cursor, 16);
+    }
+
+    #[test]
+    fn hex_view_scrolls_to_keep_cursor_visible() {
+        let area = Rect::new(0, 0, 80, 2);
+        let mut buffer = Buffer::empty(area);
+        let mut state = HexViewState {
+            bytes: vec![0; 100],
+            cursor: 50,
+            first_row: 0,
+        };
+
+        HexDumpWidget.render(area, &mut buffer, &mut state);
+
+        assert_eq!(state.first_row, 2);
+        assert_eq!(
+            state.cursor_render_position(area),
+            TerminalPosition::new(10 + 2 * 3, 1)
+        );
+        assert!(nth_line_from_terminal_buffer(&buffer, 0).starts_with("00000020"));
+    }
+
+    #[test]
+    fn diff_view_prefixes_and_colors_changed_lines() {
+        let area = Rect::new(0, 0, 10, 2);
+        let mut buffer = Buffer::empty(area);
+        let mut state = DiffViewState::new(vec![
+            DiffLine::Unchanged("same".to_string()),
+            DiffLine::Removed("old".to_string()),
+            DiffLine::Added("new".to_string()),
+        ]);
+        state.scroll_down();
+        state.scroll_down();
+        state.scroll_down();
+        state.scroll_up();
+
+        DiffWidget.render(area, &mut buffer, &mut state);
+
+        assert_eq!(nth_line_from_terminal_buffer(&buffer, 0), "- old     ");
+        assert_eq!(nth_line_from_terminal_buffer(&buffer, 1), "+ new     ");
+        assert_eq!(buffer[(2, 0)].fg, Color::Red);
+        assert_eq!(buffer[(2, 1)].fg, Color::Green);
+    }
+
+    #[test]
+    fn smooth_scroll_spreads_jumps_over_frames() {
+        let area = Rect::new(0, 0, 10, 10);
+        let mut state = BufferDisplayState::default();
+
+        state.update_y_offset(area, 39);
+        assert_eq!(state.offset.y, 30);
+        assert!(!state.is_scrolling());
+
+        state.smooth_scroll = true;
+        let mut offsets = vec![];
+        state.update_y_offset(area, 0);
+        while state.is_scrolling() {
+            offsets.push(state.offset.y);
+            state.update_y_offset(area, 0);
+        }
+        offsets.push(state.offset.y);
+        assert_eq!(offsets, vec![20, 10, 0]);
+
+        // Scrolling by a single line is never delayed
+        state.update_y_offset(area, 10);
+        assert_eq!(state.offset.y, 1);
+        assert!(!state.is_scrolling());
+    }
+
+    #[test]
+    fn status_bar_fields_fill_the_format() {
+        let mut fields = StatusBarFields {
+            state: EditorStateSummary {
+                filename: "main.rs".to_string(),
+                is_modified: true,
+                is_read_only: false,
+                cursor: Some(BufferPosition { line: 4, offset: 2 }),
+                line_count: 20,
+                selection_length: 0,
+                mode: EditorMode::Insert,
+            },
+            unsaved_changes: None,
+        };
+
+        assert_eq!(
+            fields.format("{filename}{modified} {read_only}%={line}:{col} {percent} {encoding}"),
+            ("main.rs*".to_string(), "5:3 25% utf-8".to_string())
+        );
+        assert_eq!(
+            fields.format("{filename} {line}"),
+            ("main.rs 5".to_string(), String::new())
+        );
+
+        fields.state.cursor = None;
+        fields.state.is_read_only = true;
+        assert_eq!(
+            fields.format("{read_only}%={line}:{col}"),
+            ("[RO]".to_string(), ":".to_string())
+        );
+
+        fields.state.mode = EditorMode::Normal;
+        assert_eq!(
+            fields.format("{mode} {filename}"),
+            ("NORMAL main.rs".to_string(), String::new())
+        );
+
+        fields.unsaved_changes = Some((12, 3));
+        assert_eq!(
+            fields.format("{filename}%={changes}"),
+            ("main.rs".to_string(), "+12 -3".to_string())
+        );
+
+        // Placeholders in the filename are shown as they are, as are unknown ones
+        fields.state.filename = "{line}{mode}.rs".to_string();
+        assert_eq!(
+            fields.format("{filename} {unknown} {"),
+            ("{line}{mode}.rs {unknown} {".to_string(), String::new())
+        );
+    }
+
+    #[test]
+    fn control_characters_are_rendered_visibly() {
+        let contents = "a\u{1b}[31mbc\0\nzażółć\u{7f}";
+        let area = Rect::new(0, 0, 12, 2);
+        let mut state = BufferDisplayState::default();
+        let mut buffer = Buffer::empty(area);
+
+        BufferDisplayWidget::new(contents, None).render(area, &mut buffer, &mut state);
+
+        assert_eq!(nth_line_from_terminal_buffer(&buffer, 0), "a␛[31mbc␀   ");
+        assert_eq!(nth_line_from_terminal_buffer(&buffer, 1), "zażółć␡     ");
+    }
+
+    #[test]
+    fn tab_indentation_is_not_rendered_as_a_control_character() {
+        let contents = "fn main() {\n\tlet a = 1;\u{1b}\n}";
+        let area = Rect::new(0, 0, 12, 3);
+        let mut state = BufferDisplayState::default();
+        let mut buffer = Buffer::empty(area);
+
+        BufferDisplayWidget::new(contents, None).render(area, &mut buffer, &mut state);
+
+        let line = nth_line_from_terminal_buffer(&buffer, 1);
+        assert!(!line.contains('␉'), "{line:?}");
+        assert!(line.contains("let a = 1;␛"), "{line:?}");
+    }
+
+    #[test]
+    fn line_number_gutter_grows_with_the_visible_line_numbers() {
+        let contents = "abcdef\n".repeat(11);
+        let area = Rect::new(0, 0, 6, 3);
+        let mut state = BufferDisplayState::default();
+        state.line_numbers = true;
+
+        let cursor = BufferPosition { line: 0, offset: 3 };
+        let mut buffer = Buffer::empty(area);
+        BufferDisplayWidget::new(&contents, Some(cursor)).render(area, &mut buffer, &mut state);
+        assert_eq!(state.gutter_width(), 2);
+        assert_eq!(nth_line_from_terminal_buffer(&buffer, 0), "1 abcd");
+        assert_eq!(nth_line_from_terminal_buffer(&buffer, 2), "3 abcd");
+
+        // Scrolling down to the last lines makes room for two digits
+        let cursor = BufferPosition {
+            line: 11,
+            offset: 0,
+        };
+        let mut buffer = Buffer::empty(area);
+        BufferDisplayWidget::new(&contents, Some(cursor)).render(area, &mut buffer, &mut state);
+        assert_eq!(state.gutter_width(), 3);
+        assert_eq!(nth_line_from_terminal_buffer(&buffer, 0), "10 abc");
+        assert_eq!(nth_line_from_terminal_buffer(&buffer, 2), "12    ");
+
+        let ui_state = UIState {
+            buffer_state: state,
+            ..Default::default()
+        };
+        assert_eq!(
+            ui_state.calculate_cursor_for_buffer(area, Some(cursor)),
+            TerminalPosition::new(3, 2)
+        );
+    }
+
+    #[test]
+    fn gutter_padding_and_separator_shift_the_text_and_cursor() {
+        let contents = "abc\ndef";
+        let area = Rect::new(0, 0, 10, 2);
+        let cursor = BufferPosition { line: 1, offset: 2 };
+        let cursor_column = |style: GutterStyle| {
+            let mut state = BufferDisplayState::default();
+            state.line_numbers = true;
+            state.gutter_style = style;
+            let mut buffer = Buffer::empty(area);
+            BufferDisplayWidget::new(contents, Some(cursor)).render(area, &mut buffer, &mut state);
+            let ui_state = UIState {
+                buffer_state: state,
+                ..Default::default()
+            };
+            let position = ui_state.calculate_cursor_for_buffer(area, Some(cursor));
+            (nth_line_from_terminal_buffer(&buffer, 1), position.x)
+        };
+
+        assert_eq!(
+            cursor_column(GutterStyle::default()),
+            ("2 def     ".to_string(), 4)
+        );
+        let padded = GutterStyle {
+            separator: None,
+            padding_left: 2,
+            padding_right: 3,
+        };
+        assert_eq!(cursor_column(padded), ("  2   def ".to_string(), 8));
+        let separated = GutterStyle {
+            separator: Some('│'),
+            padding_left: 1,
+            padding_right: 1,
+        };
+        assert_eq!(cursor_column(separated), (" 2 │def   ".to_string(), 6));
+    }
+
+    #[test]
+    fn relative_line_numbers_count_from_the_cursor_line() {
+        let contents = "abc\n".repeat(11);
+        let area = Rect::new(0, 0, 6, 3);
+        let mut state = BufferDisplayState::default();
+        state.line_numbers = true;
+        state.relative_line_numbers = true;
+
+        let cursor = BufferPosition { line: 1, offset: 0 };
+        let mut buffer = Buffer::empty(area);
+        BufferDisplayWidget::new(&contents, Some(cursor)).render(area, &mut buffer, &mut state);
+        assert_eq!(nth_line_from_terminal_buffer(&buffer, 0), "1 abc ");
+        assert_eq!(nth_line_from_terminal_buffer(&buffer, 1), "2 abc ");
+        assert_eq!(nth_line_from_terminal_buffer(&buffer, 2), "1 abc ");
+
+        // The numbers follow the cursor as it moves
+        let cursor = BufferPosition {
+            line: 10,
+            offset: 0,
+        };
+        let mut buffer = Buffer::empty(area);
+        BufferDisplayWidget::new(&contents, Some(cursor)).render(area, &mut buffer, &mut state);
+        assert_eq!(nth_line_from_terminal_buffer(&buffer, 0), " 2 abc");
+        assert_eq!(nth_line_from_terminal_buffer(&buffer, 2), "11 abc");
+    }
+
+    #[test]
+    fn marked_lines_have_a_glyph_in_the_gutter() {
+        let contents = "abc\ndef\nghi";
+        let area = Rect::new(0, 0, 6, 3);
+        let mut state = BufferDisplayState::default();
+        state.line_marks = vec![1];
+
+        let mut buffer = Buffer::empty(area);
+        BufferDisplayWidget::new(contents, None).render(area, &mut buffer, &mut state);
+        assert_eq!(state.gutter_width(), 2);
+        assert_eq!(nth_line_from_terminal_buffer(&buffer, 0), "  abc ");
+        assert_eq!(nth_line_from_terminal_buffer(&buffer, 1), "● def ");
+
+        state.line_numbers = true;
+        let mut buffer = Buffer::empty(area);
+        BufferDisplayWidget::new(contents, None).render(area, &mut buffer, &mut state);
+        assert_eq!(state.gutter_width(), 3);
+        assert_eq!(nth_line_from_terminal_buffer(&buffer, 0), " 1 abc");
+        assert_eq!(nth_line_from_terminal_buffer(&buffer, 1), "●2 def");
+    }
+
+    #[test]
+    fn only_the_visible_window_of_long_lines_is_rendered() {
+        let long_line = format!("{}xyz", "a".repeat(1 << 20));
+        let contents = format!("{long_line}\n{long_line}\n{long_line}");
+        let area = Rect::new(0, 0, 10, 2);
+        let mut state = BufferDisplayState::default();
+
+        let cursor = BufferPosition {
+            line: 2,
+            offset: long_line.len(),
+        };
+        let mut buffer = Buffer::empty(area);
+        BufferDisplayWidget::new(&contents, Some(cursor)).render(area, &mut buffer, &mut state);
+
+        assert_eq!((state.offset.x, state.offset.y), (long_line.len() - 9, 1));
+        let shifted = state.shift_contents(&contents, area);
+        assert_eq!(shifted, "aaaaaaxyz\naaaaaaxyz");
+        assert_eq!(nth_line_from_terminal_buffer(&buffer, 1), "aaaaaaxyz ");
+
+        let ui_state = UIState {
+            buffer_state: state,
+            ..Default::default()
+        };
+        assert_eq!(
+            ui_state.calculate_cursor_for_buffer(area, Some(cursor)),
+            TerminalPosition::new(9, 1)
+        );
+    }
+
     #[test]
     fn test_add_highlights_single_line_unselected() {
         // Setup a default display state with no offset.
@@ -494,8 +2061,13 @@ mod tests {
         let content = "Hello world";
         let highlight = Highlight {
             start: BufferPosition { line: 0, offset: 6 },
-            length: 5,
+            end: BufferPosition {
+                line: 0,
+                offset: 11,
+            },
             is_selected: false,
+            is_replace_target: false,
+            is_text_selection: false,
         };
 
         let text = state.add_highlights(content, &[highlight]);
@@ -530,6 +2102,75 @@ mod tests {
         assert_eq!(spans[1].1, expected_style);
     }
 
+    #[test]
+    fn test_add_highlights_replace_target() {
+        let mut state = BufferDisplayState::default();
+        state.highlight_state.highlights = (0..3)
+            .map(|i| Highlight {
+                start: BufferPosition {
+                    line: 0,
+                    offset: 2 * i,
+                },
+                end: BufferPosition {
+                    line: 0,
+                    offset: 2 * i + 1,
+                },
+                is_selected: i == 1,
+                is_replace_target: false,
+                is_text_selection: false,
+            })
+            .collect();
+        state.highlight_state.set_replace_target(Some(1));
+        state.highlight_state.set_replace_target(Some(2));
+
+        let text = state.add_highlights("a a a", &state.highlight_state.highlights);
+
+        let backgrounds: Vec<_> = text.lines[0]
+            .spans
+            .iter()
+            .filter_map(|span| span.style.bg)
+            .collect();
+        assert_eq!(
+            backgrounds,
+            vec![
+                Color::Rgb(240, 137, 48),
+                Color::Rgb(245, 206, 88),
+                Color::Rgb(224, 90, 90)
+            ]
+        );
+    }
+
+    #[test]
+    fn test_backward_selection_rendered_with_search_results() {
+        let mut state = BufferDisplayState::default();
+        state.highlight_state.highlights = vec![Highlight {
+            start: BufferPosition { line: 0, offset: 0 },
+            end: BufferPosition { line: 0, offset: 1 },
+            ..Default::default()
+        }];
+        let selection = Selection {
+            anchor: BufferPosition { line: 0, offset: 8 },
+            active: BufferPosition { line: 0, offset: 5 },
+        };
+        state.selection = Some(selection.to_highlight());
+
+        let text = state.add_highlights("a bc def", &state.highlights_with_selection());
+
+        let spans: Vec<_> = text.lines[0]
+            .spans
+            .iter()
+            .map(|span| (span.content.as_ref(), span.style.bg))
+            .collect();
+        assert_eq!(
+            spans,
+            vec![
+                ("a", Some(HIGHLIGHT_BG_UNSELECTED)),
+                (" bc ", None),
+                ("def", Some(SELECTION_BG)),
+            ]
+        );
+    }
+
     #[test]
     fn test_add_highlights_single_line_selected() {
         // Test with a selected highlight which uses a different background color.
@@ -538,8 +2179,13 @@ mod tests {
         let content = "Hello world";
         let highlight = Highlight {
             start: BufferPosition { line: 0, offset: 6 },
-            length: 5,
+            end: BufferPosition {
+                line: 0,
+                offset: 11,
+            },
             is_selected: true,
+            is_replace_target: false,
+            is_text_selection: false,
         };
 
         let text = state.add_highlights(content, &[highlight]);
@@ -580,13 +2226,17 @@ mod tests {
         let highlights = vec![
             Highlight {
                 start: BufferPosition { line: 0, offset: 5 },
-                length: 3,
+                end: BufferPosition { line: 0, offset: 8 },
                 is_selected: false,
+                is_replace_target: false,
+                is_text_selection: false,
             },
             Highlight {
                 start: BufferPosition { line: 1, offset: 5 },
-                length: 3,
+                end: BufferPosition { line: 1, offset: 8 },
                 is_selected: true,
+                is_replace_target: false,
+                is_text_selection: false,
             },
         ];
 
@@ -631,4 +2281,79 @@ mod tests {
         assert_eq!(second_line[1].0, "two");
         assert_eq!(second_line[1].1, expected_style_second);
     }
+
+    #[test]
+    fn test_add_highlights_spanning_lines() {
+        let state = BufferDisplayState::default();
+
+        let content = "Line one\nLine two\nLine three";
+        let highlight = Highlight {
+            start: BufferPosition { line: 0, offset: 5 },
+            end: BufferPosition { line: 2, offset: 4 },
+            is_selected: false,
+            is_replace_target: false,
+            is_text_selection: false,
+        };
+
+        let text = state.add_highlights(content, &[highlight]);
+
+        let highlighted: Vec<Vec<(String, bool)>> = text
+            .lines
+            .iter()
+            .map(|line| {
+                line.spans
+                    .iter()
+                    .map(|span| (span.content.to_string(), span.style != Style::default()))
+                    .collect()
+            })
+            .collect();
+
+        assert_eq!(
+            highlighted,
+            vec![
+                vec![("Line ".to_string(), false), ("one".to_string(), true)],
+                vec![("Line two".to_string(), true)],
+                vec![("Line".to_string(), true), (" three".to_string(), false)],
+            ]
+        );
+    }
+
+    #[test]
+    fn test_add_highlights_spanning_lines_scrolled_horizontally() {
+        let mut state = BufferDisplayState::default();
+        state.offset = BufferDisplayOffset { x: 2, y: 1 };
+
+        // Buffer lines 1 to 3 of "Line one\nLine two\nx\nLine three", as
+        // they're visible with the offset applied
+        let content = "ne two\n\nne three";
+        let highlight = Highlight {
+            start: BufferPosition { line: 1, offset: 6 },
+            end: BufferPosition { line: 3, offset: 6 },
+            is_text_selection: true,
+            ..Default::default()
+        };
+
+        let text = state.add_highlights(content, &[highlight]);
+
+        let highlighted: Vec<Vec<(String, bool)>> = text
+            .lines
+            .iter()
+            .map(|line| {
+                line.spans
+                    .iter()
+                    .map(|span| (span.content.to_string(), span.style != Style::default()))
+                    .collect()
+            })
+            .collect();
+
+        // Each segment is clamped to the visible part of its line
+        assert_eq!(
+            highlighted,
+            vec![
+                vec![("ne t".to_string(), false), ("wo".to_string(), true)],
+                vec![],
+                vec![("ne t".to_string(), true), ("hree".to_string(), false)],
+            ]
+        );
+    }
 }